@@ -0,0 +1,636 @@
+pub const USAGE: &str = "Usage:
+  fns [file] [args...]    run a file, or start the repl without one
+  fns run <file> [args...]
+                          run a file, giving it the arguments after it as
+                          `process.args`, and all of those after `--` even
+                          when they look like flags
+    --strict              make operations mixing types errors
+    --check               refuse to run when names can't be resolved, see `check`
+    --trace               print each statement and its value to <stderr>
+    --profile             print the 10 slowest expressions to <stderr> once done
+    --log-level <level>   only log from this level on (debug, info, warn or error),
+                          overriding both FNS_LOG_LEVEL and `log.set_level`
+    --max-statements <n>  refuse programs of more than n statements
+    --max-object-pairs <n>
+                          refuse object literals of more than n pairs
+    --max-string-length <n>
+                          refuse string literals of more than n chars
+    --max-nesting <n>     refuse expressions nested more than n levels deep
+    --max-chain-length <n>
+                          refuse chains of more than n operators, like `1 + 2`
+    --max-call-depth <n>  stop at calls nested more than n deep
+    --max-steps <n>       stop after evaluating n expressions
+  fns repl                start the repl
+    --no-rc               skip the startup file ($FNS_RC, or ~/.fnsrc without it)
+  fns eval <source>       evaluate source code and print its value, `-e` for short
+    --output <format>     print the value as `display` (the default) or `json`
+    --pretty              indent the json output
+    --strict              make operations mixing types errors
+    --max-*               the same limits as for `run`
+  fns test <path>         run the tests in a file or a directory
+  fns tokens <file>       print the tokens of a file
+  fns ast <file>          print the syntax tree of a file, `--ast=<format>` for short
+    --format <format>     print it as `debug` (the default), as a `dot` graph or as
+                          formatted `source` code
+  fns check <file>        check a file for syntax errors, undefined variables,
+                          assigned constants and names used before their
+                          declaration without running it
+    --types               also report the type errors that can be found statically
+  fns lint <file>         report suspicious code in a file without running it
+    --allow <lint>        leave out a lint, can be repeated
+  fns help                print this message";
+
+use fns::{
+    frontend::{limits::Limits, lint::LINT_NAMES},
+    runtime::log::LogLevel,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Run(RunOptions),
+    Repl(ReplOptions),
+    Eval(EvalOptions),
+    Test(TestOptions),
+    Tokens(InspectOptions),
+    Ast(AstOptions),
+    Check(CheckOptions),
+    Lint(LintOptions),
+    Help,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunOptions {
+    pub path: String,
+    // The arguments the script gets, the ones after its path.
+    pub arguments: Vec<String>,
+    pub strict: bool,
+    pub check: bool,
+    pub trace: bool,
+    pub profile: bool,
+    pub log_level: Option<LogLevel>,
+    pub limits: Limits,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplOptions {
+    pub no_rc: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvalOptions {
+    pub source_code: String,
+    pub output: OutputFormat,
+    pub pretty: bool,
+    pub strict: bool,
+    pub limits: Limits,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputFormat {
+    Display,
+    Json,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestOptions {
+    pub path: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InspectOptions {
+    pub path: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AstOptions {
+    pub path: String,
+    pub format: AstFormat,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AstFormat {
+    Debug,
+    Dot,
+    Source,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckOptions {
+    pub path: String,
+    pub types: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintOptions {
+    pub path: String,
+    pub allowed: Vec<String>,
+}
+
+// Parses the command line arguments (without the program name) into a command. Bare
+// `fns` and `fns <file>` are kept as aliases of `fns repl` and `fns run <file>`.
+pub fn parse_arguments(arguments: Vec<String>) -> Result<Command, String> {
+    let mut arguments = arguments.into_iter();
+    let command = match arguments.next() {
+        Some(command) => command,
+        None => return Ok(Command::Repl(ReplOptions { no_rc: false })),
+    };
+
+    let command = match command.as_str() {
+        "help" | "--help" | "-h" => Command::Help,
+        "repl" => return parse_repl_options(arguments.collect()).map(Command::Repl),
+        "--no-rc" => {
+            let arguments = std::iter::once(command).chain(arguments).collect();
+            return parse_repl_options(arguments).map(Command::Repl);
+        }
+        "run" => return parse_run_options(arguments.collect()).map(Command::Run),
+        "eval" | "-e" => return parse_eval_options(arguments.collect()).map(Command::Eval),
+        "test" => Command::Test(TestOptions {
+            path: expect_operand(&mut arguments, "test", "path")?,
+        }),
+        "tokens" => Command::Tokens(InspectOptions {
+            path: expect_operand(&mut arguments, "tokens", "file")?,
+        }),
+        "ast" => return parse_ast_options(arguments.collect(), AstFormat::Debug).map(Command::Ast),
+        flag if flag.starts_with("--ast=") => {
+            let format = ast_format(&flag["--ast=".len()..])?;
+            return parse_ast_options(arguments.collect(), format).map(Command::Ast);
+        }
+        "check" => return parse_check_options(arguments.collect()).map(Command::Check),
+        "lint" => return parse_lint_options(arguments.collect()).map(Command::Lint),
+        _ => {
+            let arguments = std::iter::once(command).chain(arguments).collect();
+            return parse_run_options(arguments).map(Command::Run);
+        }
+    };
+
+    match arguments.next() {
+        Some(argument) if argument.starts_with('-') => Err(format!("Unknown flag '{argument}'")),
+        Some(argument) => Err(format!("Unexpected argument '{argument}'")),
+        None => Ok(command),
+    }
+}
+
+fn parse_repl_options(arguments: Vec<String>) -> Result<ReplOptions, String> {
+    let mut no_rc = false;
+    for argument in arguments {
+        match argument.as_str() {
+            "--no-rc" => no_rc = true,
+            flag if flag.starts_with('-') => return Err(format!("Unknown flag '{flag}'")),
+            _ => return Err(format!("Unexpected argument '{argument}'")),
+        }
+    }
+    Ok(ReplOptions { no_rc })
+}
+
+// The first argument that isn't a flag is the file, the following ones are arguments of
+// the script, and so is everything after `--`, flags included.
+fn parse_run_options(arguments: Vec<String>) -> Result<RunOptions, String> {
+    let mut path = None;
+    let mut script_arguments = vec![];
+    let mut strict = false;
+    let mut check = false;
+    let mut trace = false;
+    let mut profile = false;
+    let mut log_level = None;
+    let mut limits = Limits::default();
+    let mut arguments = arguments.into_iter();
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--strict" => strict = true,
+            "--check" => check = true,
+            "--trace" => trace = true,
+            "--profile" => profile = true,
+            "--log-level" => {
+                let name = expect_operand(&mut arguments, "--log-level", "level")?;
+                log_level = Some(
+                    LogLevel::from_name(&name)
+                        .ok_or_else(|| format!("Unknown log level '{name}'"))?,
+                );
+            }
+            "--" => {
+                script_arguments.extend(arguments.by_ref());
+            }
+            flag if flag.starts_with("--max-") => set_limit(&mut limits, flag, &mut arguments)?,
+            flag if flag.starts_with('-') => return Err(format!("Unknown flag '{flag}'")),
+            _ if path.is_some() => script_arguments.push(argument),
+            _ => path = Some(argument),
+        }
+    }
+    // `fns -- <file>` runs a file whose name starts with a '-'.
+    if path.is_none() && !script_arguments.is_empty() {
+        path = Some(script_arguments.remove(0));
+    }
+    match path {
+        Some(path) => Ok(RunOptions {
+            path,
+            arguments: script_arguments,
+            strict,
+            check,
+            trace,
+            profile,
+            log_level,
+            limits,
+        }),
+        None => Err("'run' expects a <file>".to_string()),
+    }
+}
+
+fn parse_ast_options(arguments: Vec<String>, format: AstFormat) -> Result<AstOptions, String> {
+    let mut path = None;
+    let mut format = format;
+    let mut arguments = arguments.into_iter();
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--format" => {
+                format = ast_format(&expect_operand(&mut arguments, "--format", "format")?)?
+            }
+            flag if flag.starts_with('-') => return Err(format!("Unknown flag '{flag}'")),
+            _ if path.is_some() => return Err(format!("Unexpected argument '{argument}'")),
+            _ => path = Some(argument),
+        }
+    }
+    match path {
+        Some(path) => Ok(AstOptions { path, format }),
+        None => Err("'ast' expects a <file>".to_string()),
+    }
+}
+
+fn ast_format(name: &str) -> Result<AstFormat, String> {
+    match name {
+        "debug" => Ok(AstFormat::Debug),
+        "dot" => Ok(AstFormat::Dot),
+        "source" => Ok(AstFormat::Source),
+        _ => Err(format!("Unknown ast format '{name}'")),
+    }
+}
+
+fn parse_check_options(arguments: Vec<String>) -> Result<CheckOptions, String> {
+    let mut path = None;
+    let mut types = false;
+    for argument in arguments {
+        match argument.as_str() {
+            "--types" => types = true,
+            flag if flag.starts_with('-') => return Err(format!("Unknown flag '{flag}'")),
+            _ if path.is_some() => return Err(format!("Unexpected argument '{argument}'")),
+            _ => path = Some(argument),
+        }
+    }
+    match path {
+        Some(path) => Ok(CheckOptions { path, types }),
+        None => Err("'check' expects a <file>".to_string()),
+    }
+}
+
+fn parse_lint_options(arguments: Vec<String>) -> Result<LintOptions, String> {
+    let mut path = None;
+    let mut allowed = vec![];
+    let mut arguments = arguments.into_iter();
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--allow" => {
+                let name = expect_operand(&mut arguments, "--allow", "lint")?;
+                if !LINT_NAMES.contains(&name.as_str()) {
+                    return Err(format!("Unknown lint '{name}'"));
+                }
+                allowed.push(name);
+            }
+            flag if flag.starts_with('-') => return Err(format!("Unknown flag '{flag}'")),
+            _ if path.is_some() => return Err(format!("Unexpected argument '{argument}'")),
+            _ => path = Some(argument),
+        }
+    }
+    match path {
+        Some(path) => Ok(LintOptions { path, allowed }),
+        None => Err("'lint' expects a <file>".to_string()),
+    }
+}
+
+// The source code may start with a '-' (e.g. `-1 + 2`), so only arguments starting
+// with '--' are taken for flags.
+fn parse_eval_options(arguments: Vec<String>) -> Result<EvalOptions, String> {
+    let mut source_code = None;
+    let mut output = OutputFormat::Display;
+    let mut pretty = false;
+    let mut strict = false;
+    let mut limits = Limits::default();
+    let mut arguments = arguments.into_iter();
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--output" => {
+                output = match expect_operand(&mut arguments, "--output", "format")?.as_str() {
+                    "display" => OutputFormat::Display,
+                    "json" => OutputFormat::Json,
+                    format => return Err(format!("Unknown output format '{format}'")),
+                }
+            }
+            "--pretty" => pretty = true,
+            "--strict" => strict = true,
+            flag if flag.starts_with("--max-") => set_limit(&mut limits, flag, &mut arguments)?,
+            flag if flag.starts_with("--") => return Err(format!("Unknown flag '{flag}'")),
+            _ if source_code.is_some() => return Err(format!("Unexpected argument '{argument}'")),
+            _ => source_code = Some(argument),
+        }
+    }
+    match source_code {
+        Some(source_code) => Ok(EvalOptions {
+            source_code,
+            output,
+            pretty,
+            strict,
+            limits,
+        }),
+        None => Err("'eval' expects a <source>".to_string()),
+    }
+}
+
+fn set_limit(
+    limits: &mut Limits,
+    flag: &str,
+    arguments: &mut impl Iterator<Item = String>,
+) -> Result<(), String> {
+    let limit = match flag {
+        "--max-statements" => &mut limits.max_statements,
+        "--max-object-pairs" => &mut limits.max_object_pairs,
+        "--max-string-length" => &mut limits.max_string_length,
+        "--max-nesting" => &mut limits.max_nesting,
+        "--max-chain-length" => &mut limits.max_chain_length,
+        "--max-call-depth" => &mut limits.max_call_depth,
+        "--max-steps" => &mut limits.max_steps,
+        _ => return Err(format!("Unknown flag '{flag}'")),
+    };
+    let operand = expect_operand(arguments, flag, "n")?;
+    *limit = operand
+        .parse()
+        .map_err(|_| format!("'{flag}' expects a number, not '{operand}'"))?;
+    Ok(())
+}
+
+fn expect_operand(
+    arguments: &mut impl Iterator<Item = String>,
+    command: &str,
+    operand: &str,
+) -> Result<String, String> {
+    match arguments.next() {
+        Some(argument) if argument.starts_with('-') && argument != "-" => {
+            Err(format!("Unknown flag '{argument}'"))
+        }
+        Some(argument) => Ok(argument),
+        None => Err(format!("'{command}' expects a <{operand}>")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fns::{frontend::limits::Limits, runtime::log::LogLevel};
+
+    use super::{
+        parse_arguments, AstFormat, AstOptions, CheckOptions, Command, EvalOptions, LintOptions,
+        OutputFormat, ReplOptions, RunOptions, TestOptions,
+    };
+
+    fn arguments(arguments: &[&str]) -> Vec<String> {
+        arguments
+            .iter()
+            .map(|argument| argument.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_arguments_aliases() {
+        assert_eq!(
+            parse_arguments(arguments(&[])),
+            Ok(Command::Repl(ReplOptions { no_rc: false }))
+        );
+        assert_eq!(
+            parse_arguments(arguments(&["main.fns"])),
+            Ok(Command::Run(RunOptions {
+                path: "main.fns".to_string(),
+                arguments: vec![],
+                strict: false,
+                check: false,
+                trace: false,
+                profile: false,
+                log_level: None,
+                limits: Limits::default()
+            }))
+        );
+        assert_eq!(
+            parse_arguments(arguments(&["--trace", "main.fns"])),
+            Ok(Command::Run(RunOptions {
+                path: "main.fns".to_string(),
+                arguments: vec![],
+                strict: false,
+                check: false,
+                trace: true,
+                profile: false,
+                log_level: None,
+                limits: Limits::default()
+            }))
+        );
+    }
+
+    #[test]
+    fn test_parse_arguments_subcommands() {
+        assert_eq!(
+            parse_arguments(arguments(&[
+                "run",
+                "main.fns",
+                "--trace",
+                "--log-level",
+                "warn"
+            ])),
+            Ok(Command::Run(RunOptions {
+                path: "main.fns".to_string(),
+                arguments: vec![],
+                strict: false,
+                check: false,
+                trace: true,
+                profile: false,
+                log_level: Some(LogLevel::Warn),
+                limits: Limits::default()
+            }))
+        );
+        assert_eq!(
+            parse_arguments(arguments(&["eval", "1 + 2"])),
+            Ok(Command::Eval(EvalOptions {
+                source_code: "1 + 2".to_string(),
+                output: OutputFormat::Display,
+                pretty: false,
+                strict: false,
+                limits: Limits::default()
+            }))
+        );
+        assert_eq!(
+            parse_arguments(arguments(&[
+                "-e", "-1", "--output", "json", "--pretty", "--strict"
+            ])),
+            Ok(Command::Eval(EvalOptions {
+                source_code: "-1".to_string(),
+                output: OutputFormat::Json,
+                pretty: true,
+                strict: true,
+                limits: Limits::default()
+            }))
+        );
+        assert_eq!(
+            parse_arguments(arguments(&[
+                "run",
+                "main.fns",
+                "--max-statements",
+                "100",
+                "--max-string-length",
+                "10",
+                "--check"
+            ])),
+            Ok(Command::Run(RunOptions {
+                path: "main.fns".to_string(),
+                arguments: vec![],
+                strict: false,
+                check: true,
+                trace: false,
+                profile: false,
+                log_level: None,
+                limits: Limits {
+                    max_statements: 100,
+                    max_string_length: 10,
+                    ..Limits::default()
+                }
+            }))
+        );
+        assert_eq!(
+            parse_arguments(arguments(&["test", "tests"])),
+            Ok(Command::Test(TestOptions {
+                path: "tests".to_string()
+            }))
+        );
+        assert_eq!(
+            parse_arguments(arguments(&["ast", "main.fns"])),
+            Ok(Command::Ast(AstOptions {
+                path: "main.fns".to_string(),
+                format: AstFormat::Debug
+            }))
+        );
+        assert_eq!(
+            parse_arguments(arguments(&["--ast=dot", "main.fns"])),
+            Ok(Command::Ast(AstOptions {
+                path: "main.fns".to_string(),
+                format: AstFormat::Dot
+            }))
+        );
+        assert_eq!(
+            parse_arguments(arguments(&["ast", "--format", "source", "main.fns"])),
+            Ok(Command::Ast(AstOptions {
+                path: "main.fns".to_string(),
+                format: AstFormat::Source
+            }))
+        );
+        assert_eq!(
+            parse_arguments(arguments(&["check", "--types", "main.fns"])),
+            Ok(Command::Check(CheckOptions {
+                path: "main.fns".to_string(),
+                types: true
+            }))
+        );
+        assert_eq!(
+            parse_arguments(arguments(&[
+                "lint",
+                "main.fns",
+                "--allow",
+                "shadowed-builtin",
+                "--allow",
+                "self-comparison"
+            ])),
+            Ok(Command::Lint(LintOptions {
+                path: "main.fns".to_string(),
+                allowed: vec![
+                    "shadowed-builtin".to_string(),
+                    "self-comparison".to_string()
+                ]
+            }))
+        );
+        assert_eq!(
+            parse_arguments(arguments(&["repl", "--no-rc"])),
+            Ok(Command::Repl(ReplOptions { no_rc: true }))
+        );
+        assert_eq!(parse_arguments(arguments(&["help"])), Ok(Command::Help));
+    }
+
+    #[test]
+    fn test_parse_arguments_script_arguments() {
+        let run = |path: &str, script_arguments: &[&str], trace: bool| {
+            Ok(Command::Run(RunOptions {
+                path: path.to_string(),
+                arguments: arguments(script_arguments),
+                strict: false,
+                check: false,
+                trace,
+                profile: false,
+                log_level: None,
+                limits: Limits::default(),
+            }))
+        };
+        assert_eq!(
+            parse_arguments(arguments(&["main.fns", "foo", "bar"])),
+            run("main.fns", &["foo", "bar"], false)
+        );
+        // The flags of fns are taken wherever they are until `--`.
+        assert_eq!(
+            parse_arguments(arguments(&["run", "main.fns", "a.fns", "--trace"])),
+            run("main.fns", &["a.fns"], true)
+        );
+        assert_eq!(
+            parse_arguments(arguments(&[
+                "--trace", "main.fns", "x", "--", "--trace", "-v", "--"
+            ])),
+            run("main.fns", &["x", "--trace", "-v", "--"], true)
+        );
+        assert_eq!(
+            parse_arguments(arguments(&["run", "--", "-weird.fns", "1"])),
+            run("-weird.fns", &["1"], false)
+        );
+        assert_eq!(
+            parse_arguments(arguments(&["main.fns", "--verbose"])),
+            Err("Unknown flag '--verbose'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_arguments_errors() {
+        assert_eq!(
+            parse_arguments(arguments(&["--verbose"])),
+            Err("Unknown flag '--verbose'".to_string())
+        );
+        assert_eq!(
+            parse_arguments(arguments(&["run", "main.fns", "--fast"])),
+            Err("Unknown flag '--fast'".to_string())
+        );
+        assert_eq!(
+            parse_arguments(arguments(&["main.fns", "--log-level", "loud"])),
+            Err("Unknown log level 'loud'".to_string())
+        );
+        assert_eq!(
+            parse_arguments(arguments(&["-e", "1", "--output", "xml"])),
+            Err("Unknown output format 'xml'".to_string())
+        );
+        assert_eq!(
+            parse_arguments(arguments(&["lint", "main.fns", "--allow", "everything"])),
+            Err("Unknown lint 'everything'".to_string())
+        );
+        assert_eq!(
+            parse_arguments(arguments(&["ast", "main.fns", "--format", "svg"])),
+            Err("Unknown ast format 'svg'".to_string())
+        );
+        assert_eq!(
+            parse_arguments(arguments(&["-e", "1", "--max-object-pairs", "many"])),
+            Err("'--max-object-pairs' expects a number, not 'many'".to_string())
+        );
+        assert_eq!(
+            parse_arguments(arguments(&["-e", "1", "--max-depth", "3"])),
+            Err("Unknown flag '--max-depth'".to_string())
+        );
+        assert_eq!(
+            parse_arguments(arguments(&["run"])),
+            Err("'run' expects a <file>".to_string())
+        );
+    }
+}
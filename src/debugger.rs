@@ -0,0 +1,162 @@
+use std::{
+    fmt::Debug,
+    io::{Read, Write},
+};
+
+use fns::{
+    frontend::{ast::Statement, utils::Error},
+    runtime::{
+        environment::Environment,
+        trace::{describe_statement, Tracer},
+        types::Value,
+    },
+};
+
+const COMMANDS: &str = "commands: n (next), c (continue), p <name> (print), q (quit)";
+
+// Stops before each statement to print it and read commands from `input` until one of
+// them resumes or aborts the evaluation.
+#[derive(Debug)]
+pub struct Debugger<R: Read + Debug, W: Write + Debug> {
+    source_code: Vec<char>,
+    input: R,
+    pub output: W,
+    is_stepping: bool,
+}
+
+impl<R: Read + Debug, W: Write + Debug> Debugger<R, W> {
+    pub fn new(source_code: &str, input: R, output: W) -> Self {
+        Self {
+            source_code: source_code.chars().collect(),
+            input,
+            output,
+            is_stepping: true,
+        }
+    }
+
+    fn read_command(&mut self) -> Option<String> {
+        let _ = write!(self.output, "debug ⇒  ");
+        let _ = self.output.flush();
+        // Reading a byte at a time leaves whatever follows the command to the next reader
+        // of the input, e.g. the repl once the evaluation is over.
+        let mut command = vec![];
+        let mut byte = [0];
+        loop {
+            match self.input.read(&mut byte) {
+                Ok(0) | Err(_) if command.is_empty() => return None,
+                Ok(0) | Err(_) => break,
+                Ok(_) if byte[0] == b'\n' => break,
+                Ok(_) => command.push(byte[0]),
+            }
+        }
+        Some(String::from_utf8_lossy(&command).trim().to_string())
+    }
+}
+
+impl<R: Read + Debug, W: Write + Debug> Tracer for Debugger<R, W> {
+    fn before_statement(
+        &mut self,
+        statement: &Statement,
+        _: usize,
+        environment: &Environment,
+    ) -> Result<(), Error> {
+        if !self.is_stepping {
+            return Ok(());
+        }
+
+        let description = describe_statement(&self.source_code, statement);
+        let _ = writeln!(self.output, "{description}");
+        loop {
+            // Running out of input aborts, as nothing could resume the evaluation anymore.
+            let command = self.read_command().unwrap_or_else(|| "q".to_string());
+            match command.split_once(' ') {
+                None if command == "n" || command.is_empty() => return Ok(()),
+                None if command == "c" => {
+                    self.is_stepping = false;
+                    return Ok(());
+                }
+                None if command == "q" => {
+                    return Err(Error::new(
+                        "Evaluation was aborted from the debugger".to_string(),
+                        statement.text_span(),
+                    ))
+                }
+                Some(("p", identifier)) => {
+                    let identifier = identifier.trim();
+                    let _ = match environment.access(identifier) {
+                        Some(value) => writeln!(self.output, "{identifier} = {value}"),
+                        None => writeln!(self.output, "'{identifier}' isn't defined"),
+                    };
+                }
+                _ => {
+                    let _ = writeln!(self.output, "{COMMANDS}");
+                }
+            }
+        }
+    }
+
+    fn after_statement(&mut self, _: &Statement, value: &Value, _: usize) {
+        if self.is_stepping {
+            let _ = writeln!(self.output, "  => {value}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use fns::{
+        run_source_with,
+        runtime::{environment::Environment, types::Value},
+    };
+
+    use super::Debugger;
+
+    const SOURCE: &str = "let a = 1\nlet b = a + 1\nb * 2";
+
+    fn debug(input: &'static str) -> (Result<Value, String>, String) {
+        let debugger = Rc::new(RefCell::new(Debugger::new(
+            SOURCE,
+            input.as_bytes(),
+            vec![],
+        )));
+        let mut environment = Environment::new(None);
+        environment.tracer = Some(debugger.clone());
+        let result = run_source_with(SOURCE, &mut environment).map_err(|error| error.message);
+        let output = String::from_utf8(debugger.borrow().output.clone()).unwrap();
+        (result, output)
+    }
+
+    #[test]
+    fn test_debugger_steps() {
+        let (result, output) = debug("n\np a\np c\nn\nn\n");
+        assert_eq!(result, Ok(Value::Number(4.0)));
+        assert_eq!(
+            output,
+            "[line 1] let a = 1\ndebug ⇒    => none\n\
+             [line 2] let b = a + 1\ndebug ⇒  a = 1\ndebug ⇒  'c' isn't defined\ndebug ⇒    => none\n\
+             [line 3] b * 2\ndebug ⇒    => 4\n"
+        );
+    }
+
+    #[test]
+    fn test_debugger_continues() {
+        let (result, output) = debug("c\n");
+        assert_eq!(result, Ok(Value::Number(4.0)));
+        assert_eq!(output, "[line 1] let a = 1\ndebug ⇒  ");
+    }
+
+    #[test]
+    fn test_debugger_quits() {
+        let (result, output) = debug("n\nq\n");
+        assert_eq!(
+            result,
+            Err("Evaluation was aborted from the debugger".to_string())
+        );
+        assert_eq!(
+            output,
+            "[line 1] let a = 1\ndebug ⇒    => none\n[line 2] let b = a + 1\ndebug ⇒  "
+        );
+    }
+}
@@ -1,47 +1,200 @@
-use super::{token::Token, utils::TextSpan};
+use std::{ops::Range, rc::Rc};
+
+use super::{
+    token::{Token, TokenKind},
+    tokenizer::escaped_char,
+    utils::TextSpan,
+};
 
 pub type Program = Vec<Statement>;
 pub type Number = f64;
 
+// The names a binding can be annotated with, one per kind of value.
+pub const TYPE_NAMES: [&str; 9] = [
+    "object", "function", "set", "array", "tuple", "string", "number", "boolean", "none",
+];
+
 #[derive(Debug, PartialEq)]
 pub enum Statement {
     Let(LetStatement),
     Const(ConstStatement),
+    Destructuring(DestructuringStatement),
+    Function(FunctionStatement),
+    While(WhileStatement),
+    For(ForStatement),
     Expression(Expression),
 }
 
+impl Statement {
+    // From the keyword through the initializer, or the whole expression of an expression
+    // statement.
+    pub fn text_span(&self) -> TextSpan {
+        match self {
+            Statement::Let(l) => l.text_span(),
+            Statement::Const(c) => c.text_span(),
+            Statement::Destructuring(d) => d.text_span(),
+            Statement::Function(f) => f.text_span(),
+            Statement::While(w) => w.text_span(),
+            Statement::For(f) => f.text_span(),
+            Statement::Expression(e) => e.text_span(),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct LetStatement {
     pub keyword: Token,
     pub identifier: Token,
+    pub annotation: Option<Token>,
     pub expression: Expression,
 }
 
 impl LetStatement {
-    pub fn new(keyword: Token, identifier: Token, expression: Expression) -> Self {
+    pub fn new(
+        keyword: Token,
+        identifier: Token,
+        annotation: Option<Token>,
+        expression: Expression,
+    ) -> Self {
         Self {
             keyword,
             identifier,
+            annotation,
             expression,
         }
     }
+
+    pub fn text_span(&self) -> TextSpan {
+        TextSpan::add(self.keyword.text_span.clone(), self.expression.text_span())
+    }
 }
 
 #[derive(Debug, PartialEq)]
 pub struct ConstStatement {
     pub keyword: Token,
     pub identifier: Token,
+    pub annotation: Option<Token>,
     pub expression: Expression,
 }
 
 impl ConstStatement {
-    pub fn new(keyword: Token, identifier: Token, expression: Expression) -> Self {
+    pub fn new(
+        keyword: Token,
+        identifier: Token,
+        annotation: Option<Token>,
+        expression: Expression,
+    ) -> Self {
         Self {
             keyword,
             identifier,
+            annotation,
             expression,
         }
     }
+
+    pub fn text_span(&self) -> TextSpan {
+        TextSpan::add(self.keyword.text_span.clone(), self.expression.text_span())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct DestructuringStatement {
+    pub keyword: Token,
+    pub identifiers: Vec<Token>,
+    pub expression: Expression,
+}
+
+impl DestructuringStatement {
+    pub fn new(keyword: Token, identifiers: Vec<Token>, expression: Expression) -> Self {
+        Self {
+            keyword,
+            identifiers,
+            expression,
+        }
+    }
+
+    pub fn text_span(&self) -> TextSpan {
+        TextSpan::add(self.keyword.text_span.clone(), self.expression.text_span())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct FunctionStatement {
+    pub identifier: Token,
+    pub function: FunctionExpression,
+}
+
+impl FunctionStatement {
+    pub fn new(identifier: Token, function: FunctionExpression) -> Self {
+        Self {
+            identifier,
+            function,
+        }
+    }
+
+    pub fn text_span(&self) -> TextSpan {
+        self.function.text_span()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct WhileStatement {
+    pub keyword: Token,
+    pub condition: Expression,
+    pub body: Program,
+    pub close_brace: Token,
+}
+
+impl WhileStatement {
+    pub fn new(keyword: Token, condition: Expression, body: Program, close_brace: Token) -> Self {
+        Self {
+            keyword,
+            condition,
+            body,
+            close_brace,
+        }
+    }
+
+    pub fn text_span(&self) -> TextSpan {
+        TextSpan::add(
+            self.keyword.text_span.clone(),
+            self.close_brace.text_span.clone(),
+        )
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ForStatement {
+    pub keyword: Token,
+    pub identifier: Token,
+    pub iterable: Expression,
+    pub body: Program,
+    pub close_brace: Token,
+}
+
+impl ForStatement {
+    pub fn new(
+        keyword: Token,
+        identifier: Token,
+        iterable: Expression,
+        body: Program,
+        close_brace: Token,
+    ) -> Self {
+        Self {
+            keyword,
+            identifier,
+            iterable,
+            body,
+            close_brace,
+        }
+    }
+
+    pub fn text_span(&self) -> TextSpan {
+        TextSpan::add(
+            self.keyword.text_span.clone(),
+            self.close_brace.text_span.clone(),
+        )
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -51,11 +204,18 @@ pub enum Expression {
     Numeric(NumericLiteralExpression),
     String(StringLiteralExpression),
     Object(ObjectLiteralExpression),
+    Tuple(TupleLiteralExpression),
+    Array(ArrayLiteralExpression),
     Access(AccessExpression),
+    Index(IndexExpression),
     Identifier(IdentifierExpression),
     Unary(UnaryExpression),
     Binary(BinaryExpression),
+    Conditional(ConditionalExpression),
     Assignment(AssignmentExpression),
+    Function(FunctionExpression),
+    Call(CallExpression),
+    Block(BlockExpression),
 }
 
 impl Expression {
@@ -66,34 +226,129 @@ impl Expression {
             Expression::Numeric(n) => n.text_span(),
             Expression::String(s) => s.text_span(),
             Expression::Object(o) => o.text_span(),
+            Expression::Tuple(t) => t.text_span(),
+            Expression::Array(a) => a.text_span(),
             Expression::Access(a) => a.text_span(),
+            Expression::Index(i) => i.text_span(),
             Expression::Identifier(i) => i.text_span(),
             Expression::Unary(u) => u.text_span(),
             Expression::Binary(b) => b.text_span(),
+            Expression::Conditional(c) => c.text_span(),
             Expression::Assignment(a) => a.text_span(),
+            Expression::Function(f) => f.text_span(),
+            Expression::Call(c) => c.text_span(),
+            Expression::Block(b) => b.text_span(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct FunctionExpression {
+    pub keyword: Token,
+    pub parameters: Vec<Token>,
+    pub body: Rc<Program>,
+    pub close_brace: Token,
+}
+
+impl FunctionExpression {
+    pub fn new(keyword: Token, parameters: Vec<Token>, body: Program, close_brace: Token) -> Self {
+        Self {
+            keyword,
+            parameters,
+            body: Rc::new(body),
+            close_brace,
         }
     }
+
+    pub fn text_span(&self) -> TextSpan {
+        TextSpan::add(
+            self.keyword.text_span.clone(),
+            self.close_brace.text_span.clone(),
+        )
+    }
+}
+
+// Statements between braces evaluating to the value of the last one, e.g.
+// `{ let x = 1 x + 1 }`.
+#[derive(Debug, PartialEq)]
+pub struct BlockExpression {
+    pub open_brace: Token,
+    pub statements: Program,
+    pub close_brace: Token,
+}
+
+impl BlockExpression {
+    pub fn new(open_brace: Token, statements: Program, close_brace: Token) -> Self {
+        Self {
+            open_brace,
+            statements,
+            close_brace,
+        }
+    }
+
+    pub fn text_span(&self) -> TextSpan {
+        TextSpan::add(
+            self.open_brace.text_span.clone(),
+            self.close_brace.text_span.clone(),
+        )
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct CallExpression {
+    pub callee: Box<Expression>,
+    pub arguments: Vec<Expression>,
+    pub close_paren: Token,
+}
+
+impl CallExpression {
+    pub fn new(callee: Expression, arguments: Vec<Expression>, close_paren: Token) -> Self {
+        Self {
+            callee: Box::new(callee),
+            arguments,
+            close_paren,
+        }
+    }
+
+    pub fn text_span(&self) -> TextSpan {
+        TextSpan::add(self.callee.text_span(), self.close_paren.text_span.clone())
+    }
 }
 
 #[derive(Debug, PartialEq)]
 pub struct AssignmentExpression {
-    pub identifier: Token,
+    // A variable, or a path of properties and indexes starting from one like `a.b[0]`.
+    pub target: Box<Expression>,
     pub expression: Box<Expression>,
 }
 
 impl AssignmentExpression {
-    pub fn new(identifier: Token, expression: Expression) -> Self {
+    pub fn new(target: Expression, expression: Expression) -> Self {
         Self {
-            identifier,
+            target: Box::new(target),
             expression: Box::new(expression),
         }
     }
 
+    // The variable the target starts from, which the parser makes sure there is.
+    pub fn identifier(&self) -> Option<&Token> {
+        assigned_variable(&self.target)
+    }
+
     pub fn text_span(&self) -> TextSpan {
-        TextSpan::add(
-            self.identifier.text_span.clone(),
-            self.expression.text_span(),
-        )
+        TextSpan::add(self.target.text_span(), self.expression.text_span())
+    }
+}
+
+// The variable assigning to the expression changes, or `None` when it can't be assigned
+// to, i.e. it isn't a variable or a path of properties and indexes starting from one.
+// Optional accesses can't be assigned to, as there may be nothing to assign.
+pub fn assigned_variable(expression: &Expression) -> Option<&Token> {
+    match expression {
+        Expression::Identifier(i) => Some(&i.identifier),
+        Expression::Access(a) if !a.is_optional() => assigned_variable(&a.object),
+        Expression::Index(i) => assigned_variable(&i.object),
+        _ => None,
     }
 }
 
@@ -137,6 +392,28 @@ impl UnaryExpression {
     }
 }
 
+// `condition ? consequent : alternative`, where only the chosen branch is evaluated.
+#[derive(Debug, PartialEq)]
+pub struct ConditionalExpression {
+    pub condition: Box<Expression>,
+    pub consequent: Box<Expression>,
+    pub alternative: Box<Expression>,
+}
+
+impl ConditionalExpression {
+    pub fn new(condition: Expression, consequent: Expression, alternative: Expression) -> Self {
+        Self {
+            condition: Box::new(condition),
+            consequent: Box::new(consequent),
+            alternative: Box::new(alternative),
+        }
+    }
+
+    pub fn text_span(&self) -> TextSpan {
+        TextSpan::add(self.condition.text_span(), self.alternative.text_span())
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct IdentifierExpression {
     pub identifier: Token,
@@ -155,17 +432,26 @@ impl IdentifierExpression {
 #[derive(Debug, PartialEq)]
 pub struct AccessExpression {
     pub object: Box<Expression>,
+    pub dot: Token,
     pub property: Token,
 }
 
 impl AccessExpression {
-    pub fn new(object: Expression, property: Token) -> Self {
+    pub fn new(object: Expression, dot: Token, property: Token) -> Self {
         Self {
             object: Box::new(object),
+            dot,
             property,
         }
     }
 
+    // Written with `?.`, which gives none rather than an error when the object is none
+    // or an object without the property.
+    pub fn is_optional(&self) -> bool {
+        self.dot.kind == TokenKind::QuestionDot
+    }
+
+    // From the start of the object, however many tokens it spans, to the property.
     pub fn text_span(&self) -> TextSpan {
         TextSpan::add(
             self.object.text_span().clone(),
@@ -174,6 +460,45 @@ impl AccessExpression {
     }
 }
 
+#[derive(Debug, PartialEq)]
+pub struct IndexExpression {
+    pub object: Box<Expression>,
+    pub open_bracket: Token,
+    pub index: Box<Expression>,
+    pub close_bracket: Token,
+}
+
+impl IndexExpression {
+    pub fn new(
+        object: Expression,
+        open_bracket: Token,
+        index: Expression,
+        close_bracket: Token,
+    ) -> Self {
+        Self {
+            object: Box::new(object),
+            open_bracket,
+            index: Box::new(index),
+            close_bracket,
+        }
+    }
+
+    pub fn text_span(&self) -> TextSpan {
+        TextSpan::add(
+            self.object.text_span(),
+            self.close_bracket.text_span.clone(),
+        )
+    }
+
+    // The brackets and what's between them, which errors about the index point at.
+    pub fn subscript_span(&self) -> TextSpan {
+        TextSpan::add(
+            self.open_bracket.text_span.clone(),
+            self.close_bracket.text_span.clone(),
+        )
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct ObjectLiteralExpression {
     pub open_brace: Token,
@@ -198,15 +523,184 @@ impl ObjectLiteralExpression {
     }
 }
 
+#[derive(Debug, PartialEq)]
+pub struct TupleLiteralExpression {
+    pub open_paren: Token,
+    pub elements: Vec<Expression>,
+    pub close_paren: Token,
+}
+
+impl TupleLiteralExpression {
+    pub fn new(open_paren: Token, elements: Vec<Expression>, close_paren: Token) -> Self {
+        Self {
+            open_paren,
+            elements,
+            close_paren,
+        }
+    }
+
+    pub fn text_span(&self) -> TextSpan {
+        TextSpan::add(
+            self.open_paren.text_span.clone(),
+            self.close_paren.text_span.clone(),
+        )
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ArrayLiteralExpression {
+    pub open_bracket: Token,
+    pub elements: Vec<Expression>,
+    pub close_bracket: Token,
+}
+
+impl ArrayLiteralExpression {
+    pub fn new(open_bracket: Token, elements: Vec<Expression>, close_bracket: Token) -> Self {
+        Self {
+            open_bracket,
+            elements,
+            close_bracket,
+        }
+    }
+
+    pub fn text_span(&self) -> TextSpan {
+        TextSpan::add(
+            self.open_bracket.text_span.clone(),
+            self.close_bracket.text_span.clone(),
+        )
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct StringLiteralExpression {
     pub string: Token,
-    pub value: String,
+    // Shared with the values the literal evaluates to, so evaluating it doesn't copy it.
+    pub value: Rc<String>,
+    // Where the chars of the value are written in the source, in order.
+    pub segments: Vec<StringSegment>,
+}
+
+// A run of chars of a string's value, by their offsets in the value, and the source they
+// come from. The two only differ in length where the source is processed into the value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StringSegment {
+    pub value: Range<usize>,
+    pub source: TextSpan,
 }
 
 impl StringLiteralExpression {
+    // A string whose value is its source text as written, which is one segment.
     pub fn new(string: Token, value: String) -> Self {
-        Self { string, value }
+        let length = value.chars().count();
+        let start = string.text_span.starting_index + 1;
+        let segments = vec![StringSegment {
+            value: 0..length,
+            source: TextSpan::new(start, start + length),
+        }];
+        Self::with_segments(string, value, segments)
+    }
+
+    // A string whose value is its source text with the escape sequences replaced by the
+    // chars they stand for, each of them a segment of its own. A backslash starting no
+    // known escape is kept as it is, which only tokens not made by the tokenizer have.
+    pub fn unescaped(string: Token) -> Self {
+        let mut value = String::new();
+        let mut segments: Vec<StringSegment> = vec![];
+        let mut length = 0;
+        let mut source = string.text_span.starting_index + 1;
+        let mut chars = string.lexeme.chars().peekable();
+        while let Some(c) = chars.next() {
+            // A line break is a newline in the value whichever way it's written, so
+            // the value doesn't depend on the line endings of the file.
+            let escaped = match c {
+                '\\' => chars.peek().copied().and_then(escaped_char),
+                '\r' => Some('\n'),
+                _ => None,
+            };
+            let (char, source_length) = match escaped {
+                Some(escaped) if c == '\\' || chars.peek() == Some(&'\n') => {
+                    chars.next();
+                    (escaped, 2)
+                }
+                Some(escaped) => (escaped, 1),
+                None => (c, 1),
+            };
+            value.push(char);
+            match segments.last_mut() {
+                // Verbatim chars extend the run before them.
+                Some(segment)
+                    if escaped.is_none()
+                        && segment.value.len()
+                            == segment.source.ending_index - segment.source.starting_index =>
+                {
+                    segment.value.end += 1;
+                    segment.source.ending_index += 1;
+                }
+                _ => segments.push(StringSegment {
+                    value: length..length + 1,
+                    source: TextSpan::new(source, source + source_length),
+                }),
+            }
+            length += 1;
+            source += source_length;
+        }
+        if segments.is_empty() {
+            segments.push(StringSegment {
+                value: 0..0,
+                source: TextSpan::new(source, source),
+            });
+        }
+        Self::with_segments(string, value, segments)
+    }
+
+    pub fn with_segments(string: Token, value: String, segments: Vec<StringSegment>) -> Self {
+        Self {
+            string,
+            value: Rc::new(value),
+            segments,
+        }
+    }
+
+    // The text between the quotes exactly as it's written in the source.
+    pub fn raw(&self) -> &str {
+        &self.string.lexeme
+    }
+
+    // Where the chars of the value in `range` are written in the source. Within a
+    // segment whose source is processed into a different length, the offsets map to the
+    // whole segment.
+    pub fn source_span(&self, range: Range<usize>) -> TextSpan {
+        TextSpan::new(
+            self.source_offset(range.start, false),
+            self.source_offset(range.end, true),
+        )
+    }
+
+    fn source_offset(&self, offset: usize, is_end: bool) -> usize {
+        let segment = self.segments.iter().find(|segment| {
+            if is_end {
+                segment.value.start < offset && offset <= segment.value.end
+            } else {
+                segment.value.start <= offset && offset < segment.value.end
+            }
+        });
+        match segment {
+            Some(segment)
+                if segment.value.len()
+                    == segment.source.ending_index - segment.source.starting_index =>
+            {
+                segment.source.starting_index + offset - segment.value.start
+            }
+            Some(segment) if is_end => segment.source.ending_index,
+            Some(segment) => segment.source.starting_index,
+            // Past the last char, or anywhere in an empty string.
+            None => self
+                .segments
+                .last()
+                .map_or(self.string.text_span.starting_index + 1, |segment| {
+                    segment.source.ending_index
+                }),
+        }
     }
 
     pub fn text_span(&self) -> TextSpan {
@@ -214,14 +708,21 @@ impl StringLiteralExpression {
     }
 }
 
+// The value of a numeric literal, an integer unless it's written with a decimal point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NumericValue {
+    Integer(i64),
+    Float(Number),
+}
+
 #[derive(Debug, PartialEq)]
 pub struct NumericLiteralExpression {
     pub number: Token,
-    pub value: Number,
+    pub value: NumericValue,
 }
 
 impl NumericLiteralExpression {
-    pub fn new(number: Token, value: Number) -> Self {
+    pub fn new(number: Token, value: NumericValue) -> Self {
         Self { number, value }
     }
 
@@ -0,0 +1,155 @@
+use super::{
+    ast::{Expression, Program, Statement},
+    node::Node,
+    token::Token,
+};
+
+// Renders a program as a Graphviz DOT graph with a vertex per node, labeled with its
+// operator, literal or name, and edges from each node to its children. Vertices are
+// named by the order they're written in, so the same program always gives the same
+// graph.
+pub fn to_dot(program: &Program) -> String {
+    let mut output = String::from("digraph ast {\n  node [shape=box];\n");
+    let mut next_id = 0;
+    for statement in program {
+        write_node(Node::Statement(statement), &mut next_id, &mut output);
+    }
+    output.push_str("}\n");
+    output
+}
+
+// Writes a node and everything under it, returning the name of its vertex.
+fn write_node(node: Node, next_id: &mut usize, output: &mut String) -> String {
+    // An expression statement adds nothing to the expression it wraps.
+    if let Node::Statement(Statement::Expression(e)) = node {
+        return write_node(Node::Expression(e), next_id, output);
+    }
+
+    let name = format!("n{next_id}");
+    *next_id += 1;
+    output.push_str(&format!("  {name} [label=\"{}\"];\n", escape(&label(node))));
+
+    let keys: Vec<&Token> = match node {
+        Node::Expression(Expression::Object(o)) => o.pairs.iter().map(|pair| &pair.key).collect(),
+        _ => vec![],
+    };
+    for (index, child) in node.children(true).into_iter().enumerate() {
+        let child_name = write_node(child, next_id, output);
+        match keys.get(index) {
+            Some(key) => output.push_str(&format!(
+                "  {name} -> {child_name} [label=\"{}\"];\n",
+                escape(&key.lexeme)
+            )),
+            None => output.push_str(&format!("  {name} -> {child_name};\n")),
+        }
+    }
+    name
+}
+
+fn label(node: Node) -> String {
+    match node {
+        Node::Statement(Statement::Let(l)) => match &l.annotation {
+            Some(annotation) => format!("let {}: {}", l.identifier.lexeme, annotation.lexeme),
+            None => format!("let {}", l.identifier.lexeme),
+        },
+        Node::Statement(Statement::Const(c)) => match &c.annotation {
+            Some(annotation) => format!("const {}: {}", c.identifier.lexeme, annotation.lexeme),
+            None => format!("const {}", c.identifier.lexeme),
+        },
+        Node::Statement(Statement::Destructuring(d)) => {
+            format!("let ({})", lexemes(&d.identifiers))
+        }
+        Node::Statement(Statement::Function(f)) => format!(
+            "fn {}({})",
+            f.identifier.lexeme,
+            lexemes(&f.function.parameters)
+        ),
+        Node::Statement(Statement::While(_)) => "while".to_string(),
+        Node::Statement(Statement::For(f)) => format!("for {}", f.identifier.lexeme),
+        Node::Statement(Statement::Expression(_)) => String::new(),
+        Node::Expression(Expression::None(_)) => "none".to_string(),
+        Node::Expression(Expression::Boolean(b)) => b.value.to_string(),
+        Node::Expression(Expression::Numeric(n)) => n.number.lexeme.to_string(),
+        Node::Expression(Expression::String(s)) => format!("\"{}\"", s.value),
+        Node::Expression(Expression::Object(_)) => "{}".to_string(),
+        Node::Expression(Expression::Tuple(_)) => "()".to_string(),
+        Node::Expression(Expression::Array(_)) => "[]".to_string(),
+        Node::Expression(Expression::Access(a)) => {
+            format!("{}{}", a.dot.lexeme, a.property.lexeme)
+        }
+        Node::Expression(Expression::Index(_)) => "index".to_string(),
+        Node::Expression(Expression::Identifier(i)) => i.identifier.lexeme.to_string(),
+        Node::Expression(Expression::Unary(u)) => u.operator.lexeme.to_string(),
+        Node::Expression(Expression::Binary(b)) => b.operator.lexeme.to_string(),
+        Node::Expression(Expression::Conditional(_)) => "? :".to_string(),
+        Node::Expression(Expression::Assignment(a)) => format!("{} =", a.target.to_source()),
+        Node::Expression(Expression::Function(f)) => format!("fn({})", lexemes(&f.parameters)),
+        Node::Expression(Expression::Call(_)) => "call".to_string(),
+        Node::Expression(Expression::Block(_)) => "block".to_string(),
+    }
+}
+
+fn lexemes(tokens: &[Token]) -> String {
+    tokens
+        .iter()
+        .map(|token| &*token.lexeme)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frontend::{parser::parse, tokenizer::tokenize};
+
+    use super::to_dot;
+
+    fn dot(src: &str) -> String {
+        to_dot(&parse(tokenize(src).unwrap()).unwrap())
+    }
+
+    #[test]
+    fn test_to_dot_precedence() {
+        assert_eq!(
+            dot("1 + 2 * 3"),
+            "digraph ast {
+  node [shape=box];
+  n0 [label=\"+\"];
+  n1 [label=\"1\"];
+  n0 -> n1;
+  n2 [label=\"*\"];
+  n3 [label=\"2\"];
+  n2 -> n3;
+  n4 [label=\"3\"];
+  n2 -> n4;
+  n0 -> n2;
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_to_dot_object_literal() {
+        assert_eq!(
+            dot("let config = {name: \"fns\", port: f(8080)}"),
+            "digraph ast {
+  node [shape=box];
+  n0 [label=\"let config\"];
+  n1 [label=\"{}\"];
+  n2 [label=\"\\\"fns\\\"\"];
+  n1 -> n2 [label=\"name\"];
+  n3 [label=\"call\"];
+  n4 [label=\"f\"];
+  n3 -> n4;
+  n5 [label=\"8080\"];
+  n3 -> n5;
+  n1 -> n3 [label=\"port\"];
+  n0 -> n1;
+}
+"
+        );
+    }
+}
@@ -0,0 +1,395 @@
+use std::cell::Cell;
+
+use super::{
+    parser::starts_object,
+    token::{Token, TokenKind},
+    utils::{Error, TextSpan},
+};
+
+// How large a program may be before it's refused rather than parsed, and how much it
+// may do once it runs, so an oversized input fails with an error instead of running out
+// of memory or stack. The defaults leave room for any program written by hand, except
+// for the number of steps, which is unbounded. The stack that nesting and calls need
+// grows with both limits, see `stack_size`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Limits {
+    pub max_statements: usize,
+    pub max_object_pairs: usize,
+    // Also bounds the strings made by concatenation.
+    pub max_string_length: usize,
+    // How deeply expressions may nest, each operand, element or body a level deeper.
+    pub max_nesting: usize,
+    // How many binary operators may chain, as in `1 + 2 + 3`, counted apart from the
+    // nesting since chains are evaluated in a loop rather than one level per operator.
+    pub max_chain_length: usize,
+    pub max_call_depth: usize,
+    // How many expressions may be evaluated, counted over everything run in an
+    // environment and the ones made from it.
+    pub max_steps: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_statements: 1_000_000,
+            max_object_pairs: 1_000_000,
+            max_string_length: 16 * 1024 * 1024,
+            max_nesting: 128,
+            max_chain_length: 1_000,
+            max_call_depth: 256,
+            max_steps: usize::MAX,
+        }
+    }
+}
+
+// The stack taken by a call and by a level of nesting in it, measured on the deepest
+// nesting constructs, loops in blocks, with room to spare.
+const STACK_PER_CALL: usize = if cfg!(debug_assertions) {
+    128 * 1024
+} else {
+    32 * 1024
+};
+const STACK_PER_LEVEL: usize = if cfg!(debug_assertions) {
+    48 * 1024
+} else {
+    16 * 1024
+};
+// The stack taken by an operator of a chain in the walks of the tree that recurse
+// through it, like formatting it as source.
+const STACK_PER_OPERATOR: usize = if cfg!(debug_assertions) {
+    8 * 1024
+} else {
+    2 * 1024
+};
+// The stack taken by everything around the evaluation, from parsing to printing.
+const STACK_BASE: usize = 8 * 1024 * 1024;
+
+impl Limits {
+    // The stack to run code on so nesting as deep as `max_nesting` in calls as deep as
+    // `max_call_depth`, or chains as long as `max_chain_length`, fail with an error
+    // rather than overflowing it. Each level of each call takes its share of the stack,
+    // so with the defaults that's hundreds of megabytes, more than any main thread has,
+    // but it's only reserved, not used until the code gets that deep.
+    pub fn stack_size(&self) -> usize {
+        let per_call = self
+            .max_nesting
+            .saturating_add(1)
+            .saturating_mul(STACK_PER_LEVEL)
+            .saturating_add(STACK_PER_CALL);
+        self.max_call_depth
+            .saturating_add(1)
+            .saturating_mul(per_call)
+            .saturating_add(self.max_chain_length.saturating_mul(STACK_PER_OPERATOR))
+            .saturating_add(STACK_BASE)
+    }
+}
+
+enum Opening {
+    Object { open_brace: TextSpan, pairs: usize },
+    Other,
+}
+
+// Checks the limits that can be told from the tokens alone, before any node is built:
+// the length of string literals and the number of pairs of object literals. Braces are
+// told apart from bodies and blocks the way the parser does.
+pub fn check_tokens(tokens: &[Token], limits: &Limits) -> Result<(), Error> {
+    let mut openings = vec![];
+    for (index, token) in tokens.iter().enumerate() {
+        let starts_pair = match token.kind {
+            TokenKind::String if token.lexeme.chars().count() > limits.max_string_length => {
+                return Err(Error::new(
+                    format!(
+                        "Can't parse a string literal longer than the limit of {} chars",
+                        limits.max_string_length
+                    ),
+                    token.text_span.clone(),
+                ));
+            }
+            TokenKind::OpenParen | TokenKind::OpenBracket => {
+                openings.push(Opening::Other);
+                false
+            }
+            TokenKind::OpenBrace if !starts_object(tokens, index) => {
+                openings.push(Opening::Other);
+                false
+            }
+            TokenKind::OpenBrace => {
+                openings.push(Opening::Object {
+                    open_brace: token.text_span.clone(),
+                    pairs: 0,
+                });
+                true
+            }
+            TokenKind::Comma => matches!(openings.last(), Some(Opening::Object { .. })),
+            TokenKind::CloseParen | TokenKind::CloseBrace | TokenKind::CloseBracket => {
+                openings.pop();
+                false
+            }
+            _ => false,
+        };
+        let next = tokens.get(index + 1);
+        if starts_pair && next.is_some_and(|next| next.kind != TokenKind::CloseBrace) {
+            if let Some(Opening::Object { open_brace, pairs }) = openings.last_mut() {
+                *pairs += 1;
+                if *pairs > limits.max_object_pairs {
+                    return Err(Error::new(
+                        format!(
+                            "Can't parse an object literal with more than the limit of {} pairs",
+                            limits.max_object_pairs
+                        ),
+                        TextSpan::add(open_brace.clone(), token.text_span.clone()),
+                    ));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+thread_local! {
+    // How deeply the expression being parsed nests, and how deeply it may.
+    static NESTING: Cell<(usize, usize)> = const { Cell::new((0, usize::MAX)) };
+    // How many operators it chains, and how many it may.
+    static CHAIN: Cell<(usize, usize)> = const { Cell::new((0, usize::MAX)) };
+}
+
+// Parses with expressions nesting and chaining at most as much as `limits` allow.
+pub fn with_limits<T>(limits: &Limits, parse: impl FnOnce() -> T) -> T {
+    let outer_nesting = NESTING.replace((0, limits.max_nesting));
+    let outer_chain = CHAIN.replace((0, limits.max_chain_length));
+    let result = parse();
+    NESTING.set(outer_nesting);
+    CHAIN.set(outer_chain);
+    result
+}
+
+// A level of nesting, held by the parser while it parses what's nested in it. The
+// parser recurses once per level, as does the evaluator, so bounding the levels keeps
+// both from overflowing the stack on deeply nested input.
+pub struct Nesting;
+
+impl Nesting {
+    pub fn enter(token: &Token) -> Result<Self, Error> {
+        let (depth, max_nesting) = NESTING.get();
+        if depth == max_nesting {
+            return Err(Error::new(
+                format!(
+                    "Can't parse an expression nested deeper than the limit of {max_nesting} levels"
+                ),
+                token.text_span.clone(),
+            ));
+        }
+        NESTING.set((depth + 1, max_nesting));
+        Ok(Nesting)
+    }
+}
+
+impl Drop for Nesting {
+    fn drop(&mut self) {
+        let (depth, max_nesting) = NESTING.get();
+        NESTING.set((depth - 1, max_nesting));
+    }
+}
+
+// The operators of a binary expression, counted by the parser until the expression
+// ends, along with the ones of the expressions it's nested in. The evaluator goes
+// through a chain of them in a loop but other walks of the tree recurse once per
+// operator, so chains are bounded too, if far less tightly than nesting.
+pub struct Chain(usize);
+
+impl Chain {
+    pub fn start() -> Self {
+        Chain(CHAIN.get().0)
+    }
+
+    pub fn extend(token: &Token) -> Result<(), Error> {
+        let (length, max_chain_length) = CHAIN.get();
+        if length == max_chain_length {
+            return Err(Error::new(
+                format!(
+                    "Can't parse an expression chaining more than the limit of {max_chain_length} operators"
+                ),
+                token.text_span.clone(),
+            ));
+        }
+        CHAIN.set((length + 1, max_chain_length));
+        Ok(())
+    }
+}
+
+impl Drop for Chain {
+    fn drop(&mut self) {
+        let (_, max_chain_length) = CHAIN.get();
+        CHAIN.set((self.0, max_chain_length));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frontend::{parser::parse_with_limits, tokenizer::tokenize, utils::TextSpan};
+
+    use super::Limits;
+
+    fn limits() -> Limits {
+        Limits {
+            max_statements: 3,
+            max_object_pairs: 3,
+            max_string_length: 3,
+            ..Limits::default()
+        }
+    }
+
+    fn object(pairs: usize) -> String {
+        let pairs: Vec<String> = (0..pairs).map(|i| format!("k{i}: {i}")).collect();
+        format!("let o = {{{}}}", pairs.join(", "))
+    }
+
+    #[test]
+    fn test_limit_statements() {
+        let within = "let a = 1 ".repeat(3);
+        assert!(parse_with_limits(tokenize(&within).unwrap(), &limits()).is_ok());
+
+        let over = "let a = 1 ".repeat(4);
+        let error = parse_with_limits(tokenize(&over).unwrap(), &limits()).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Can't parse a program of more than the limit of 3 statements"
+        );
+        assert_eq!(error.text_span, TextSpan::new(30, 33));
+
+        // The statements of function bodies aren't counted.
+        let nested = format!("fn f() {{ {over} }}");
+        assert!(parse_with_limits(tokenize(&nested).unwrap(), &limits()).is_ok());
+    }
+
+    #[test]
+    fn test_limit_object_pairs() {
+        let within = object(3);
+        assert!(parse_with_limits(tokenize(&within).unwrap(), &limits()).is_ok());
+
+        let over = object(4);
+        let error = parse_with_limits(tokenize(&over).unwrap(), &limits()).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Can't parse an object literal with more than the limit of 3 pairs"
+        );
+        assert_eq!(
+            error.text_span,
+            TextSpan::new(8, over.rfind(',').unwrap() + 1)
+        );
+
+        // Pairs of nested objects are counted for the object they're in.
+        let nested = "let o = {a: {b: 1, c: 2}, d: fn() { {e: 1, f: 2} }, g: { f((1, 2), 3, 4) }}";
+        assert!(parse_with_limits(tokenize(nested).unwrap(), &limits()).is_ok());
+    }
+
+    #[test]
+    fn test_limit_string_length() {
+        assert!(parse_with_limits(tokenize("\"abc\"").unwrap(), &limits()).is_ok());
+
+        let error =
+            parse_with_limits(tokenize("let s = \"abcd\"").unwrap(), &limits()).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Can't parse a string literal longer than the limit of 3 chars"
+        );
+        assert_eq!(error.text_span, TextSpan::new(8, 14));
+    }
+
+    #[test]
+    fn test_limit_nesting() {
+        let limits = Limits {
+            max_nesting: 3,
+            ..Limits::default()
+        };
+        // Chains of operators don't nest.
+        for within in [
+            "((1))",
+            "[[[]]]",
+            "1 + 2 + 3 + 4 + 5",
+            "--1",
+            "a.b.c",
+            "a = b = 1",
+        ] {
+            assert!(
+                parse_with_limits(tokenize(within).unwrap(), &limits).is_ok(),
+                "{within}"
+            );
+        }
+        for (over, span) in [
+            ("(((1)))", (3, 4)),
+            ("[[[[]]]]", (3, 4)),
+            ("1 + (2 + (3 + (4)))", (15, 16)),
+            ("---1", (2, 3)),
+            ("a.b.c.d", (5, 6)),
+            ("a = b = c = 1", (10, 11)),
+            ("fn() { fn() { [1] } }", (15, 16)),
+        ] {
+            let error = parse_with_limits(tokenize(over).unwrap(), &limits).unwrap_err();
+            assert_eq!(
+                error.message,
+                "Can't parse an expression nested deeper than the limit of 3 levels"
+            );
+            assert_eq!(error.text_span, TextSpan::new(span.0, span.1), "{over}");
+        }
+    }
+
+    #[test]
+    fn test_limit_chain_length() {
+        let limits = Limits {
+            max_chain_length: 3,
+            ..Limits::default()
+        };
+        for within in ["1 + 2 * 3 - 4", "a && b || c ?? d", "(1 + 2) + (3 + 4)"] {
+            assert!(
+                parse_with_limits(tokenize(within).unwrap(), &limits).is_ok(),
+                "{within}"
+            );
+        }
+        // The operators of all levels count, as do the ones around nested expressions.
+        for (over, span) in [
+            ("1 + 2 + 3 + 4 + 5", (14, 15)),
+            ("1 < 2 == 3 < 4 && 5", (15, 17)),
+            ("1 + 2 * (3 + 4 + 5)", (15, 16)),
+        ] {
+            let error = parse_with_limits(tokenize(over).unwrap(), &limits).unwrap_err();
+            assert_eq!(
+                error.message,
+                "Can't parse an expression chaining more than the limit of 3 operators"
+            );
+            assert_eq!(error.text_span, TextSpan::new(span.0, span.1), "{over}");
+        }
+
+        let operands = vec!["1"; 500].join(" + ");
+        assert!(parse_with_limits(tokenize(&operands).unwrap(), &Limits::default()).is_ok());
+    }
+
+    #[test]
+    fn test_stack_size() {
+        let defaults = Limits::default();
+        let deeper = Limits {
+            max_call_depth: defaults.max_call_depth * 2,
+            ..Limits::default()
+        };
+        let nested = Limits {
+            max_nesting: defaults.max_nesting * 2,
+            ..Limits::default()
+        };
+        let chained = Limits {
+            max_chain_length: defaults.max_chain_length * 2,
+            ..Limits::default()
+        };
+        assert!(deeper.stack_size() > defaults.stack_size());
+        assert!(nested.stack_size() > defaults.stack_size());
+        assert!(chained.stack_size() > defaults.stack_size());
+        // Far more than the 8 MiB of a main thread.
+        assert!(defaults.stack_size() > 64 * 1024 * 1024);
+
+        let unbounded = Limits {
+            max_call_depth: usize::MAX,
+            max_nesting: usize::MAX,
+            ..Limits::default()
+        };
+        assert_eq!(unbounded.stack_size(), usize::MAX);
+    }
+}
@@ -0,0 +1,397 @@
+use std::collections::HashSet;
+
+use super::{
+    ast::{Expression, Program, Statement},
+    node::{walk_program, Node},
+    resolve::{resolve, ResolveErrorKind},
+    symbol::Symbol,
+    token::{Token, TokenKind},
+    utils::TextSpan,
+};
+
+pub const LINT_NAMES: [&str; 7] = [
+    "undefined-variable",
+    "const-reassign",
+    "use-before-declaration",
+    "shadowed-builtin",
+    "unused-empty-object",
+    "self-comparison",
+    "unused-expression",
+];
+
+#[derive(Debug, PartialEq)]
+pub struct Lint {
+    pub name: &'static str,
+    pub message: String,
+    pub text_span: TextSpan,
+}
+
+impl Lint {
+    pub fn report(&self, source_code: &str) {
+        let (line, column) = self.text_span.position(source_code);
+        eprintln!("[warning in line: {line}, column: {column}]");
+        eprintln!("Warning ({}): {}", self.name, self.message);
+    }
+}
+
+// Runs every lint but the allowed ones over a program, without evaluating it. The lints
+// are sorted by where they start in the source code.
+pub fn lint(program: &Program, builtins: &[String], allowed: &[String]) -> Vec<Lint> {
+    let mut linter = Linter {
+        builtins: builtins.iter().cloned().collect(),
+        referenced: HashSet::new(),
+        lints: vec![],
+    };
+    walk_program(program, true, &mut |node| {
+        if let Node::Expression(Expression::Identifier(i)) = node {
+            linter.referenced.insert(i.identifier.lexeme.clone());
+        }
+    });
+    walk_program(program, true, &mut |node| linter.check_node(node));
+    linter.check_names(program);
+
+    // The last statement of a program, function or block is its value, the ones of loop
+    // bodies are all dropped.
+    let mut bodies = vec![(program, true)];
+    walk_program(program, true, &mut |node| match node {
+        Node::Statement(Statement::Function(f)) => bodies.push((&f.function.body, true)),
+        Node::Statement(Statement::While(w)) => bodies.push((&w.body, false)),
+        Node::Statement(Statement::For(f)) => bodies.push((&f.body, false)),
+        Node::Expression(Expression::Function(f)) => bodies.push((&f.body, true)),
+        Node::Expression(Expression::Block(b)) => bodies.push((&b.statements, true)),
+        _ => {}
+    });
+    for (body, ends_with_value) in bodies {
+        linter.check_unused_expressions(body, ends_with_value);
+    }
+
+    let mut lints: Vec<Lint> = linter
+        .lints
+        .into_iter()
+        .filter(|lint| !allowed.iter().any(|name| name == lint.name))
+        .collect();
+    lints.sort_by_key(|lint| lint.text_span.starting_index);
+    lints
+}
+
+struct Linter {
+    builtins: HashSet<String>,
+    // The names read anywhere in the program.
+    referenced: HashSet<Symbol>,
+    lints: Vec<Lint>,
+}
+
+impl Linter {
+    fn report(&mut self, name: &'static str, message: String, text_span: TextSpan) {
+        self.lints.push(Lint {
+            name,
+            message,
+            text_span,
+        });
+    }
+
+    fn check_node(&mut self, node: Node) {
+        match node {
+            Node::Statement(Statement::Let(l)) => {
+                self.check_declaration(&l.identifier);
+                self.check_empty_object(&l.identifier, &l.expression);
+            }
+            Node::Statement(Statement::Const(c)) => {
+                self.check_declaration(&c.identifier);
+                self.check_empty_object(&c.identifier, &c.expression);
+            }
+            Node::Statement(Statement::Destructuring(d)) => {
+                for identifier in &d.identifiers {
+                    self.check_declaration(identifier);
+                }
+            }
+            Node::Statement(Statement::Function(f)) => {
+                self.check_declaration(&f.identifier);
+                for parameter in &f.function.parameters {
+                    self.check_declaration(parameter);
+                }
+            }
+            Node::Statement(Statement::For(f)) => self.check_declaration(&f.identifier),
+            Node::Expression(Expression::Function(f)) => {
+                for parameter in &f.parameters {
+                    self.check_declaration(parameter);
+                }
+            }
+            Node::Expression(Expression::Binary(b)) => {
+                let is_comparison = matches!(
+                    b.operator.kind,
+                    TokenKind::DoubleEqual
+                        | TokenKind::BangEqual
+                        | TokenKind::TripleEqual
+                        | TokenKind::BangDoubleEqual
+                        | TokenKind::Greater
+                        | TokenKind::Lesser
+                        | TokenKind::GreaterOrEqual
+                        | TokenKind::LesserOrEqual
+                );
+                match (reference(&b.left), reference(&b.right)) {
+                    (Some(left), Some(right)) if is_comparison && left == right => {
+                        self.report(
+                            "self-comparison",
+                            format!("'{left}' is compared with itself"),
+                            b.text_span(),
+                        );
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Reports the expression statements that do nothing but give a value nobody uses.
+    fn check_unused_expressions(&mut self, body: &Program, ends_with_value: bool) {
+        let dropped = if ends_with_value {
+            body.len().saturating_sub(1)
+        } else {
+            body.len()
+        };
+        for statement in &body[..dropped] {
+            let Statement::Expression(expression) = statement else {
+                continue;
+            };
+            if !is_pure(expression) {
+                continue;
+            }
+            let message = match expression {
+                Expression::Binary(b) if b.operator.kind == TokenKind::DoubleEqual => format!(
+                    "'{}' has no effect as its value is unused, did you mean '=' instead of '=='?",
+                    expression.to_source()
+                ),
+                _ => format!(
+                    "'{}' has no effect as its value is unused",
+                    expression.to_source()
+                ),
+            };
+            self.report("unused-expression", message, statement.text_span());
+        }
+    }
+
+    fn check_declaration(&mut self, identifier: &Token) {
+        if self.builtins.contains(&*identifier.lexeme) {
+            self.report(
+                "shadowed-builtin",
+                format!(
+                    "'{}' shadows the builtin of the same name",
+                    identifier.lexeme
+                ),
+                identifier.text_span.clone(),
+            );
+        }
+    }
+
+    fn check_empty_object(&mut self, identifier: &Token, expression: &Expression) {
+        if let Expression::Object(o) = expression {
+            if o.pairs.is_empty() && !self.referenced.contains(&identifier.lexeme) {
+                self.report(
+                    "unused-empty-object",
+                    format!(
+                        "'{}' is bound to an empty object but never used",
+                        identifier.lexeme
+                    ),
+                    identifier.text_span.clone(),
+                );
+            }
+        }
+    }
+
+    // Reports the names the resolver finds would fail once the code using them runs.
+    // Builtins are constants like for the evaluator.
+    fn check_names(&mut self, program: &Program) {
+        let globals: Vec<(String, bool)> = self
+            .builtins
+            .iter()
+            .map(|name| (name.clone(), true))
+            .collect();
+        for error in resolve(program, &globals) {
+            let name = &error.name;
+            let (lint, message) = match error.kind {
+                ResolveErrorKind::UndefinedVariable => {
+                    ("undefined-variable", format!("'{name}' isn't defined"))
+                }
+                ResolveErrorKind::ConstantAssignment => (
+                    "const-reassign",
+                    format!("'{name}' is declared as a constant, assigning to it will fail"),
+                ),
+                ResolveErrorKind::UseBeforeDeclaration => (
+                    "use-before-declaration",
+                    format!("'{name}' is used before it's declared"),
+                ),
+            };
+            self.report(lint, message, error.text_span);
+        }
+    }
+}
+
+// The name of the variable or property an expression refers to, like `a` or `a.b`.
+fn reference(expression: &Expression) -> Option<String> {
+    match expression {
+        Expression::Identifier(i) => Some(i.identifier.lexeme.to_string()),
+        Expression::Access(a) => {
+            reference(&a.object).map(|object| format!("{object}.{}", a.property.lexeme))
+        }
+        _ => None,
+    }
+}
+
+// Whether evaluating the expression does nothing but give a value. Operators count as
+// pure even though objects can hook into them, which is rare enough not to matter here.
+fn is_pure(expression: &Expression) -> bool {
+    match expression {
+        Expression::None(_)
+        | Expression::Boolean(_)
+        | Expression::Numeric(_)
+        | Expression::String(_)
+        | Expression::Identifier(_) => true,
+        Expression::Access(a) => is_pure(&a.object),
+        Expression::Index(i) => is_pure(&i.object) && is_pure(&i.index),
+        Expression::Unary(u) => is_pure(&u.right),
+        Expression::Binary(b) => is_pure(&b.left) && is_pure(&b.right),
+        Expression::Conditional(c) => {
+            is_pure(&c.condition) && is_pure(&c.consequent) && is_pure(&c.alternative)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frontend::{parser::parse, tokenizer::tokenize, utils::TextSpan};
+
+    use super::lint;
+
+    fn lints(src: &str, allowed: &[&str]) -> Vec<(&'static str, String, TextSpan)> {
+        let builtins = vec!["len".to_string(), "set".to_string()];
+        let allowed: Vec<String> = allowed.iter().map(|name| name.to_string()).collect();
+        lint(&parse(tokenize(src).unwrap()).unwrap(), &builtins, &allowed)
+            .into_iter()
+            .map(|lint| (lint.name, lint.message, lint.text_span))
+            .collect()
+    }
+
+    #[test]
+    fn test_lint_clean_program() {
+        let src = "
+const limit = 10
+let count = 0
+fn bump(by) { count = count + by total }
+let total = {}
+bump(limit)
+";
+        assert_eq!(lints(src, &[]), vec![]);
+    }
+
+    #[test]
+    fn test_lint_each_lint() {
+        assert_eq!(
+            lints("const a = 1 let f = fn() { a = 2 }", &[]),
+            vec![(
+                "const-reassign",
+                "'a' is declared as a constant, assigning to it will fail".to_string(),
+                TextSpan::new(27, 32)
+            )]
+        );
+        // A parameter of the same name isn't the constant.
+        assert_eq!(lints("const a = 1 fn f(a) { a = 2 }", &[]), vec![]);
+        assert_eq!(
+            lints("fn f() { total + len(\"a\") }", &[]),
+            vec![(
+                "undefined-variable",
+                "'total' isn't defined".to_string(),
+                TextSpan::new(9, 14)
+            )]
+        );
+        assert_eq!(
+            lints("let b = a + 1 let a = 1", &[]),
+            vec![(
+                "use-before-declaration",
+                "'a' is used before it's declared".to_string(),
+                TextSpan::new(8, 9)
+            )]
+        );
+        assert_eq!(
+            lints("let len = 1 fn f(set) { set }", &[]),
+            vec![
+                (
+                    "shadowed-builtin",
+                    "'len' shadows the builtin of the same name".to_string(),
+                    TextSpan::new(4, 7)
+                ),
+                (
+                    "shadowed-builtin",
+                    "'set' shadows the builtin of the same name".to_string(),
+                    TextSpan::new(17, 20)
+                ),
+            ]
+        );
+        assert_eq!(
+            lints("let cache = {} let used = {} used", &[]),
+            vec![(
+                "unused-empty-object",
+                "'cache' is bound to an empty object but never used".to_string(),
+                TextSpan::new(4, 9)
+            )]
+        );
+        assert_eq!(
+            lints("let o = {a: 1} o.a == o.a", &[]),
+            vec![(
+                "self-comparison",
+                "'o.a' is compared with itself".to_string(),
+                TextSpan::new(15, 25)
+            )]
+        );
+    }
+
+    #[test]
+    fn test_lint_unused_expression() {
+        assert_eq!(
+            lints("let name = \"a\" name == \"x\" 1 + 2 name.length name", &[]),
+            vec![
+                (
+                    "unused-expression",
+                    "'name == \"x\"' has no effect as its value is unused, did you mean '=' instead of '=='?"
+                        .to_string(),
+                    TextSpan::new(15, 26)
+                ),
+                (
+                    "unused-expression",
+                    "'1 + 2' has no effect as its value is unused".to_string(),
+                    TextSpan::new(27, 32)
+                ),
+                (
+                    "unused-expression",
+                    "'name.length' has no effect as its value is unused".to_string(),
+                    TextSpan::new(33, 44)
+                ),
+            ]
+        );
+
+        // The last statement of a program, function or block is its value, while calls and
+        // assignments do something.
+        let src = "let a = 1 fn f() { a } let b = { a = 2 f() a + 1 } b";
+        assert_eq!(lints(src, &[]), vec![]);
+        let spans: Vec<TextSpan> = lints("let a = 1 while false { a } fn f() { a a }", &[])
+            .into_iter()
+            .map(|(_, _, text_span)| text_span)
+            .collect();
+        assert_eq!(spans, vec![TextSpan::new(24, 25), TextSpan::new(37, 38)]);
+    }
+
+    #[test]
+    fn test_lint_allowed() {
+        let src = "let len = {} len != len";
+        assert_eq!(lints(src, &[]).len(), 2);
+        assert_eq!(
+            lints(src, &["shadowed-builtin"])
+                .into_iter()
+                .map(|(name, _, _)| name)
+                .collect::<Vec<_>>(),
+            vec!["self-comparison"]
+        );
+    }
+}
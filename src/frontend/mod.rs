@@ -1,5 +1,14 @@
 pub mod ast;
+pub mod dot;
+pub mod limits;
+pub mod lint;
+pub mod node;
 pub mod parser;
+pub mod progress;
+pub mod resolve;
+pub mod source;
+pub mod symbol;
 pub mod token;
 pub mod tokenizer;
+pub mod typecheck;
 pub mod utils;
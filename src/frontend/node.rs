@@ -0,0 +1,289 @@
+use super::{
+    ast::{Expression, Program, Statement},
+    utils::TextSpan,
+};
+
+// Identifies a node of a program by its position in a walk of the program, so the same
+// source code always gets the same identifiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(pub usize);
+
+#[derive(Debug, Clone, Copy)]
+pub enum Node<'a> {
+    Statement(&'a Statement),
+    Expression(&'a Expression),
+}
+
+// Calls `visit` on every statement and expression of a program, parents before their
+// children. Function bodies are only walked into when `into_functions` is set.
+pub fn walk_program<'a>(
+    program: &'a Program,
+    into_functions: bool,
+    visit: &mut impl FnMut(Node<'a>),
+) {
+    for statement in program {
+        walk_statement(statement, into_functions, visit);
+    }
+}
+
+pub fn walk_statement<'a>(
+    statement: &'a Statement,
+    into_functions: bool,
+    visit: &mut impl FnMut(Node<'a>),
+) {
+    walk_node(Node::Statement(statement), into_functions, visit);
+}
+
+fn walk_node<'a>(node: Node<'a>, into_functions: bool, visit: &mut impl FnMut(Node<'a>)) {
+    visit(node);
+    for child in node.children(into_functions) {
+        walk_node(child, into_functions, visit);
+    }
+}
+
+impl<'a> Node<'a> {
+    // The nodes directly under this one, in source order. The statements of a function
+    // body are only included when `into_functions` is set.
+    pub fn children(&self, into_functions: bool) -> Vec<Node<'a>> {
+        let expressions: Vec<&'a Expression> = match *self {
+            Node::Statement(Statement::Let(l)) => vec![&l.expression],
+            Node::Statement(Statement::Const(c)) => vec![&c.expression],
+            Node::Statement(Statement::Destructuring(d)) => vec![&d.expression],
+            Node::Statement(Statement::Function(f)) => {
+                return body(&f.function.body, into_functions)
+            }
+            // Unlike the body of a function, the body of a loop runs where it's written.
+            Node::Statement(Statement::While(w)) => {
+                return std::iter::once(Node::Expression(&w.condition))
+                    .chain(w.body.iter().map(Node::Statement))
+                    .collect()
+            }
+            Node::Statement(Statement::For(f)) => {
+                return std::iter::once(Node::Expression(&f.iterable))
+                    .chain(f.body.iter().map(Node::Statement))
+                    .collect()
+            }
+            Node::Statement(Statement::Expression(e)) => vec![e],
+            Node::Expression(Expression::Block(b)) => {
+                return b.statements.iter().map(Node::Statement).collect()
+            }
+            Node::Expression(Expression::Object(o)) => {
+                o.pairs.iter().map(|pair| &pair.value).collect()
+            }
+            Node::Expression(Expression::Tuple(t)) => t.elements.iter().collect(),
+            Node::Expression(Expression::Array(a)) => a.elements.iter().collect(),
+            Node::Expression(Expression::Access(a)) => vec![&a.object],
+            Node::Expression(Expression::Index(i)) => vec![&i.object, &i.index],
+            Node::Expression(Expression::Unary(u)) => vec![&u.right],
+            Node::Expression(Expression::Binary(b)) => vec![&b.left, &b.right],
+            Node::Expression(Expression::Conditional(c)) => {
+                vec![&c.condition, &c.consequent, &c.alternative]
+            }
+            // A plain variable is only written, while a path reads the variable it starts
+            // from and the indexes along it.
+            Node::Expression(Expression::Assignment(a)) => match *a.target {
+                Expression::Identifier(_) => vec![&a.expression],
+                _ => vec![&a.target, &a.expression],
+            },
+            Node::Expression(Expression::Function(f)) => return body(&f.body, into_functions),
+            Node::Expression(Expression::Call(c)) => std::iter::once(c.callee.as_ref())
+                .chain(c.arguments.iter())
+                .collect(),
+            Node::Expression(
+                Expression::None(_)
+                | Expression::Boolean(_)
+                | Expression::Numeric(_)
+                | Expression::String(_)
+                | Expression::Identifier(_),
+            ) => vec![],
+        };
+        expressions.into_iter().map(Node::Expression).collect()
+    }
+
+    pub fn text_span(&self) -> TextSpan {
+        match self {
+            Node::Statement(statement) => statement.text_span(),
+            Node::Expression(expression) => expression.text_span(),
+        }
+    }
+}
+
+fn body(program: &Program, into_functions: bool) -> Vec<Node<'_>> {
+    if into_functions {
+        program.iter().map(Node::Statement).collect()
+    } else {
+        vec![]
+    }
+}
+
+// Every node of a program, function bodies included, in the order their identifiers
+// are assigned.
+pub fn nodes(program: &Program) -> Vec<Node<'_>> {
+    let mut nodes = vec![];
+    walk_program(program, true, &mut |node| nodes.push(node));
+    nodes
+}
+
+// Lookups for tooling, like an editor asking what is under the cursor. Identifiers are
+// positions in the walk, so they only hold for the program they were taken from: an
+// edit can shift them all, and they're to be looked up again in the program parsed anew.
+pub trait NodeLookup {
+    // The innermost node whose span contains the offset, counted in chars.
+    fn node_at(&self, offset: usize) -> Option<(Node<'_>, NodeId)>;
+
+    fn node_span(&self, id: NodeId) -> Option<TextSpan>;
+}
+
+// The nodes of a program walked once, for any number of lookups in it.
+pub struct NodeIndex<'a> {
+    nodes: Vec<Node<'a>>,
+}
+
+impl<'a> NodeIndex<'a> {
+    pub fn new(program: &'a Program) -> Self {
+        Self {
+            nodes: nodes(program),
+        }
+    }
+}
+
+impl NodeLookup for NodeIndex<'_> {
+    fn node_at(&self, offset: usize) -> Option<(Node<'_>, NodeId)> {
+        innermost(&self.nodes, offset)
+    }
+
+    fn node_span(&self, id: NodeId) -> Option<TextSpan> {
+        self.nodes.get(id.0).map(|node| node.text_span())
+    }
+}
+
+// Walks the program for every lookup, which a single one is no worse off for.
+impl NodeLookup for Program {
+    fn node_at(&self, offset: usize) -> Option<(Node<'_>, NodeId)> {
+        innermost(&nodes(self), offset)
+    }
+
+    fn node_span(&self, id: NodeId) -> Option<TextSpan> {
+        nodes(self).get(id.0).map(|node| node.text_span())
+    }
+}
+
+// Parents come before their children and siblings don't overlap, so the last node
+// containing the offset is the innermost one.
+fn innermost<'a>(nodes: &[Node<'a>], offset: usize) -> Option<(Node<'a>, NodeId)> {
+    nodes
+        .iter()
+        .enumerate()
+        .rev()
+        .find(|(_, node)| {
+            let text_span = node.text_span();
+            text_span.starting_index <= offset && offset < text_span.ending_index
+        })
+        .map(|(index, node)| (*node, NodeId(index)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frontend::{
+        ast::{Expression, Statement},
+        parser::parse,
+        tokenizer::tokenize,
+        utils::TextSpan,
+    };
+
+    use super::{Node, NodeId, NodeIndex, NodeLookup};
+
+    #[test]
+    fn test_node_at_nested_access() {
+        let program = parse(tokenize("let a = { b: 1 }\nlet d = (2 * a.b, 3)").unwrap()).unwrap();
+
+        // `a` in `a.b`.
+        match program.node_at(30) {
+            Some((Node::Expression(Expression::Identifier(i)), id)) => {
+                assert_eq!(&*i.identifier.lexeme, "a");
+                assert_eq!(program.node_span(id), Some(TextSpan::new(30, 31)));
+            }
+            node => panic!("Expected the identifier 'a', got {node:?}"),
+        }
+        // `b` is a property, so the innermost node is the whole `a.b`.
+        match program.node_at(32) {
+            Some((Node::Expression(Expression::Access(a)), id)) => {
+                assert_eq!(&*a.property.lexeme, "b");
+                assert_eq!(program.node_span(id), Some(TextSpan::new(30, 33)));
+            }
+            node => panic!("Expected the access 'a.b', got {node:?}"),
+        }
+        assert!(matches!(
+            program.node_at(28),
+            Some((Node::Expression(Expression::Binary(_)), _))
+        ));
+        assert!(matches!(
+            program.node_at(35),
+            Some((Node::Expression(Expression::Numeric(_)), _))
+        ));
+        // Asking twice gives the same identifier.
+        assert_eq!(
+            program.node_at(32).map(|(_, id)| id),
+            program.node_at(32).map(|(_, id)| id)
+        );
+    }
+
+    #[test]
+    fn test_node_at_statement_boundaries() {
+        let program = parse(
+            tokenize(
+                "let a = 1
+
+fn f(x) { x }",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        assert!(matches!(
+            program.node_at(0),
+            Some((Node::Statement(Statement::Let(_)), NodeId(0)))
+        ));
+        assert!(matches!(
+            program.node_at(8),
+            Some((Node::Expression(Expression::Numeric(_)), NodeId(1)))
+        ));
+        assert!(program.node_at(10).is_none());
+        assert!(matches!(
+            program.node_at(11),
+            Some((Node::Statement(Statement::Function(_)), NodeId(2)))
+        ));
+        // The body of a function is walked into.
+        match program.node_at(21) {
+            Some((Node::Expression(Expression::Identifier(i)), NodeId(4))) => {
+                assert_eq!(&*i.identifier.lexeme, "x")
+            }
+            node => panic!("Expected the identifier 'x', got {node:?}"),
+        }
+        assert_eq!(program.node_span(NodeId(2)), Some(TextSpan::new(11, 24)));
+        assert_eq!(program.node_span(NodeId(5)), None);
+        assert!(program.node_at(24).is_none());
+    }
+
+    #[test]
+    fn test_node_index() {
+        let source_code = "let a = [1, fn(x) { x + a[0] }]\nprint(a[1](2))";
+        let program = parse(tokenize(source_code).unwrap()).unwrap();
+        let index = NodeIndex::new(&program);
+
+        // The index gives the same nodes as looking them up in the program.
+        for offset in 0..=source_code.len() {
+            let found = index
+                .node_at(offset)
+                .map(|(node, id)| (node.text_span(), id));
+            let expected = program
+                .node_at(offset)
+                .map(|(node, id)| (node.text_span(), id));
+            assert_eq!(found, expected, "{offset}");
+            if let Some((text_span, id)) = found {
+                assert_eq!(index.node_span(id), Some(text_span));
+            }
+        }
+        assert_eq!(index.node_span(NodeId(usize::MAX)), None);
+    }
+}
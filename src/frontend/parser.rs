@@ -1,25 +1,108 @@
 use super::{
     ast::{
-        AccessExpression, AssignmentExpression, BinaryExpression, BooleanLiteralExpression,
-        ConstStatement, Expression, IdentifierExpression, KeyValuePair, LetStatement,
-        NoneLiteralExpression, NumericLiteralExpression, ObjectLiteralExpression, Program,
-        Statement, StringLiteralExpression, UnaryExpression,
+        assigned_variable, AccessExpression, ArrayLiteralExpression, AssignmentExpression,
+        BinaryExpression, BlockExpression, BooleanLiteralExpression, CallExpression,
+        ConditionalExpression, ConstStatement, DestructuringStatement, Expression, ForStatement,
+        FunctionExpression, FunctionStatement, IdentifierExpression, IndexExpression, KeyValuePair,
+        LetStatement, NoneLiteralExpression, NumericLiteralExpression, NumericValue,
+        ObjectLiteralExpression, Program, Statement, StringLiteralExpression,
+        TupleLiteralExpression, UnaryExpression, WhileStatement, TYPE_NAMES,
     },
+    limits::{check_tokens, with_limits, Chain, Limits, Nesting},
+    progress::Control,
     token::{Token, TokenKind},
-    utils::Error,
+    tokenizer::tokenize,
+    utils::{Error, TextSpan},
 };
 
+#[derive(Debug)]
+pub enum PartialParse {
+    Complete(Program),
+    // The source code could still be completed by what comes after it, e.g. `let a = {`.
+    Incomplete,
+    Err(Error),
+}
+
+// Parses source code that may be the start of a longer input, as typed in the repl. An
+// error is only taken for the input being incomplete when it's about running out of
+// input: the parser met the end of it, or a string runs up to it.
+pub fn parse_partial(source_code: &str) -> PartialParse {
+    let length = source_code.chars().count();
+    let tokens = match tokenize(source_code) {
+        Ok(tokens) => tokens,
+        Err(error) if error.text_span.ending_index >= length => return PartialParse::Incomplete,
+        Err(error) => return PartialParse::Err(error),
+    };
+    match parse(tokens) {
+        Ok(program) => PartialParse::Complete(program),
+        Err(error) if error.text_span.starting_index >= length => PartialParse::Incomplete,
+        Err(error) => PartialParse::Err(error),
+    }
+}
+
 pub fn parse(tokens: Vec<Token>) -> Result<Program, Error> {
+    parse_with_limits(tokens, &Limits::default())
+}
+
+pub fn parse_with_limits(tokens: Vec<Token>, limits: &Limits) -> Result<Program, Error> {
+    parse_with_progress(tokens, limits, None)
+}
+
+// Like `parse_with_limits`, calling `on_progress` after every top-level statement. The
+// parse stops with an error when it asks to cancel.
+pub fn parse_with_progress(
+    mut tokens: Vec<Token>,
+    limits: &Limits,
+    on_progress: Option<&dyn Fn(usize, usize) -> Control>,
+) -> Result<Program, Error> {
+    // Tokens from elsewhere than the tokenizer may not end with one, while `peek` needs
+    // a last token to stand for the end of input.
+    if tokens.last().map(|token| &token.kind) != Some(&TokenKind::Eof) {
+        let end = tokens
+            .last()
+            .map_or(0, |token| token.text_span.ending_index);
+        tokens.push(Token::new(
+            TokenKind::Eof,
+            "\0".to_string(),
+            TextSpan::new(end, end + 1),
+        ));
+    }
+    check_tokens(&tokens, limits)?;
+    with_limits(limits, || parse_program(&tokens, limits, on_progress))
+}
+
+fn parse_program(
+    tokens: &[Token],
+    limits: &Limits,
+    on_progress: Option<&dyn Fn(usize, usize) -> Control>,
+) -> Result<Program, Error> {
     let mut program = vec![];
     let mut current_token_index = 0;
 
     while current_token_index < tokens.len() {
-        if tokens[current_token_index].kind == TokenKind::Eof {
+        if peek(tokens, current_token_index).kind == TokenKind::Eof {
             break;
         }
-        let (statement, consumed_until) = parse_statement(&tokens, current_token_index)?;
+        if program.len() == limits.max_statements {
+            return Err(Error::new(
+                format!(
+                    "Can't parse a program of more than the limit of {} statements",
+                    limits.max_statements
+                ),
+                peek(tokens, current_token_index).text_span.clone(),
+            ));
+        }
+        let (statement, consumed_until) = parse_statement(tokens, current_token_index)?;
         current_token_index = consumed_until;
         program.push(statement);
+        if let Some(on_progress) = on_progress {
+            if on_progress(current_token_index, tokens.len()) == Control::Cancel {
+                return Err(Error::new(
+                    "Parsing was interrupted".to_string(),
+                    peek(tokens, current_token_index).text_span.clone(),
+                ));
+            }
+        }
     }
 
     Ok(program)
@@ -29,9 +112,18 @@ fn parse_statement(
     tokens: &[Token],
     current_token_index: usize,
 ) -> Result<(Statement, usize), Error> {
-    match tokens[current_token_index].kind {
+    match peek(tokens, current_token_index).kind {
         TokenKind::Let => parse_let_statement(tokens, current_token_index),
         TokenKind::Const => parse_const_statement(tokens, current_token_index),
+        TokenKind::Fn
+            if tokens
+                .get(current_token_index + 1)
+                .is_some_and(|token| token.kind == TokenKind::Identifier) =>
+        {
+            parse_function_statement(tokens, current_token_index)
+        }
+        TokenKind::While => parse_while_statement(tokens, current_token_index),
+        TokenKind::For => parse_for_statement(tokens, current_token_index),
         _ => {
             let (expression, current_token_index) = parse_expression(tokens, current_token_index)?;
             Ok((Statement::Expression(expression), current_token_index))
@@ -45,12 +137,18 @@ fn parse_let_statement(
 ) -> Result<(Statement, usize), Error> {
     let (keyword, current_token_index) =
         expect_to_match(tokens, current_token_index, TokenKind::Let)?;
+    if peek(tokens, current_token_index).kind == TokenKind::OpenParen {
+        return parse_destructuring_statement(tokens, keyword, current_token_index);
+    }
     let (identifier, current_token_index) =
         expect_to_match(tokens, current_token_index, TokenKind::Identifier)?;
+    let (annotation, current_token_index) = parse_annotation(tokens, current_token_index)?;
     let (_, current_token_index) = expect_to_match(tokens, current_token_index, TokenKind::Equal)?;
     let (expression, current_token_index) = parse_expression(tokens, current_token_index)?;
     Ok((
-        Statement::Let(LetStatement::new(keyword, identifier, expression)),
+        Statement::Let(LetStatement::new(
+            keyword, identifier, annotation, expression,
+        )),
         current_token_index,
     ))
 }
@@ -61,12 +159,74 @@ fn parse_const_statement(
 ) -> Result<(Statement, usize), Error> {
     let (keyword, current_token_index) =
         expect_to_match(tokens, current_token_index, TokenKind::Const)?;
+    if peek(tokens, current_token_index).kind == TokenKind::OpenParen {
+        return parse_destructuring_statement(tokens, keyword, current_token_index);
+    }
     let (identifier, current_token_index) =
         expect_to_match(tokens, current_token_index, TokenKind::Identifier)?;
+    let (annotation, current_token_index) = parse_annotation(tokens, current_token_index)?;
+    let (_, current_token_index) = expect_to_match(tokens, current_token_index, TokenKind::Equal)?;
+    let (expression, current_token_index) = parse_expression(tokens, current_token_index)?;
+    Ok((
+        Statement::Const(ConstStatement::new(
+            keyword, identifier, annotation, expression,
+        )),
+        current_token_index,
+    ))
+}
+
+// An optional `: type` following the identifier of a binding.
+fn parse_annotation(
+    tokens: &[Token],
+    current_token_index: usize,
+) -> Result<(Option<Token>, usize), Error> {
+    if peek(tokens, current_token_index).kind != TokenKind::Colon {
+        return Ok((None, current_token_index));
+    }
+    let (annotation, current_token_index) = eat_token(tokens, current_token_index + 1);
+    if TYPE_NAMES.contains(&&*annotation.lexeme) {
+        Ok((Some(annotation), current_token_index))
+    } else {
+        Err(Error::new(
+            format!(
+                "Unknown type '{}', expected one of {}",
+                annotation.lexeme,
+                TYPE_NAMES.join(", ")
+            ),
+            annotation.text_span,
+        ))
+    }
+}
+
+fn parse_destructuring_statement(
+    tokens: &[Token],
+    keyword: Token,
+    current_token_index: usize,
+) -> Result<(Statement, usize), Error> {
+    let (_, mut current_token_index) =
+        expect_to_match(tokens, current_token_index, TokenKind::OpenParen)?;
+    let mut identifiers = vec![];
+    while peek(tokens, current_token_index).kind != TokenKind::CloseParen {
+        let (identifier, consumed_until) =
+            expect_to_match(tokens, current_token_index, TokenKind::Identifier)?;
+        identifiers.push(identifier);
+        current_token_index = consumed_until;
+        if peek(tokens, current_token_index).kind == TokenKind::CloseParen {
+            break;
+        }
+        let (_, consumed_until) = expect_to_match(tokens, current_token_index, TokenKind::Comma)?;
+        current_token_index = consumed_until;
+    }
+    let (_, current_token_index) =
+        expect_to_match(tokens, current_token_index, TokenKind::CloseParen)?;
     let (_, current_token_index) = expect_to_match(tokens, current_token_index, TokenKind::Equal)?;
     let (expression, current_token_index) = parse_expression(tokens, current_token_index)?;
     Ok((
-        Statement::Const(ConstStatement::new(keyword, identifier, expression)),
+        Statement::Destructuring(DestructuringStatement::new(
+            keyword,
+            identifiers,
+            expression,
+        )),
         current_token_index,
     ))
 }
@@ -75,6 +235,7 @@ fn parse_expression(
     tokens: &[Token],
     current_token_index: usize,
 ) -> Result<(Expression, usize), Error> {
+    let _nesting = Nesting::enter(peek(tokens, current_token_index))?;
     parse_assignment_expression(tokens, current_token_index)
 }
 
@@ -82,30 +243,86 @@ fn parse_assignment_expression(
     tokens: &[Token],
     current_token_index: usize,
 ) -> Result<(Expression, usize), Error> {
-    if tokens.get(current_token_index + 1).is_some()
-        && tokens[current_token_index].kind == TokenKind::Identifier
-        && tokens[current_token_index + 1].kind == TokenKind::Equal
-    {
-        let (identifier, current_token_index) =
-            expect_to_match(tokens, current_token_index, TokenKind::Identifier)?;
-        let (_, current_token_index) =
-            expect_to_match(tokens, current_token_index, TokenKind::Equal)?;
-        let (expression, current_token_index) =
-            parse_assignment_expression(tokens, current_token_index)?;
-        Ok((
-            Expression::Assignment(AssignmentExpression::new(identifier, expression)),
-            current_token_index,
-        ))
-    } else {
-        parse_binary_expression(tokens, current_token_index)
+    let (target, current_token_index) = parse_conditional_expression(tokens, current_token_index)?;
+    if peek(tokens, current_token_index).kind != TokenKind::Equal {
+        return Ok((target, current_token_index));
+    }
+    if assigned_variable(&target).is_none() {
+        return Err(Error::new(
+            format!(
+                "Can't assign to '{}' as it's not a variable, property or element",
+                target.to_source()
+            ),
+            target.text_span(),
+        ));
     }
+    let (equal, current_token_index) =
+        expect_to_match(tokens, current_token_index, TokenKind::Equal)?;
+    let _nesting = Nesting::enter(&equal)?;
+    let (expression, current_token_index) =
+        parse_assignment_expression(tokens, current_token_index)?;
+    Ok((
+        Expression::Assignment(AssignmentExpression::new(target, expression)),
+        current_token_index,
+    ))
 }
 
+// `condition ? consequent : alternative`, where the alternative may be another
+// conditional, so `a ? b : c ? d : e` is `a ? b : (c ? d : e)`.
+fn parse_conditional_expression(
+    tokens: &[Token],
+    current_token_index: usize,
+) -> Result<(Expression, usize), Error> {
+    let (condition, current_token_index) = parse_binary_expression(tokens, current_token_index)?;
+    if peek(tokens, current_token_index).kind != TokenKind::Question {
+        return Ok((condition, current_token_index));
+    }
+    let (question, current_token_index) =
+        expect_to_match(tokens, current_token_index, TokenKind::Question)?;
+    let _nesting = Nesting::enter(&question)?;
+    let (consequent, current_token_index) = parse_expression(tokens, current_token_index)?;
+    let (_, current_token_index) = expect_to_match(tokens, current_token_index, TokenKind::Colon)?;
+    let (alternative, current_token_index) =
+        parse_conditional_expression(tokens, current_token_index)?;
+    Ok((
+        Expression::Conditional(ConditionalExpression::new(
+            condition,
+            consequent,
+            alternative,
+        )),
+        current_token_index,
+    ))
+}
+
+// Each level folds its operators to the left, so `10 - 3 - 2` is `(10 - 3) - 2`, and
+// parses their operands at the level above. The operators count against the length of
+// the chain rather than the nesting, until the whole expression ends.
 fn parse_binary_expression(
     tokens: &[Token],
     current_token_index: usize,
 ) -> Result<(Expression, usize), Error> {
-    parse_binary_logical_expression(tokens, current_token_index)
+    let _chain = Chain::start();
+    parse_binary_coalescing_expression(tokens, current_token_index)
+}
+
+// `??` binds looser than `||`, so `a || b ?? c` is `(a || b) ?? c`.
+fn parse_binary_coalescing_expression(
+    tokens: &[Token],
+    current_token_index: usize,
+) -> Result<(Expression, usize), Error> {
+    let mut current_token_index = current_token_index;
+    let (mut left, consumed_until) = parse_binary_logical_expression(tokens, current_token_index)?;
+    current_token_index = consumed_until;
+    while peek(tokens, current_token_index).kind == TokenKind::DoubleQuestion {
+        let operator = peek(tokens, current_token_index).clone();
+        current_token_index += 1;
+        Chain::extend(&operator)?;
+        let (right, consumed_until) = parse_binary_logical_expression(tokens, current_token_index)?;
+        current_token_index = consumed_until;
+        left = Expression::Binary(BinaryExpression::new(left, operator, right));
+    }
+
+    Ok((left, current_token_index))
 }
 
 fn parse_binary_logical_expression(
@@ -116,12 +333,14 @@ fn parse_binary_logical_expression(
     let (mut left, consumed_until) = parse_binary_equality_expression(tokens, current_token_index)?;
     current_token_index = consumed_until;
     while token_matches(
-        &tokens[current_token_index].kind,
+        &peek(tokens, current_token_index).kind,
         &[TokenKind::DoubleAmpersand, TokenKind::DoublePipe],
     ) {
-        let operator = tokens[current_token_index].clone();
+        let operator = peek(tokens, current_token_index).clone();
         current_token_index += 1;
-        let (right, consumed_until) = parse_binary_logical_expression(tokens, current_token_index)?;
+        Chain::extend(&operator)?;
+        let (right, consumed_until) =
+            parse_binary_equality_expression(tokens, current_token_index)?;
         current_token_index = consumed_until;
         left = Expression::Binary(BinaryExpression::new(left, operator, right));
     }
@@ -138,13 +357,19 @@ fn parse_binary_equality_expression(
         parse_binary_comparison_expression(tokens, current_token_index)?;
     current_token_index = consumed_until;
     while token_matches(
-        &tokens[current_token_index].kind,
-        &[TokenKind::DoubleEqual, TokenKind::BangEqual],
+        &peek(tokens, current_token_index).kind,
+        &[
+            TokenKind::DoubleEqual,
+            TokenKind::BangEqual,
+            TokenKind::TripleEqual,
+            TokenKind::BangDoubleEqual,
+        ],
     ) {
-        let operator = tokens[current_token_index].clone();
+        let operator = peek(tokens, current_token_index).clone();
         current_token_index += 1;
+        Chain::extend(&operator)?;
         let (right, consumed_until) =
-            parse_binary_equality_expression(tokens, current_token_index)?;
+            parse_binary_comparison_expression(tokens, current_token_index)?;
         current_token_index = consumed_until;
         left = Expression::Binary(BinaryExpression::new(left, operator, right));
     }
@@ -160,7 +385,7 @@ fn parse_binary_comparison_expression(
     let (mut left, consumed_until) = parse_binary_additive_expression(tokens, current_token_index)?;
     current_token_index = consumed_until;
     while token_matches(
-        &tokens[current_token_index].kind,
+        &peek(tokens, current_token_index).kind,
         &[
             TokenKind::Greater,
             TokenKind::Lesser,
@@ -168,10 +393,11 @@ fn parse_binary_comparison_expression(
             TokenKind::LesserOrEqual,
         ],
     ) {
-        let operator = tokens[current_token_index].clone();
+        let operator = peek(tokens, current_token_index).clone();
         current_token_index += 1;
+        Chain::extend(&operator)?;
         let (right, consumed_until) =
-            parse_binary_comparison_expression(tokens, current_token_index)?;
+            parse_binary_additive_expression(tokens, current_token_index)?;
         current_token_index = consumed_until;
         left = Expression::Binary(BinaryExpression::new(left, operator, right));
     }
@@ -188,13 +414,14 @@ fn parse_binary_additive_expression(
         parse_binary_multiplicative_expression(tokens, current_token_index)?;
     current_token_index = consumed_until;
     while token_matches(
-        &tokens[current_token_index].kind,
+        &peek(tokens, current_token_index).kind,
         &[TokenKind::Plus, TokenKind::Minus],
     ) {
-        let operator = tokens[current_token_index].clone();
+        let operator = peek(tokens, current_token_index).clone();
         current_token_index += 1;
+        Chain::extend(&operator)?;
         let (right, consumed_until) =
-            parse_binary_additive_expression(tokens, current_token_index)?;
+            parse_binary_multiplicative_expression(tokens, current_token_index)?;
         current_token_index = consumed_until;
         left = Expression::Binary(BinaryExpression::new(left, operator, right));
     }
@@ -210,13 +437,13 @@ fn parse_binary_multiplicative_expression(
     let (mut left, consumed_until) = parse_unary_expression(tokens, current_token_index)?;
     current_token_index = consumed_until;
     while token_matches(
-        &tokens[current_token_index].kind,
+        &peek(tokens, current_token_index).kind,
         &[TokenKind::Asterisk, TokenKind::Slash],
     ) {
-        let operator = tokens[current_token_index].clone();
+        let operator = peek(tokens, current_token_index).clone();
         current_token_index += 1;
-        let (right, consumed_until) =
-            parse_binary_multiplicative_expression(tokens, current_token_index)?;
+        Chain::extend(&operator)?;
+        let (right, consumed_until) = parse_unary_expression(tokens, current_token_index)?;
         current_token_index = consumed_until;
         left = Expression::Binary(BinaryExpression::new(left, operator, right));
     }
@@ -229,130 +456,431 @@ fn parse_unary_expression(
     current_token_index: usize,
 ) -> Result<(Expression, usize), Error> {
     if token_matches(
-        &tokens[current_token_index].kind,
+        &peek(tokens, current_token_index).kind,
         &[TokenKind::Bang, TokenKind::Plus, TokenKind::Minus],
     ) {
         let (operator, current_token_index) = eat_token(tokens, current_token_index);
+        let _nesting = Nesting::enter(&operator)?;
         let (right, current_token_index) = parse_unary_expression(tokens, current_token_index)?;
         return Ok((
             Expression::Unary(UnaryExpression::new(operator, right)),
             current_token_index,
         ));
     }
-    parse_access_expression(tokens, current_token_index)
+    parse_postfix_expression(tokens, current_token_index)
 }
 
-fn parse_access_expression(
+// Calls, accesses and indexes all follow an expression and chain in any order, e.g.
+// `points[0].scale(2)`.
+fn parse_postfix_expression(
     tokens: &[Token],
     current_token_index: usize,
 ) -> Result<(Expression, usize), Error> {
-    let (object, current_token_index) = parse_primary_expression(tokens, current_token_index)?;
-    if tokens[current_token_index].kind == TokenKind::Dot {
-        let (_, current_token_index) =
-            expect_to_match(tokens, current_token_index, TokenKind::Dot)?;
-        let (property, current_token_index) =
-            expect_to_match(tokens, current_token_index, TokenKind::Identifier)?;
-        Ok((
-            Expression::Access(AccessExpression::new(object, property)),
-            current_token_index,
-        ))
-    } else {
-        Ok((object, current_token_index))
+    let (mut expression, mut current_token_index) =
+        parse_primary_expression(tokens, current_token_index)?;
+    // Each operation is a level deeper than the last, held until the chain ends.
+    let mut nesting = vec![];
+    loop {
+        if token_matches(
+            &peek(tokens, current_token_index).kind,
+            &[
+                TokenKind::OpenParen,
+                TokenKind::Dot,
+                TokenKind::QuestionDot,
+                TokenKind::OpenBracket,
+            ],
+        ) {
+            nesting.push(Nesting::enter(peek(tokens, current_token_index))?);
+        }
+        match peek(tokens, current_token_index).kind {
+            TokenKind::OpenParen => {
+                let (arguments, close_paren, consumed_until) =
+                    parse_arguments(tokens, current_token_index)?;
+                current_token_index = consumed_until;
+                expression =
+                    Expression::Call(CallExpression::new(expression, arguments, close_paren));
+            }
+            TokenKind::Dot | TokenKind::QuestionDot => {
+                let (dot, consumed_until) = eat_token(tokens, current_token_index);
+                let (property, consumed_until) =
+                    if peek(tokens, consumed_until).kind == TokenKind::Number {
+                        eat_token(tokens, consumed_until)
+                    } else {
+                        expect_word(tokens, consumed_until)?
+                    };
+                current_token_index = consumed_until;
+                expression = Expression::Access(AccessExpression::new(expression, dot, property));
+            }
+            TokenKind::OpenBracket => {
+                let (open_bracket, consumed_until) =
+                    expect_to_match(tokens, current_token_index, TokenKind::OpenBracket)?;
+                let (index, consumed_until) = parse_expression(tokens, consumed_until)?;
+                let (close_bracket, consumed_until) =
+                    expect_to_match(tokens, consumed_until, TokenKind::CloseBracket)?;
+                current_token_index = consumed_until;
+                expression = Expression::Index(IndexExpression::new(
+                    expression,
+                    open_bracket,
+                    index,
+                    close_bracket,
+                ));
+            }
+            _ => return Ok((expression, current_token_index)),
+        }
+    }
+}
+
+fn parse_arguments(
+    tokens: &[Token],
+    current_token_index: usize,
+) -> Result<(Vec<Expression>, Token, usize), Error> {
+    let (_, mut current_token_index) =
+        expect_to_match(tokens, current_token_index, TokenKind::OpenParen)?;
+    let mut arguments = vec![];
+    while peek(tokens, current_token_index).kind != TokenKind::CloseParen {
+        let (argument, consumed_until) = parse_expression(tokens, current_token_index)?;
+        arguments.push(argument);
+        current_token_index = consumed_until;
+        if peek(tokens, current_token_index).kind == TokenKind::CloseParen {
+            break;
+        }
+        let (_, consumed_until) = expect_to_match(tokens, current_token_index, TokenKind::Comma)?;
+        current_token_index = consumed_until;
     }
+    let (close_paren, consumed_until) =
+        expect_to_match(tokens, current_token_index, TokenKind::CloseParen)?;
+    Ok((arguments, close_paren, consumed_until))
 }
 
 fn parse_primary_expression(
     tokens: &[Token],
     current_token_index: usize,
 ) -> Result<(Expression, usize), Error> {
-    match tokens[current_token_index].kind {
-        TokenKind::OpenParen => {
-            let (_, current_token_index) =
-                expect_to_match(tokens, current_token_index, TokenKind::OpenParen)?;
-            let (expression, current_token_index) = parse_expression(tokens, current_token_index)?;
-            let (_, current_token_index) =
-                expect_to_match(tokens, current_token_index, TokenKind::CloseParen)?;
-            Ok((expression, current_token_index))
-        }
+    match peek(tokens, current_token_index).kind {
+        TokenKind::OpenParen => parse_parenthesized_expression(tokens, current_token_index),
         TokenKind::None => Ok((
             Expression::None(NoneLiteralExpression::new(
-                tokens[current_token_index].clone(),
+                peek(tokens, current_token_index).clone(),
             )),
             current_token_index + 1,
         )),
         TokenKind::True => Ok((
             Expression::Boolean(BooleanLiteralExpression::new(
-                tokens[current_token_index].clone(),
+                peek(tokens, current_token_index).clone(),
                 true,
             )),
             current_token_index + 1,
         )),
         TokenKind::False => Ok((
             Expression::Boolean(BooleanLiteralExpression::new(
-                tokens[current_token_index].clone(),
+                peek(tokens, current_token_index).clone(),
                 false,
             )),
             current_token_index + 1,
         )),
-        TokenKind::Number => Ok((
-            Expression::Numeric(NumericLiteralExpression::new(
-                tokens[current_token_index].clone(),
-                tokens[current_token_index].lexeme.parse().unwrap(),
-            )),
-            current_token_index + 1,
-        )),
+        TokenKind::Number => {
+            let number = peek(tokens, current_token_index);
+            let value = if number.lexeme.contains('.') {
+                number
+                    .lexeme
+                    .parse()
+                    .map(NumericValue::Float)
+                    .map_err(|_| {
+                        Error::new(
+                            format!("Can't parse '{}' as a number", number.lexeme),
+                            number.text_span.clone(),
+                        )
+                    })?
+            } else {
+                number
+                    .lexeme
+                    .parse()
+                    .map(NumericValue::Integer)
+                    .map_err(|_| {
+                        Error::new(
+                            format!(
+                                "Can't parse '{}' as an integer as it's too large",
+                                number.lexeme
+                            ),
+                            number.text_span.clone(),
+                        )
+                        .with_note(format!(
+                            "'{}.0', with a decimal point, is a float",
+                            number.lexeme
+                        ))
+                    })?
+            };
+            Ok((
+                Expression::Numeric(NumericLiteralExpression::new(number.clone(), value)),
+                current_token_index + 1,
+            ))
+        }
         TokenKind::String => Ok((
-            Expression::String(StringLiteralExpression::new(
-                tokens[current_token_index].clone(),
-                tokens[current_token_index].lexeme.parse().unwrap(),
+            Expression::String(StringLiteralExpression::unescaped(
+                peek(tokens, current_token_index).clone(),
             )),
             current_token_index + 1,
         )),
-        TokenKind::OpenBrace => {
-            let (open_brace, current_token_index) =
-                expect_to_match(tokens, current_token_index, TokenKind::OpenBrace)?;
-            let mut pairs = vec![];
-            let mut global_current_token_index = current_token_index;
-            while tokens[current_token_index].kind != TokenKind::CloseBrace {
-                let current_token_index = global_current_token_index;
-                let (pair, current_token_index) =
-                    parse_key_value_pair(tokens, current_token_index)?;
-                pairs.push(pair);
-                if tokens[current_token_index].kind == TokenKind::CloseBrace {
-                    global_current_token_index = current_token_index;
-                    break;
-                }
-                let (_, current_token_index) =
-                    expect_to_match(tokens, current_token_index, TokenKind::Comma)?;
-                global_current_token_index = current_token_index;
-            }
-            let (close_brace, current_token_index) =
-                expect_to_match(tokens, global_current_token_index, TokenKind::CloseBrace)?;
-            Ok((
-                Expression::Object(ObjectLiteralExpression::new(open_brace, pairs, close_brace)),
-                current_token_index,
-            ))
+        TokenKind::OpenBrace if starts_object(tokens, current_token_index) => {
+            parse_object_literal_expression(tokens, current_token_index)
         }
+        TokenKind::OpenBrace => parse_block_expression(tokens, current_token_index),
+        TokenKind::OpenBracket => parse_array_literal_expression(tokens, current_token_index),
         TokenKind::Identifier => Ok((
             Expression::Identifier(IdentifierExpression::new(
-                tokens[current_token_index].clone(),
+                peek(tokens, current_token_index).clone(),
             )),
             current_token_index + 1,
         )),
+        TokenKind::Fn => parse_function_expression(tokens, current_token_index),
 
-        _ => Err(Error::new(
-            format!("Unexpected token '{}'", tokens[current_token_index].lexeme),
-            tokens[current_token_index].text_span.clone(),
+        _ => Err(unexpected(peek(tokens, current_token_index), None)),
+    }
+}
+
+// A brace starts an object when it's followed by a key and a colon or directly closed,
+// e.g. `{a: 1}` or `{}`, and a block otherwise, e.g. `{ let a = 1 a }`, as no statement
+// starts with a key and a colon.
+pub fn starts_object(tokens: &[Token], open_brace_index: usize) -> bool {
+    match tokens.get(open_brace_index + 1) {
+        Some(token) if token.kind == TokenKind::CloseBrace => true,
+        Some(token) if token.is_word() => tokens
+            .get(open_brace_index + 2)
+            .is_some_and(|token| token.kind == TokenKind::Colon),
+        _ => false,
+    }
+}
+
+fn parse_object_literal_expression(
+    tokens: &[Token],
+    current_token_index: usize,
+) -> Result<(Expression, usize), Error> {
+    let (open_brace, current_token_index) =
+        expect_to_match(tokens, current_token_index, TokenKind::OpenBrace)?;
+    let mut pairs = vec![];
+    let mut global_current_token_index = current_token_index;
+    while peek(tokens, global_current_token_index).kind != TokenKind::CloseBrace {
+        let current_token_index = global_current_token_index;
+        let (pair, current_token_index) = parse_key_value_pair(tokens, current_token_index)?;
+        pairs.push(pair);
+        if peek(tokens, current_token_index).kind == TokenKind::CloseBrace {
+            global_current_token_index = current_token_index;
+            break;
+        }
+        let (_, current_token_index) =
+            expect_to_match(tokens, current_token_index, TokenKind::Comma)?;
+        global_current_token_index = current_token_index;
+    }
+    let (close_brace, current_token_index) =
+        expect_to_match(tokens, global_current_token_index, TokenKind::CloseBrace)?;
+    Ok((
+        Expression::Object(ObjectLiteralExpression::new(open_brace, pairs, close_brace)),
+        current_token_index,
+    ))
+}
+
+// The elements are separated by commas, and one may follow the last element too.
+fn parse_array_literal_expression(
+    tokens: &[Token],
+    current_token_index: usize,
+) -> Result<(Expression, usize), Error> {
+    let (open_bracket, mut current_token_index) =
+        expect_to_match(tokens, current_token_index, TokenKind::OpenBracket)?;
+    let mut elements = vec![];
+    while peek(tokens, current_token_index).kind != TokenKind::CloseBracket {
+        let (element, consumed_until) = parse_expression(tokens, current_token_index)?;
+        elements.push(element);
+        current_token_index = consumed_until;
+        if peek(tokens, current_token_index).kind == TokenKind::CloseBracket {
+            break;
+        }
+        let (_, consumed_until) = expect_to_match(tokens, current_token_index, TokenKind::Comma)?;
+        current_token_index = consumed_until;
+    }
+    let (close_bracket, current_token_index) =
+        expect_to_match(tokens, current_token_index, TokenKind::CloseBracket)?;
+    Ok((
+        Expression::Array(ArrayLiteralExpression::new(
+            open_bracket,
+            elements,
+            close_bracket,
         )),
+        current_token_index,
+    ))
+}
+
+fn parse_block_expression(
+    tokens: &[Token],
+    current_token_index: usize,
+) -> Result<(Expression, usize), Error> {
+    let open_brace = peek(tokens, current_token_index).clone();
+    let (statements, close_brace, current_token_index) = parse_block(tokens, current_token_index)?;
+    Ok((
+        Expression::Block(BlockExpression::new(open_brace, statements, close_brace)),
+        current_token_index,
+    ))
+}
+
+// A parenthesized expression is a plain grouping unless it contains a comma, which makes
+// it a tuple: `(1)` is 1 while `(1,)` and `(1, 2)` are tuples, and `()` is the empty tuple.
+fn parse_parenthesized_expression(
+    tokens: &[Token],
+    current_token_index: usize,
+) -> Result<(Expression, usize), Error> {
+    let (open_paren, current_token_index) =
+        expect_to_match(tokens, current_token_index, TokenKind::OpenParen)?;
+    if peek(tokens, current_token_index).kind == TokenKind::CloseParen {
+        let (close_paren, current_token_index) =
+            expect_to_match(tokens, current_token_index, TokenKind::CloseParen)?;
+        return Ok((
+            Expression::Tuple(TupleLiteralExpression::new(open_paren, vec![], close_paren)),
+            current_token_index,
+        ));
     }
+    let (expression, mut current_token_index) = parse_expression(tokens, current_token_index)?;
+    if peek(tokens, current_token_index).kind != TokenKind::Comma {
+        let (_, current_token_index) =
+            expect_to_match(tokens, current_token_index, TokenKind::CloseParen)?;
+        return Ok((expression, current_token_index));
+    }
+    let mut elements = vec![expression];
+    while peek(tokens, current_token_index).kind == TokenKind::Comma {
+        let (_, consumed_until) = expect_to_match(tokens, current_token_index, TokenKind::Comma)?;
+        current_token_index = consumed_until;
+        if peek(tokens, current_token_index).kind == TokenKind::CloseParen {
+            break;
+        }
+        let (element, consumed_until) = parse_expression(tokens, current_token_index)?;
+        elements.push(element);
+        current_token_index = consumed_until;
+    }
+    let (close_paren, current_token_index) =
+        expect_to_match(tokens, current_token_index, TokenKind::CloseParen)?;
+    Ok((
+        Expression::Tuple(TupleLiteralExpression::new(
+            open_paren,
+            elements,
+            close_paren,
+        )),
+        current_token_index,
+    ))
+}
+
+fn parse_function_statement(
+    tokens: &[Token],
+    current_token_index: usize,
+) -> Result<(Statement, usize), Error> {
+    let (keyword, current_token_index) =
+        expect_to_match(tokens, current_token_index, TokenKind::Fn)?;
+    let (identifier, current_token_index) =
+        expect_to_match(tokens, current_token_index, TokenKind::Identifier)?;
+    let (function, current_token_index) =
+        parse_function_signature_and_body(tokens, keyword, current_token_index)?;
+    Ok((
+        Statement::Function(FunctionStatement::new(identifier, function)),
+        current_token_index,
+    ))
+}
+
+fn parse_function_expression(
+    tokens: &[Token],
+    current_token_index: usize,
+) -> Result<(Expression, usize), Error> {
+    let (keyword, current_token_index) =
+        expect_to_match(tokens, current_token_index, TokenKind::Fn)?;
+    let (function, current_token_index) =
+        parse_function_signature_and_body(tokens, keyword, current_token_index)?;
+    Ok((Expression::Function(function), current_token_index))
+}
+
+fn parse_function_signature_and_body(
+    tokens: &[Token],
+    keyword: Token,
+    current_token_index: usize,
+) -> Result<(FunctionExpression, usize), Error> {
+    let (_, mut current_token_index) =
+        expect_to_match(tokens, current_token_index, TokenKind::OpenParen)?;
+    let mut parameters = vec![];
+    while peek(tokens, current_token_index).kind != TokenKind::CloseParen {
+        let (parameter, consumed_until) =
+            expect_to_match(tokens, current_token_index, TokenKind::Identifier)?;
+        parameters.push(parameter);
+        current_token_index = consumed_until;
+        if peek(tokens, current_token_index).kind == TokenKind::CloseParen {
+            break;
+        }
+        let (_, consumed_until) = expect_to_match(tokens, current_token_index, TokenKind::Comma)?;
+        current_token_index = consumed_until;
+    }
+    let (_, current_token_index) =
+        expect_to_match(tokens, current_token_index, TokenKind::CloseParen)?;
+    let (body, close_brace, current_token_index) = parse_block(tokens, current_token_index)?;
+    Ok((
+        FunctionExpression::new(keyword, parameters, body, close_brace),
+        current_token_index,
+    ))
+}
+
+fn parse_while_statement(
+    tokens: &[Token],
+    current_token_index: usize,
+) -> Result<(Statement, usize), Error> {
+    let (keyword, current_token_index) =
+        expect_to_match(tokens, current_token_index, TokenKind::While)?;
+    let (condition, current_token_index) = parse_expression(tokens, current_token_index)?;
+    let (body, close_brace, current_token_index) = parse_block(tokens, current_token_index)?;
+    Ok((
+        Statement::While(WhileStatement::new(keyword, condition, body, close_brace)),
+        current_token_index,
+    ))
+}
+
+fn parse_for_statement(
+    tokens: &[Token],
+    current_token_index: usize,
+) -> Result<(Statement, usize), Error> {
+    let (keyword, current_token_index) =
+        expect_to_match(tokens, current_token_index, TokenKind::For)?;
+    let (identifier, current_token_index) =
+        expect_to_match(tokens, current_token_index, TokenKind::Identifier)?;
+    let (_, current_token_index) = expect_to_match(tokens, current_token_index, TokenKind::In)?;
+    let (iterable, current_token_index) = parse_expression(tokens, current_token_index)?;
+    let (body, close_brace, current_token_index) = parse_block(tokens, current_token_index)?;
+    Ok((
+        Statement::For(ForStatement::new(
+            keyword,
+            identifier,
+            iterable,
+            body,
+            close_brace,
+        )),
+        current_token_index,
+    ))
+}
+
+// The statements between braces, as the body of a function or a loop.
+fn parse_block(
+    tokens: &[Token],
+    current_token_index: usize,
+) -> Result<(Program, Token, usize), Error> {
+    let (_, mut current_token_index) =
+        expect_to_match(tokens, current_token_index, TokenKind::OpenBrace)?;
+    let mut body = vec![];
+    while peek(tokens, current_token_index).kind != TokenKind::CloseBrace {
+        let (statement, consumed_until) = parse_statement(tokens, current_token_index)?;
+        body.push(statement);
+        current_token_index = consumed_until;
+    }
+    let (close_brace, current_token_index) =
+        expect_to_match(tokens, current_token_index, TokenKind::CloseBrace)?;
+    Ok((body, close_brace, current_token_index))
 }
 
 fn parse_key_value_pair(
     tokens: &[Token],
     current_token_index: usize,
 ) -> Result<(KeyValuePair, usize), Error> {
-    let (key, current_token_index) =
-        expect_to_match(tokens, current_token_index, TokenKind::Identifier)?;
+    let (key, current_token_index) = expect_word(tokens, current_token_index)?;
     let (_, current_token_index) = expect_to_match(tokens, current_token_index, TokenKind::Colon)?;
     let (value, current_token_index) = parse_expression(tokens, current_token_index)?;
     Ok((KeyValuePair::new(key, value), current_token_index))
@@ -367,21 +895,49 @@ fn expect_to_match(
     current_token_index: usize,
     expected: TokenKind,
 ) -> Result<(Token, usize), Error> {
-    if tokens[current_token_index].kind == expected {
-        Ok((tokens[current_token_index].clone(), current_token_index + 1))
+    let token = peek(tokens, current_token_index);
+    if token.kind == expected {
+        Ok((token.clone(), current_token_index + 1))
     } else {
-        Err(Error::new(
-            format!(
-                "Unexpected token '{}', expected '{}'",
-                tokens[current_token_index].lexeme, expected
-            ),
-            tokens[current_token_index].text_span.clone(),
-        ))
+        Err(unexpected(token, Some(expected)))
+    }
+}
+
+// The token at the index, or the `Eof` ending the tokens once the index is past them,
+// so running out of input is an error about its end rather than a panic.
+fn peek(tokens: &[Token], current_token_index: usize) -> &Token {
+    &tokens[current_token_index.min(tokens.len() - 1)]
+}
+
+// The error for a token that can't come where it is, pointing at the end of the source
+// code when the input ended too early.
+fn unexpected(token: &Token, expected: Option<TokenKind>) -> Error {
+    let found = match token.kind {
+        TokenKind::Eof => "Unexpected end of input".to_string(),
+        _ => format!("Unexpected token '{}'", token.lexeme),
+    };
+    let message = match expected {
+        Some(expected) => format!("{found}, expected '{expected}'"),
+        None => found,
+    };
+    Error::new(message, token.text_span.clone())
+}
+
+// Keys and properties can't be mistaken for anything else, so keywords are accepted as
+// names there and keep their meaning everywhere else.
+fn expect_word(tokens: &[Token], current_token_index: usize) -> Result<(Token, usize), Error> {
+    if peek(tokens, current_token_index).is_word() {
+        Ok(eat_token(tokens, current_token_index))
+    } else {
+        expect_to_match(tokens, current_token_index, TokenKind::Identifier)
     }
 }
 
 fn eat_token(tokens: &[Token], current_token_index: usize) -> (Token, usize) {
-    (tokens[current_token_index].clone(), current_token_index + 1)
+    (
+        peek(tokens, current_token_index).clone(),
+        current_token_index + 1,
+    )
 }
 
 #[cfg(test)]
@@ -389,14 +945,15 @@ mod tests {
     use crate::frontend::{
         ast::{
             AccessExpression, AssignmentExpression, BinaryExpression, BooleanLiteralExpression,
-            ConstStatement, Expression, IdentifierExpression, KeyValuePair, LetStatement,
-            NumericLiteralExpression, ObjectLiteralExpression, Statement, StringLiteralExpression,
-            UnaryExpression,
+            CallExpression, ConstStatement, DestructuringStatement, Expression, FunctionExpression,
+            IdentifierExpression, KeyValuePair, LetStatement, NumericLiteralExpression,
+            NumericValue, ObjectLiteralExpression, Statement, StringLiteralExpression,
+            StringSegment, TupleLiteralExpression, UnaryExpression,
         },
         parser::{
-            parse_access_expression, parse_assignment_expression, parse_binary_expression,
-            parse_const_statement, parse_key_value_pair, parse_let_statement,
-            parse_primary_expression, parse_unary_expression,
+            parse, parse_assignment_expression, parse_binary_expression, parse_const_statement,
+            parse_key_value_pair, parse_let_statement, parse_partial, parse_postfix_expression,
+            parse_primary_expression, parse_unary_expression, PartialParse,
         },
         token::{Token, TokenKind},
         tokenizer::tokenize,
@@ -410,9 +967,10 @@ mod tests {
             Statement::Let(LetStatement::new(
                 Token::new(TokenKind::Let, "let".to_string(), TextSpan::new(0, 3)),
                 Token::new(TokenKind::Identifier, "a".to_string(), TextSpan::new(4, 5)),
+                None,
                 Expression::Numeric(NumericLiteralExpression::new(
                     Token::new(TokenKind::Number, "2.5".to_string(), TextSpan::new(8, 11)),
-                    2.5,
+                    NumericValue::Float(2.5),
                 )),
             )),
             4,
@@ -423,19 +981,57 @@ mod tests {
     }
 
     #[test]
+    fn test_parse_annotated_let_statement() {
+        let source_code = "let count: number = 0";
+        let expected_output = (
+            Statement::Let(LetStatement::new(
+                Token::new(TokenKind::Let, "let".to_string(), TextSpan::new(0, 3)),
+                Token::new(
+                    TokenKind::Identifier,
+                    "count".to_string(),
+                    TextSpan::new(4, 9),
+                ),
+                Some(Token::new(
+                    TokenKind::Identifier,
+                    "number".to_string(),
+                    TextSpan::new(11, 17),
+                )),
+                Expression::Numeric(NumericLiteralExpression::new(
+                    Token::new(TokenKind::Number, "0".to_string(), TextSpan::new(20, 21)),
+                    NumericValue::Integer(0),
+                )),
+            )),
+            6,
+        );
+        let tokens = tokenize(source_code).unwrap();
+        let output = parse_let_statement(&tokens, 0).unwrap();
+        assert_eq!(expected_output, output);
+
+        let tokens = tokenize("let count: integer = 0").unwrap();
+        let error = parse_let_statement(&tokens, 0).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Unknown type 'integer', expected one of object, function, set, array, tuple, string, number, boolean, none"
+        );
+        assert_eq!(error.text_span, TextSpan::new(11, 18));
+    }
+
+    #[test]
+    #[allow(clippy::approx_constant)]
     fn test_parse_const_statement() {
         let source_code = "const PI = 3.14159";
         let expected_output = (
             Statement::Const(ConstStatement::new(
                 Token::new(TokenKind::Const, "const".to_string(), TextSpan::new(0, 5)),
                 Token::new(TokenKind::Identifier, "PI".to_string(), TextSpan::new(6, 8)),
+                None,
                 Expression::Numeric(NumericLiteralExpression::new(
                     Token::new(
                         TokenKind::Number,
                         "3.14159".to_string(),
                         TextSpan::new(11, 18),
                     ),
-                    3.14159,
+                    NumericValue::Float(3.14159),
                 )),
             )),
             4,
@@ -450,10 +1046,14 @@ mod tests {
         let source_code = "a = 2.5";
         let expected_output = (
             Expression::Assignment(AssignmentExpression::new(
-                Token::new(TokenKind::Identifier, "a".to_string(), TextSpan::new(0, 1)),
+                Expression::Identifier(IdentifierExpression::new(Token::new(
+                    TokenKind::Identifier,
+                    "a".to_string(),
+                    TextSpan::new(0, 1),
+                ))),
                 Expression::Numeric(NumericLiteralExpression::new(
                     Token::new(TokenKind::Number, "2.5".to_string(), TextSpan::new(4, 7)),
-                    2.5,
+                    NumericValue::Float(2.5),
                 )),
             )),
             3,
@@ -463,34 +1063,62 @@ mod tests {
         assert_eq!(expected_output, output);
     }
 
+    #[test]
+    fn test_parse_assignment_to_path() {
+        let program = parse(tokenize("a.b[0].c = d[1] = 2").unwrap()).unwrap();
+        match &program[..] {
+            [Statement::Expression(Expression::Assignment(a))] => {
+                assert!(matches!(*a.target, Expression::Access(_)));
+                assert_eq!(&*a.identifier().unwrap().lexeme, "a");
+                assert!(
+                    matches!(*a.expression, Expression::Assignment(ref inner) if matches!(*inner.target, Expression::Index(_)))
+                );
+                assert_eq!(a.text_span(), TextSpan::new(0, 19));
+            }
+            program => panic!("Expected an assignment, got {program:?}"),
+        }
+
+        let error = parse(tokenize("f().x = 1").unwrap()).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Can't assign to 'f().x' as it's not a variable, property or element"
+        );
+        assert_eq!(error.text_span, TextSpan::new(0, 5));
+        let error = parse(tokenize("a + b = 1").unwrap()).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Can't assign to 'a + b' as it's not a variable, property or element"
+        );
+    }
+
     #[test]
     fn test_parse_binary_logical_expression() {
         let source_code = "a&&b||c";
         let expected_output = (
             Expression::Binary(BinaryExpression::new(
-                Expression::Identifier(IdentifierExpression::new(Token::new(
-                    TokenKind::Identifier,
-                    "a".to_string(),
-                    TextSpan::new(0, 1),
-                ))),
-                Token::new(
-                    TokenKind::DoubleAmpersand,
-                    "&&".to_string(),
-                    TextSpan::new(1, 3),
-                ),
                 Expression::Binary(BinaryExpression::new(
                     Expression::Identifier(IdentifierExpression::new(Token::new(
                         TokenKind::Identifier,
-                        "b".to_string(),
-                        TextSpan::new(3, 4),
+                        "a".to_string(),
+                        TextSpan::new(0, 1),
                     ))),
-                    Token::new(TokenKind::DoublePipe, "||".to_string(), TextSpan::new(4, 6)),
+                    Token::new(
+                        TokenKind::DoubleAmpersand,
+                        "&&".to_string(),
+                        TextSpan::new(1, 3),
+                    ),
                     Expression::Identifier(IdentifierExpression::new(Token::new(
                         TokenKind::Identifier,
-                        "c".to_string(),
-                        TextSpan::new(6, 7),
+                        "b".to_string(),
+                        TextSpan::new(3, 4),
                     ))),
                 )),
+                Token::new(TokenKind::DoublePipe, "||".to_string(), TextSpan::new(4, 6)),
+                Expression::Identifier(IdentifierExpression::new(Token::new(
+                    TokenKind::Identifier,
+                    "c".to_string(),
+                    TextSpan::new(6, 7),
+                ))),
             )),
             5,
         );
@@ -504,29 +1132,29 @@ mod tests {
         let source_code = "a==b!=c";
         let expected_output = (
             Expression::Binary(BinaryExpression::new(
-                Expression::Identifier(IdentifierExpression::new(Token::new(
-                    TokenKind::Identifier,
-                    "a".to_string(),
-                    TextSpan::new(0, 1),
-                ))),
-                Token::new(
-                    TokenKind::DoubleEqual,
-                    "==".to_string(),
-                    TextSpan::new(1, 3),
-                ),
                 Expression::Binary(BinaryExpression::new(
                     Expression::Identifier(IdentifierExpression::new(Token::new(
                         TokenKind::Identifier,
-                        "b".to_string(),
-                        TextSpan::new(3, 4),
+                        "a".to_string(),
+                        TextSpan::new(0, 1),
                     ))),
-                    Token::new(TokenKind::BangEqual, "!=".to_string(), TextSpan::new(4, 6)),
+                    Token::new(
+                        TokenKind::DoubleEqual,
+                        "==".to_string(),
+                        TextSpan::new(1, 3),
+                    ),
                     Expression::Identifier(IdentifierExpression::new(Token::new(
                         TokenKind::Identifier,
-                        "c".to_string(),
-                        TextSpan::new(6, 7),
+                        "b".to_string(),
+                        TextSpan::new(3, 4),
                     ))),
                 )),
+                Token::new(TokenKind::BangEqual, "!=".to_string(), TextSpan::new(4, 6)),
+                Expression::Identifier(IdentifierExpression::new(Token::new(
+                    TokenKind::Identifier,
+                    "c".to_string(),
+                    TextSpan::new(6, 7),
+                ))),
             )),
             5,
         );
@@ -540,49 +1168,49 @@ mod tests {
         let source_code = "a>b<c>=d<=e";
         let expected_output = (
             Expression::Binary(BinaryExpression::new(
-                Expression::Identifier(IdentifierExpression::new(Token::new(
-                    TokenKind::Identifier,
-                    "a".to_string(),
-                    TextSpan::new(0, 1),
-                ))),
-                Token::new(TokenKind::Greater, ">".to_string(), TextSpan::new(1, 2)),
                 Expression::Binary(BinaryExpression::new(
-                    Expression::Identifier(IdentifierExpression::new(Token::new(
-                        TokenKind::Identifier,
-                        "b".to_string(),
-                        TextSpan::new(2, 3),
-                    ))),
-                    Token::new(TokenKind::Lesser, "<".to_string(), TextSpan::new(3, 4)),
                     Expression::Binary(BinaryExpression::new(
-                        Expression::Identifier(IdentifierExpression::new(Token::new(
-                            TokenKind::Identifier,
-                            "c".to_string(),
-                            TextSpan::new(4, 5),
-                        ))),
-                        Token::new(
-                            TokenKind::GreaterOrEqual,
-                            ">=".to_string(),
-                            TextSpan::new(5, 7),
-                        ),
                         Expression::Binary(BinaryExpression::new(
                             Expression::Identifier(IdentifierExpression::new(Token::new(
                                 TokenKind::Identifier,
-                                "d".to_string(),
-                                TextSpan::new(7, 8),
+                                "a".to_string(),
+                                TextSpan::new(0, 1),
                             ))),
-                            Token::new(
-                                TokenKind::LesserOrEqual,
-                                "<=".to_string(),
-                                TextSpan::new(8, 10),
-                            ),
+                            Token::new(TokenKind::Greater, ">".to_string(), TextSpan::new(1, 2)),
                             Expression::Identifier(IdentifierExpression::new(Token::new(
                                 TokenKind::Identifier,
-                                "e".to_string(),
-                                TextSpan::new(10, 11),
+                                "b".to_string(),
+                                TextSpan::new(2, 3),
                             ))),
                         )),
+                        Token::new(TokenKind::Lesser, "<".to_string(), TextSpan::new(3, 4)),
+                        Expression::Identifier(IdentifierExpression::new(Token::new(
+                            TokenKind::Identifier,
+                            "c".to_string(),
+                            TextSpan::new(4, 5),
+                        ))),
                     )),
+                    Token::new(
+                        TokenKind::GreaterOrEqual,
+                        ">=".to_string(),
+                        TextSpan::new(5, 7),
+                    ),
+                    Expression::Identifier(IdentifierExpression::new(Token::new(
+                        TokenKind::Identifier,
+                        "d".to_string(),
+                        TextSpan::new(7, 8),
+                    ))),
                 )),
+                Token::new(
+                    TokenKind::LesserOrEqual,
+                    "<=".to_string(),
+                    TextSpan::new(8, 10),
+                ),
+                Expression::Identifier(IdentifierExpression::new(Token::new(
+                    TokenKind::Identifier,
+                    "e".to_string(),
+                    TextSpan::new(10, 11),
+                ))),
             )),
             9,
         );
@@ -596,19 +1224,21 @@ mod tests {
         let source_code = "a+b-c*d/e";
         let expected_output = (
             Expression::Binary(BinaryExpression::new(
-                Expression::Identifier(IdentifierExpression::new(Token::new(
-                    TokenKind::Identifier,
-                    "a".to_string(),
-                    TextSpan::new(0, 1),
-                ))),
-                Token::new(TokenKind::Plus, "+".to_string(), TextSpan::new(1, 2)),
                 Expression::Binary(BinaryExpression::new(
+                    Expression::Identifier(IdentifierExpression::new(Token::new(
+                        TokenKind::Identifier,
+                        "a".to_string(),
+                        TextSpan::new(0, 1),
+                    ))),
+                    Token::new(TokenKind::Plus, "+".to_string(), TextSpan::new(1, 2)),
                     Expression::Identifier(IdentifierExpression::new(Token::new(
                         TokenKind::Identifier,
                         "b".to_string(),
                         TextSpan::new(2, 3),
                     ))),
-                    Token::new(TokenKind::Minus, "-".to_string(), TextSpan::new(3, 4)),
+                )),
+                Token::new(TokenKind::Minus, "-".to_string(), TextSpan::new(3, 4)),
+                Expression::Binary(BinaryExpression::new(
                     Expression::Binary(BinaryExpression::new(
                         Expression::Identifier(IdentifierExpression::new(Token::new(
                             TokenKind::Identifier,
@@ -616,20 +1246,18 @@ mod tests {
                             TextSpan::new(4, 5),
                         ))),
                         Token::new(TokenKind::Asterisk, "*".to_string(), TextSpan::new(5, 6)),
-                        Expression::Binary(BinaryExpression::new(
-                            Expression::Identifier(IdentifierExpression::new(Token::new(
-                                TokenKind::Identifier,
-                                "d".to_string(),
-                                TextSpan::new(6, 7),
-                            ))),
-                            Token::new(TokenKind::Slash, "/".to_string(), TextSpan::new(7, 8)),
-                            Expression::Identifier(IdentifierExpression::new(Token::new(
-                                TokenKind::Identifier,
-                                "e".to_string(),
-                                TextSpan::new(8, 9),
-                            ))),
-                        )),
+                        Expression::Identifier(IdentifierExpression::new(Token::new(
+                            TokenKind::Identifier,
+                            "d".to_string(),
+                            TextSpan::new(6, 7),
+                        ))),
                     )),
+                    Token::new(TokenKind::Slash, "/".to_string(), TextSpan::new(7, 8)),
+                    Expression::Identifier(IdentifierExpression::new(Token::new(
+                        TokenKind::Identifier,
+                        "e".to_string(),
+                        TextSpan::new(8, 9),
+                    ))),
                 )),
             )),
             9,
@@ -651,7 +1279,7 @@ mod tests {
                         Token::new(TokenKind::Minus, "-".to_string(), TextSpan::new(2, 3)),
                         Expression::Numeric(NumericLiteralExpression::new(
                             Token::new(TokenKind::Number, "2.5".to_string(), TextSpan::new(3, 6)),
-                            2.5,
+                            NumericValue::Float(2.5),
                         )),
                     )),
                 )),
@@ -679,6 +1307,47 @@ mod tests {
         assert_eq!(expected_output, output);
     }
 
+    #[test]
+    fn test_parse_primary_tuple_expression() {
+        let source_code = "(a,)";
+        let expected_output = (
+            Expression::Tuple(TupleLiteralExpression::new(
+                Token::new(TokenKind::OpenParen, "(".to_string(), TextSpan::new(0, 1)),
+                vec![Expression::Identifier(IdentifierExpression::new(
+                    Token::new(TokenKind::Identifier, "a".to_string(), TextSpan::new(1, 2)),
+                ))],
+                Token::new(TokenKind::CloseParen, ")".to_string(), TextSpan::new(3, 4)),
+            )),
+            4,
+        );
+        let tokens = tokenize(source_code).unwrap();
+        let output = parse_primary_expression(&tokens, 0).unwrap();
+        assert_eq!(expected_output, output);
+    }
+
+    #[test]
+    fn test_parse_destructuring_statement() {
+        let source_code = "let (a, b) = c";
+        let expected_output = (
+            Statement::Destructuring(DestructuringStatement::new(
+                Token::new(TokenKind::Let, "let".to_string(), TextSpan::new(0, 3)),
+                vec![
+                    Token::new(TokenKind::Identifier, "a".to_string(), TextSpan::new(5, 6)),
+                    Token::new(TokenKind::Identifier, "b".to_string(), TextSpan::new(8, 9)),
+                ],
+                Expression::Identifier(IdentifierExpression::new(Token::new(
+                    TokenKind::Identifier,
+                    "c".to_string(),
+                    TextSpan::new(13, 14),
+                ))),
+            )),
+            8,
+        );
+        let tokens = tokenize(source_code).unwrap();
+        let output = parse_let_statement(&tokens, 0).unwrap();
+        assert_eq!(expected_output, output);
+    }
+
     #[test]
     fn test_parse_primary_boolean_true_expression() {
         let source_code = "true";
@@ -715,7 +1384,7 @@ mod tests {
         let expected_output = (
             Expression::Numeric(NumericLiteralExpression::new(
                 Token::new(TokenKind::Number, "2.5".to_string(), TextSpan::new(0, 3)),
-                2.5,
+                NumericValue::Float(2.5),
             )),
             1,
         );
@@ -724,6 +1393,137 @@ mod tests {
         assert_eq!(expected_output, output);
     }
 
+    #[test]
+    fn test_parse_string_source_spans() {
+        let tokens = tokenize("f(\"héllo\", \"\")").unwrap();
+        let (call, _) = parse_postfix_expression(&tokens, 0).unwrap();
+        let Expression::Call(call) = call else {
+            panic!("{call:?} isn't a call");
+        };
+        let Expression::String(hello) = &call.arguments[0] else {
+            panic!("{:?} isn't a string", call.arguments[0]);
+        };
+        assert_eq!(hello.raw(), "héllo");
+        assert_eq!(hello.source_span(1..3), TextSpan::new(4, 6));
+        assert_eq!(hello.source_span(5..5), TextSpan::new(8, 8));
+        let Expression::String(empty) = &call.arguments[1] else {
+            panic!("{:?} isn't a string", call.arguments[1]);
+        };
+        assert_eq!(empty.source_span(0..0), TextSpan::new(12, 12));
+
+        // A value processed from source of another length, like `a\nb` into three chars.
+        let string = StringLiteralExpression::with_segments(
+            Token::new(TokenKind::String, "a\\nb".to_string(), TextSpan::new(0, 6)),
+            "a\nb".to_string(),
+            vec![
+                StringSegment {
+                    value: 0..1,
+                    source: TextSpan::new(1, 2),
+                },
+                StringSegment {
+                    value: 1..2,
+                    source: TextSpan::new(2, 4),
+                },
+                StringSegment {
+                    value: 2..3,
+                    source: TextSpan::new(4, 5),
+                },
+            ],
+        );
+        assert_eq!(string.source_span(1..2), TextSpan::new(2, 4));
+        assert_eq!(string.source_span(1..3), TextSpan::new(2, 5));
+        assert_eq!(string.source_span(0..3), TextSpan::new(1, 5));
+
+        // Escape sequences are the segments of that kind.
+        let tokens = tokenize(r#""a\nbc\\""#).unwrap();
+        let (escaped, _) = parse_primary_expression(&tokens, 0).unwrap();
+        let Expression::String(escaped) = escaped else {
+            panic!("{escaped:?} isn't a string");
+        };
+        assert_eq!(*escaped.value, "a\nbc\\");
+        assert_eq!(escaped.raw(), r"a\nbc\\");
+        assert_eq!(
+            escaped.segments,
+            vec![
+                StringSegment {
+                    value: 0..1,
+                    source: TextSpan::new(1, 2),
+                },
+                StringSegment {
+                    value: 1..2,
+                    source: TextSpan::new(2, 4),
+                },
+                StringSegment {
+                    value: 2..4,
+                    source: TextSpan::new(4, 6),
+                },
+                StringSegment {
+                    value: 4..5,
+                    source: TextSpan::new(6, 8),
+                },
+            ]
+        );
+        assert_eq!(escaped.source_span(2..4), TextSpan::new(4, 6));
+
+        // A line break is a newline however it's written.
+        for (source_code, newline) in [
+            ("\"a\r\nb\"", TextSpan::new(2, 4)),
+            ("\"a\rb\"", TextSpan::new(2, 3)),
+        ] {
+            let tokens = tokenize(source_code).unwrap();
+            let (string, _) = parse_primary_expression(&tokens, 0).unwrap();
+            let Expression::String(string) = string else {
+                panic!("{string:?} isn't a string");
+            };
+            assert_eq!(*string.value, "a\nb");
+            assert_eq!(string.source_span(1..2), newline);
+        }
+        assert_eq!(escaped.source_span(1..3), TextSpan::new(2, 5));
+    }
+
+    #[test]
+    fn test_parse_unexpected_end_of_input() {
+        for (source_code, message) in [
+            ("let", "Unexpected end of input, expected 'IDENTIFIER'"),
+            ("let x", "Unexpected end of input, expected '='"),
+            ("let x =", "Unexpected end of input"),
+            ("(1 + 2", "Unexpected end of input, expected ')'"),
+            ("{a:", "Unexpected end of input"),
+            ("!", "Unexpected end of input"),
+            ("a ? b :", "Unexpected end of input"),
+            ("f(1,", "Unexpected end of input"),
+            // A trailing dot is an access rather than a decimal point.
+            ("5.", "Unexpected end of input, expected 'IDENTIFIER'"),
+        ] {
+            let error = parse(tokenize(source_code).unwrap()).unwrap_err();
+            assert_eq!(error.message, message, "{source_code}");
+            let end = source_code.len();
+            assert_eq!(
+                error.text_span,
+                TextSpan::new(end, end + 1),
+                "{source_code}"
+            );
+        }
+        // The error is at the end of the last line.
+        let source_code = "let a = 1\nlet b = (a +";
+        let error = parse(tokenize(source_code).unwrap()).unwrap_err();
+        assert_eq!(error.text_span.position(source_code), (2, 13));
+
+        // Tokens that don't come from the tokenizer may lack the `Eof` ending them.
+        assert_eq!(parse(vec![]).unwrap(), vec![]);
+        let tokens = vec![Token::new(
+            TokenKind::Let,
+            "let".to_string(),
+            TextSpan::new(0, 3),
+        )];
+        let error = parse(tokens).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Unexpected end of input, expected 'IDENTIFIER'"
+        );
+        assert_eq!(error.text_span, TextSpan::new(3, 4));
+    }
+
     #[test]
     fn test_parse_primary_string_expression() {
         let source_code = "\"hello, world\"";
@@ -812,6 +1612,7 @@ mod tests {
                     "lang".to_string(),
                     TextSpan::new(0, 4),
                 ))),
+                Token::new(TokenKind::Dot, ".".to_string(), TextSpan::new(4, 5)),
                 Token::new(
                     TokenKind::Identifier,
                     "name".to_string(),
@@ -821,10 +1622,241 @@ mod tests {
             3,
         );
         let tokens = tokenize(source_code).unwrap();
-        let output = parse_access_expression(&tokens, 0).unwrap();
+        let output = parse_postfix_expression(&tokens, 0).unwrap();
         assert_eq!(expected_output, output);
     }
 
+    #[test]
+    fn test_parse_partial() {
+        let complete = [
+            "let a = 1",
+            "let s = \"{\"",
+            "f(1, (2, 3))",
+            "fn f(x) { x }",
+            "",
+        ];
+        let incomplete = [
+            "let a =",
+            "let a = {b: ",
+            "1 +",
+            "f(1,",
+            "fn f(x) {",
+            "let s = \"abc",
+            "a.",
+            "let",
+        ];
+        let wrong = [")", "let 1 = 2", "1 + * 2", "let a = }"];
+        for source_code in complete {
+            assert!(
+                matches!(parse_partial(source_code), PartialParse::Complete(_)),
+                "{source_code}"
+            );
+        }
+        for source_code in incomplete {
+            assert!(
+                matches!(parse_partial(source_code), PartialParse::Incomplete),
+                "{source_code}"
+            );
+        }
+        for source_code in wrong {
+            assert!(
+                matches!(parse_partial(source_code), PartialParse::Err(_)),
+                "{source_code}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_statement_spans() {
+        let source_code = "let a = 1 +
+  2
+const b: number = a
+a *
+  b";
+        let program = parse(tokenize(source_code).unwrap()).unwrap();
+        let spans: Vec<TextSpan> = program
+            .iter()
+            .map(|statement| statement.text_span())
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                TextSpan::new(0, 15),
+                TextSpan::new(16, 35),
+                TextSpan::new(36, 43),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_array_literal_expression() {
+        let program = parse(tokenize("[1, [], [2, 3,],]").unwrap()).unwrap();
+        match &program[..] {
+            [Statement::Expression(Expression::Array(a))] => {
+                assert_eq!(a.elements.len(), 3);
+                assert!(matches!(a.elements[1], Expression::Array(ref e) if e.elements.is_empty()));
+                assert!(matches!(a.elements[2], Expression::Array(ref e) if e.elements.len() == 2));
+                assert_eq!(a.text_span(), TextSpan::new(0, 17));
+            }
+            program => panic!("Expected an array, got {program:?}"),
+        }
+
+        let error = parse(tokenize("[1 2]").unwrap()).unwrap_err();
+        assert_eq!(error.message, "Unexpected token '2', expected ','");
+        let error = parse(tokenize("[1,,]").unwrap()).unwrap_err();
+        assert_eq!(error.message, "Unexpected token ','");
+    }
+
+    #[test]
+    fn test_parse_index_expression() {
+        let program = parse(tokenize("matrix[0][i + 1] points[i].x f(1)[0]").unwrap()).unwrap();
+        match &program[..] {
+            [Statement::Expression(Expression::Index(outer)), Statement::Expression(Expression::Access(access)), Statement::Expression(Expression::Index(call))] =>
+            {
+                assert!(
+                    matches!(*outer.object, Expression::Index(ref inner) if matches!(*inner.index, Expression::Numeric(_)))
+                );
+                assert!(matches!(*outer.index, Expression::Binary(_)));
+                assert_eq!(outer.text_span(), TextSpan::new(0, 16));
+                assert_eq!(outer.subscript_span(), TextSpan::new(9, 16));
+                assert!(matches!(*access.object, Expression::Index(_)));
+                assert!(matches!(*call.object, Expression::Call(_)));
+            }
+            program => panic!("Expected two indexes around an access, got {program:?}"),
+        }
+
+        let error = parse(tokenize("a[1, 2]").unwrap()).unwrap_err();
+        assert_eq!(error.message, "Unexpected token ',', expected ']'");
+        let error = parse(tokenize("a[]").unwrap()).unwrap_err();
+        assert_eq!(error.message, "Unexpected token ']'");
+    }
+
+    #[test]
+    fn test_parse_conditional_expression() {
+        let program = parse(tokenize("let a = x > 0 ? 1 : y ? 2 : 3").unwrap()).unwrap();
+        match &program[..] {
+            [Statement::Let(LetStatement {
+                expression: Expression::Conditional(outer),
+                ..
+            })] => {
+                assert!(matches!(*outer.condition, Expression::Binary(_)));
+                assert!(matches!(*outer.consequent, Expression::Numeric(_)));
+                assert!(matches!(*outer.alternative, Expression::Conditional(_)));
+                assert_eq!(outer.text_span(), TextSpan::new(8, 29));
+            }
+            program => panic!("Expected a conditional nested on the right, got {program:?}"),
+        }
+
+        let program = parse(tokenize("a = b ? c = 1 : d").unwrap()).unwrap();
+        assert!(matches!(
+            &program[..],
+            [Statement::Expression(Expression::Assignment(a))]
+                if matches!(*a.expression, Expression::Conditional(ref c) if matches!(*c.consequent, Expression::Assignment(_)))
+        ));
+
+        let error = parse(tokenize("a ? b").unwrap()).unwrap_err();
+        assert_eq!(error.message, "Unexpected end of input, expected ':'");
+    }
+
+    #[test]
+    fn test_parse_block_or_object() {
+        let kinds = |source_code: &str| -> Vec<&'static str> {
+            parse(tokenize(source_code).unwrap())
+                .unwrap()
+                .iter()
+                .map(|statement| match statement {
+                    Statement::Expression(Expression::Object(_)) => "object",
+                    Statement::Expression(Expression::Block(_)) => "block",
+                    _ => "other",
+                })
+                .collect()
+        };
+        assert_eq!(kinds("{}"), vec!["object"]);
+        assert_eq!(kinds("{a: 1}"), vec!["object"]);
+        assert_eq!(kinds("{let: 1, fn: 2}"), vec!["object"]);
+        assert_eq!(kinds("{ a }"), vec!["block"]);
+        assert_eq!(kinds("{ let a = 1 a + 1 }"), vec!["block"]);
+        assert_eq!(kinds("{ a = 1 }"), vec!["block"]);
+        assert_eq!(kinds("{ {a: 1} }"), vec!["block"]);
+        assert_eq!(kinds("{ { a } } {b: { c }}"), vec!["block", "object"]);
+
+        match &parse(tokenize("{ let a = 1 {b: a} }").unwrap()).unwrap()[0] {
+            Statement::Expression(Expression::Block(b)) => {
+                assert_eq!(b.statements.len(), 2);
+                assert!(matches!(
+                    b.statements[1],
+                    Statement::Expression(Expression::Object(_))
+                ));
+                assert_eq!(b.text_span(), TextSpan::new(0, 20));
+            }
+            statement => panic!("Expected a block, got {statement:?}"),
+        }
+
+        // Without its colon, a key is read as a statement rather than a broken object.
+        assert_eq!(kinds("{a 1}"), vec!["block"]);
+        let error = parse(tokenize("{a: 1 b: 2}").unwrap()).unwrap_err();
+        assert_eq!(error.message, "Unexpected token 'b', expected ','");
+    }
+
+    #[test]
+    fn test_parse_access_expression_spans() {
+        let source_code = "{ inner: { value: 1 } }.inner";
+        let tokens = tokenize(source_code).unwrap();
+        match parse_postfix_expression(&tokens, 0).unwrap() {
+            (Expression::Access(a), 11) => {
+                assert_eq!(a.object.text_span(), TextSpan::new(0, 23));
+                assert_eq!(a.dot.text_span, TextSpan::new(23, 24));
+                assert_eq!(a.property.text_span, TextSpan::new(24, 29));
+                assert_eq!(a.text_span(), TextSpan::new(0, 29));
+            }
+            output => panic!("Expected an access expression, got {output:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_nested_access_expression() {
+        let tokens = tokenize("config.server.http.port").unwrap();
+        match parse_postfix_expression(&tokens, 0).unwrap() {
+            (Expression::Access(port), 7) => {
+                assert_eq!(&*port.property.lexeme, "port");
+                assert_eq!(port.text_span(), TextSpan::new(0, 23));
+                let Expression::Access(http) = &*port.object else {
+                    panic!("Expected 'config.server.http', got {:?}", port.object);
+                };
+                assert_eq!(&*http.property.lexeme, "http");
+                assert_eq!(http.text_span(), TextSpan::new(0, 18));
+                let Expression::Access(server) = &*http.object else {
+                    panic!("Expected 'config.server', got {:?}", http.object);
+                };
+                assert_eq!(&*server.property.lexeme, "server");
+                assert!(matches!(*server.object, Expression::Identifier(_)));
+            }
+            output => panic!("Expected an access expression, got {output:?}"),
+        }
+
+        let tokens = tokenize("(obj).field.sub").unwrap();
+        match parse_postfix_expression(&tokens, 0).unwrap() {
+            (Expression::Access(sub), 7) => {
+                assert_eq!(&*sub.property.lexeme, "sub");
+                assert!(matches!(&*sub.object, Expression::Access(field)
+                    if &*field.property.lexeme == "field"
+                        && matches!(*field.object, Expression::Identifier(_))));
+                assert_eq!(sub.text_span(), TextSpan::new(1, 15));
+            }
+            output => panic!("Expected an access expression, got {output:?}"),
+        }
+
+        // Elements of nested tuples are chained the same way.
+        let tokens = tokenize("t.0.1").unwrap();
+        match parse_postfix_expression(&tokens, 0).unwrap() {
+            (Expression::Access(a), 5) => {
+                assert_eq!(&*a.property.lexeme, "1");
+                assert!(matches!(&*a.object, Expression::Access(b) if &*b.property.lexeme == "0"));
+            }
+            output => panic!("Expected an access expression, got {output:?}"),
+        }
+    }
+
     #[test]
     fn test_parse_key_value_pair() {
         let source_code = "works: true";
@@ -846,4 +1878,84 @@ mod tests {
         let output = parse_key_value_pair(&tokens, 0).unwrap();
         assert_eq!(expected_output, output);
     }
+
+    #[test]
+    fn test_parse_keywords_as_keys_and_properties() {
+        let tokens = tokenize("none: 1").unwrap();
+        let (pair, _) = parse_key_value_pair(&tokens, 0).unwrap();
+        assert_eq!(
+            pair.key,
+            Token::new(TokenKind::None, "none".to_string(), TextSpan::new(0, 4))
+        );
+
+        let tokens = tokenize("config.let").unwrap();
+        match parse_postfix_expression(&tokens, 0).unwrap() {
+            (Expression::Access(a), 3) => assert_eq!(&*a.property.lexeme, "let"),
+            output => panic!("Expected an access expression, got {output:?}"),
+        }
+
+        let tokens = tokenize("config.\"name\"").unwrap();
+        let error = parse_postfix_expression(&tokens, 0).unwrap_err();
+        assert_eq!(error.text_span, TextSpan::new(7, 13));
+    }
+
+    #[test]
+    fn test_parse_primary_function_expression() {
+        let source_code = "fn(x) { x }";
+        let expected_output = (
+            Expression::Function(FunctionExpression::new(
+                Token::new(TokenKind::Fn, "fn".to_string(), TextSpan::new(0, 2)),
+                vec![Token::new(
+                    TokenKind::Identifier,
+                    "x".to_string(),
+                    TextSpan::new(3, 4),
+                )],
+                vec![Statement::Expression(Expression::Identifier(
+                    IdentifierExpression::new(Token::new(
+                        TokenKind::Identifier,
+                        "x".to_string(),
+                        TextSpan::new(8, 9),
+                    )),
+                ))],
+                Token::new(
+                    TokenKind::CloseBrace,
+                    "}".to_string(),
+                    TextSpan::new(10, 11),
+                ),
+            )),
+            7,
+        );
+        let tokens = tokenize(source_code).unwrap();
+        let output = parse_primary_expression(&tokens, 0).unwrap();
+        assert_eq!(expected_output, output);
+    }
+
+    #[test]
+    fn test_parse_call_expression() {
+        let source_code = "add(1, 2)";
+        let expected_output = (
+            Expression::Call(CallExpression::new(
+                Expression::Identifier(IdentifierExpression::new(Token::new(
+                    TokenKind::Identifier,
+                    "add".to_string(),
+                    TextSpan::new(0, 3),
+                ))),
+                vec![
+                    Expression::Numeric(NumericLiteralExpression::new(
+                        Token::new(TokenKind::Number, "1".to_string(), TextSpan::new(4, 5)),
+                        NumericValue::Integer(1),
+                    )),
+                    Expression::Numeric(NumericLiteralExpression::new(
+                        Token::new(TokenKind::Number, "2".to_string(), TextSpan::new(7, 8)),
+                        NumericValue::Integer(2),
+                    )),
+                ],
+                Token::new(TokenKind::CloseParen, ")".to_string(), TextSpan::new(8, 9)),
+            )),
+            6,
+        );
+        let tokens = tokenize(source_code).unwrap();
+        let output = parse_postfix_expression(&tokens, 0).unwrap();
+        assert_eq!(expected_output, output);
+    }
 }
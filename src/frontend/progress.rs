@@ -0,0 +1,121 @@
+use std::fmt;
+
+use super::utils::TextSpan;
+
+// What a progress callback wants the run to do next.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Control {
+    Continue,
+    Cancel,
+}
+
+// Called with the index of a top-level statement about to be evaluated, the number of
+// them and its span.
+pub type StatementCallback = dyn Fn(usize, usize, &TextSpan) -> Control;
+// Called with the tokens the parser consumed so far and the number of them.
+pub type ParseCallback = dyn Fn(usize, usize) -> Control;
+
+// Callbacks a host following a long run sets on the environment. They're given positions
+// rather than the environment, so they can't change the state of the interpreter, and
+// cancelling raises its interruption flag the way Ctrl-C does. Unset ones cost nothing.
+#[derive(Default)]
+pub struct Progress {
+    pub on_statement: Option<Box<StatementCallback>>,
+    pub on_parse_progress: Option<Box<ParseCallback>>,
+}
+
+impl fmt::Debug for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Progress")
+            .field("on_statement", &self.on_statement.is_some())
+            .field("on_parse_progress", &self.on_parse_progress.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use crate::{run_source_with, runtime::environment::Environment};
+
+    use super::{Control, Progress};
+
+    // Ten statements, each adding one to `n`.
+    const PROGRAM: &str = "let n = 0 n = n + 1 n = n + 1 n = n + 1 n = n + 1 n = n + 1 n = n + 1 n = n + 1 n = n + 1 n";
+
+    #[test]
+    fn test_progress_callbacks() {
+        let statements = Rc::new(RefCell::new(vec![]));
+        let parsed = Rc::new(RefCell::new(vec![]));
+        let mut environment = Environment::new(None);
+        environment.progress = Some(Rc::new(Progress {
+            on_statement: Some(Box::new({
+                let statements = statements.clone();
+                move |index, total, text_span| {
+                    statements
+                        .borrow_mut()
+                        .push((index, total, text_span.starting_index));
+                    Control::Continue
+                }
+            })),
+            on_parse_progress: Some(Box::new({
+                let parsed = parsed.clone();
+                move |consumed, total| {
+                    parsed.borrow_mut().push((consumed, total));
+                    Control::Continue
+                }
+            })),
+        }));
+        let value = run_source_with(PROGRAM, &mut environment).unwrap();
+        assert_eq!(value.to_string(), "8");
+        let statements = statements.borrow();
+        assert_eq!(statements.len(), 10);
+        assert!(statements
+            .iter()
+            .enumerate()
+            .all(|(i, (index, total, _))| *index == i && *total == 10));
+        assert_eq!(statements[1].2, 10);
+        let parsed = parsed.borrow();
+        assert_eq!(parsed.len(), 10);
+        // The parser is done once it's consumed every token but the end of input.
+        assert_eq!(parsed[0], (4, 46));
+        assert_eq!(parsed[9], (45, 46));
+    }
+
+    #[test]
+    fn test_progress_cancels() {
+        let mut environment = Environment::new(None);
+        environment.progress = Some(Rc::new(Progress {
+            on_statement: Some(Box::new(|index, _, _| {
+                if index == 5 {
+                    Control::Cancel
+                } else {
+                    Control::Continue
+                }
+            })),
+            on_parse_progress: None,
+        }));
+        let error = run_source_with(PROGRAM, &mut environment).unwrap_err();
+        assert_eq!(error.message, "Evaluation was interrupted");
+        assert_eq!(error.text_span.starting_index, 50);
+        assert!(environment.is_interrupted());
+        // Nothing the cancelled run did is kept.
+        assert_eq!(environment.access("n"), None);
+
+        environment.clear_interrupt();
+        environment.progress = Some(Rc::new(Progress {
+            on_statement: None,
+            on_parse_progress: Some(Box::new(|consumed, _| {
+                if consumed > 20 {
+                    Control::Cancel
+                } else {
+                    Control::Continue
+                }
+            })),
+        }));
+        let error = run_source_with(PROGRAM, &mut environment).unwrap_err();
+        assert_eq!(error.message, "Parsing was interrupted");
+        assert!(environment.is_interrupted());
+    }
+}
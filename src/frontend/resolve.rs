@@ -0,0 +1,343 @@
+use std::collections::HashMap;
+
+use super::{
+    ast::{Expression, Program, Statement},
+    node::Node,
+    symbol::Symbol,
+    token::{Token, TokenKind},
+    utils::{Error, TextSpan},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResolveErrorKind {
+    UndefinedVariable,
+    ConstantAssignment,
+    UseBeforeDeclaration,
+}
+
+// A name the resolver found would fail if the code using it ran.
+#[derive(Debug, PartialEq)]
+pub struct ResolveError {
+    pub kind: ResolveErrorKind,
+    pub name: Symbol,
+    pub is_assignment: bool,
+    pub text_span: TextSpan,
+}
+
+impl ResolveError {
+    // The message the evaluator would give, or a close one for names it would find
+    // undefined because they're declared later.
+    pub fn message(&self) -> String {
+        let name = &self.name;
+        match (self.kind, self.is_assignment) {
+            (ResolveErrorKind::UndefinedVariable, false) => {
+                format!("Can't access the variable '{name}' as it's not defined")
+            }
+            (ResolveErrorKind::UndefinedVariable, true) => {
+                format!("Can't assign to the variable '{name}' as it's not defined")
+            }
+            (ResolveErrorKind::ConstantAssignment, _) => {
+                format!("Can't assign the variable '{name}' as it's a constant")
+            }
+            (ResolveErrorKind::UseBeforeDeclaration, false) => {
+                format!("Can't access the variable '{name}' before it's declared")
+            }
+            (ResolveErrorKind::UseBeforeDeclaration, true) => {
+                format!("Can't assign to the variable '{name}' before it's declared")
+            }
+        }
+    }
+
+    pub fn to_error(&self) -> Error {
+        Error::new(self.message(), self.text_span.clone())
+    }
+}
+
+// Finds the variables a program reads or assigns that no scope declares, the constants
+// it assigns and the names it uses before declaring them, without evaluating it.
+// `globals` are the names the program runs with, like the builtins and whatever the host
+// defined, along with whether they're constants.
+//
+// Function bodies only run once called, which can't be told statically, so they can see
+// every name of the scopes around them wherever it's declared. Code running where it's
+// written only sees what is declared before it.
+pub fn resolve(program: &Program, globals: &[(String, bool)]) -> Vec<ResolveError> {
+    let mut resolver = Resolver {
+        globals: globals
+            .iter()
+            .map(|(name, is_constant)| (Symbol::from(name.as_str()), *is_constant))
+            .collect(),
+        scopes: vec![],
+        errors: vec![],
+    };
+    resolver.resolve_scope(program, &[], false);
+    resolver.errors
+}
+
+struct Scope {
+    // Every name the scope declares, and whether the last declaration is a constant.
+    declared: HashMap<Symbol, bool>,
+    // The names declared by the statements resolved so far.
+    known: HashMap<Symbol, bool>,
+    is_function: bool,
+}
+
+enum Lookup {
+    Found { is_constant: bool },
+    Later,
+    Undefined,
+}
+
+struct Resolver {
+    globals: HashMap<Symbol, bool>,
+    scopes: Vec<Scope>,
+    errors: Vec<ResolveError>,
+}
+
+impl Resolver {
+    fn resolve_scope(&mut self, program: &Program, parameters: &[Token], is_function: bool) {
+        let mut declared: HashMap<Symbol, bool> = parameters
+            .iter()
+            .map(|parameter| (parameter.lexeme.clone(), false))
+            .collect();
+        let known = declared.clone();
+        declared.extend(program.iter().flat_map(declarations));
+        self.scopes.push(Scope {
+            declared,
+            known,
+            is_function,
+        });
+        for statement in program {
+            self.resolve_node(Node::Statement(statement));
+        }
+        self.scopes.pop();
+    }
+
+    fn resolve_node(&mut self, node: Node) {
+        match node {
+            // The initializer runs before the name is declared, so `let x = x + 1` reads
+            // the `x` of an outer scope.
+            Node::Statement(
+                Statement::Let(_) | Statement::Const(_) | Statement::Destructuring(_),
+            ) => {
+                for child in node.children(false) {
+                    self.resolve_node(child);
+                }
+                if let Node::Statement(statement) = node {
+                    self.declare(statement);
+                }
+            }
+            // The function is defined before anything calls it, itself included.
+            Node::Statement(statement @ Statement::Function(f)) => {
+                self.declare(statement);
+                self.resolve_scope(&f.function.body, &f.function.parameters, true);
+            }
+            Node::Statement(Statement::While(w)) => {
+                self.resolve_node(Node::Expression(&w.condition));
+                self.resolve_scope(&w.body, &[], false);
+            }
+            Node::Statement(Statement::For(f)) => {
+                self.resolve_node(Node::Expression(&f.iterable));
+                self.resolve_scope(&f.body, std::slice::from_ref(&f.identifier), false);
+            }
+            Node::Expression(Expression::Block(b)) => self.resolve_scope(&b.statements, &[], false),
+            Node::Expression(Expression::Function(f)) => {
+                self.resolve_scope(&f.body, &f.parameters, true)
+            }
+            Node::Expression(Expression::Identifier(i)) => {
+                let kind = match self.lookup(&i.identifier.lexeme) {
+                    Lookup::Found { .. } => return,
+                    Lookup::Later => ResolveErrorKind::UseBeforeDeclaration,
+                    Lookup::Undefined => ResolveErrorKind::UndefinedVariable,
+                };
+                self.report(kind, &i.identifier, false, i.text_span());
+            }
+            Node::Expression(Expression::Assignment(a)) => {
+                if let Some(identifier) = a.identifier() {
+                    let kind = match self.lookup(&identifier.lexeme) {
+                        Lookup::Found { is_constant: false } => None,
+                        Lookup::Found { is_constant: true } => {
+                            Some(ResolveErrorKind::ConstantAssignment)
+                        }
+                        Lookup::Later => Some(ResolveErrorKind::UseBeforeDeclaration),
+                        Lookup::Undefined => Some(ResolveErrorKind::UndefinedVariable),
+                    };
+                    if let Some(kind) = kind {
+                        self.report(kind, identifier, true, a.text_span());
+                    }
+                }
+                self.resolve_path(&a.target);
+                self.resolve_node(Node::Expression(&a.expression));
+            }
+            _ => {
+                for child in node.children(false) {
+                    self.resolve_node(child);
+                }
+            }
+        }
+    }
+
+    // The indexes along an assignment target, its variable being resolved as assigned.
+    fn resolve_path(&mut self, target: &Expression) {
+        match target {
+            Expression::Identifier(_) => {}
+            Expression::Access(a) => self.resolve_path(&a.object),
+            Expression::Index(i) => {
+                self.resolve_path(&i.object);
+                self.resolve_node(Node::Expression(&i.index));
+            }
+            _ => self.resolve_node(Node::Expression(target)),
+        }
+    }
+
+    fn declare(&mut self, statement: &Statement) {
+        let scope = self.scopes.last_mut().unwrap();
+        scope.known.extend(declarations(statement));
+    }
+
+    // Looks a name up from the innermost scope out. Past a function body everything the
+    // outer scopes declare counts, as the function may be called once it all is.
+    fn lookup(&self, name: &str) -> Lookup {
+        let mut sees_everything = false;
+        let mut is_declared_later = false;
+        for scope in self.scopes.iter().rev() {
+            let declaration = if sees_everything {
+                scope.declared.get(name)
+            } else {
+                scope.known.get(name)
+            };
+            if let Some(is_constant) = declaration {
+                return Lookup::Found {
+                    is_constant: *is_constant,
+                };
+            }
+            is_declared_later |= scope.declared.contains_key(name);
+            sees_everything |= scope.is_function;
+        }
+        match self.globals.get(name) {
+            Some(is_constant) => Lookup::Found {
+                is_constant: *is_constant,
+            },
+            None if is_declared_later => Lookup::Later,
+            None => Lookup::Undefined,
+        }
+    }
+
+    fn report(
+        &mut self,
+        kind: ResolveErrorKind,
+        identifier: &Token,
+        is_assignment: bool,
+        text_span: TextSpan,
+    ) {
+        self.errors.push(ResolveError {
+            kind,
+            name: identifier.lexeme.clone(),
+            is_assignment,
+            text_span,
+        });
+    }
+}
+
+// The names a statement declares in its scope, and whether they're constants.
+fn declarations(statement: &Statement) -> Vec<(Symbol, bool)> {
+    match statement {
+        Statement::Let(l) => vec![(l.identifier.lexeme.clone(), false)],
+        Statement::Const(c) => vec![(c.identifier.lexeme.clone(), true)],
+        Statement::Destructuring(d) => {
+            let is_constant = d.keyword.kind == TokenKind::Const;
+            d.identifiers
+                .iter()
+                .map(|identifier| (identifier.lexeme.clone(), is_constant))
+                .collect()
+        }
+        Statement::Function(f) => vec![(f.identifier.lexeme.clone(), false)],
+        Statement::While(_) | Statement::For(_) | Statement::Expression(_) => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        frontend::{parser::parse, tokenizer::tokenize, utils::TextSpan},
+        run_source,
+    };
+
+    use super::resolve;
+
+    fn errors(src: &str) -> Vec<(String, TextSpan)> {
+        let globals = vec![("len".to_string(), true), ("host".to_string(), false)];
+        resolve(&parse(tokenize(src).unwrap()).unwrap(), &globals)
+            .into_iter()
+            .map(|error| (error.message(), error.text_span))
+            .collect()
+    }
+
+    #[test]
+    fn test_resolve_flags_code_that_never_ran() {
+        for (src, message, text_span) in [
+            (
+                "false && missing",
+                "Can't access the variable 'missing' as it's not defined",
+                TextSpan::new(9, 16),
+            ),
+            (
+                "let f = fn() { missing(1) } 1",
+                "Can't access the variable 'missing' as it's not defined",
+                TextSpan::new(15, 22),
+            ),
+            (
+                "let f = fn() { count = 1 } 1",
+                "Can't assign to the variable 'count' as it's not defined",
+                TextSpan::new(15, 24),
+            ),
+            (
+                "const a = 1 fn f() { a.b = 2 } 1",
+                "Can't assign the variable 'a' as it's a constant",
+                TextSpan::new(21, 28),
+            ),
+            (
+                "while false { len = 1 } 1",
+                "Can't assign the variable 'len' as it's a constant",
+                TextSpan::new(14, 21),
+            ),
+            (
+                "true ? 1 : later let later = 2",
+                "Can't access the variable 'later' before it's declared",
+                TextSpan::new(11, 16),
+            ),
+            (
+                "for x in [] { let y = x + z } let z = 1",
+                "Can't access the variable 'z' before it's declared",
+                TextSpan::new(26, 27),
+            ),
+            (
+                "{ let inner = 1 } let f = fn() { inner } 1",
+                "Can't access the variable 'inner' as it's not defined",
+                TextSpan::new(33, 38),
+            ),
+        ] {
+            assert!(run_source(src).is_ok(), "{src} should run");
+            assert_eq!(errors(src), vec![(message.to_string(), text_span)], "{src}");
+        }
+    }
+
+    #[test]
+    fn test_resolve_leaves_dynamic_code() {
+        for src in [
+            // Functions see what is declared after them, themselves included.
+            "fn f(n) { n == 0 ? total : f(n - 1) } let total = 1 f(2)",
+            "let count = 0 let bump = fn() { count = count + 1 } bump()",
+            "let x = 1 { let y = x let x = 2 y + x }",
+            "let x = 1 let f = fn(x) { x = 2 } f(x)",
+            "const (a, b) = (1, 2) let (c, d) = (a, b) c = d",
+            "for i in [1, 2] { let s = i } let s = len(\"ab\") s",
+            "let o = {a: [1]} let i = 0 o.a[i] = 2 o",
+        ] {
+            assert_eq!(errors(src), vec![], "{src}");
+            assert!(run_source(src).is_ok(), "{src} should run");
+        }
+        // Globals are declared by the host rather than by the program.
+        assert_eq!(errors("host = len(\"a\") let host = 2 host"), vec![]);
+    }
+}
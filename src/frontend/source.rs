@@ -0,0 +1,261 @@
+use super::ast::{Expression, FunctionExpression, Program, Statement};
+
+// Renders a program back into source code that parses to the same tree, one statement
+// per line. Spacing is canonical and parentheses are only added where the tree couldn't
+// be told apart without them, so this is what the program would look like formatted.
+pub fn to_source(program: &Program) -> String {
+    program
+        .iter()
+        .map(Statement::to_source)
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+impl Statement {
+    pub fn to_source(&self) -> String {
+        match self {
+            Statement::Let(l) => binding(
+                "let",
+                &l.identifier.lexeme,
+                l.annotation.as_ref().map(|a| &*a.lexeme),
+                &l.expression,
+            ),
+            Statement::Const(c) => binding(
+                "const",
+                &c.identifier.lexeme,
+                c.annotation.as_ref().map(|a| &*a.lexeme),
+                &c.expression,
+            ),
+            Statement::Destructuring(d) => format!(
+                "{} ({}) = {}",
+                d.keyword.lexeme,
+                join(d.identifiers.iter().map(|i| i.lexeme.to_string())),
+                d.expression.to_source()
+            ),
+            Statement::Function(f) => function(Some(&f.identifier.lexeme), &f.function),
+            Statement::While(w) => {
+                format!("while {} {}", w.condition.to_source(), block(&w.body))
+            }
+            Statement::For(f) => format!(
+                "for {} in {} {}",
+                f.identifier.lexeme,
+                f.iterable.to_source(),
+                block(&f.body)
+            ),
+            Statement::Expression(e) => e.to_source(),
+        }
+    }
+}
+
+impl Expression {
+    pub fn to_source(&self) -> String {
+        match self {
+            Expression::None(_) => "none".to_string(),
+            Expression::Boolean(b) => b.value.to_string(),
+            Expression::Numeric(n) => n.number.lexeme.to_string(),
+            Expression::String(s) => format!("\"{}\"", s.raw()),
+            Expression::Object(o) if o.pairs.is_empty() => "{}".to_string(),
+            Expression::Object(o) => format!(
+                "{{{}}}",
+                join(o.pairs.iter().map(|pair| format!(
+                    "{}: {}",
+                    pair.key.lexeme,
+                    pair.value.to_source()
+                )))
+            ),
+            Expression::Tuple(t) if t.elements.len() == 1 => {
+                format!("({},)", t.elements[0].to_source())
+            }
+            Expression::Tuple(t) => format!("({})", join(t.elements.iter().map(|e| e.to_source()))),
+            Expression::Array(a) => format!("[{}]", join(a.elements.iter().map(|e| e.to_source()))),
+            // A number is followed by its decimals rather than a property, so it's grouped.
+            Expression::Access(a) => {
+                let object = match *a.object {
+                    Expression::Numeric(_) => format!("({})", a.object.to_source()),
+                    _ => operand(&a.object, POSTFIX),
+                };
+                format!("{object}{}{}", a.dot.lexeme, a.property.lexeme)
+            }
+            Expression::Index(i) => {
+                format!("{}[{}]", operand(&i.object, POSTFIX), i.index.to_source())
+            }
+            Expression::Identifier(i) => i.identifier.lexeme.to_string(),
+            Expression::Unary(u) => format!("{}{}", u.operator.lexeme, operand(&u.right, UNARY)),
+            // Operators of the same precedence group to the left, e.g. `1 - 2 - 3` is
+            // `(1 - 2) - 3`, so only a right operand of the same precedence is grouped.
+            Expression::Binary(b) => {
+                let precedence = precedence(self);
+                format!(
+                    "{} {} {}",
+                    operand(&b.left, precedence),
+                    b.operator.lexeme,
+                    operand(&b.right, precedence + 1)
+                )
+            }
+            // The condition binds tighter and the alternative groups to the right, the
+            // consequent can be any expression as it's enclosed by `?` and `:`.
+            Expression::Conditional(c) => format!(
+                "{} ? {} : {}",
+                operand(&c.condition, CONDITIONAL + 1),
+                c.consequent.to_source(),
+                operand(&c.alternative, CONDITIONAL)
+            ),
+            Expression::Assignment(a) => {
+                format!("{} = {}", a.target.to_source(), a.expression.to_source())
+            }
+            Expression::Function(f) => function(None, f),
+            Expression::Block(b) => block(&b.statements),
+            Expression::Call(c) => format!(
+                "{}({})",
+                operand(&c.callee, POSTFIX),
+                join(c.arguments.iter().map(|a| a.to_source()))
+            ),
+        }
+    }
+}
+
+const ASSIGNMENT: u8 = 0;
+const CONDITIONAL: u8 = 1;
+const UNARY: u8 = 7;
+const POSTFIX: u8 = 8;
+const PRIMARY: u8 = 9;
+
+// How tightly an expression binds, following the levels of the parser.
+fn precedence(expression: &Expression) -> u8 {
+    match expression {
+        Expression::Assignment(_) => ASSIGNMENT,
+        Expression::Conditional(_) => CONDITIONAL,
+        Expression::Binary(b) => match &*b.operator.lexeme {
+            "&&" | "||" => 2,
+            "==" | "!=" | "===" | "!==" => 3,
+            ">" | "<" | ">=" | "<=" => 4,
+            "+" | "-" => 5,
+            _ => 6,
+        },
+        Expression::Unary(_) => UNARY,
+        Expression::Call(_) | Expression::Access(_) | Expression::Index(_) => POSTFIX,
+        _ => PRIMARY,
+    }
+}
+
+// An expression in a place the parser only reads expressions of at least `minimum`
+// precedence from, grouped when it binds more loosely.
+fn operand(expression: &Expression, minimum: u8) -> String {
+    if precedence(expression) < minimum {
+        format!("({})", expression.to_source())
+    } else {
+        expression.to_source()
+    }
+}
+
+fn binding(
+    keyword: &str,
+    identifier: &str,
+    annotation: Option<&str>,
+    value: &Expression,
+) -> String {
+    match annotation {
+        Some(annotation) => format!(
+            "{keyword} {identifier}: {annotation} = {}",
+            value.to_source()
+        ),
+        None => format!("{keyword} {identifier} = {}", value.to_source()),
+    }
+}
+
+fn function(name: Option<&str>, function: &FunctionExpression) -> String {
+    let parameters = join(function.parameters.iter().map(|p| p.lexeme.to_string()));
+    let signature = match name {
+        Some(name) => format!("fn {name}({parameters})"),
+        None => format!("fn({parameters})"),
+    };
+    format!("{signature} {}", block(&function.body))
+}
+
+fn block(body: &Program) -> String {
+    if body.is_empty() {
+        return "{}".to_string();
+    }
+    let body: Vec<String> = body.iter().map(Statement::to_source).collect();
+    format!("{{ {} }}", body.join(" "))
+}
+
+fn join(items: impl Iterator<Item = String>) -> String {
+    items.collect::<Vec<String>>().join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frontend::{ast::Program, parser::parse, tokenizer::tokenize};
+
+    use super::to_source;
+
+    fn parse_source(source_code: &str) -> Program {
+        parse(tokenize(source_code).unwrap()).unwrap()
+    }
+
+    // The tree without the spans of its tokens, which are all that differ between two
+    // spellings of the same program.
+    fn shape(program: &Program) -> String {
+        let mut shape = format!("{program:?}");
+        while let Some(start) = shape.find("TextSpan {") {
+            let end = start + shape[start..].find('}').unwrap() + 1;
+            shape.replace_range(start..end, "_");
+        }
+        shape
+    }
+
+    #[test]
+    fn test_to_source_round_trip() {
+        let corpus = [
+            "let a = 1 const b: string = \"hi\" let (c, d) = (1, 2)",
+            "fn add(a, b) { let c = a + b c } add(1, 2)",
+            "let i = 0 while i < 3 { let j = i i = j + 1 } while false {}",
+            "for x in (1, 2) { let y = x * 2 } for c in \"ab\" {}",
+            "let b = { let x = {a: 1} { x.a } } let e = {} let k = {let: 1}",
+            "let f = fn() {} let g = fn(x) { fn(y) { x * y } } g(2)(3)",
+            "let o = {a: 1, let: {b: (true, false)}, c: none} o.let",
+            "let t = ((1,), (), (1, 2, 3)) t.0",
+            "let a = [[], [1], [1, 2,], [{a: [none]}, (1,)]]",
+            "let m = a[0][i + 1] let p = points[i].x let q = (-a)[0] let r = f(1)[0]",
+            "(1 + 2) * 3 - 4 / (5 - 6)",
+            "(1 - 2) - 3 1 - (2 - 3) 1 - 2 - 3",
+            "a = b = (c = 1) + 2",
+            "let c = a > 0 ? \"positive\" : a < 0 ? \"negative\" : \"zero\"",
+            "(a ? b : c) ? d = 1 : (e ? f : g) + 1 let h = (x = y ? 1 : 2)",
+            "a.b[i + 1].c = d[0] = 1",
+            "!(a == b) && (c || d) || -(-1) - +2 - - 3",
+            "(a < b) == (c >= d) !== e",
+            "let r = (fn(x) { x })(1) let s = (a.b).c let n = (3.7).floor let p = (f(1)).x",
+            "let u = (-a).b let v = (!a)(1) let w = -a.b",
+        ];
+        for source_code in corpus {
+            let program = parse_source(source_code);
+            let regenerated = to_source(&program);
+            assert_eq!(
+                shape(&parse_source(&regenerated)),
+                shape(&program),
+                "{source_code} was regenerated as {regenerated}"
+            );
+            assert_eq!(to_source(&parse_source(&regenerated)), regenerated);
+        }
+    }
+
+    #[test]
+    fn test_to_source_is_minimal() {
+        let cases = [
+            ("let   a:number=(1)", "let a: number = 1"),
+            ("((1 + 2)) * (3 * 4)", "(1 + 2) * (3 * 4)"),
+            ("(1 * 2) + 3", "1 * 2 + 3"),
+            ("1 - (2 - 3)", "1 - (2 - 3)"),
+            ("(1 - 2) - 3", "1 - 2 - 3"),
+            ("f((1), (2,))", "f(1, (2,))"),
+            ("fn f( a,b ){let c=a c}", "fn f(a, b) { let c = a c }"),
+            ("{ }", "{}"),
+            ("a ? (b ? c : d) : (e ? f : g)", "a ? b ? c : d : e ? f : g"),
+        ];
+        for (source_code, expected) in cases {
+            assert_eq!(to_source(&parse_source(source_code)), expected);
+        }
+    }
+}
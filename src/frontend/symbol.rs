@@ -0,0 +1,44 @@
+use std::{collections::HashSet, rc::Rc};
+
+// A name shared by every token spelling it, so copying one around is a reference count
+// increment instead of an allocation. Lookups can still be done with a plain `&str`.
+pub type Symbol = Rc<str>;
+
+#[derive(Debug, Default)]
+pub struct Interner {
+    symbols: HashSet<Symbol>,
+}
+
+impl Interner {
+    pub fn intern(&mut self, name: &str) -> Symbol {
+        match self.symbols.get(name) {
+            Some(symbol) => symbol.clone(),
+            None => {
+                let symbol: Symbol = Rc::from(name);
+                self.symbols.insert(symbol.clone());
+                symbol
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::frontend::tokenizer::tokenize;
+
+    use super::Interner;
+
+    #[test]
+    fn test_intern() {
+        let mut interner = Interner::default();
+        let a = interner.intern("count");
+        let b = interner.intern("count");
+        assert!(Rc::ptr_eq(&a, &b));
+        assert!(!Rc::ptr_eq(&a, &interner.intern("total")));
+
+        let tokens = tokenize("let count = count + 1").unwrap();
+        assert!(Rc::ptr_eq(&tokens[1].lexeme, &tokens[3].lexeme));
+    }
+}
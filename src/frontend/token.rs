@@ -1,6 +1,6 @@
 use std::fmt;
 
-use super::utils::TextSpan;
+use super::{symbol::Symbol, utils::TextSpan};
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum TokenKind {
@@ -13,6 +13,10 @@ pub enum TokenKind {
 
     Let,
     Const,
+    Fn,
+    While,
+    For,
+    In,
     True,
     False,
     None,
@@ -20,11 +24,14 @@ pub enum TokenKind {
     Colon,
     Comma,
     Dot,
+    Question,
 
     OpenParen,
     CloseParen,
     OpenBrace,
     CloseBrace,
+    OpenBracket,
+    CloseBracket,
 
     Plus,
     Minus,
@@ -39,8 +46,12 @@ pub enum TokenKind {
 
     DoubleAmpersand,
     DoublePipe,
+    DoubleQuestion,
+    QuestionDot,
     DoubleEqual,
     BangEqual,
+    TripleEqual,
+    BangDoubleEqual,
     GreaterOrEqual,
     LesserOrEqual,
 }
@@ -50,6 +61,10 @@ impl TokenKind {
         match lexeme {
             "let" => TokenKind::Let,
             "const" => TokenKind::Const,
+            "fn" => TokenKind::Fn,
+            "while" => TokenKind::While,
+            "for" => TokenKind::For,
+            "in" => TokenKind::In,
             "true" => TokenKind::True,
             "false" => TokenKind::False,
             "none" => TokenKind::None,
@@ -70,6 +85,10 @@ impl fmt::Display for TokenKind {
 
             TokenKind::Let => write!(f, "let"),
             TokenKind::Const => write!(f, "const"),
+            TokenKind::Fn => write!(f, "fn"),
+            TokenKind::While => write!(f, "while"),
+            TokenKind::For => write!(f, "for"),
+            TokenKind::In => write!(f, "in"),
             TokenKind::True => write!(f, "true"),
             TokenKind::False => write!(f, "false"),
             TokenKind::None => write!(f, "none"),
@@ -77,11 +96,14 @@ impl fmt::Display for TokenKind {
             TokenKind::Colon => write!(f, ":"),
             TokenKind::Comma => write!(f, ","),
             TokenKind::Dot => write!(f, "."),
+            TokenKind::Question => write!(f, "?"),
 
             TokenKind::OpenParen => write!(f, "("),
             TokenKind::CloseParen => write!(f, ")"),
             TokenKind::OpenBrace => write!(f, "{{"),
             TokenKind::CloseBrace => write!(f, "}}"),
+            TokenKind::OpenBracket => write!(f, "["),
+            TokenKind::CloseBracket => write!(f, "]"),
 
             TokenKind::Plus => write!(f, "+"),
             TokenKind::Minus => write!(f, "-"),
@@ -96,8 +118,12 @@ impl fmt::Display for TokenKind {
 
             TokenKind::DoubleAmpersand => write!(f, "&&"),
             TokenKind::DoublePipe => write!(f, "||"),
+            TokenKind::DoubleQuestion => write!(f, "??"),
+            TokenKind::QuestionDot => write!(f, "?."),
             TokenKind::DoubleEqual => write!(f, "=="),
             TokenKind::BangEqual => write!(f, "!="),
+            TokenKind::TripleEqual => write!(f, "==="),
+            TokenKind::BangDoubleEqual => write!(f, "!=="),
             TokenKind::GreaterOrEqual => write!(f, ">="),
             TokenKind::LesserOrEqual => write!(f, "<="),
         }
@@ -107,7 +133,7 @@ impl fmt::Display for TokenKind {
 #[derive(Debug, PartialEq, Clone)]
 pub struct Token {
     pub kind: TokenKind,
-    pub lexeme: String,
+    pub lexeme: Symbol,
     pub text_span: TextSpan,
 }
 
@@ -115,8 +141,39 @@ impl Token {
     pub fn new(kind: TokenKind, lexeme: String, text_span: TextSpan) -> Self {
         Self {
             kind,
-            lexeme,
+            lexeme: lexeme.into(),
             text_span,
         }
     }
+
+    // Whether the token is an identifier or a keyword, which covers every keyword added
+    // to `TokenKind::get_lexeme_type`.
+    pub fn is_word(&self) -> bool {
+        TokenKind::get_lexeme_type(&self.lexeme) == self.kind
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TriviaKind {
+    Whitespace,
+    Comment,
+}
+
+// Source the tokenizer skips, kept by `tokenize_lossless`. A comment runs up to the end
+// of its line, the line break being whitespace.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+    pub text_span: TextSpan,
+}
+
+// A token along with the trivia before it and its text exactly as written, quotes of
+// strings included. The end of input has no text, its trivia being whatever trails the
+// last token.
+#[derive(Debug, PartialEq, Clone)]
+pub struct LosslessToken {
+    pub leading_trivia: Vec<Trivia>,
+    pub text: String,
+    pub token: Token,
 }
@@ -1,11 +1,13 @@
 use super::{
-    token::{Token, TokenKind},
+    symbol::Interner,
+    token::{LosslessToken, Token, TokenKind, Trivia, TriviaKind},
     utils::{Error, TextSpan},
 };
 
 pub fn tokenize(source_code: &str) -> Result<Vec<Token>, Error> {
     let mut tokens = vec![];
-    let source_code: Vec<char> = format!("{source_code}\0").chars().collect();
+    let mut interner = Interner::default();
+    let source_code: Vec<char> = source_code.chars().collect();
     let mut starting_index = 0;
     let mut current_index = 0;
 
@@ -16,17 +18,45 @@ pub fn tokenize(source_code: &str) -> Result<Vec<Token>, Error> {
         match current_char {
             ' ' | '\t' | '\n' | '\r' => {}
 
-            '\0' => tokens.push(Token::new(
-                TokenKind::Eof,
-                "\0".to_string(),
-                TextSpan::new(starting_index, current_index),
-            )),
+            // The end of input is where the source ends, a NUL in it being a mistake
+            // rather than its end.
+            '\0' => {
+                return Err(Error::new(
+                    "Unexpected NUL character".to_string(),
+                    TextSpan::new(starting_index, current_index),
+                ))
+            }
 
             ':' => tokens.push(Token::new(
                 TokenKind::Colon,
                 source_code[starting_index..current_index].iter().collect(),
                 TextSpan::new(starting_index, current_index),
             )),
+            '?' => {
+                if source_code.get(current_index).is_some() && source_code[current_index] == '?' {
+                    current_index += 1;
+                    tokens.push(Token::new(
+                        TokenKind::DoubleQuestion,
+                        source_code[starting_index..current_index].iter().collect(),
+                        TextSpan::new(starting_index, current_index),
+                    ));
+                } else if source_code.get(current_index).is_some()
+                    && source_code[current_index] == '.'
+                {
+                    current_index += 1;
+                    tokens.push(Token::new(
+                        TokenKind::QuestionDot,
+                        source_code[starting_index..current_index].iter().collect(),
+                        TextSpan::new(starting_index, current_index),
+                    ));
+                } else {
+                    tokens.push(Token::new(
+                        TokenKind::Question,
+                        source_code[starting_index..current_index].iter().collect(),
+                        TextSpan::new(starting_index, current_index),
+                    ));
+                }
+            }
             ',' => tokens.push(Token::new(
                 TokenKind::Comma,
                 source_code[starting_index..current_index].iter().collect(),
@@ -58,6 +88,16 @@ pub fn tokenize(source_code: &str) -> Result<Vec<Token>, Error> {
                 source_code[starting_index..current_index].iter().collect(),
                 TextSpan::new(starting_index, current_index),
             )),
+            '[' => tokens.push(Token::new(
+                TokenKind::OpenBracket,
+                source_code[starting_index..current_index].iter().collect(),
+                TextSpan::new(starting_index, current_index),
+            )),
+            ']' => tokens.push(Token::new(
+                TokenKind::CloseBracket,
+                source_code[starting_index..current_index].iter().collect(),
+                TextSpan::new(starting_index, current_index),
+            )),
 
             '+' => tokens.push(Token::new(
                 TokenKind::Plus,
@@ -77,9 +117,9 @@ pub fn tokenize(source_code: &str) -> Result<Vec<Token>, Error> {
 
             '/' => {
                 if source_code.get(current_index).is_some() && source_code[current_index] == '/' {
+                    // The comment ends before the line break, `\r\n` included.
                     while current_index < source_code.len()
-                        && source_code[current_index] != '\n'
-                        && source_code[current_index] != '\0'
+                        && !matches!(source_code[current_index], '\n' | '\r')
                     {
                         current_index += 1;
                     }
@@ -126,8 +166,14 @@ pub fn tokenize(source_code: &str) -> Result<Vec<Token>, Error> {
             '=' => {
                 if source_code.get(current_index).is_some() && source_code[current_index] == '=' {
                     current_index += 1;
+                    let kind = if source_code.get(current_index) == Some(&'=') {
+                        current_index += 1;
+                        TokenKind::TripleEqual
+                    } else {
+                        TokenKind::DoubleEqual
+                    };
                     tokens.push(Token::new(
-                        TokenKind::DoubleEqual,
+                        kind,
                         source_code[starting_index..current_index].iter().collect(),
                         TextSpan::new(starting_index, current_index),
                     ));
@@ -142,8 +188,14 @@ pub fn tokenize(source_code: &str) -> Result<Vec<Token>, Error> {
             '!' => {
                 if source_code.get(current_index).is_some() && source_code[current_index] == '=' {
                     current_index += 1;
+                    let kind = if source_code.get(current_index) == Some(&'=') {
+                        current_index += 1;
+                        TokenKind::BangDoubleEqual
+                    } else {
+                        TokenKind::BangEqual
+                    };
                     tokens.push(Token::new(
-                        TokenKind::BangEqual,
+                        kind,
                         source_code[starting_index..current_index].iter().collect(),
                         TextSpan::new(starting_index, current_index),
                     ));
@@ -190,18 +242,36 @@ pub fn tokenize(source_code: &str) -> Result<Vec<Token>, Error> {
 
             '"' => {
                 loop {
-                    if source_code[current_index] == '"' {
-                        current_index += 1;
-                        break;
+                    match source_code.get(current_index) {
+                        Some('"') => {
+                            current_index += 1;
+                            break;
+                        }
+                        None => {
+                            return Err(Error::new(
+                                "Unterminated string".to_string(),
+                                TextSpan::new(starting_index, current_index),
+                            ));
+                        }
+                        Some('\0') => {
+                            return Err(Error::new(
+                                "Unexpected NUL character".to_string(),
+                                TextSpan::new(current_index, current_index + 1),
+                            ));
+                        }
+                        // The lexeme keeps escapes as written, they're only checked here.
+                        Some('\\') => match source_code.get(current_index + 1) {
+                            Some(c) if escaped_char(*c).is_some() => current_index += 2,
+                            Some(c) if *c != '\0' => {
+                                return Err(Error::new(
+                                    format!("Unknown escape sequence '\\{c}'"),
+                                    TextSpan::new(current_index, current_index + 1),
+                                ));
+                            }
+                            _ => current_index += 1,
+                        },
+                        Some(_) => current_index += 1,
                     }
-                    if source_code[current_index] == '\0' {
-                        return Err(Error::new(
-                            "Unterminated string".to_string(),
-                            TextSpan::new(starting_index, current_index),
-                        ));
-                    }
-
-                    current_index += 1;
                 }
                 tokens.push(Token::new(
                     TokenKind::String,
@@ -214,27 +284,71 @@ pub fn tokenize(source_code: &str) -> Result<Vec<Token>, Error> {
 
             _ => {
                 if current_char.is_alphabetic() || current_char == '_' {
-                    while source_code[current_index].is_alphanumeric()
-                        || source_code[current_index] == '_'
+                    while source_code
+                        .get(current_index)
+                        .is_some_and(|c| c.is_alphanumeric() || *c == '_')
                     {
                         current_index += 1;
                     }
                     let lexeme: String =
                         source_code[starting_index..current_index].iter().collect();
-                    tokens.push(Token::new(
-                        TokenKind::get_lexeme_type(&lexeme),
-                        lexeme,
-                        TextSpan::new(starting_index, current_index),
-                    ));
+                    tokens.push(Token {
+                        kind: TokenKind::get_lexeme_type(&lexeme),
+                        lexeme: interner.intern(&lexeme),
+                        text_span: TextSpan::new(starting_index, current_index),
+                    });
                 } else if current_char.is_ascii_digit() {
-                    while source_code[current_index].is_ascii_digit()
-                        || source_code[current_index] == '.'
-                    {
-                        current_index += 1;
+                    // A number right after a dot is an element of a tuple, so `t.0.1` is
+                    // two accesses rather than one of element `0.1`.
+                    let is_element = tokens
+                        .last()
+                        .is_some_and(|token: &Token| token.kind == TokenKind::Dot);
+                    // A dot is only part of the number with a digit after it, so `5.abs`
+                    // is an access and `5.` a number missing its property.
+                    let mut points = 0;
+                    loop {
+                        match (
+                            source_code.get(current_index),
+                            source_code.get(current_index + 1),
+                        ) {
+                            (Some(c), _) if c.is_ascii_digit() || *c == '_' => current_index += 1,
+                            (Some('.'), Some(next)) if !is_element && next.is_ascii_digit() => {
+                                points += 1;
+                                current_index += 1;
+                            }
+                            _ => break,
+                        }
+                    }
+                    let lexeme: String =
+                        source_code[starting_index..current_index].iter().collect();
+                    if points > 1 {
+                        return Err(Error::new(
+                            format!("Invalid number literal '{lexeme}'"),
+                            TextSpan::new(starting_index, current_index),
+                        ));
+                    }
+                    // Underscores separate digits, `1_000`, and are left out of the lexeme.
+                    // The number starts with a digit, so only the ones after it can be
+                    // misplaced: `_1` is an identifier.
+                    let misplaced = (starting_index..current_index).find(|&index| {
+                        source_code[index] == '_'
+                            && !(source_code[index - 1].is_ascii_digit()
+                                && source_code
+                                    .get(index + 1)
+                                    .is_some_and(|next| next.is_ascii_digit()))
+                    });
+                    if let Some(index) = misplaced {
+                        return Err(Error::new(
+                            format!("Invalid number literal '{lexeme}'"),
+                            TextSpan::new(index, index + 1),
+                        )
+                        .with_note(
+                            "underscores in numbers must be between two digits".to_string(),
+                        ));
                     }
                     tokens.push(Token::new(
                         TokenKind::Number,
-                        source_code[starting_index..current_index].iter().collect(),
+                        lexeme.replace('_', ""),
                         TextSpan::new(starting_index, current_index),
                     ));
                 } else {
@@ -247,18 +361,104 @@ pub fn tokenize(source_code: &str) -> Result<Vec<Token>, Error> {
         }
         starting_index = current_index;
     }
+    tokens.push(Token::new(
+        TokenKind::Eof,
+        "\0".to_string(),
+        TextSpan::new(source_code.len(), source_code.len() + 1),
+    ));
 
     Ok(tokens)
 }
 
+// The char an escape sequence of a string stands for, by the char after its backslash.
+pub fn escaped_char(c: char) -> Option<char> {
+    match c {
+        'n' => Some('\n'),
+        't' => Some('\t'),
+        'r' => Some('\r'),
+        '0' => Some('\0'),
+        '\\' => Some('\\'),
+        '"' => Some('"'),
+        _ => None,
+    }
+}
+
+// The tokens of `tokenize` along with the whitespace and comments it skips, so that
+// `reconstruct` gives the source back exactly, for tools that rewrite code while
+// keeping its layout.
+pub fn tokenize_lossless(source_code: &str) -> Result<Vec<LosslessToken>, Error> {
+    let chars: Vec<char> = source_code.chars().collect();
+    let mut previous_end = 0;
+    let mut lossless_tokens = vec![];
+    for token in tokenize(source_code)? {
+        let TextSpan {
+            starting_index,
+            ending_index,
+        } = token.text_span;
+        lossless_tokens.push(LosslessToken {
+            leading_trivia: trivia(&chars, previous_end, starting_index),
+            text: chars[starting_index.min(chars.len())..ending_index.min(chars.len())]
+                .iter()
+                .collect(),
+            token,
+        });
+        previous_end = ending_index;
+    }
+    Ok(lossless_tokens)
+}
+
+// The source the tokens of `tokenize_lossless` came from.
+pub fn reconstruct(tokens: &[LosslessToken]) -> String {
+    let mut source_code = String::new();
+    for token in tokens {
+        for trivia in &token.leading_trivia {
+            source_code.push_str(&trivia.text);
+        }
+        source_code.push_str(&token.text);
+    }
+    source_code
+}
+
+// Splits the chars between two tokens, which the tokenizer skipped, into comments and
+// runs of whitespace.
+fn trivia(chars: &[char], starting_index: usize, ending_index: usize) -> Vec<Trivia> {
+    let mut trivia = vec![];
+    let mut start = starting_index;
+    while start < ending_index {
+        let kind = if chars[start] == '/' {
+            TriviaKind::Comment
+        } else {
+            TriviaKind::Whitespace
+        };
+        let mut end = start + 1;
+        while end < ending_index
+            && match kind {
+                TriviaKind::Comment => !matches!(chars[end], '\n' | '\r'),
+                TriviaKind::Whitespace => chars[end] != '/',
+            }
+        {
+            end += 1;
+        }
+        trivia.push(Trivia {
+            kind,
+            text: chars[start..end].iter().collect(),
+            text_span: TextSpan::new(start, end),
+        });
+        start = end;
+    }
+    trivia
+}
+
 #[cfg(test)]
 mod tests {
+    use std::{fs, path::Path};
+
     use crate::frontend::{
-        token::{Token, TokenKind},
+        token::{Token, TokenKind, Trivia, TriviaKind},
         utils::TextSpan,
     };
 
-    use super::tokenize;
+    use super::{reconstruct, tokenize, tokenize_lossless};
 
     #[test]
     fn test_tokenize() {
@@ -276,10 +476,9 @@ mod tests {
         ];
         let tokens = tokenize(source_code).unwrap();
         assert_eq!(tokens, expected_tokens);
-        for i in 0..expected_tokens.len() - 1 {
-            let token = &tokens[i];
+        for token in tokens.iter().take(expected_tokens.len() - 1) {
             assert_eq!(
-                token.lexeme,
+                *token.lexeme,
                 source_code[token.text_span.starting_index..token.text_span.ending_index]
                     .to_string()
             );
@@ -299,10 +498,9 @@ mod tests {
         ];
         let tokens = tokenize(source_code).unwrap();
         assert_eq!(tokens, expected_tokens);
-        for i in 0..expected_tokens.len() - 1 {
-            let token = &tokens[i];
+        for token in tokens.iter().take(expected_tokens.len() - 1) {
             assert_eq!(
-                token.lexeme,
+                *token.lexeme,
                 source_code[token.text_span.starting_index..token.text_span.ending_index]
                     .to_string()
             );
@@ -322,10 +520,9 @@ mod tests {
         ];
         let tokens = tokenize(source_code).unwrap();
         assert_eq!(tokens, expected_tokens);
-        for i in 0..expected_tokens.len() - 1 {
-            let token = &tokens[i];
+        for token in tokens.iter().take(expected_tokens.len() - 1) {
             assert_eq!(
-                token.lexeme,
+                *token.lexeme,
                 source_code[token.text_span.starting_index + 1..token.text_span.ending_index - 1]
                     .to_string()
             );
@@ -334,7 +531,7 @@ mod tests {
 
     #[test]
     fn test_tokenize_with_single_and_double_character_tokens() {
-        let source_code = "=(+-*/)!&&&|||><>=<===!={}:,.";
+        let source_code = "=(+-*/)!&&&|||><>=<===!={}:,.???.";
         let expected_tokens = vec![
             Token::new(TokenKind::Equal, "=".to_string(), TextSpan::new(0, 1)),
             Token::new(TokenKind::OpenParen, "(".to_string(), TextSpan::new(1, 2)),
@@ -387,20 +584,53 @@ mod tests {
             Token::new(TokenKind::Colon, ":".to_string(), TextSpan::new(26, 27)),
             Token::new(TokenKind::Comma, ",".to_string(), TextSpan::new(27, 28)),
             Token::new(TokenKind::Dot, ".".to_string(), TextSpan::new(28, 29)),
-            Token::new(TokenKind::Eof, "\0".to_string(), TextSpan::new(29, 30)),
+            Token::new(
+                TokenKind::DoubleQuestion,
+                "??".to_string(),
+                TextSpan::new(29, 31),
+            ),
+            Token::new(
+                TokenKind::QuestionDot,
+                "?.".to_string(),
+                TextSpan::new(31, 33),
+            ),
+            Token::new(TokenKind::Eof, "\0".to_string(), TextSpan::new(33, 34)),
         ];
         let tokens = tokenize(source_code).unwrap();
         assert_eq!(tokens, expected_tokens);
-        for i in 0..expected_tokens.len() - 1 {
-            let token = &tokens[i];
+        for token in tokens.iter().take(expected_tokens.len() - 1) {
             assert_eq!(
-                token.lexeme,
+                *token.lexeme,
                 source_code[token.text_span.starting_index..token.text_span.ending_index]
                     .to_string()
             );
         }
     }
 
+    #[test]
+    fn test_tokenize_with_triple_character_tokens() {
+        let kinds: Vec<TokenKind> = tokenize("a === b !== c == d ====")
+            .unwrap()
+            .into_iter()
+            .map(|token| token.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Identifier,
+                TokenKind::TripleEqual,
+                TokenKind::Identifier,
+                TokenKind::BangDoubleEqual,
+                TokenKind::Identifier,
+                TokenKind::DoubleEqual,
+                TokenKind::Identifier,
+                TokenKind::TripleEqual,
+                TokenKind::Equal,
+                TokenKind::Eof,
+            ]
+        );
+    }
+
     #[test]
     fn test_tokenize_with_empty_input() {
         let source_code = "";
@@ -411,10 +641,9 @@ mod tests {
         )];
         let tokens = tokenize(source_code).unwrap();
         assert_eq!(tokens, expected_tokens);
-        for i in 0..expected_tokens.len() - 1 {
-            let token = &tokens[i];
+        for token in tokens.iter().take(expected_tokens.len() - 1) {
             assert_eq!(
-                token.lexeme,
+                *token.lexeme,
                 source_code[token.text_span.starting_index..token.text_span.ending_index]
                     .to_string()
             );
@@ -431,10 +660,9 @@ mod tests {
         )];
         let tokens = tokenize(source_code).unwrap();
         assert_eq!(tokens, expected_tokens);
-        for i in 0..expected_tokens.len() - 1 {
-            let token = &tokens[i];
+        for token in tokens.iter().take(expected_tokens.len() - 1) {
             assert_eq!(
-                token.lexeme,
+                *token.lexeme,
                 source_code[token.text_span.starting_index..token.text_span.ending_index]
                     .to_string()
             );
@@ -451,13 +679,254 @@ mod tests {
         )];
         let tokens = tokenize(source_code).unwrap();
         assert_eq!(tokens, expected_tokens);
-        for i in 0..expected_tokens.len() - 1 {
-            let token = &tokens[i];
+        for token in tokens.iter().take(expected_tokens.len() - 1) {
             assert_eq!(
-                token.lexeme,
+                *token.lexeme,
                 source_code[token.text_span.starting_index..token.text_span.ending_index]
                     .to_string()
             );
         }
     }
+
+    #[test]
+    fn test_tokenize_lossless() {
+        let tokens = tokenize_lossless("let a = 1 // one\r\n  \"b\"  ").unwrap();
+        assert_eq!(
+            tokens[3].leading_trivia,
+            vec![Trivia {
+                kind: TriviaKind::Whitespace,
+                text: " ".to_string(),
+                text_span: TextSpan::new(7, 8),
+            }]
+        );
+        assert_eq!(tokens[4].text, "\"b\"");
+        assert_eq!(
+            tokens[4].leading_trivia,
+            vec![
+                Trivia {
+                    kind: TriviaKind::Whitespace,
+                    text: " ".to_string(),
+                    text_span: TextSpan::new(9, 10),
+                },
+                Trivia {
+                    kind: TriviaKind::Comment,
+                    text: "// one".to_string(),
+                    text_span: TextSpan::new(10, 16),
+                },
+                Trivia {
+                    kind: TriviaKind::Whitespace,
+                    text: "\r\n  ".to_string(),
+                    text_span: TextSpan::new(16, 20),
+                },
+            ]
+        );
+        assert_eq!(tokens[5].token.kind, TokenKind::Eof);
+        assert_eq!(tokens[5].text, "");
+        assert_eq!(tokens[5].leading_trivia[0].text, "  ");
+        // The tokens are those of `tokenize`.
+        assert_eq!(
+            tokens
+                .into_iter()
+                .map(|token| token.token)
+                .collect::<Vec<_>>(),
+            tokenize("let a = 1 // one\r\n  \"b\"  ").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_reconstruct() {
+        let mut corpus: Vec<String> = [
+            "",
+            "   ",
+            "let a=1+2//no space\n",
+            "let  a\t=\t[ 1 ,2 ,\t3 ]  \n\n\n",
+            "fn f(x) {\r\n    x * 2\r\n}\r\nf(2) // done\r\n",
+            "// only a comment",
+            "{ a : \"é  ü\" , b:(1,) }.a   \t ",
+            "let x = 1 /  2 // a / b\n\t// another\n  x",
+            "t.0.1 == 2.5 ? \"a\" : \"\"",
+        ]
+        .map(String::from)
+        .to_vec();
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+        for directory in fs::read_dir(fixtures).unwrap() {
+            for entry in fs::read_dir(directory.unwrap().path()).unwrap() {
+                let bytes = fs::read(entry.unwrap().path()).unwrap();
+                corpus.push(String::from_utf8_lossy(&bytes).to_string());
+            }
+        }
+        for source_code in corpus {
+            // Inputs the tokenizer rejects have no tokens to give them back.
+            if let Ok(tokens) = tokenize_lossless(&source_code) {
+                assert_eq!(reconstruct(&tokens), source_code);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tokenize_at_the_end_of_input() {
+        for (source_code, kinds) in [
+            ("abc", vec![TokenKind::Identifier]),
+            ("let x_1", vec![TokenKind::Let, TokenKind::Identifier]),
+            ("42", vec![TokenKind::Number]),
+            ("4.", vec![TokenKind::Number, TokenKind::Dot]),
+            ("4.5", vec![TokenKind::Number]),
+            (
+                "t.0",
+                vec![TokenKind::Identifier, TokenKind::Dot, TokenKind::Number],
+            ),
+            ("a.", vec![TokenKind::Identifier, TokenKind::Dot]),
+            (".", vec![TokenKind::Dot]),
+        ] {
+            let tokens = tokenize(source_code).unwrap();
+            let length = source_code.chars().count();
+            assert_eq!(
+                tokens.last(),
+                Some(&Token::new(
+                    TokenKind::Eof,
+                    "\0".to_string(),
+                    TextSpan::new(length, length + 1)
+                ))
+            );
+            assert_eq!(
+                tokens[..tokens.len() - 1]
+                    .iter()
+                    .map(|token| token.kind.clone())
+                    .collect::<Vec<_>>(),
+                kinds,
+                "{source_code:?}"
+            );
+            assert_eq!(tokens[tokens.len() - 2].text_span.ending_index, length);
+        }
+    }
+
+    #[test]
+    fn test_tokenize_with_nul() {
+        for (source_code, text_span) in [
+            ("1\0 + 2", TextSpan::new(1, 2)),
+            ("\0", TextSpan::new(0, 1)),
+            ("let a = \"b\0c\"", TextSpan::new(10, 11)),
+        ] {
+            let error = tokenize(source_code).unwrap_err();
+            assert_eq!(error.message, "Unexpected NUL character");
+            assert_eq!(error.text_span, text_span, "{source_code:?}");
+        }
+        // A comment runs to the end of its line whatever it holds.
+        assert_eq!(tokenize("// a \0 b").unwrap().len(), 1);
+        assert_eq!(tokenize("\"ab").unwrap_err().message, "Unterminated string");
+    }
+
+    #[test]
+    fn test_tokenize_numbers_with_dots() {
+        for (source_code, lexemes) in [
+            ("1.5", vec!["1.5"]),
+            ("5.abs", vec!["5", ".", "abs"]),
+            ("1.5.abs", vec!["1.5", ".", "abs"]),
+            ("5.", vec!["5", "."]),
+            ("5..2", vec!["5", ".", ".", "2"]),
+            ("t.0.1", vec!["t", ".", "0", ".", "1"]),
+        ] {
+            let tokens = tokenize(source_code).unwrap();
+            assert_eq!(
+                tokens[..tokens.len() - 1]
+                    .iter()
+                    .map(|token| &*token.lexeme)
+                    .collect::<Vec<_>>(),
+                lexemes,
+                "{source_code}"
+            );
+        }
+        for (source_code, text_span) in [
+            ("1.2.3", TextSpan::new(0, 5)),
+            ("let a = 10.0.25.1 + 1", TextSpan::new(8, 17)),
+        ] {
+            let error = tokenize(source_code).unwrap_err();
+            assert_eq!(
+                error.message,
+                format!(
+                    "Invalid number literal '{}'",
+                    &source_code[text_span.starting_index..text_span.ending_index]
+                )
+            );
+            assert_eq!(error.text_span, text_span);
+        }
+    }
+
+    #[test]
+    fn test_tokenize_numbers_with_underscores() {
+        for (source_code, lexemes) in [
+            ("1_000_000_000", vec!["1000000000"]),
+            ("1_0.2_5", vec!["10.25"]),
+            ("_1", vec!["_1"]),
+            ("1._5", vec!["1", ".", "_5"]),
+        ] {
+            let tokens = tokenize(source_code).unwrap();
+            assert_eq!(
+                tokens[..tokens.len() - 1]
+                    .iter()
+                    .map(|token| &*token.lexeme)
+                    .collect::<Vec<_>>(),
+                lexemes,
+                "{source_code}"
+            );
+        }
+        assert_eq!(tokenize("_1").unwrap()[0].kind, TokenKind::Identifier);
+        for (source_code, lexeme, text_span) in [
+            ("1_", "1_", TextSpan::new(1, 2)),
+            ("let a = 1__000", "1__000", TextSpan::new(9, 10)),
+            ("1_.5", "1_.5", TextSpan::new(1, 2)),
+            ("2.5_ + 1", "2.5_", TextSpan::new(3, 4)),
+            ("1_a", "1_", TextSpan::new(1, 2)),
+        ] {
+            let error = tokenize(source_code).unwrap_err();
+            assert_eq!(
+                error.message,
+                format!("Invalid number literal '{lexeme}'"),
+                "{source_code}"
+            );
+            assert_eq!(error.text_span, text_span, "{source_code}");
+            assert_eq!(
+                error.notes,
+                vec!["underscores in numbers must be between two digits".to_string()]
+            );
+        }
+    }
+
+    #[test]
+    fn test_tokenize_string_escapes() {
+        // The lexeme is the source between the quotes, escapes as written.
+        for (source_code, lexeme) in [
+            (r#""a\nb\t\r\0""#, r"a\nb\t\r\0"),
+            (r#""ends in \\""#, r"ends in \\"),
+            (r#""say \"hi\"""#, r#"say \"hi\""#),
+            (r#""\"""#, r#"\""#),
+        ] {
+            let tokens = tokenize(source_code).unwrap();
+            assert_eq!(tokens[0].kind, TokenKind::String);
+            assert_eq!(*tokens[0].lexeme, *lexeme);
+            assert_eq!(
+                tokens[0].text_span,
+                TextSpan::new(0, source_code.chars().count())
+            );
+        }
+        for (source_code, message, text_span) in [
+            (
+                r#"let s = "a\qb""#,
+                r"Unknown escape sequence '\q'",
+                TextSpan::new(10, 11),
+            ),
+            (
+                r#""é\ ""#,
+                r"Unknown escape sequence '\ '",
+                TextSpan::new(2, 3),
+            ),
+            // An escaped quote doesn't close the string, nor does a backslash at the end.
+            (r#""a\""#, "Unterminated string", TextSpan::new(0, 4)),
+            (r#""a\"#, "Unterminated string", TextSpan::new(0, 3)),
+        ] {
+            let error = tokenize(source_code).unwrap_err();
+            assert_eq!(error.message, message, "{source_code}");
+            assert_eq!(error.text_span, text_span, "{source_code}");
+        }
+    }
 }
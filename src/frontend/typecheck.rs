@@ -0,0 +1,438 @@
+use std::collections::HashMap;
+
+use super::{
+    ast::{Expression, Program, Statement, TYPE_NAMES},
+    symbol::Symbol,
+    token::{Token, TokenKind},
+    utils::{Error, TextSpan},
+};
+
+// The kinds of values a `for` loop can go over.
+const ITERABLE_TYPES: [&str; 5] = ["array", "tuple", "set", "string", "object"];
+
+// The type of an expression as far as it can be told without running the program,
+// `Any` standing for everything that can't.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Type {
+    Any,
+    Named(&'static str),
+}
+
+impl Type {
+    fn from_annotation(annotation: &Token) -> Self {
+        match TYPE_NAMES
+            .iter()
+            .find(|type_name| **type_name == &*annotation.lexeme)
+        {
+            Some(type_name) => Type::Named(type_name),
+            None => Type::Any,
+        }
+    }
+
+    fn is(&self, type_name: &str) -> bool {
+        matches!(self, Type::Named(name) if *name == type_name)
+    }
+
+    // Whether a value of this type could be a value of the given type.
+    fn may_be(&self, type_name: &str) -> bool {
+        matches!(self, Type::Any) || self.is(type_name)
+    }
+}
+
+// Checks the types of a program without evaluating it and reports every mismatch it
+// can prove. Only annotated `let`s and `const`s keep their type through identifiers,
+// other variables may be assigned anything and are taken for `Any`.
+pub fn typecheck(program: &Program) -> Vec<Error> {
+    let mut checker = TypeChecker {
+        scopes: vec![HashMap::new()],
+        errors: vec![],
+    };
+    checker.check_program(program);
+    checker.errors
+}
+
+struct TypeChecker {
+    scopes: Vec<HashMap<Symbol, Type>>,
+    errors: Vec<Error>,
+}
+
+impl TypeChecker {
+    fn check_program(&mut self, program: &Program) {
+        for statement in program {
+            self.check_statement(statement);
+        }
+    }
+
+    fn define(&mut self, identifier: &Symbol, type_: Type) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(identifier.clone(), type_);
+        }
+    }
+
+    fn lookup(&self, identifier: &str) -> Type {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(identifier).copied())
+            .unwrap_or(Type::Any)
+    }
+
+    fn report(&mut self, message: String, text_span: TextSpan) {
+        self.errors.push(Error::new(message, text_span));
+    }
+
+    fn check_binding(
+        &mut self,
+        identifier: &Token,
+        annotation: &Option<Token>,
+        expression: &Expression,
+        is_constant: bool,
+    ) {
+        let type_ = self.check_expression(expression);
+        let declared = match annotation {
+            Some(annotation) => {
+                let declared = Type::from_annotation(annotation);
+                if let (Type::Named(declared), Type::Named(actual)) = (declared, type_) {
+                    if declared != actual {
+                        self.report(
+                            format!(
+                                "Can't bind a {actual} to '{}' as it's annotated as a {declared}",
+                                identifier.lexeme
+                            ),
+                            annotation.text_span.clone(),
+                        );
+                    }
+                }
+                declared
+            }
+            None if is_constant => type_,
+            None => Type::Any,
+        };
+        self.define(&identifier.lexeme, declared);
+    }
+
+    fn check_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Let(l) => {
+                self.check_binding(&l.identifier, &l.annotation, &l.expression, false)
+            }
+            Statement::Const(c) => {
+                self.check_binding(&c.identifier, &c.annotation, &c.expression, true)
+            }
+            Statement::Destructuring(d) => {
+                self.check_expression(&d.expression);
+                for identifier in &d.identifiers {
+                    self.define(&identifier.lexeme, Type::Any);
+                }
+            }
+            Statement::Function(f) => {
+                self.define(&f.identifier.lexeme, Type::Named("function"));
+                self.check_function(&f.function.parameters, &f.function.body);
+            }
+            Statement::While(w) => {
+                if let Type::Named(condition) = self.check_expression(&w.condition) {
+                    if condition != "boolean" {
+                        self.report(
+                            format!("Can't loop while a {condition} as it's not a boolean"),
+                            w.condition.text_span(),
+                        );
+                    }
+                }
+                self.scopes.push(HashMap::new());
+                self.check_program(&w.body);
+                self.scopes.pop();
+            }
+            Statement::For(f) => {
+                if let Type::Named(iterable) = self.check_expression(&f.iterable) {
+                    if !ITERABLE_TYPES.contains(&iterable) {
+                        self.report(
+                            format!("Can't iterate over a {iterable}"),
+                            f.iterable.text_span(),
+                        );
+                    }
+                }
+                self.scopes
+                    .push(HashMap::from([(f.identifier.lexeme.clone(), Type::Any)]));
+                self.check_program(&f.body);
+                self.scopes.pop();
+            }
+            Statement::Expression(e) => {
+                self.check_expression(e);
+            }
+        }
+    }
+
+    fn check_function(&mut self, parameters: &[Token], body: &Program) {
+        self.scopes.push(
+            parameters
+                .iter()
+                .map(|parameter| (parameter.lexeme.clone(), Type::Any))
+                .collect(),
+        );
+        self.check_program(body);
+        self.scopes.pop();
+    }
+
+    fn check_expression(&mut self, expression: &Expression) -> Type {
+        match expression {
+            Expression::None(_) => Type::Named("none"),
+            Expression::Boolean(_) => Type::Named("boolean"),
+            Expression::Numeric(_) => Type::Named("number"),
+            Expression::String(_) => Type::Named("string"),
+            Expression::Object(o) => {
+                for pair in &o.pairs {
+                    self.check_expression(&pair.value);
+                }
+                Type::Named("object")
+            }
+            Expression::Tuple(t) => {
+                for element in &t.elements {
+                    self.check_expression(element);
+                }
+                Type::Named("tuple")
+            }
+            Expression::Array(a) => {
+                for element in &a.elements {
+                    self.check_expression(element);
+                }
+                Type::Named("array")
+            }
+            Expression::Function(f) => {
+                self.check_function(&f.parameters, &f.body);
+                Type::Named("function")
+            }
+            // Declarations evaluate to none, so a block ending with one does too.
+            Expression::Block(b) => {
+                self.scopes.push(HashMap::new());
+                let mut type_ = Type::Named("none");
+                for statement in &b.statements {
+                    type_ = match statement {
+                        Statement::Expression(e) => self.check_expression(e),
+                        statement => {
+                            self.check_statement(statement);
+                            Type::Named("none")
+                        }
+                    };
+                }
+                self.scopes.pop();
+                type_
+            }
+            Expression::Identifier(i) => self.lookup(&i.identifier.lexeme),
+            Expression::Access(a) => {
+                self.check_expression(&a.object);
+                Type::Any
+            }
+            Expression::Index(i) => {
+                self.check_expression(&i.object);
+                self.check_expression(&i.index);
+                Type::Any
+            }
+            Expression::Call(c) => {
+                self.check_expression(&c.callee);
+                for argument in &c.arguments {
+                    self.check_expression(argument);
+                }
+                Type::Any
+            }
+            // Only a plain variable takes the type of the value, a path changes a part of it.
+            Expression::Assignment(a) => {
+                let Expression::Identifier(target) = &*a.target else {
+                    self.check_expression(&a.target);
+                    return self.check_expression(&a.expression);
+                };
+                let type_ = self.check_expression(&a.expression);
+                let declared = self.lookup(&target.identifier.lexeme);
+                if let (Type::Named(declared), Type::Named(actual)) = (declared, type_) {
+                    if declared != actual {
+                        self.report(
+                            format!(
+                                "Can't assign a {actual} to '{}' as it's annotated as a {declared}",
+                                target.identifier.lexeme
+                            ),
+                            a.text_span(),
+                        );
+                    }
+                }
+                type_
+            }
+            Expression::Conditional(c) => {
+                let condition = self.check_expression(&c.condition);
+                if let Type::Named(name) = condition {
+                    if name != "boolean" {
+                        self.report(
+                            format!("Can't use a {name} as a condition as it's not a boolean"),
+                            c.condition.text_span(),
+                        );
+                    }
+                }
+                let consequent = self.check_expression(&c.consequent);
+                let alternative = self.check_expression(&c.alternative);
+                match (consequent, alternative) {
+                    (Type::Named(a), Type::Named(b)) if a == b => Type::Named(a),
+                    _ => Type::Any,
+                }
+            }
+            Expression::Unary(u) => {
+                let right = self.check_expression(&u.right);
+                let expected = match u.operator.kind {
+                    TokenKind::Bang => "boolean",
+                    _ => "number",
+                };
+                if !right.may_be(expected) {
+                    self.report_operand(&u.operator, &[right], u.text_span());
+                }
+                Type::Named(expected)
+            }
+            Expression::Binary(b) => {
+                let left = self.check_expression(&b.left);
+                let right = self.check_expression(&b.right);
+                // Objects may overload any operator, so nothing is known about the result
+                // unless the left operand is known not to be one.
+                if left.may_be("object") {
+                    return Type::Any;
+                }
+                let (operands, result) = match b.operator.kind {
+                    TokenKind::Plus => {
+                        if left.is("string") || right.is("string") {
+                            ("string", "string")
+                        } else {
+                            ("number", "number")
+                        }
+                    }
+                    TokenKind::Minus | TokenKind::Asterisk | TokenKind::Slash => {
+                        ("number", "number")
+                    }
+                    TokenKind::Greater
+                    | TokenKind::Lesser
+                    | TokenKind::GreaterOrEqual
+                    | TokenKind::LesserOrEqual => ("number", "boolean"),
+                    // They take any operands and give back one of them.
+                    TokenKind::DoubleAmpersand
+                    | TokenKind::DoublePipe
+                    | TokenKind::DoubleQuestion => {
+                        return if left == right { left } else { Type::Any };
+                    }
+                    _ => return Type::Named("boolean"),
+                };
+                if !left.may_be(operands) || !right.may_be(operands) {
+                    self.report_operand(&b.operator, &[left, right], b.text_span());
+                }
+                Type::Named(result)
+            }
+        }
+    }
+
+    fn report_operand(&mut self, operator: &Token, operands: &[Type], text_span: TextSpan) {
+        let operands: Vec<String> = operands
+            .iter()
+            .map(|operand| match operand {
+                Type::Named(name) => format!("a {name}"),
+                Type::Any => "a value".to_string(),
+            })
+            .collect();
+        self.report(
+            format!(
+                "Can't use '{}' with {}",
+                operator.lexeme,
+                operands.join(" and ")
+            ),
+            text_span,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frontend::{parser::parse, tokenizer::tokenize, utils::TextSpan};
+
+    use super::typecheck;
+
+    fn errors(src: &str) -> Vec<(String, TextSpan)> {
+        typecheck(&parse(tokenize(src).unwrap()).unwrap())
+            .into_iter()
+            .map(|error| (error.message, error.text_span))
+            .collect()
+    }
+
+    #[test]
+    fn test_typecheck_accepts() {
+        for src in [
+            "let n: number = 1 + 2 * 3",
+            "let s: string = \"a\" + \"b\" let t: string = s + \"c\"",
+            "let b: boolean = 1 < 2 && !(\"a\" == 1)",
+            "let o: object = { a: 1 } let p = o + o",
+            "let x = \"a\" x = 1 x - 1",
+            "let f = fn(a) { a - 1 } let n: number = f(\"dynamic\")",
+            "const n = 1 let m: number = n * 2",
+            "let n: number = len(\"abc\") n = n + 1",
+            "let i = 0 while i < 3 { let s: string = \"a\" i = i + 1 } let s: number = 1",
+            "let n: number = 1 < 2 ? 1 : 2 let x = true ? 1 : \"a\" x = \"b\"",
+            "let port: number = 8080 || 80 let name = none || \"fns\" name = 1",
+        ] {
+            assert_eq!(errors(src), vec![], "{src}");
+        }
+    }
+
+    #[test]
+    fn test_typecheck_rejects() {
+        assert_eq!(
+            errors("for x in 1 {}"),
+            vec![(
+                "Can't iterate over a number".to_string(),
+                TextSpan::new(9, 10)
+            )]
+        );
+        assert_eq!(
+            errors("while 1 + 1 {}"),
+            vec![(
+                "Can't loop while a number as it's not a boolean".to_string(),
+                TextSpan::new(6, 11)
+            )]
+        );
+        assert_eq!(
+            errors("let n: number = \"hi\""),
+            vec![(
+                "Can't bind a string to 'n' as it's annotated as a number".to_string(),
+                TextSpan::new(7, 13)
+            )]
+        );
+        assert_eq!(
+            errors("\"a\" - 1"),
+            vec![(
+                "Can't use '-' with a string and a number".to_string(),
+                TextSpan::new(0, 7)
+            )]
+        );
+        assert_eq!(
+            errors("let n: number = 1 let f = fn() { n = true }"),
+            vec![(
+                "Can't assign a boolean to 'n' as it's annotated as a number".to_string(),
+                TextSpan::new(33, 41)
+            )]
+        );
+        assert_eq!(
+            errors("const s = \"a\" let b: boolean = s + 1 let c = -true"),
+            vec![
+                (
+                    "Can't use '+' with a string and a number".to_string(),
+                    TextSpan::new(31, 36)
+                ),
+                (
+                    "Can't bind a string to 'b' as it's annotated as a boolean".to_string(),
+                    TextSpan::new(21, 28)
+                ),
+                (
+                    "Can't use '-' with a boolean".to_string(),
+                    TextSpan::new(45, 50)
+                ),
+            ]
+        );
+        assert_eq!(
+            errors("let s: string = 1 ? \"a\" : 2"),
+            vec![(
+                "Can't use a number as a condition as it's not a boolean".to_string(),
+                TextSpan::new(16, 17)
+            ),]
+        );
+    }
+}
@@ -12,43 +12,107 @@ impl TextSpan {
         }
     }
 
+    // Joins two spans, which isn't the arithmetic `Add` clippy takes it for.
+    #[allow(clippy::should_implement_trait)]
     pub fn add(starting_span: Self, ending_span: Self) -> Self {
         Self {
             starting_index: starting_span.starting_index,
             ending_index: ending_span.ending_index,
         }
     }
+
+    // The span moved `offset` chars further, e.g. into the document a snippet was taken
+    // from.
+    pub fn shifted(&self, offset: usize) -> Self {
+        Self::new(self.starting_index + offset, self.ending_index + offset)
+    }
+
+    // The line and column the span starts at, both counted from 1.
+    pub fn position(&self, source_code: &str) -> (usize, usize) {
+        let mut line = 1;
+        let mut column = 1;
+
+        let chars: Vec<char> = source_code.chars().take(self.starting_index).collect();
+        for index in 0..chars.len() {
+            if ends_line(&chars, index) {
+                line += 1;
+                column = 1;
+            } else if chars[index] != '\r' {
+                column += 1;
+            }
+        }
+
+        (line, column)
+    }
+}
+
+// Whether the char at `index` ends a line. Lines end with `\n`, `\r\n` or a lone `\r`,
+// the `\n` ending the line of a `\r\n`.
+pub fn ends_line(chars: &[char], index: usize) -> bool {
+    match chars[index] {
+        '\n' => true,
+        '\r' => chars.get(index + 1) != Some(&'\n'),
+        _ => false,
+    }
 }
 
 #[derive(Debug)]
 pub struct Error {
     pub message: String,
     pub text_span: TextSpan,
+    // The span of the larger piece of code the error happened in, when the error points
+    // at a part of it.
+    pub context: Option<TextSpan>,
+    // What was being done when the error happened, innermost first, e.g. `while
+    // initializing 'x'`.
+    pub notes: Vec<String>,
 }
 
 impl Error {
     pub fn new(message: String, text_span: TextSpan) -> Self {
-        Self { message, text_span }
+        Self {
+            message,
+            text_span,
+            context: None,
+            notes: vec![],
+        }
     }
 
-    pub(crate) fn report(&self, source_code: &str) {
-        let mut line = 1;
-        let mut column = 1;
+    pub fn with_context(mut self, context: TextSpan) -> Self {
+        self.context = Some(context);
+        self
+    }
 
-        for (index, char) in source_code.chars().enumerate() {
-            if index == self.text_span.starting_index {
-                break;
-            }
+    pub fn with_note(mut self, note: String) -> Self {
+        self.notes.push(note);
+        self
+    }
 
-            if char == '\n' {
-                line += 1;
-                column = 1;
-            } else {
-                column += 1;
-            }
-        }
+    // Maps both the span and the context, for hosts translating positions into their
+    // own documents.
+    pub fn map_spans(mut self, map: impl Fn(TextSpan) -> TextSpan) -> Self {
+        self.text_span = map(self.text_span);
+        self.context = self.context.map(&map);
+        self
+    }
 
+    pub fn report(&self, source_code: &str) {
+        let (line, column) = self.text_span.position(source_code);
         eprintln!("[error in line: {line}, column: {column}]");
         eprintln!("Error: {}", self.message);
+        if let Some(context) = &self.context {
+            let text: String = source_code
+                .chars()
+                .skip(context.starting_index)
+                .take(context.ending_index - context.starting_index)
+                .collect();
+            match text.split_once(['\n', '\r']) {
+                Some((first_line, _)) => eprintln!("  in '{} ...'", first_line.trim_end()),
+                None => eprintln!("  in '{text}'"),
+            }
+        }
+        for note in &self.notes {
+            eprintln!("  {note}");
+        }
     }
 }
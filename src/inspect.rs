@@ -0,0 +1,27 @@
+use fns::frontend::{
+    dot::to_dot, parser::parse, source::to_source, tokenizer::tokenize, utils::Error,
+};
+
+// The printers shared by the `tokens` and `ast` subcommands and their repl commands.
+
+pub fn format_tokens(source_code: &str) -> Result<String, Error> {
+    let tokens: Vec<String> = tokenize(source_code)?
+        .iter()
+        .map(|token| format!("{token:?}"))
+        .collect();
+    Ok(tokens.join("\n"))
+}
+
+pub fn format_ast(source_code: &str) -> Result<String, Error> {
+    Ok(format!("{:#?}", parse(tokenize(source_code)?)?))
+}
+
+pub fn format_ast_dot(source_code: &str) -> Result<String, Error> {
+    Ok(to_dot(&parse(tokenize(source_code)?)?)
+        .trim_end()
+        .to_string())
+}
+
+pub fn format_ast_source(source_code: &str) -> Result<String, Error> {
+    Ok(to_source(&parse(tokenize(source_code)?)?))
+}
@@ -0,0 +1,57 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, OnceLock,
+};
+
+static INTERRUPTED: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+static FORCE_EXIT_ON_REPEAT: AtomicBool = AtomicBool::new(false);
+
+// Routes Ctrl-C to the given interruption flag instead of terminating the process. When
+// `force_exit_on_repeat` is set, a Ctrl-C arriving while the flag is still raised (i.e.
+// the evaluation didn't stop yet) exits right away. Only unix signals are handled, other
+// platforms keep the default behavior.
+pub fn install_interrupt_handler(interrupted: Arc<AtomicBool>, force_exit_on_repeat: bool) {
+    FORCE_EXIT_ON_REPEAT.store(force_exit_on_repeat, Ordering::SeqCst);
+    if INTERRUPTED.set(interrupted).is_ok() {
+        platform::install();
+    }
+}
+
+extern "C" fn handle_interrupt(_: i32) {
+    if let Some(interrupted) = INTERRUPTED.get() {
+        if interrupted.swap(true, Ordering::SeqCst) && FORCE_EXIT_ON_REPEAT.load(Ordering::SeqCst) {
+            platform::force_exit(130);
+        }
+    }
+}
+
+#[cfg(unix)]
+mod platform {
+    const SIGINT: i32 = 2;
+
+    extern "C" {
+        fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+        fn _exit(status: i32) -> !;
+    }
+
+    pub fn install() {
+        // SAFETY: the handler only touches atomics and calls the async-signal-safe `_exit`.
+        unsafe {
+            signal(SIGINT, super::handle_interrupt);
+        }
+    }
+
+    pub fn force_exit(status: i32) -> ! {
+        // SAFETY: `_exit` is async-signal-safe and never returns.
+        unsafe { _exit(status) }
+    }
+}
+
+#[cfg(not(unix))]
+mod platform {
+    pub fn install() {}
+
+    pub fn force_exit(status: i32) -> ! {
+        std::process::exit(status)
+    }
+}
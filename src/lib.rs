@@ -0,0 +1,140 @@
+pub mod frontend;
+pub mod runtime;
+#[cfg(any(test, feature = "fuzz"))]
+pub mod testing;
+
+use std::sync::atomic::Ordering;
+
+use frontend::{
+    ast::Program,
+    parser::parse_with_progress,
+    progress::Control,
+    tokenizer::tokenize,
+    utils::{Error, TextSpan},
+};
+use runtime::{
+    environment::Environment,
+    evaluator::{evaluate, evaluate_statements},
+    types::Value,
+};
+
+/// Runs `source_code` in a fresh environment and gives back the value of its last
+/// statement.
+///
+/// ```
+/// use fns::{run_source, runtime::types::Value};
+///
+/// assert_eq!(run_source("let a = 2 a * 21").unwrap(), Value::Integer(42));
+///
+/// let error = run_source("let a = 2 a * b").unwrap_err();
+/// assert_eq!(error.message, "Can't access the variable 'b' as it's not defined");
+/// assert_eq!((error.text_span.starting_index, error.text_span.ending_index), (14, 15));
+/// ```
+pub fn run_source(source_code: &str) -> Result<Value, Error> {
+    run_source_with(source_code, &mut Environment::new(None))
+}
+
+/// Runs `source_code` on top of `environment` and gives back the value of its last
+/// statement. The environment only takes the definitions and assignments made by
+/// `source_code` when it ran without an error.
+///
+/// ```
+/// use fns::{run_source_with, runtime::{environment::Environment, types::Value}};
+///
+/// let mut environment = Environment::new(None);
+/// run_source_with("let a = 2", &mut environment).unwrap();
+/// assert_eq!(run_source_with("a * 21", &mut environment).unwrap(), Value::Integer(42));
+///
+/// let error = run_source_with("let b = 1 a = 3 a()", &mut environment).unwrap_err();
+/// assert_eq!(error.message, "Can't call '3' as it's not a function");
+/// assert_eq!((error.text_span.starting_index, error.text_span.ending_index), (16, 19));
+/// assert_eq!(environment.access("b"), None);
+/// assert_eq!(environment.access("a"), Some(Value::Integer(2)));
+/// ```
+pub fn run_source_with(source_code: &str, environment: &mut Environment) -> Result<Value, Error> {
+    run(source_code, environment, evaluate)
+}
+
+/// Runs `source_code` like `run_source_with`, also giving back the span and value of each
+/// of its top-level statements. Spans, these and the ones of errors alike, are shifted
+/// by the environment's `source_offset`, so a host embedding the code in a larger
+/// document gets positions in that document.
+///
+/// ```
+/// use fns::{evaluate_with_spans, runtime::{environment::Environment, types::Value}};
+///
+/// let mut environment = Environment::new(None);
+/// environment.source_offset = 10;
+/// let (value, statements) = evaluate_with_spans("let a = 2 a * 21", &mut environment).unwrap();
+/// assert_eq!(value, Value::Integer(42));
+/// let spans: Vec<(usize, usize)> = statements
+///     .iter()
+///     .map(|(span, _)| (span.starting_index, span.ending_index))
+///     .collect();
+/// assert_eq!(spans, vec![(10, 19), (20, 26)]);
+/// ```
+pub fn evaluate_with_spans(
+    source_code: &str,
+    environment: &mut Environment,
+) -> Result<(Value, Vec<(TextSpan, Value)>), Error> {
+    let offset = environment.source_offset;
+    let statements = run(source_code, environment, evaluate_statements)?;
+    let value = statements
+        .last()
+        .map_or(Value::None, |(_, value)| value.clone());
+    Ok((
+        value,
+        statements
+            .into_iter()
+            .map(|(text_span, value)| (text_span.shifted(offset), value))
+            .collect(),
+    ))
+}
+
+type Evaluate<T> = fn(Program, Option<Environment>) -> Result<(T, Environment), Error>;
+
+fn run<T>(
+    source_code: &str,
+    environment: &mut Environment,
+    evaluate: Evaluate<T>,
+) -> Result<T, Error> {
+    let offset = environment.source_offset;
+    let shift = |error: Error| error.map_spans(|text_span| text_span.shifted(offset));
+    // Cancelling the parse interrupts the environment, as cancelling the evaluation does.
+    let progress = environment.progress.clone();
+    let interrupted = environment.interrupted.clone();
+    let on_parse_progress = progress
+        .as_ref()
+        .and_then(|progress| progress.on_parse_progress.as_ref())
+        .map(|on_parse_progress| {
+            move |consumed: usize, total: usize| {
+                let control = on_parse_progress(consumed, total);
+                if control == Control::Cancel {
+                    interrupted.store(true, Ordering::SeqCst);
+                }
+                control
+            }
+        });
+    let program = tokenize(source_code)
+        .and_then(|tokens| {
+            parse_with_progress(
+                tokens,
+                &environment.limits,
+                on_parse_progress
+                    .as_ref()
+                    .map(|f| f as &dyn Fn(usize, usize) -> Control),
+            )
+        })
+        .map_err(shift)?;
+    let saved = environment.save();
+    match evaluate(program, Some(environment.clone())) {
+        Ok((result, new_environment)) => {
+            *environment = new_environment;
+            Ok(result)
+        }
+        Err(error) => {
+            environment.restore(saved);
+            Err(shift(error))
+        }
+    }
+}
@@ -1,39 +1,275 @@
-mod frontend;
+mod cli;
+mod debugger;
+mod inspect;
+mod interrupt;
 mod repl;
-mod runtime;
+mod source;
+mod test_runner;
 
-use std::{env::args, fs::read_to_string, process::exit};
+use std::{
+    cell::RefCell,
+    env::{args, var},
+    fs::canonicalize,
+    io::stderr,
+    path::Path,
+    process::exit,
+    rc::Rc,
+    thread,
+};
 
-use frontend::utils::Error;
-use runtime::evaluator::evaluate;
+use fns::{
+    frontend::{
+        ast::Program,
+        limits::Limits,
+        lint::lint,
+        parser::parse,
+        resolve::resolve,
+        tokenizer::tokenize,
+        typecheck::typecheck,
+        utils::{Error, TextSpan},
+    },
+    run_source_with,
+    runtime::{
+        environment::Environment, evaluator::display, json::to_json, log::LogLevel,
+        profile::Profiler, registry::BuiltinRegistry, trace::StatementTracer, types::Value,
+    },
+};
 
 use crate::{
-    frontend::{parser::parse, tokenizer::tokenize},
+    cli::{
+        parse_arguments, AstFormat, CheckOptions, Command, EvalOptions, LintOptions, OutputFormat,
+        RunOptions, USAGE,
+    },
+    inspect::{format_ast, format_ast_dot, format_ast_source, format_tokens},
+    interrupt::install_interrupt_handler,
     repl::repl,
+    source::load_source_or_exit,
+    test_runner::run_tests,
 };
 
 fn main() {
-    let args: Vec<String> = args().collect();
-
-    match args.len() {
-        1 => repl(),
-        2 => {
-            let source_code = read_to_string(&args[1])
-                .expect("Error: Could not read source code file from given path.");
-            run(&source_code).unwrap_or_else(|error| {
-                error.report(&source_code);
-            });
-        }
-        _ => {
-            eprintln!("Error: Unknown number of argument.\nUsage: yai <filename>");
+    let command = match parse_arguments(args().skip(1).collect()) {
+        Ok(command) => command,
+        Err(message) => {
+            eprintln!("Error: {message}.\n\n{USAGE}");
             exit(65);
         }
+    };
+
+    // Code runs on a thread with the stack its limits need, far more than the main
+    // thread's, so nesting and calls reaching them fail with an error rather than
+    // overflowing the stack. The other commands only parse, which the main thread has
+    // room for.
+    let mut limits = match &command {
+        Command::Run(options) => options.limits.clone(),
+        Command::Eval(options) => options.limits.clone(),
+        Command::Repl(_) | Command::Test(_) => Limits::default(),
+        _ => return execute(command, Limits::default()),
+    };
+    // Where the address space is capped, as by `ulimit -v`, a stack that large can't be
+    // reserved, so calls and nesting are bounded lower until one can be.
+    let outcome = loop {
+        let (command, thread_limits) = (command.clone(), limits.clone());
+        match thread::Builder::new()
+            .stack_size(limits.stack_size())
+            .spawn(move || execute(command, thread_limits))
+        {
+            Ok(thread) => break thread.join(),
+            Err(error) if limits.max_call_depth <= 1 && limits.max_nesting <= 1 => {
+                eprintln!(
+                    "Error: Can't reserve a stack of {} bytes for the limits: {error}.",
+                    limits.stack_size()
+                );
+                exit(70);
+            }
+            Err(_) => {
+                limits.max_call_depth /= 2;
+                limits.max_nesting /= 2;
+            }
+        }
+    };
+    if outcome.is_err() {
+        exit(101);
+    }
+}
+
+fn execute(command: Command, limits: Limits) {
+    match command {
+        Command::Run(options) => run(RunOptions { limits, ..options }),
+        Command::Repl(options) => repl(options, forced_log_level(None), limits),
+        Command::Eval(options) => eval(EvalOptions { limits, ..options }),
+        Command::Test(options) => {
+            if run_tests(Path::new(&options.path), &limits).failed > 0 {
+                exit(1);
+            }
+        }
+        Command::Tokens(options) => inspect(&options.path, |source_code| {
+            println!("{}", format_tokens(source_code)?);
+            Ok(())
+        }),
+        Command::Ast(options) => {
+            let printer = match options.format {
+                AstFormat::Debug => format_ast,
+                AstFormat::Dot => format_ast_dot,
+                AstFormat::Source => format_ast_source,
+            };
+            inspect(&options.path, |source_code| {
+                println!("{}", printer(source_code)?);
+                Ok(())
+            })
+        }
+        Command::Check(options) => check(options),
+        Command::Lint(options) => lint_file(options),
+        Command::Help => println!("{USAGE}"),
+    }
+}
+
+fn run(options: RunOptions) {
+    let source_code = load_source_or_exit(Path::new(&options.path));
+    let profiler = options
+        .profile
+        .then(|| Rc::new(RefCell::new(Profiler::default())));
+    // The script's arguments take the place of the empty `process.args`.
+    let mut builtins = BuiltinRegistry::standard();
+    builtins.register_value(
+        "process.args",
+        Value::Array(
+            options
+                .arguments
+                .into_iter()
+                .map(|argument| Value::String(argument.into()))
+                .collect(),
+        ),
+    );
+    let mut environment = Environment::with_builtins(Rc::new(builtins));
+    environment.is_strict = options.strict;
+    environment.limits = options.limits;
+    environment.profiler = profiler.clone();
+    // Imports starting with `.` are relative to the file.
+    environment.module = canonicalize(&options.path)
+        .ok()
+        .map(|path| path.display().to_string().into());
+    environment.logger.borrow_mut().forced_level = forced_log_level(options.log_level);
+    if options.trace {
+        environment.tracer = Some(Rc::new(RefCell::new(StatementTracer::new(
+            &source_code,
+            stderr(),
+        ))));
+    }
+    if options.check {
+        let program = parse_source(&source_code);
+        report_unresolved(&program, &environment.globals(), &source_code);
+    }
+    install_interrupt_handler(environment.interrupted.clone(), true);
+    let result = run_source_with(&source_code, &mut environment);
+    if let Some(profiler) = profiler {
+        eprint!("{}", profiler.borrow().report(&source_code, 10));
+    }
+    if let Err(error) = result {
+        error.report(&source_code);
+        exit(1);
+    }
+}
+
+// A log level given on the command line wins over the one in FNS_LOG_LEVEL.
+fn forced_log_level(log_level: Option<LogLevel>) -> Option<LogLevel> {
+    log_level.or_else(|| {
+        var("FNS_LOG_LEVEL")
+            .ok()
+            .and_then(|name| LogLevel::from_name(&name))
+    })
+}
+
+fn eval(options: EvalOptions) {
+    let source_code = options.source_code;
+    let mut environment = Environment::new(None);
+    environment.is_strict = options.strict;
+    environment.limits = options.limits;
+    let value = match run_source_with(&source_code, &mut environment) {
+        Ok(value) => value,
+        Err(error) => {
+            error.report(&source_code);
+            exit(1);
+        }
+    };
+    match options.output {
+        OutputFormat::Display => {
+            let text_span = TextSpan::new(0, source_code.len());
+            match display(&value, text_span, &mut environment) {
+                Ok(output) => println!("{output}"),
+                Err(error) => {
+                    error.report(&source_code);
+                    exit(1);
+                }
+            }
+        }
+        OutputFormat::Json => match to_json(&value, options.pretty) {
+            Ok(output) => println!("{output}"),
+            Err(message) => {
+                eprintln!("Error: {message}");
+                exit(1);
+            }
+        },
+    }
+}
+
+fn inspect(path: &str, inspector: impl Fn(&str) -> Result<(), Error>) {
+    let source_code = load_source_or_exit(Path::new(path));
+    if let Err(error) = inspector(&source_code) {
+        error.report(&source_code);
+        exit(1);
+    }
+}
+
+// Reports every error rather than only the first, as none of them stops the check.
+fn check(options: CheckOptions) {
+    let source_code = load_source_or_exit(Path::new(&options.path));
+    let program = parse_source(&source_code);
+    report_unresolved(&program, &Environment::new(None).globals(), &source_code);
+    if options.types {
+        let errors = typecheck(&program);
+        for error in &errors {
+            error.report(&source_code);
+        }
+        if !errors.is_empty() {
+            exit(1);
+        }
+    }
+}
+
+// Exits once the names the program can't resolve are reported, if there are any.
+fn report_unresolved(program: &Program, globals: &[(String, bool)], source_code: &str) {
+    let errors = resolve(program, globals);
+    for error in &errors {
+        error.to_error().report(source_code);
+    }
+    if !errors.is_empty() {
+        exit(1);
+    }
+}
+
+fn lint_file(options: LintOptions) {
+    let source_code = load_source_or_exit(Path::new(&options.path));
+    let program = parse_source(&source_code);
+    let builtins: Vec<String> = BuiltinRegistry::global()
+        .builtins()
+        .iter()
+        .map(|builtin| builtin.name.clone())
+        .collect();
+    let lints = lint(&program, &builtins, &options.allowed);
+    for lint in &lints {
+        lint.report(&source_code);
+    }
+    if !lints.is_empty() {
+        exit(1);
     }
 }
 
-fn run(source_code: &str) -> Result<(), Error> {
-    let tokens = tokenize(source_code)?;
-    let program = parse(tokens)?;
-    evaluate(program, None)?;
-    Ok(())
+fn parse_source(source_code: &str) -> Program {
+    tokenize(source_code)
+        .and_then(parse)
+        .unwrap_or_else(|error| {
+            error.report(source_code);
+            exit(1);
+        })
 }
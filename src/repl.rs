@@ -1,36 +1,475 @@
-use std::io::{stdin, stdout, Write};
+use std::{
+    cell::RefCell,
+    env::var_os,
+    io::{stdin, stdout, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use fns::{
+    frontend::{
+        ast::Statement,
+        limits::Limits,
+        parser::{parse, parse_partial, PartialParse},
+        tokenizer::tokenize,
+        utils::{Error, TextSpan},
+    },
+    run_source_with,
+    runtime::{
+        environment::Environment,
+        evaluator::{display, display_with},
+        log::LogLevel,
+        types::{Value, ValuePrinter},
+    },
+};
 
 use crate::{
-    frontend::{parser::parse, tokenizer::tokenize, utils::Error},
-    runtime::{environment::Environment, evaluator::evaluate},
+    cli::ReplOptions,
+    debugger::Debugger,
+    inspect::{format_ast, format_tokens},
+    interrupt::install_interrupt_handler,
+    source::load_source,
 };
 
-pub fn repl() {
+pub fn repl(options: ReplOptions, forced_log_level: Option<LogLevel>, limits: Limits) {
     let mut source_code = String::new();
-    let mut environment = Environment::new(None);
+    let mut last_source_code = None;
+    let mut show_changes = false;
+    let mut session = Session::new(Environment::new(None));
+    let environment = &mut session.environment;
+    environment.logger.borrow_mut().forced_level = forced_log_level;
+    environment.limits = limits;
     let mut stdout = stdout();
     let stdin = stdin();
 
+    install_interrupt_handler(environment.interrupted.clone(), false);
+    if !options.no_rc {
+        if let Some(path) = startup_file() {
+            load(&path, environment);
+        }
+    }
+
     println!("fns repl v0.0.1");
-    println!("press [ctrl + c] to exit\n");
+    println!("press [ctrl + c] to interrupt an evaluation, [ctrl + d] to exit");
+    println!("type `:debug <source>` or `:debug :load <file>` to step through statements");
+    println!(
+        "type `:ast <source>` or `:tokens <source>` to inspect source code without running it"
+    );
+    println!("type `:load <file>` to run a file in this session");
+    println!("type `:undo` to take back the last function defined with `fn`");
+    println!("type `:changes on` to see the variables each input defines or reassigns\n");
     loop {
-        print!("fns ⇒  ");
+        if source_code.is_empty() {
+            print!("fns ⇒  ");
+        } else {
+            print!("    …  ");
+        }
         stdout.flush().expect("Error: Could not flush <stdout>.");
-        stdin
+        let start_of_line = source_code.len();
+        let read = stdin
             .read_line(&mut source_code)
             .expect("Error: Could not read from <stdin>.");
-        match run(&source_code, environment.clone()) {
-            Ok(old_environment) => environment = old_environment,
-            Err(error) => error.report(&source_code),
+        if read == 0 {
+            println!();
+            break;
+        }
+        // A Ctrl-C pressed at the prompt only discards the line being typed.
+        session.environment.clear_interrupt();
+        let line = source_code.trim();
+        // Input that could still be completed waits for the lines after it, until one of
+        // them is left blank.
+        let is_blank = source_code[start_of_line..].trim().is_empty();
+        if !line.starts_with(':')
+            && !is_blank
+            && matches!(parse_partial(&source_code), PartialParse::Incomplete)
+        {
+            continue;
+        }
+        if let Some(target) = command_argument(line, ":debug") {
+            if let Some(old_environment) = debug(target, session.environment.clone()) {
+                session.environment = old_environment;
+            }
+        } else if let Some(snippet) = command_argument(line, ":ast") {
+            inspect(snippet, last_source_code.as_deref(), format_ast);
+        } else if let Some(snippet) = command_argument(line, ":tokens") {
+            inspect(snippet, last_source_code.as_deref(), format_tokens);
+        } else if let Some(path) = command_argument(line, ":load") {
+            load(Path::new(path), &mut session.environment);
+        } else if let Some(toggle) = command_argument(line, ":changes") {
+            match toggle {
+                "on" => show_changes = true,
+                "off" => show_changes = false,
+                _ => eprintln!("Error: ':changes' expects 'on' or 'off'"),
+            }
+        } else if command_argument(line, ":undo").is_some() {
+            match session.undo() {
+                Ok(output) => println!("{output}"),
+                Err(message) => eprintln!("{message}"),
+            }
+        } else {
+            // Copying every binding is only worth it when the changes are shown.
+            let before = show_changes.then(|| session.environment.bindings());
+            match session.evaluate(&source_code) {
+                Ok(output) => {
+                    println!("{output}");
+                    last_source_code = Some(source_code.clone());
+                    if let Some(before) = before {
+                        for change in describe_changes(&before, &session.environment.bindings()) {
+                            println!("{change}");
+                        }
+                    }
+                }
+                Err(error) => error.report(&source_code),
+            }
         }
         source_code.clear();
     }
 }
 
-fn run(source_code: &str, environment: Environment) -> Result<Environment, Error> {
-    let tokens = tokenize(source_code)?;
-    let program = parse(tokens)?;
-    let (value, environment) = evaluate(program, Some(environment))?;
-    println!("{value}");
+// The environment of a repl session along with the functions its inputs defined with
+// `fn`, most recent last. Each input runs in an environment of its own, so a function
+// defined again shadows the previous definition rather than replacing it, even when that
+// one was a constant, and `:undo` brings it back by taking the new one out of the
+// environment of the input that made it.
+struct Session {
+    environment: Environment,
+    definitions: Vec<(String, Environment)>,
+}
+
+impl Session {
+    fn new(environment: Environment) -> Self {
+        Self {
+            environment,
+            definitions: vec![],
+        }
+    }
+
+    // Runs an input and gives back what to print for it: a line for each function it
+    // defined, and its value unless that's none after definitions.
+    fn evaluate(&mut self, source_code: &str) -> Result<String, Error> {
+        let functions: Vec<(String, String)> = parse(tokenize(source_code)?)?
+            .iter()
+            .filter_map(|statement| match statement {
+                Statement::Function(f) => Some((
+                    f.identifier.lexeme.to_string(),
+                    f.function
+                        .parameters
+                        .iter()
+                        .map(|parameter| parameter.lexeme.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                )),
+                _ => None,
+            })
+            .collect();
+        let existed: Vec<bool> = functions
+            .iter()
+            .map(|(name, _)| self.environment.access(name).is_some())
+            .collect();
+        let value = run_source_with(source_code, &mut self.environment)?;
+
+        let mut lines = vec![];
+        for ((name, parameters), existed) in functions.into_iter().zip(existed) {
+            let verb = if existed { "redefined" } else { "defined" };
+            lines.push(format!("{verb} fn {name}({parameters})"));
+            self.definitions.push((name, self.environment.clone()));
+        }
+        if lines.is_empty() || value != Value::None {
+            // Echoed values are shown as the repl shows them, within the limits the
+            // environment shows values in.
+            let printer = ValuePrinter {
+                max_depth: self.environment.printer.max_depth,
+                max_length: self.environment.printer.max_length,
+                ..ValuePrinter::repl()
+            };
+            lines.push(display_with(
+                &value,
+                TextSpan::new(0, source_code.len()),
+                &mut self.environment,
+                &printer,
+            )?);
+        }
+        Ok(lines.join("\n"))
+    }
+
+    fn undo(&mut self) -> Result<String, String> {
+        let Some((name, environment)) = self.definitions.pop() else {
+            return Err("Error: There's no function definition to undo".to_string());
+        };
+        environment.variables.borrow_mut().remove(name.as_str());
+        Ok(match self.environment.access(&name) {
+            Some(Value::Function(function)) => {
+                format!("restored fn {name}({})", function.parameters.join(", "))
+            }
+            Some(value) => format!("restored {name} = {}", truncate(&value)),
+            None => format!("removed fn {name}"),
+        })
+    }
+}
+
+// `$FNS_RC` when it's set, `~/.fnsrc` otherwise. A missing `~/.fnsrc` is skipped while
+// a missing `$FNS_RC` is reported.
+fn startup_file() -> Option<PathBuf> {
+    if let Some(path) = var_os("FNS_RC") {
+        return Some(PathBuf::from(path));
+    }
+    let path = PathBuf::from(var_os("HOME")?).join(".fnsrc");
+    path.exists().then_some(path)
+}
+
+// Runs a file in the session environment, for the startup file and `:load`. Errors are
+// reported against the file's own source and leave the environment as it was.
+fn load(path: &Path, environment: &mut Environment) -> bool {
+    let source_code = match load_source(path) {
+        Ok(source_code) => source_code,
+        Err(error) => {
+            eprintln!("{error}");
+            return false;
+        }
+    };
+    match run_source_with(&source_code, environment) {
+        Ok(_) => true,
+        Err(error) => {
+            error.report(&source_code);
+            false
+        }
+    }
+}
+
+// The argument of a repl command, e.g. `1 + 2` for `:ast 1 + 2`.
+fn command_argument<'a>(line: &'a str, command: &str) -> Option<&'a str> {
+    let argument = line.strip_prefix(command)?;
+    if argument.is_empty() || argument.starts_with(char::is_whitespace) {
+        Some(argument.trim())
+    } else {
+        None
+    }
+}
+
+// Prints `snippet` without evaluating it, or the last evaluated input without one.
+fn inspect(
+    snippet: &str,
+    last_source_code: Option<&str>,
+    printer: fn(&str) -> Result<String, Error>,
+) {
+    let source_code = match (snippet, last_source_code) {
+        ("", Some(last_source_code)) => last_source_code,
+        ("", None) => {
+            eprintln!("Error: There's no evaluated input to show yet");
+            return;
+        }
+        (snippet, _) => snippet,
+    };
+    match printer(source_code) {
+        Ok(output) => println!("{output}"),
+        Err(error) => error.report(source_code),
+    }
+}
+
+// One line per binding that was added or whose value changed between two snapshots of
+// `Environment::bindings`.
+fn describe_changes(
+    before: &[(String, Value, bool)],
+    after: &[(String, Value, bool)],
+) -> Vec<String> {
+    after
+        .iter()
+        .filter_map(|(identifier, value, is_constant)| {
+            match before
+                .iter()
+                .find(|(old_identifier, ..)| old_identifier == identifier)
+            {
+                None if *is_constant => {
+                    Some(format!("defined const {identifier} = {}", truncate(value)))
+                }
+                None => Some(format!("defined {identifier} = {}", truncate(value))),
+                Some((_, old_value, _)) if old_value != value => Some(format!(
+                    "reassigned {identifier}: {} -> {}",
+                    truncate(old_value),
+                    truncate(value)
+                )),
+                Some(_) => None,
+            }
+        })
+        .collect()
+}
+
+// A value on a single line, cut short when it's too long for a line of changes.
+fn truncate(value: &Value) -> String {
+    const LIMIT: usize = 32;
+    let text = value
+        .to_string()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if text.chars().count() > LIMIT {
+        format!("{}...", text.chars().take(LIMIT).collect::<String>())
+    } else {
+        text
+    }
+}
+
+fn run(source_code: &str, mut environment: Environment) -> Result<Environment, Error> {
+    let value = run_source_with(source_code, &mut environment)?;
+    println!(
+        "{}",
+        display(
+            &value,
+            TextSpan::new(0, source_code.len()),
+            &mut environment
+        )?
+    );
     Ok(environment)
 }
+
+fn debug(target: &str, mut environment: Environment) -> Option<Environment> {
+    let source_code = match target.strip_prefix(":load") {
+        Some(path) => match load_source(Path::new(path.trim())) {
+            Ok(source_code) => source_code,
+            Err(error) => {
+                eprintln!("{error}");
+                return None;
+            }
+        },
+        None => target.to_string(),
+    };
+
+    let debugger = Debugger::new(&source_code, stdin(), stdout());
+    environment.tracer = Some(Rc::new(RefCell::new(debugger)));
+    match run(&source_code, environment) {
+        Ok(mut environment) => {
+            environment.tracer = None;
+            Some(environment)
+        }
+        Err(error) => {
+            error.report(&source_code);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env::temp_dir, fs::write, process};
+
+    use fns::{
+        frontend::parser::{parse_partial, PartialParse},
+        run_source_with,
+        runtime::environment::Environment,
+    };
+
+    use super::{command_argument, describe_changes, load, Session};
+
+    #[test]
+    fn test_command_argument() {
+        assert_eq!(command_argument(":ast {a: 1}.a", ":ast"), Some("{a: 1}.a"));
+        assert_eq!(command_argument(":tokens", ":tokens"), Some(""));
+        assert_eq!(command_argument(":asteroid", ":ast"), None);
+        assert_eq!(command_argument("let a = 1", ":ast"), None);
+    }
+
+    #[test]
+    fn test_describe_changes() {
+        let mut environment = Environment::new(None);
+        run_source_with("let total = 10 let unchanged = 1", &mut environment).unwrap();
+        let before = environment.bindings();
+        run_source_with(
+            "const PI = 3.14 total = total + 5 unchanged = 1 let long = \"abcdefghijklmnopqrstuvwxyz0123456789\"",
+            &mut environment,
+        )
+        .unwrap();
+
+        assert_eq!(
+            describe_changes(&before, &environment.bindings()),
+            vec![
+                "defined const PI = 3.14",
+                "defined long = abcdefghijklmnopqrstuvwxyz012345...",
+                "reassigned total: 10 -> 15",
+            ]
+        );
+        assert_eq!(
+            describe_changes(&environment.bindings(), &environment.bindings()),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn test_load() {
+        let path = temp_dir().join(format!("fns-rc-{}.fns", process::id()));
+        write(&path, "const greeting = \"hi\" let count = 1").unwrap();
+        let mut environment = Environment::new(None);
+        assert!(load(&path, &mut environment));
+        assert_eq!(
+            environment
+                .access("greeting")
+                .map(|value| value.to_string()),
+            Some("hi".to_string())
+        );
+
+        // A failing file leaves the environment untouched.
+        write(&path, "count = 2 undefined").unwrap();
+        assert!(!load(&path, &mut environment));
+        assert_eq!(
+            environment.access("count").map(|value| value.to_string()),
+            Some("1".to_string())
+        );
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(!load(&path, &mut environment));
+    }
+
+    #[test]
+    fn test_session_incomplete_input() {
+        let mut session = Session::new(Environment::new(None));
+        for source_code in ["let x =", "(1 + 2", "!"] {
+            assert!(matches!(
+                parse_partial(source_code),
+                PartialParse::Incomplete
+            ));
+            let error = session.evaluate(source_code).unwrap_err();
+            assert!(error.message.starts_with("Unexpected end of input"));
+        }
+        assert_eq!(session.evaluate("1 + 1").unwrap(), "2");
+    }
+
+    #[test]
+    fn test_session_echo() {
+        let mut session = Session::new(Environment::new(None));
+        assert_eq!(
+            session.evaluate("{b: \"x\", a: [1, \"2\"]}").unwrap(),
+            "{a : [1, \"2\"], b : \"x\"}"
+        );
+        // Strings are echoed as string literals, escapes and all.
+        assert_eq!(session.evaluate("string(\"a\\nb\")").unwrap(), "\"a\\nb\"");
+    }
+
+    #[test]
+    fn test_session_redefinition() {
+        let mut session = Session::new(Environment::new(None));
+        let evaluate =
+            |session: &mut Session, source_code: &str| session.evaluate(source_code).unwrap();
+        assert_eq!(
+            evaluate(&mut session, "fn area(r) { r * r }"),
+            "defined fn area(r)"
+        );
+        assert_eq!(evaluate(&mut session, "area(2)"), "4");
+        assert_eq!(
+            evaluate(&mut session, "fn area(r) { 3 * r * r }"),
+            "redefined fn area(r)"
+        );
+        assert_eq!(evaluate(&mut session, "area(2)"), "12");
+
+        assert_eq!(session.undo().unwrap(), "restored fn area(r)");
+        assert_eq!(evaluate(&mut session, "area(2)"), "4");
+        assert_eq!(session.undo().unwrap(), "removed fn area");
+        assert!(session.undo().is_err());
+
+        // A constant is shadowed too, and an input's value still follows its definitions.
+        evaluate(&mut session, "const side = 2");
+        assert_eq!(
+            evaluate(&mut session, "fn side(s) { s } side(5)"),
+            "redefined fn side(s)\n5"
+        );
+        assert_eq!(session.undo().unwrap(), "restored side = 2");
+    }
+}
@@ -0,0 +1,78 @@
+use crate::frontend::limits::Limits;
+
+// Builds a string out of pieces appended in place, the way repeated concatenation grows
+// one, so a string built a piece at a time is copied a number of times logarithmic in
+// its length rather than once per piece. Every piece is charged against the limit on the
+// length of strings before it's appended.
+pub struct StringBuilder {
+    text: String,
+    max_length: usize,
+}
+
+impl StringBuilder {
+    // Starts from a string it takes over, reusing its allocation.
+    pub fn new(text: String, limits: &Limits) -> Self {
+        Self {
+            text,
+            max_length: limits.max_string_length,
+        }
+    }
+
+    pub fn push(&mut self, piece: &str) -> Result<(), String> {
+        // Chars are never more than bytes, so they're only counted for long strings.
+        if self.text.len() + piece.len() > self.max_length
+            && self.text.chars().count() + piece.chars().count() > self.max_length
+        {
+            return Err(format!(
+                "Can't make a string longer than the limit of {} chars",
+                self.max_length
+            ));
+        }
+        self.text.push_str(piece);
+        Ok(())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn finish(self) -> String {
+        self.text
+    }
+}
+
+// The two strings one after the other, in a string of its own.
+pub fn concatenate(left: &str, right: &str, limits: &Limits) -> Result<String, String> {
+    let mut builder = StringBuilder::new(String::with_capacity(left.len() + right.len()), limits);
+    builder.push(left)?;
+    builder.push(right)?;
+    Ok(builder.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frontend::limits::Limits;
+
+    use super::{concatenate, StringBuilder};
+
+    #[test]
+    fn test_string_builder() {
+        let limits = Limits {
+            max_string_length: 4,
+            ..Limits::default()
+        };
+        let mut builder = StringBuilder::new("ab".to_string(), &limits);
+        builder.push("é").unwrap();
+        assert_eq!(
+            builder.push("cd"),
+            Err("Can't make a string longer than the limit of 4 chars".to_string())
+        );
+        builder.push("c").unwrap();
+        assert_eq!(builder.finish(), "abéc");
+        assert_eq!(
+            concatenate("ab", "ééé", &limits),
+            Err("Can't make a string longer than the limit of 4 chars".to_string())
+        );
+        assert_eq!(concatenate("ab", "éé", &limits), Ok("abéé".to_string()));
+    }
+}
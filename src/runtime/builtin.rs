@@ -1,26 +1,2090 @@
-use std::f64::consts::{E, PI};
+use std::{
+    cmp::Ordering,
+    collections::BTreeSet,
+    env::{var_os, vars_os},
+    f64::consts::{E, PI},
+    rc::Rc,
+    sync::OnceLock,
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
-use super::types::Value;
+use crate::frontend::utils::{Error, TextSpan};
 
-pub fn get_builtin() -> Vec<(String, Value)> {
-    vec![
-        (
-            "fns".to_string(),
-            Value::Object(
-                vec![("version", Value::String("0.0.1".to_string()))]
-                    .iter()
-                    .map(|(key, value)| (key.to_string(), Box::new(value.clone())))
-                    .collect(),
+use super::{
+    builder::StringBuilder,
+    encoding::{base64_decode, base64_encode, hex_decode, hex_encode},
+    environment::Environment,
+    evaluator::{call_value, display},
+    format::{parse_template, to_fixed, NumberFormat, Piece, MAX_DECIMALS},
+    hash::{fnv1a, sha256},
+    log::LogLevel,
+    module,
+    random::Random,
+    regex::{compile_cached, Captures, Regex},
+    registry::{constant, function, Builtin, BuiltinRegistry},
+    types::{numeric_order, plural, whole_integer, NativeFunctionPointer, SetElement, Value},
+};
+
+// Registers every builtin fns comes with, one module or function at a time.
+pub fn register_builtins(registry: &mut BuiltinRegistry) {
+    registry.register_module(
+        "fns",
+        "details about the interpreter",
+        vec![constant(
+            "version",
+            Value::String("0.0.1".to_string().into()),
+            "the version of the interpreter",
+        )],
+    );
+    registry.register_module(
+        "math",
+        "mathematical constants and functions",
+        vec![
+            constant(
+                "pi",
+                Value::Number(PI),
+                "the ratio of a circle's circumference to its diameter",
+            ),
+            constant("e", Value::Number(E), "the base of the natural logarithm"),
+            constant("inf", Value::Number(f64::INFINITY), "positive infinity"),
+            constant(
+                "nan",
+                Value::Number(f64::NAN),
+                "not a number, unequal to itself",
+            ),
+            function(
+                "math.floor(x: number)",
+                math_floor,
+                "the largest whole number not above x",
+            ),
+            function(
+                "math.ceil(x: number)",
+                math_ceil,
+                "the smallest whole number not below x",
+            ),
+            function(
+                "math.round(x: number)",
+                math_round,
+                "the whole number nearest to x, halves away from zero",
+            ),
+            function("math.abs(x: number)", math_abs, "x without its sign"),
+            function("math.sin(x: number)", math_sin, "the sine of x radians"),
+            function("math.cos(x: number)", math_cos, "the cosine of x radians"),
+            function("math.tan(x: number)", math_tan, "the tangent of x radians"),
+            function(
+                "math.asin(x: number)",
+                math_asin,
+                "the angle in radians whose sine is x, from -pi/2 to pi/2",
+            ),
+            function(
+                "math.acos(x: number)",
+                math_acos,
+                "the angle in radians whose cosine is x, from 0 to pi",
+            ),
+            function(
+                "math.atan(x: number)",
+                math_atan,
+                "the angle in radians whose tangent is x, from -pi/2 to pi/2",
+            ),
+            function(
+                "math.to_radians(x: number)",
+                math_to_radians,
+                "x degrees in radians",
+            ),
+            function(
+                "math.to_degrees(x: number)",
+                math_to_degrees,
+                "x radians in degrees",
+            ),
+            function(
+                "math.atan2(y: number, x: number)",
+                math_atan2,
+                "the angle in radians from the x axis to the point (x, y), from -pi to pi",
+            ),
+            function(
+                "math.random()",
+                math_random,
+                "a number from 0 up to but not including 1",
+            ),
+            function(
+                "math.random_int(low: number, high: number)",
+                math_random_int,
+                "a whole number from low to high, both included",
+            ),
+            function(
+                "math.seed(seed: number)",
+                math_seed,
+                "restarts the sequence of random numbers from the whole number seed",
+            ),
+            function(
+                "math.is_nan(x: number)",
+                math_is_nan,
+                "whether x is NaN, which `x == x` also tells",
+            ),
+            function(
+                "math.is_finite(x: number)",
+                math_is_finite,
+                "whether x is neither NaN nor an infinity",
+            ),
+        ],
+    );
+    registry.register_module(
+        "numbers",
+        "conversions of numbers to text",
+        vec![function(
+            "numbers.to_fixed(x: number, decimals: number)",
+            numbers_to_fixed,
+            "x as text with exactly that many decimals, halves rounded away from zero",
+        )],
+    );
+    registry.register(function(
+        "string(value, options?: object)",
+        string,
+        "the value as text, a number with the decimals and thousands separator in the options",
+    ));
+    registry.register(function(
+        "number(value)",
+        number,
+        "the value as a number: text parsed, true and false as 1 and 0, none when it isn't one",
+    ));
+    registry.register(function(
+        "boolean(value)",
+        boolean,
+        "whether the value is truthy, false only for none and false",
+    ));
+    registry.register(function(
+        "format(template: string, values...)",
+        format,
+        "the template with each {} replaced by the next value, {:,.2} adding a separator and decimals",
+    ));
+    registry.register(function(
+        "help(value?)",
+        help,
+        "describes a builtin module or function, or lists the builtins without a value",
+    ));
+    registry.register(function(
+        "input(prompt?: string)",
+        input,
+        "writes the prompt and reads a line of input without its line ending, none at its end",
+    ));
+    registry.register(function(
+        "type(value)",
+        type_,
+        "the type of the value, the name it's annotated with like \"number\" or \"object\"",
+    ));
+    registry.register(function(
+        "assert(condition, message?: string)",
+        assert,
+        "an error, with the message when there's one, unless the condition is true",
+    ));
+    registry.register(function(
+        "assert_eq(a, b)",
+        assert_eq,
+        "an error showing both values unless they're equal",
+    ));
+    registry.register(function(
+        "clone(value)",
+        clone,
+        "a deep copy of the value, sharing nothing with it",
+    ));
+    registry.register(function(
+        "env(include_builtins?: boolean)",
+        env,
+        "the variables in scope as an object, the builtins only when asked for",
+    ));
+    registry.register(function(
+        "import(specifier: string)",
+        import,
+        "the top-level variables of a module as an object, the module running once however often it's imported",
+    ));
+    registry.register(function(
+        "len(value)",
+        len,
+        "the number of elements of a set or tuple, characters of a string or keys of an object",
+    ));
+    registry.register(function(
+        "set(elements...)",
+        set,
+        "a set of the given elements",
+    ));
+    registry.register_module(
+        "sets",
+        "operations on sets, which all leave their arguments untouched",
+        vec![
+            function(
+                "sets.add(set: set, element)",
+                sets_add,
+                "a copy of the set with the element added",
+            ),
+            function(
+                "sets.has(set: set, element)",
+                sets_has,
+                "whether the set holds the element",
+            ),
+            function(
+                "sets.remove(set: set, element)",
+                sets_remove,
+                "a copy of the set without the element",
+            ),
+            function(
+                "sets.union(a: set, b: set)",
+                sets_union,
+                "the elements found in either set",
+            ),
+            function(
+                "sets.intersection(a: set, b: set)",
+                sets_intersection,
+                "the elements found in both sets",
+            ),
+        ],
+    );
+    registry.register_module(
+        "arrays",
+        "operations on arrays, which all leave their arguments untouched",
+        vec![
+            function(
+                "arrays.len(array: array)",
+                arrays_len,
+                "the number of elements in the array",
+            ),
+            function(
+                "arrays.push(array: array, element)",
+                arrays_push,
+                "a copy of the array with the element added at its end",
+            ),
+            function(
+                "arrays.concat(a: array, b: array)",
+                arrays_concat,
+                "the elements of a followed by the ones of b",
+            ),
+            function(
+                "arrays.slice(array: array, start: number, end?: number)",
+                arrays_slice,
+                "the elements from start up to end, or the end, negative positions counting from the end",
+            ),
+            function(
+                "arrays.reverse(array: array)",
+                arrays_reverse,
+                "the elements in the opposite order",
+            ),
+            function(
+                "arrays.contains(array: array, element)",
+                arrays_contains,
+                "whether an element of the array equals the element",
+            ),
+            function(
+                "arrays.index_of(array: array, element)",
+                arrays_index_of,
+                "the position of the first element equal to the element, none when there's none",
+            ),
+            function(
+                "arrays.sort(array: array)",
+                arrays_sort,
+                "the elements in ascending order, which must be all numbers or all strings",
+            ),
+            function(
+                "arrays.sort_by(array: array, compare: function)",
+                arrays_sort_by,
+                "the elements ordered by compare(a, b), which gives a negative number when a goes first, a positive one when b does and 0 to keep their order",
+            ),
+            function(
+                "arrays.map(array: array, f: function)",
+                arrays_map,
+                "the results of calling f with each element",
+            ),
+            function(
+                "arrays.filter(array: array, f: function)",
+                arrays_filter,
+                "the elements for which f gives true",
+            ),
+            function(
+                "arrays.reduce(array: array, f: function, initial?)",
+                arrays_reduce,
+                "the elements folded into one value by calling f with what it gave so far and each element, starting from initial or the first element",
+            ),
+        ],
+    );
+    registry.register_module(
+        "objects",
+        "operations on objects, which all leave their arguments untouched",
+        vec![function(
+            "objects.remove(object: object, key: string)",
+            objects_remove,
+            "a copy of the object without the key",
+        )],
+    );
+    registry.register_module(
+        "strings",
+        "operations on strings, which count and index chars rather than bytes",
+        vec![
+            function(
+                "strings.len(s: string)",
+                strings_len,
+                "the number of chars in s",
+            ),
+            function("strings.upper(s: string)", string_upper, "s in upper case"),
+            function("strings.lower(s: string)", string_lower, "s in lower case"),
+            function(
+                "strings.trim(s: string)",
+                strings_trim,
+                "s without the whitespace at its start and end",
+            ),
+            function(
+                "strings.trim_start(s: string)",
+                strings_trim_start,
+                "s without the whitespace at its start",
+            ),
+            function(
+                "strings.trim_end(s: string)",
+                strings_trim_end,
+                "s without the whitespace at its end",
+            ),
+            function(
+                "strings.slice(s: string, start: number, end?: number)",
+                strings_slice,
+                "the chars of s from start up to end, or its end, negative positions counting from the end",
+            ),
+            function(
+                "strings.char_at(s: string, position: number)",
+                strings_char_at,
+                "the char of s at the position, none when there's none",
+            ),
+            function(
+                "strings.contains(s: string, needle: string)",
+                strings_contains,
+                "whether needle is somewhere in s",
+            ),
+            function(
+                "strings.starts_with(s: string, prefix: string)",
+                strings_starts_with,
+                "whether s starts with prefix",
+            ),
+            function(
+                "strings.ends_with(s: string, suffix: string)",
+                strings_ends_with,
+                "whether s ends with suffix",
+            ),
+            function(
+                "strings.index_of(s: string, needle: string)",
+                strings_index_of,
+                "the position in chars of the first needle in s, none when there's none",
+            ),
+            function(
+                "strings.replace(s: string, from: string, to: string)",
+                strings_replace,
+                "s with every from replaced by to",
+            ),
+            function(
+                "strings.repeat(s: string, count: number)",
+                strings_repeat,
+                "s repeated a whole number of times",
+            ),
+        ],
+    );
+    registry.register_module(
+        "regex",
+        "regular expression matching",
+        vec![
+            function(
+                "regex.is_match(pattern: string, input: string)",
+                regex_is_match,
+                "whether the pattern matches anywhere in the input",
+            ),
+            function(
+                "regex.find(pattern: string, input: string)",
+                regex_find,
+                "the first match of the pattern in the input, or none",
+            ),
+            function(
+                "regex.find_all(pattern: string, input: string)",
+                regex_find_all,
+                "every match of the pattern in the input, as their groups when it has any",
+            ),
+            function(
+                "regex.replace(pattern: string, input: string, replacement: string)",
+                regex_replace,
+                "the input with every match replaced, $0 to $9 standing for the groups",
+            ),
+        ],
+    );
+    registry.register_module(
+        "hash",
+        "hash functions over the UTF-8 bytes of a string",
+        vec![
+            function(
+                "hash.fnv(input: string)",
+                hash_fnv,
+                "the 32-bit FNV-1a hash of the input as a number",
+            ),
+            function(
+                "hash.sha256(input: string)",
+                hash_sha256,
+                "the SHA-256 digest of the input as hexadecimal text",
+            ),
+        ],
+    );
+    registry.register_module(
+        "encoding",
+        "byte encodings of strings",
+        vec![
+            function(
+                "encoding.base64_encode(input: string)",
+                encoding_base64_encode,
+                "the input encoded as base64",
+            ),
+            function(
+                "encoding.base64_decode(input: string)",
+                encoding_base64_decode,
+                "the text encoded as base64 in the input",
+            ),
+            function(
+                "encoding.hex_encode(input: string)",
+                encoding_hex_encode,
+                "the input encoded as hexadecimal",
+            ),
+            function(
+                "encoding.hex_decode(input: string)",
+                encoding_hex_decode,
+                "the text encoded as hexadecimal in the input",
+            ),
+        ],
+    );
+    registry.register_module(
+        "process",
+        "the arguments and environment variables the program was run with",
+        vec![
+            // `fns` registers the arguments given to a script in place of this one.
+            constant(
+                "args",
+                Value::Array(vec![]),
+                "the arguments given after the file, as strings",
+            ),
+            function(
+                "process.get(name: string)",
+                process_get,
+                "the value of the environment variable, none when it isn't set",
+            ),
+            function(
+                "process.vars()",
+                process_vars,
+                "every environment variable as an object of strings",
+            ),
+        ],
+    );
+    registry.register_module(
+        "time",
+        "the clock and waiting",
+        vec![
+            function(
+                "time.now()",
+                time_now,
+                "the seconds since the Unix epoch, with their fraction",
+            ),
+            function(
+                "time.monotonic()",
+                time_monotonic,
+                "the seconds since an arbitrary point, never going back, to measure durations with",
+            ),
+            function(
+                "time.sleep(seconds: number)",
+                time_sleep,
+                "waits for the seconds to pass",
+            ),
+        ],
+    );
+    registry.register_module(
+        "log",
+        "leveled logging to <stderr>",
+        vec![
+            function(
+                "log.debug(values...)",
+                log_debug,
+                "logs the values at the debug level",
+            ),
+            function(
+                "log.info(values...)",
+                log_info,
+                "logs the values at the info level",
+            ),
+            function(
+                "log.warn(values...)",
+                log_warn,
+                "logs the values at the warn level",
+            ),
+            function(
+                "log.error(values...)",
+                log_error,
+                "logs the values at the error level",
+            ),
+            function(
+                "log.set_level(level: string)",
+                log_set_level,
+                "only logs from the level on (debug, info, warn or error)",
+            ),
+        ],
+    );
+}
+
+fn expect_string(name: &str, value: &Value, text_span: &TextSpan) -> Result<String, Error> {
+    if let Value::String(s) = value {
+        Ok(s.to_string())
+    } else {
+        Err(Error::new(
+            format!(
+                "Can't use '{}' with '{name}' as it's not a string",
+                value.short_repr()
+            ),
+            text_span.clone(),
+        ))
+    }
+}
+
+fn expect_number(name: &str, value: &Value, text_span: &TextSpan) -> Result<f64, Error> {
+    if let Some(n) = value.as_number() {
+        Ok(n)
+    } else {
+        Err(Error::new(
+            format!(
+                "Can't use '{}' with '{name}' as it's not a number",
+                value.short_repr()
+            ),
+            text_span.clone(),
+        ))
+    }
+}
+
+fn expect_integer(name: &str, value: &Value, text_span: &TextSpan) -> Result<i64, Error> {
+    value.as_integer().ok_or_else(|| {
+        Error::new(
+            format!(
+                "Can't use '{}' with '{name}' as it's not a whole number",
+                value.short_repr()
             ),
+            text_span.clone(),
+        )
+    })
+}
+
+fn expect_array(name: &str, value: &Value, text_span: &TextSpan) -> Result<Vec<Value>, Error> {
+    if let Value::Array(elements) = value {
+        Ok(elements.clone())
+    } else {
+        Err(Error::new(
+            format!(
+                "Can't use '{}' with '{name}' as it's not an array",
+                value.short_repr()
+            ),
+            text_span.clone(),
+        ))
+    }
+}
+
+fn expect_set(
+    name: &str,
+    value: &Value,
+    text_span: &TextSpan,
+) -> Result<BTreeSet<SetElement>, Error> {
+    if let Value::Set(set) = value {
+        Ok(set.clone())
+    } else {
+        Err(Error::new(
+            format!(
+                "Can't use '{}' with '{name}' as it's not a set",
+                value.short_repr()
+            ),
+            text_span.clone(),
+        ))
+    }
+}
+
+fn expect_set_element(value: &Value, text_span: &TextSpan) -> Result<SetElement, Error> {
+    SetElement::from_value(value).ok_or_else(|| {
+        Error::new(
+            format!(
+                "Can't put '{}' in a set as only none, booleans, numbers and strings can be",
+                value.short_repr()
+            ),
+            text_span.clone(),
+        )
+    })
+}
+
+fn len(arguments: Vec<Value>, text_span: TextSpan, _: &mut Environment) -> Result<Value, Error> {
+    match &arguments[0] {
+        Value::Set(set) => Ok(Value::Integer(set.len() as i64)),
+        Value::Array(elements) | Value::Tuple(elements) => {
+            Ok(Value::Integer(elements.len() as i64))
+        }
+        Value::String(s) => Ok(Value::Integer(s.chars().count() as i64)),
+        Value::Object(object) => Ok(Value::Integer(object.len() as i64)),
+        value => Err(Error::new(
+            format!("Can't get the length of '{}'", value.short_repr()),
+            text_span,
+        )),
+    }
+}
+
+// The properties answered by values that aren't objects, like `"hi".length`. Each is
+// computed by calling a builtin with the value, so the two can't disagree.
+pub fn properties(value: &Value) -> Vec<(&'static str, NativeFunctionPointer)> {
+    match value {
+        Value::String(_) => vec![
+            ("length", len),
+            ("lower", string_lower),
+            ("upper", string_upper),
+        ],
+        Value::Integer(_) | Value::Number(_) => vec![
+            ("abs", math_abs),
+            ("ceil", math_ceil),
+            ("floor", math_floor),
+            ("round", math_round),
+        ],
+        Value::Set(_) | Value::Array(_) | Value::Tuple(_) => vec![("length", len)],
+        _ => vec![],
+    }
+}
+
+// The value of a property from `properties`, or `None` when the value has no such one.
+pub fn property(
+    value: &Value,
+    name: &str,
+    text_span: TextSpan,
+    environment: &mut Environment,
+) -> Option<Result<Value, Error>> {
+    properties(value)
+        .into_iter()
+        .find(|(property, _)| *property == name)
+        .map(|(_, function)| function(vec![value.clone()], text_span, environment))
+}
+
+// Integers are whole already and come back as they are. Floats are rounded to a whole
+// float, given back as an integer when one can hold it.
+fn math_rounding(
+    name: &str,
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    operation: fn(f64) -> f64,
+) -> Result<Value, Error> {
+    if let Value::Integer(i) = arguments[0] {
+        return Ok(Value::Integer(i));
+    }
+    let n = operation(expect_number(name, &arguments[0], &text_span)?);
+    Ok(whole_integer(n).map_or(Value::Number(n), Value::Integer))
+}
+
+fn math_floor(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    math_rounding("math.floor", arguments, text_span, f64::floor)
+}
+
+fn math_ceil(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    math_rounding("math.ceil", arguments, text_span, f64::ceil)
+}
+
+fn math_round(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    math_rounding("math.round", arguments, text_span, f64::round)
+}
+
+fn math_abs(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    match arguments[0] {
+        Value::Integer(i) => i.checked_abs().map(Value::Integer).ok_or_else(|| {
+            Error::new(
+                format!("Can't use '{i}' with 'math.abs' as its absolute value is out of the range of integers"),
+                text_span,
+            )
+        }),
+        _ => Ok(Value::Number(
+            expect_number("math.abs", &arguments[0], &text_span)?.abs(),
+        )),
+    }
+}
+
+// Functions of floats, giving a float for integers too.
+fn math_function(
+    name: &str,
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    operation: fn(f64) -> f64,
+) -> Result<Value, Error> {
+    Ok(Value::Number(operation(expect_number(
+        name,
+        &arguments[0],
+        &text_span,
+    )?)))
+}
+
+fn math_sin(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    math_function("math.sin", arguments, text_span, f64::sin)
+}
+
+fn math_cos(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    math_function("math.cos", arguments, text_span, f64::cos)
+}
+
+fn math_tan(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    math_function("math.tan", arguments, text_span, f64::tan)
+}
+
+fn math_asin(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    math_function("math.asin", arguments, text_span, f64::asin)
+}
+
+fn math_acos(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    math_function("math.acos", arguments, text_span, f64::acos)
+}
+
+fn math_atan(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    math_function("math.atan", arguments, text_span, f64::atan)
+}
+
+fn math_to_radians(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    math_function("math.to_radians", arguments, text_span, f64::to_radians)
+}
+
+fn math_to_degrees(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    math_function("math.to_degrees", arguments, text_span, f64::to_degrees)
+}
+
+fn math_atan2(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let y = expect_number("math.atan2", &arguments[0], &text_span)?;
+    let x = expect_number("math.atan2", &arguments[1], &text_span)?;
+    Ok(Value::Number(y.atan2(x)))
+}
+
+fn math_random(_: Vec<Value>, _: TextSpan, environment: &mut Environment) -> Result<Value, Error> {
+    Ok(Value::Number(environment.random.borrow_mut().next_float()))
+}
+
+fn math_random_int(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    let low = expect_integer("math.random_int", &arguments[0], &text_span)?;
+    let high = expect_integer("math.random_int", &arguments[1], &text_span)?;
+    if low > high {
+        return Err(Error::new(
+            format!("Can't pick a number from {low} to {high} with 'math.random_int' as the range is empty"),
+            text_span,
+        ));
+    }
+    let n = environment.random.borrow_mut().next_between(low, high);
+    Ok(Value::Integer(n))
+}
+
+// The same seed gives the same numbers, every run and on every platform.
+fn math_seed(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    let seed = expect_integer("math.seed", &arguments[0], &text_span)?;
+    *environment.random.borrow_mut() = Random::new(seed as u64);
+    Ok(Value::None)
+}
+
+fn math_is_nan(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let n = expect_number("math.is_nan", &arguments[0], &text_span)?;
+    Ok(Value::Boolean(n.is_nan()))
+}
+
+fn math_is_finite(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let n = expect_number("math.is_finite", &arguments[0], &text_span)?;
+    Ok(Value::Boolean(n.is_finite()))
+}
+
+fn expect_decimals(name: &str, value: &Value, text_span: &TextSpan) -> Result<usize, Error> {
+    let n = expect_number(name, value, text_span)?;
+    if n.fract() != 0.0 || !(0.0..=MAX_DECIMALS as f64).contains(&n) {
+        return Err(Error::new(
+            format!(
+                "Can't show {} decimals with '{name}' as it's not a whole number from 0 to {MAX_DECIMALS}",
+                value.short_repr()
+            ),
+            text_span.clone(),
+        ));
+    }
+    Ok(n as usize)
+}
+
+fn numbers_to_fixed(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let n = expect_number("numbers.to_fixed", &arguments[0], &text_span)?;
+    let decimals = expect_decimals("numbers.to_fixed", &arguments[1], &text_span)?;
+    Ok(Value::String(to_fixed(n, decimals).into()))
+}
+
+// The options of `string`, `decimals` and `separator`, which only numbers can take.
+fn number_format(options: &Value, text_span: &TextSpan) -> Result<NumberFormat, Error> {
+    let Value::Object(options) = options else {
+        unreachable!("the signature of string only lets objects through")
+    };
+    let mut format = NumberFormat::default();
+    for (key, value) in options {
+        match key.as_str() {
+            "decimals" => format.decimals = Some(expect_decimals("string", value, text_span)?),
+            "separator" => format.separator = Some(expect_string("string", value, text_span)?),
+            _ => {
+                return Err(Error::new(
+                    format!("Can't use the option '{key}' with 'string'"),
+                    text_span.clone(),
+                )
+                .with_note("the options are 'decimals' and 'separator'".to_string()))
+            }
+        }
+    }
+    Ok(format)
+}
+
+fn string(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    let Some(options) = arguments.get(1) else {
+        return Ok(Value::String(
+            display(&arguments[0], text_span, environment)?.into(),
+        ));
+    };
+    let format = number_format(options, &text_span)?;
+    let n = expect_number("string", &arguments[0], &text_span)?;
+    Ok(Value::String(format.apply(n).into()))
+}
+
+fn format(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    let template = expect_string("format", &arguments[0], &text_span)?;
+    let pieces = parse_template(&template).map_err(|error| {
+        Error::new(
+            format!(
+                "Can't use the template '{template}' with 'format', {} at position {}",
+                error.message, error.position
+            ),
+            text_span.clone(),
+        )
+    })?;
+    let placeholders = pieces
+        .iter()
+        .filter(|piece| matches!(piece, Piece::Placeholder(_)))
+        .count();
+    if placeholders != arguments.len() - 1 {
+        return Err(Error::new(
+            format!(
+                "Can't fill the template '{template}' as it has {} for {}",
+                plural(placeholders, "placeholder"),
+                plural(arguments.len() - 1, "value")
+            ),
+            text_span,
+        ));
+    }
+
+    let mut values = arguments[1..].iter();
+    let mut text = String::new();
+    for piece in pieces {
+        match piece {
+            Piece::Text(piece) => text.push_str(&piece),
+            Piece::Placeholder(NumberFormat {
+                decimals: None,
+                separator: None,
+            }) => text.push_str(&display(
+                values.next().unwrap(),
+                text_span.clone(),
+                environment,
+            )?),
+            Piece::Placeholder(format) => text.push_str(&format.apply(expect_number(
+                "format",
+                values.next().unwrap(),
+                &text_span,
+            )?)),
+        }
+    }
+    Ok(Value::String(text.into()))
+}
+
+fn string_upper(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    Ok(Value::String(
+        expect_string("upper", &arguments[0], &text_span)?
+            .to_uppercase()
+            .into(),
+    ))
+}
+
+fn string_lower(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    Ok(Value::String(
+        expect_string("lower", &arguments[0], &text_span)?
+            .to_lowercase()
+            .into(),
+    ))
+}
+
+// A char being a Unicode scalar value, `"héllo"` has 5 of them in 6 bytes.
+fn strings_len(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let s = expect_string("strings.len", &arguments[0], &text_span)?;
+    Ok(Value::Integer(s.chars().count() as i64))
+}
+
+fn strings_trim(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let s = expect_string("strings.trim", &arguments[0], &text_span)?;
+    Ok(Value::String(s.trim().to_string().into()))
+}
+
+fn strings_trim_start(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let s = expect_string("strings.trim_start", &arguments[0], &text_span)?;
+    Ok(Value::String(s.trim_start().to_string().into()))
+}
+
+fn strings_trim_end(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let s = expect_string("strings.trim_end", &arguments[0], &text_span)?;
+    Ok(Value::String(s.trim_end().to_string().into()))
+}
+
+// A position counted from the end when it's negative, clamped to the length of what's
+// sliced.
+fn slice_position(position: i64, length: usize) -> usize {
+    if position < 0 {
+        length.saturating_sub(position.unsigned_abs() as usize)
+    } else {
+        (position as usize).min(length)
+    }
+}
+
+// Slicing goes by chars, so it never splits one whichever bytes they're made of.
+fn strings_slice(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let s = expect_string("strings.slice", &arguments[0], &text_span)?;
+    let length = s.chars().count();
+    let start = slice_position(
+        expect_integer("strings.slice", &arguments[1], &text_span)?,
+        length,
+    );
+    let end = match arguments.get(2) {
+        Some(end) => slice_position(expect_integer("strings.slice", end, &text_span)?, length),
+        None => length,
+    };
+    let slice: String = s
+        .chars()
+        .skip(start)
+        .take(end.saturating_sub(start))
+        .collect();
+    Ok(Value::String(slice.into()))
+}
+
+fn strings_char_at(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let s = expect_string("strings.char_at", &arguments[0], &text_span)?;
+    let position = expect_integer("strings.char_at", &arguments[1], &text_span)?;
+    let length = s.chars().count();
+    let char = match position {
+        position if position < 0 => length
+            .checked_sub(position.unsigned_abs() as usize)
+            .and_then(|position| s.chars().nth(position)),
+        position => s.chars().nth(position as usize),
+    };
+    Ok(char.map_or(Value::None, |char| Value::String(char.to_string().into())))
+}
+
+// The empty string is in every string, at its start and its end, so searching for it
+// always finds it, at position 0.
+fn strings_contains(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let s = expect_string("strings.contains", &arguments[0], &text_span)?;
+    let needle = expect_string("strings.contains", &arguments[1], &text_span)?;
+    Ok(Value::Boolean(s.contains(&needle)))
+}
+
+fn strings_starts_with(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let s = expect_string("strings.starts_with", &arguments[0], &text_span)?;
+    let prefix = expect_string("strings.starts_with", &arguments[1], &text_span)?;
+    Ok(Value::Boolean(s.starts_with(&prefix)))
+}
+
+fn strings_ends_with(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let s = expect_string("strings.ends_with", &arguments[0], &text_span)?;
+    let suffix = expect_string("strings.ends_with", &arguments[1], &text_span)?;
+    Ok(Value::Boolean(s.ends_with(&suffix)))
+}
+
+fn strings_index_of(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let s = expect_string("strings.index_of", &arguments[0], &text_span)?;
+    let needle = expect_string("strings.index_of", &arguments[1], &text_span)?;
+    Ok(s.find(&needle).map_or(Value::None, |byte| {
+        Value::Integer(s[..byte].chars().count() as i64)
+    }))
+}
+
+// Replacing the empty string has no single meaning, so it's an error rather than a
+// guess at one.
+fn strings_replace(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    let s = expect_string("strings.replace", &arguments[0], &text_span)?;
+    let from = expect_string("strings.replace", &arguments[1], &text_span)?;
+    let to = expect_string("strings.replace", &arguments[2], &text_span)?;
+    if from.is_empty() {
+        return Err(Error::new(
+            "Can't replace the empty string with 'strings.replace' as it's between every char"
+                .to_string(),
+            text_span,
+        ));
+    }
+    let mut builder = StringBuilder::new(String::with_capacity(s.len()), &environment.limits);
+    for (i, piece) in s.split(from.as_str()).enumerate() {
+        if i > 0 {
+            builder
+                .push(&to)
+                .map_err(|message| Error::new(message, text_span.clone()))?;
+        }
+        builder
+            .push(piece)
+            .map_err(|message| Error::new(message, text_span.clone()))?;
+    }
+    Ok(Value::String(builder.finish().into()))
+}
+
+fn strings_repeat(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    let s = expect_string("strings.repeat", &arguments[0], &text_span)?;
+    let count = expect_integer("strings.repeat", &arguments[1], &text_span)?;
+    if count < 0 {
+        return Err(Error::new(
+            format!("Can't repeat a string {count} times with 'strings.repeat' as the count is negative"),
+            text_span,
+        ));
+    }
+    // Checked before repeating, rather than a piece at a time, as the count may be huge.
+    let max_length = environment.limits.max_string_length;
+    if s.chars().count().saturating_mul(count as usize) > max_length {
+        return Err(Error::new(
+            format!("Can't make a string longer than the limit of {max_length} chars"),
+            text_span,
+        ));
+    }
+    Ok(Value::String(s.repeat(count as usize).into()))
+}
+
+fn arrays_len(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let array = expect_array("arrays.len", &arguments[0], &text_span)?;
+    Ok(Value::Integer(array.len() as i64))
+}
+
+fn arrays_push(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let mut array = expect_array("arrays.push", &arguments[0], &text_span)?;
+    array.push(arguments[1].clone());
+    Ok(Value::Array(array))
+}
+
+fn arrays_concat(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let mut array = expect_array("arrays.concat", &arguments[0], &text_span)?;
+    array.extend(expect_array("arrays.concat", &arguments[1], &text_span)?);
+    Ok(Value::Array(array))
+}
+
+fn arrays_slice(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let array = expect_array("arrays.slice", &arguments[0], &text_span)?;
+    let start = slice_position(
+        expect_integer("arrays.slice", &arguments[1], &text_span)?,
+        array.len(),
+    );
+    let end = match arguments.get(2) {
+        Some(end) => slice_position(
+            expect_integer("arrays.slice", end, &text_span)?,
+            array.len(),
         ),
-        (
-            "math".to_string(),
-            Value::Object(
-                vec![("pi", Value::Number(PI)), ("e", Value::Number(E))]
-                    .iter()
-                    .map(|(key, value)| (key.to_string(), Box::new(value.clone())))
-                    .collect(),
+        None => array.len(),
+    };
+    Ok(Value::Array(array[start..end.max(start)].to_vec()))
+}
+
+fn arrays_reverse(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let mut array = expect_array("arrays.reverse", &arguments[0], &text_span)?;
+    array.reverse();
+    Ok(Value::Array(array))
+}
+
+// Elements are compared with `==`, so `1` is found in `[1.0]` and NaN never is.
+fn arrays_contains(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let array = expect_array("arrays.contains", &arguments[0], &text_span)?;
+    Ok(Value::Boolean(array.contains(&arguments[1])))
+}
+
+fn arrays_index_of(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let array = expect_array("arrays.index_of", &arguments[0], &text_span)?;
+    Ok(array
+        .iter()
+        .position(|element| *element == arguments[1])
+        .map_or(Value::None, |position| Value::Integer(position as i64)))
+}
+
+// Names and values that aren't valid Unicode have their invalid bytes replaced.
+fn process_get(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let name = expect_string("process.get", &arguments[0], &text_span)?;
+    // `var_os` may panic on these, which are never the name of a variable anyway.
+    if name.is_empty() || name.contains(['=', '\0']) {
+        return Ok(Value::None);
+    }
+    Ok(var_os(name).map_or(Value::None, |value| {
+        Value::String(value.to_string_lossy().into_owned().into())
+    }))
+}
+
+fn process_vars(_: Vec<Value>, _: TextSpan, _: &mut Environment) -> Result<Value, Error> {
+    Ok(Value::Object(
+        vars_os()
+            .map(|(name, value)| {
+                (
+                    name.to_string_lossy().into_owned(),
+                    Box::new(Value::String(value.to_string_lossy().into_owned().into())),
+                )
+            })
+            .collect(),
+    ))
+}
+
+fn time_now(_: Vec<Value>, _: TextSpan, _: &mut Environment) -> Result<Value, Error> {
+    // A clock set before 1970 gives the seconds before the epoch as a negative number.
+    let seconds = match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_secs_f64(),
+        Err(error) => -error.duration().as_secs_f64(),
+    };
+    Ok(Value::Number(seconds))
+}
+
+fn time_monotonic(_: Vec<Value>, _: TextSpan, _: &mut Environment) -> Result<Value, Error> {
+    static START: OnceLock<Instant> = OnceLock::new();
+    Ok(Value::Number(
+        START.get_or_init(Instant::now).elapsed().as_secs_f64(),
+    ))
+}
+
+// How long `time.sleep` sleeps between looking whether evaluation was interrupted.
+const SLEEP_SLICE: Duration = Duration::from_millis(10);
+
+fn time_sleep(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    let seconds = expect_number("time.sleep", &arguments[0], &text_span)?;
+    if seconds.is_nan() || seconds < 0.0 {
+        return Err(Error::new(
+            format!(
+                "Can't sleep for '{}' seconds as it's not a positive number or 0",
+                arguments[0].short_repr()
+            ),
+            text_span,
+        ));
+    }
+    let end = Duration::try_from_secs_f64(seconds)
+        .ok()
+        .and_then(|duration| Instant::now().checked_add(duration))
+        .ok_or_else(|| {
+            Error::new(
+                format!(
+                    "Can't sleep for '{}' seconds as it's too long",
+                    arguments[0].short_repr()
+                ),
+                text_span.clone(),
+            )
+        })?;
+    loop {
+        if environment.is_interrupted() {
+            return Err(Error::new(
+                "Evaluation was interrupted".to_string(),
+                text_span,
+            ));
+        }
+        let left = end.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Ok(Value::None);
+        }
+        thread::sleep(left.min(SLEEP_SLICE));
+    }
+}
+
+// A stable merge sort that stops at the first error of `compare`. The standard sorts
+// can't stop early, and may panic when a comparator written in fns isn't consistent.
+fn merge_sort(
+    values: Vec<Value>,
+    compare: &mut dyn FnMut(&Value, &Value) -> Result<Ordering, Error>,
+) -> Result<Vec<Value>, Error> {
+    if values.len() < 2 {
+        return Ok(values);
+    }
+    let mut left = values;
+    let right = left.split_off(left.len() / 2);
+    let left = merge_sort(left, compare)?;
+    let right = merge_sort(right, compare)?;
+
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+        // Equal elements are taken from the left first, which keeps the sort stable.
+        if compare(a, b)?.is_gt() {
+            merged.extend(right.next());
+        } else {
+            merged.extend(left.next());
+        }
+    }
+    merged.extend(left);
+    merged.extend(right);
+    Ok(merged)
+}
+
+fn arrays_sort(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let array = expect_array("arrays.sort", &arguments[0], &text_span)?;
+    let error = |message: String| {
+        Error::new(message, text_span.clone())
+            .with_note("arrays.sort_by sorts any values with a comparator".to_string())
+    };
+    for element in &array {
+        match element {
+            Value::Number(number) if number.is_nan() => {
+                return Err(error(
+                    "Can't sort 'NaN' as it's not ordered with other numbers".to_string(),
+                ))
+            }
+            Value::Integer(_) | Value::Number(_) | Value::String(_) => {}
+            element => {
+                return Err(error(format!(
+                    "Can't sort '{}' as only numbers and strings can be sorted",
+                    element.short_repr()
+                )))
+            }
+        }
+    }
+    merge_sort(array, &mut |a, b| match (a, b) {
+        (Value::String(a), Value::String(b)) => Ok(a.cmp(b)),
+        (a, b) => numeric_order(a, b).ok_or_else(|| {
+            error(format!(
+                "Can't sort '{}' with '{}' as they're not both numbers or both strings",
+                a.short_repr(),
+                b.short_repr()
+            ))
+        }),
+    })
+    .map(Value::Array)
+}
+
+fn arrays_sort_by(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    let array = expect_array("arrays.sort_by", &arguments[0], &text_span)?;
+    let compare = &arguments[1];
+    merge_sort(array, &mut |a, b| {
+        let order = call_value(
+            compare,
+            vec![a.clone(), b.clone()],
+            text_span.clone(),
+            environment,
+        )?;
+        let number = match order {
+            Value::Integer(n) => n as f64,
+            Value::Number(n) if !n.is_nan() => n,
+            order => {
+                return Err(Error::new(
+                    format!(
+                    "Can't sort with '{}' as it gave '{}' for '{}' and '{}', which isn't a number",
+                    compare.short_repr(),
+                    order.short_repr(),
+                    a.short_repr(),
+                    b.short_repr()
+                ),
+                    text_span.clone(),
+                ))
+            }
+        };
+        Ok(number.partial_cmp(&0.0).unwrap_or(Ordering::Equal))
+    })
+    .map(Value::Array)
+}
+
+fn arrays_map(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    let array = expect_array("arrays.map", &arguments[0], &text_span)?;
+    let mut results = Vec::with_capacity(array.len());
+    for element in array {
+        results.push(call_value(
+            &arguments[1],
+            vec![element],
+            text_span.clone(),
+            environment,
+        )?);
+    }
+    Ok(Value::Array(results))
+}
+
+fn arrays_filter(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    let array = expect_array("arrays.filter", &arguments[0], &text_span)?;
+    let mut kept = vec![];
+    for element in array {
+        match call_value(
+            &arguments[1],
+            vec![element.clone()],
+            text_span.clone(),
+            environment,
+        )? {
+            Value::Boolean(true) => kept.push(element),
+            Value::Boolean(false) => {}
+            value => {
+                return Err(Error::new(
+                    format!(
+                        "Can't filter with '{}' as it gave '{}' for '{}', which isn't a boolean",
+                        arguments[1].short_repr(),
+                        value.short_repr(),
+                        element.short_repr()
+                    ),
+                    text_span,
+                ))
+            }
+        }
+    }
+    Ok(Value::Array(kept))
+}
+
+fn arrays_reduce(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    let mut elements = expect_array("arrays.reduce", &arguments[0], &text_span)?.into_iter();
+    let mut accumulator = match arguments.get(2) {
+        Some(initial) => initial.clone(),
+        None => elements.next().ok_or_else(|| {
+            Error::new(
+                "Can't reduce an empty array without an initial value".to_string(),
+                text_span.clone(),
+            )
+            .with_note("pass the value to start from as the third argument".to_string())
+        })?,
+    };
+    for element in elements {
+        accumulator = call_value(
+            &arguments[1],
+            vec![accumulator, element],
+            text_span.clone(),
+            environment,
+        )?;
+    }
+    Ok(accumulator)
+}
+
+fn set(arguments: Vec<Value>, text_span: TextSpan, _: &mut Environment) -> Result<Value, Error> {
+    let mut set = BTreeSet::new();
+    for argument in &arguments {
+        set.insert(expect_set_element(argument, &text_span)?);
+    }
+    Ok(Value::Set(set))
+}
+
+fn sets_add(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let mut set = expect_set("sets.add", &arguments[0], &text_span)?;
+    set.insert(expect_set_element(&arguments[1], &text_span)?);
+    Ok(Value::Set(set))
+}
+
+fn sets_has(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let set = expect_set("sets.has", &arguments[0], &text_span)?;
+    Ok(Value::Boolean(
+        SetElement::from_value(&arguments[1]).is_some_and(|element| set.contains(&element)),
+    ))
+}
+
+fn sets_remove(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let mut set = expect_set("sets.remove", &arguments[0], &text_span)?;
+    if let Some(element) = SetElement::from_value(&arguments[1]) {
+        set.remove(&element);
+    }
+    Ok(Value::Set(set))
+}
+
+fn objects_remove(arguments: Vec<Value>, _: TextSpan, _: &mut Environment) -> Result<Value, Error> {
+    let mut arguments = arguments.into_iter();
+    match (arguments.next(), arguments.next()) {
+        (Some(Value::Object(mut object)), Some(Value::String(key))) => {
+            object.remove(key.as_str());
+            Ok(Value::Object(object))
+        }
+        _ => unreachable!("the signature only lets an object and a string through"),
+    }
+}
+
+fn sets_union(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let left = expect_set("sets.union", &arguments[0], &text_span)?;
+    let right = expect_set("sets.union", &arguments[1], &text_span)?;
+    Ok(Value::Set(left.union(&right).cloned().collect()))
+}
+
+fn sets_intersection(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let left = expect_set("sets.intersection", &arguments[0], &text_span)?;
+    let right = expect_set("sets.intersection", &arguments[1], &text_span)?;
+    Ok(Value::Set(left.intersection(&right).cloned().collect()))
+}
+
+fn expect_regex(name: &str, value: &Value, text_span: &TextSpan) -> Result<Rc<Regex>, Error> {
+    let pattern = expect_string(name, value, text_span)?;
+    compile_cached(&pattern).map_err(|error| {
+        Error::new(
+            format!(
+                "Can't compile the pattern '{pattern}' at position {}: {}",
+                error.position, error.message
+            ),
+            text_span.clone(),
+        )
+    })
+}
+
+// Each path a search tries counts as a step of the evaluation, so a pattern trying
+// exponentially many stops at the limit of steps, or once the evaluation is interrupted.
+fn charge_search<'a>(
+    name: &'a str,
+    text_span: &'a TextSpan,
+    environment: &'a Environment,
+) -> impl FnMut() -> Result<(), Error> + 'a {
+    move || {
+        if environment.is_interrupted() {
+            return Err(Error::new(
+                "Evaluation was interrupted".to_string(),
+                text_span.clone(),
+            ));
+        }
+        let steps = environment.steps.get() + 1;
+        if steps > environment.limits.max_steps {
+            return Err(Error::new(
+                format!(
+                    "Can't finish '{name}' within the limit of {} steps",
+                    environment.limits.max_steps
+                ),
+                text_span.clone(),
+            ));
+        }
+        environment.steps.set(steps);
+        Ok(())
+    }
+}
+
+fn capture_to_value(input: &[char], capture: Option<(usize, usize)>) -> Value {
+    match capture {
+        Some((start, end)) => Value::String(input[start..end].iter().collect::<String>().into()),
+        None => Value::None,
+    }
+}
+
+fn regex_is_match(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    let regex = expect_regex("regex.is_match", &arguments[0], &text_span)?;
+    let input: Vec<char> = expect_string("regex.is_match", &arguments[1], &text_span)?
+        .chars()
+        .collect();
+    let mut charge = charge_search("regex.is_match", &text_span, environment);
+    Ok(Value::Boolean(
+        regex.find_at(&input, 0, &mut charge)?.is_some(),
+    ))
+}
+
+fn regex_find(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    let regex = expect_regex("regex.find", &arguments[0], &text_span)?;
+    let input: Vec<char> = expect_string("regex.find", &arguments[1], &text_span)?
+        .chars()
+        .collect();
+    let mut charge = charge_search("regex.find", &text_span, environment);
+    Ok(match regex.find_at(&input, 0, &mut charge)? {
+        Some(captures) => capture_to_value(&input, captures[0]),
+        None => Value::None,
+    })
+}
+
+// Without groups every match is returned as a string, with groups each match becomes a
+// tuple of its groups where a group that didn't take part in the match is none. The
+// matches come in an array as there can be any number of them.
+fn regex_find_all(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    let regex = expect_regex("regex.find_all", &arguments[0], &text_span)?;
+    let input: Vec<char> = expect_string("regex.find_all", &arguments[1], &text_span)?
+        .chars()
+        .collect();
+    let mut charge = charge_search("regex.find_all", &text_span, environment);
+    Ok(Value::Array(
+        regex
+            .find_all(&input, &mut charge)?
+            .into_iter()
+            .map(|captures| {
+                if regex.group_count() == 0 {
+                    capture_to_value(&input, captures[0])
+                } else {
+                    Value::Tuple(
+                        captures[1..]
+                            .iter()
+                            .map(|capture| capture_to_value(&input, *capture))
+                            .collect(),
+                    )
+                }
+            })
+            .collect(),
+    ))
+}
+
+// Replaces every match, `$0` in the replacement refers to the whole match, `$1` to `$9`
+// to the groups and `$$` to a literal dollar sign.
+fn regex_replace(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    let regex = expect_regex("regex.replace", &arguments[0], &text_span)?;
+    let input: Vec<char> = expect_string("regex.replace", &arguments[1], &text_span)?
+        .chars()
+        .collect();
+    let replacement: Vec<char> = expect_string("regex.replace", &arguments[2], &text_span)?
+        .chars()
+        .collect();
+
+    let mut output = String::new();
+    let mut position = 0;
+    let mut charge = charge_search("regex.replace", &text_span, environment);
+    for captures in regex.find_all(&input, &mut charge)? {
+        let (start, end) = captures[0].unwrap_or((position, position));
+        output.extend(&input[position..start]);
+        output.push_str(&expand_replacement(&replacement, &input, &captures));
+        position = end;
+    }
+    output.extend(&input[position..]);
+    Ok(Value::String(output.into()))
+}
+
+fn expand_replacement(replacement: &[char], input: &[char], captures: &Captures) -> String {
+    let mut output = String::new();
+    let mut index = 0;
+    while index < replacement.len() {
+        let char = replacement[index];
+        let next = replacement.get(index + 1);
+        match (char, next) {
+            ('$', Some('$')) => {
+                output.push('$');
+                index += 2;
+            }
+            ('$', Some(digit)) if digit.is_ascii_digit() => {
+                let group = digit.to_digit(10).unwrap_or_default() as usize;
+                if let Some(Some((start, end))) = captures.get(group) {
+                    output.extend(&input[*start..*end]);
+                }
+                index += 2;
+            }
+            (char, _) => {
+                output.push(char);
+                index += 1;
+            }
+        }
+    }
+    output
+}
+
+fn hash_fnv(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let input = expect_string("hash.fnv", &arguments[0], &text_span)?;
+    Ok(Value::Integer(fnv1a(input.as_bytes()).into()))
+}
+
+fn hash_sha256(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let input = expect_string("hash.sha256", &arguments[0], &text_span)?;
+    Ok(Value::String(hex_encode(&sha256(input.as_bytes())).into()))
+}
+
+fn decoded_to_value(
+    name: &str,
+    decoded: Result<Vec<u8>, String>,
+    text_span: TextSpan,
+) -> Result<Value, Error> {
+    let bytes = decoded.map_err(|message| {
+        Error::new(
+            format!("Can't decode the input of '{name}', {message}"),
+            text_span.clone(),
+        )
+    })?;
+    String::from_utf8(bytes)
+        .map(|text| Value::String(text.into()))
+        .map_err(|_| {
+            Error::new(
+                format!("Can't decode the input of '{name}' as it isn't valid UTF-8 text"),
+                text_span,
+            )
+        })
+}
+
+fn encoding_base64_encode(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let input = expect_string("encoding.base64_encode", &arguments[0], &text_span)?;
+    Ok(Value::String(base64_encode(input.as_bytes()).into()))
+}
+
+fn encoding_base64_decode(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let input = expect_string("encoding.base64_decode", &arguments[0], &text_span)?;
+    decoded_to_value("encoding.base64_decode", base64_decode(&input), text_span)
+}
+
+fn encoding_hex_encode(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let input = expect_string("encoding.hex_encode", &arguments[0], &text_span)?;
+    Ok(Value::String(hex_encode(input.as_bytes()).into()))
+}
+
+fn encoding_hex_decode(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    _: &mut Environment,
+) -> Result<Value, Error> {
+    let input = expect_string("encoding.hex_decode", &arguments[0], &text_span)?;
+    decoded_to_value("encoding.hex_decode", hex_decode(&input), text_span)
+}
+
+// The arguments of a log call are displayed and separated by spaces.
+fn log(
+    level: LogLevel,
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    let mut message = vec![];
+    for argument in &arguments {
+        message.push(display(argument, text_span.clone(), environment)?);
+    }
+    environment
+        .logger
+        .borrow_mut()
+        .log(level, &message.join(" "));
+    Ok(Value::None)
+}
+
+fn log_debug(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    log(LogLevel::Debug, arguments, text_span, environment)
+}
+
+fn log_info(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    log(LogLevel::Info, arguments, text_span, environment)
+}
+
+fn log_warn(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    log(LogLevel::Warn, arguments, text_span, environment)
+}
+
+fn log_error(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    log(LogLevel::Error, arguments, text_span, environment)
+}
+
+fn log_set_level(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    let name = expect_string("log.set_level", &arguments[0], &text_span)?;
+    match LogLevel::from_name(&name) {
+        Some(level) => {
+            environment.logger.borrow_mut().level = level;
+            Ok(Value::None)
+        }
+        None => Err(Error::new(
+            format!(
+                "Can't set the log level to '{name}' as it's not one of debug, info, warn or error"
             ),
+            text_span,
+        )),
+    }
+}
+
+fn help(arguments: Vec<Value>, _: TextSpan, environment: &mut Environment) -> Result<Value, Error> {
+    let builtins = environment.builtins.builtins();
+    Ok(Value::String(
+        match arguments.first() {
+            Some(value) => describe(value, builtins),
+            None => format!("builtins:\n{}", describe_members(builtins)),
+        }
+        .into(),
+    ))
+}
+
+// Values never alias one another, so cloning one copies everything nested in it and no
+// cycle can be met on the way.
+// Text is a number when it's written like one, with an optional sign and exponent and
+// spaces around it: `"42"`, `" -4.5 "` or `"1e3"`, the ones without a point or exponent
+// being integers.
+fn number(arguments: Vec<Value>, _: TextSpan, _: &mut Environment) -> Result<Value, Error> {
+    Ok(match &arguments[0] {
+        value @ (Value::Integer(_) | Value::Number(_)) => value.clone(),
+        Value::Boolean(b) => Value::Integer(i64::from(*b)),
+        Value::String(text) => {
+            let text = text.trim();
+            let is_numeric = text.starts_with(|c: char| c.is_ascii_digit() || "+-.".contains(c))
+                && text
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || "+-.eE".contains(c));
+            match (text.parse::<i64>(), text.parse::<f64>()) {
+                _ if !is_numeric => Value::None,
+                (Ok(i), _) => Value::Integer(i),
+                (_, Ok(n)) => Value::Number(n),
+                _ => Value::None,
+            }
+        }
+        _ => Value::None,
+    })
+}
+
+fn boolean(arguments: Vec<Value>, _: TextSpan, _: &mut Environment) -> Result<Value, Error> {
+    Ok(Value::Boolean(arguments[0].is_truthy()))
+}
+
+fn input(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    let prompt = match arguments.first() {
+        Some(Value::String(prompt)) => prompt.as_str(),
+        _ => "",
+    };
+    let line = environment.console.borrow_mut().read_line(prompt);
+    match line {
+        Ok(line) => Ok(line.map_or(Value::None, |line| Value::String(line.into()))),
+        Err(error) => Err(Error::new(
+            format!("Can't read a line of input as {error}"),
+            text_span,
+        )),
+    }
+}
+
+fn type_(arguments: Vec<Value>, _: TextSpan, _: &mut Environment) -> Result<Value, Error> {
+    Ok(Value::String(arguments[0].type_name().to_string().into()))
+}
+
+fn assert(arguments: Vec<Value>, text_span: TextSpan, _: &mut Environment) -> Result<Value, Error> {
+    if arguments[0] == Value::Boolean(true) {
+        return Ok(Value::None);
+    }
+    let message = match arguments.get(1) {
+        Some(message) => format!("Assertion failed: {message}"),
+        None => "Assertion failed".to_string(),
+    };
+    let error = Error::new(message, text_span);
+    Err(match &arguments[0] {
+        Value::Boolean(false) => error,
+        condition => error.with_note(format!(
+            "the condition was '{}' rather than true",
+            condition.short_repr()
+        )),
+    })
+}
+
+// Equal like `==` without the hooks of objects, showing the values as `string` would.
+fn assert_eq(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    if arguments[0] == arguments[1] {
+        return Ok(Value::None);
+    }
+    let left = display(&arguments[0], text_span.clone(), environment)?;
+    let right = display(&arguments[1], text_span.clone(), environment)?;
+    Err(Error::new(
+        "Assertion failed as the values aren't equal".to_string(),
+        text_span,
+    )
+    .with_note(format!("left: {left}"))
+    .with_note(format!("right: {right}")))
+}
+
+fn clone(arguments: Vec<Value>, _: TextSpan, _: &mut Environment) -> Result<Value, Error> {
+    Ok(arguments[0].clone())
+}
+
+fn env(arguments: Vec<Value>, _: TextSpan, environment: &mut Environment) -> Result<Value, Error> {
+    let include_builtins = matches!(arguments.first(), Some(Value::Boolean(true)));
+    Ok(Value::Object(
+        environment
+            .visible_bindings(include_builtins)
+            .into_iter()
+            .map(|(identifier, value)| (identifier, Box::new(value)))
+            .collect(),
+    ))
+}
+
+fn import(
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    let specifier = expect_string("import", &arguments[0], &text_span)?;
+    module::import(&specifier, text_span, environment)
+}
+
+// Values are matched with `compare` rather than `==`, for which a module holding NaN,
+// like `math`, isn't equal to itself.
+fn find_builtin<'a>(value: &Value, builtins: &'a [Builtin]) -> Option<&'a Builtin> {
+    builtins.iter().find_map(|builtin| {
+        let is_documented =
+            matches!(builtin.value, Value::NativeFunction(_)) || !builtin.members.is_empty();
+        if is_documented && builtin.value.compare(value) == Ordering::Equal {
+            Some(builtin)
+        } else {
+            find_builtin(value, &builtin.members)
+        }
+    })
+}
+
+fn describe(value: &Value, builtins: &[Builtin]) -> String {
+    match find_builtin(value, builtins) {
+        Some(Builtin {
+            value: Value::NativeFunction(function),
+            description,
+            ..
+        }) => format!("{}\n  {description}", function.signature),
+        Some(builtin) => format!(
+            "{}: {}\n{}",
+            builtin.name,
+            builtin.description,
+            describe_members(&builtin.members)
         ),
-    ]
+        None => format!("'{}' has no documentation", value.short_repr()),
+    }
+}
+
+// One line per builtin, functions being labeled by their signature.
+fn describe_members(builtins: &[Builtin]) -> String {
+    let labels: Vec<String> = builtins
+        .iter()
+        .map(|builtin| match &builtin.value {
+            Value::NativeFunction(function) => function.signature.clone(),
+            _ => builtin.name.clone(),
+        })
+        .collect();
+    let width = labels.iter().map(|label| label.len()).max().unwrap_or(0);
+    let lines: Vec<String> = labels
+        .iter()
+        .zip(builtins)
+        .map(|(label, builtin)| format!("  {label:width$}  {}", builtin.description))
+        .collect();
+    lines.join("\n")
 }
@@ -0,0 +1,130 @@
+use std::{
+    fmt,
+    io::{self, stdin, stdout, BufRead, Write},
+};
+
+// Where `input` reads lines from, stdin unless the host gives the environment a source
+// of its own, like a test feeding it lines.
+pub trait LineSource {
+    // The next line with its line ending, `None` at the end of input.
+    fn read_line(&mut self) -> io::Result<Option<String>>;
+}
+
+// Reads through the buffer of the process' stdin rather than one of its own, so lines
+// the repl hasn't read yet stay there for it whoever else reads stdin.
+pub struct Stdin;
+
+impl LineSource for Stdin {
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        let mut line = String::new();
+        match stdin().read_line(&mut line)? {
+            0 => Ok(None),
+            _ => Ok(Some(line)),
+        }
+    }
+}
+
+impl<R: BufRead> LineSource for R {
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        let mut line = String::new();
+        match BufRead::read_line(self, &mut line)? {
+            0 => Ok(None),
+            _ => Ok(Some(line)),
+        }
+    }
+}
+
+// The state behind the `input` builtin, shared by an environment and all of its
+// descendants: the lines it reads and where its prompts are written.
+pub struct Console {
+    pub input: Box<dyn LineSource>,
+    pub output: Box<dyn Write>,
+}
+
+impl Console {
+    pub fn new(input: Box<dyn LineSource>, output: Box<dyn Write>) -> Self {
+        Self { input, output }
+    }
+
+    // Writes the prompt, then reads a line without its line ending, `None` at the end of
+    // input.
+    pub fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        if !prompt.is_empty() {
+            self.output.write_all(prompt.as_bytes())?;
+            self.output.flush()?;
+        }
+        Ok(self.input.read_line()?.map(|mut line| {
+            if line.ends_with('\n') {
+                line.pop();
+                if line.ends_with('\r') {
+                    line.pop();
+                }
+            }
+            line
+        }))
+    }
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self::new(Box::new(Stdin), Box::new(stdout()))
+    }
+}
+
+impl fmt::Debug for Console {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Console").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, io::Cursor, io::Write, rc::Rc};
+
+    use crate::{
+        run_source_with,
+        runtime::{environment::Environment, types::Value},
+    };
+
+    use super::Console;
+
+    #[derive(Clone)]
+    struct Sink(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Sink {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_input() {
+        let sink = Sink(Rc::new(RefCell::new(vec![])));
+        let mut environment = Environment::new(None);
+        *environment.console.borrow_mut() = Console::new(
+            Box::new(Cursor::new("Ada\r\n\nlast")),
+            Box::new(sink.clone()),
+        );
+        let value = run_source_with(
+            "let name = input(\"what's your name? \") let lines = (name, input(), input(), input()) lines",
+            &mut environment,
+        )
+        .unwrap();
+        assert_eq!(value.to_string(), "(Ada, , last, none)");
+        assert_eq!(sink.0.borrow().as_slice(), b"what's your name? ");
+        assert_eq!(
+            run_source_with("input()", &mut environment).unwrap(),
+            Value::None
+        );
+
+        let error = run_source_with("input(1)", &mut environment).unwrap_err();
+        assert_eq!(
+            error.message,
+            "input expects prompt to be a string, got a number"
+        );
+    }
+}
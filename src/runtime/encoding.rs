@@ -0,0 +1,125 @@
+// Byte encodings backing the `encoding` builtin module. Decoding reports what went
+// wrong as a plain message, the builtins turn it into an error at the call.
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn base64_encode(bytes: &[u8]) -> String {
+    let mut output = String::new();
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (index, byte)| {
+            group | (*byte as u32) << (16 - 8 * index)
+        });
+        for index in 0..4 {
+            if index <= chunk.len() {
+                let sextet = (group >> (18 - 6 * index)) & 0b111111;
+                output.push(BASE64_ALPHABET[sextet as usize] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
+
+pub fn base64_decode(input: &str) -> Result<Vec<u8>, String> {
+    let input = input.as_bytes();
+    if let Some(position) = input
+        .iter()
+        .position(|byte| *byte != b'=' && !BASE64_ALPHABET.contains(byte))
+    {
+        return Err(format!(
+            "invalid character '{}' at position {position}",
+            input[position] as char
+        ));
+    }
+    if !input.len().is_multiple_of(4) {
+        return Err("length isn't a multiple of 4".to_string());
+    }
+
+    let mut output = vec![];
+    for (chunk_index, chunk) in input.chunks(4).enumerate() {
+        let is_last = chunk_index == input.len() / 4 - 1;
+        let padding = chunk.iter().rev().take_while(|byte| **byte == b'=').count();
+        if padding > 2 || (padding > 0 && !is_last) {
+            return Err(format!(
+                "unexpected padding at position {}",
+                chunk_index * 4 + 4 - padding
+            ));
+        }
+
+        let mut group = 0u32;
+        for (index, byte) in chunk[..4 - padding].iter().enumerate() {
+            let sextet = BASE64_ALPHABET
+                .iter()
+                .position(|character| character == byte)
+                .ok_or_else(|| {
+                    format!("unexpected padding at position {}", chunk_index * 4 + index)
+                })?;
+            group |= (sextet as u32) << (18 - 6 * index);
+        }
+        for index in 0..3 - padding {
+            output.push((group >> (16 - 8 * index)) as u8);
+        }
+    }
+    Ok(output)
+}
+
+pub fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+pub fn hex_decode(input: &str) -> Result<Vec<u8>, String> {
+    let input = input.as_bytes();
+    if !input.len().is_multiple_of(2) {
+        return Err("length isn't even".to_string());
+    }
+
+    let mut output = vec![];
+    for (index, pair) in input.chunks(2).enumerate() {
+        let mut byte = 0;
+        for (offset, digit) in pair.iter().enumerate() {
+            let value = (*digit as char).to_digit(16).ok_or_else(|| {
+                format!(
+                    "invalid character '{}' at position {}",
+                    *digit as char,
+                    index * 2 + offset
+                )
+            })?;
+            byte = byte << 4 | value as u8;
+        }
+        output.push(byte);
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{base64_decode, base64_encode, hex_decode, hex_encode};
+
+    #[test]
+    fn test_base64() {
+        for (decoded, encoded) in [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ] {
+            assert_eq!(base64_encode(decoded.as_bytes()), encoded);
+            assert_eq!(base64_decode(encoded).unwrap(), decoded.as_bytes());
+        }
+        assert!(base64_decode("not base64!").is_err());
+        assert!(base64_decode("Zg==Zg==").is_err());
+    }
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(hex_encode(b"fns\n"), "666e730a");
+        assert_eq!(hex_decode("666E730a").unwrap(), b"fns\n");
+        assert!(hex_decode("abc").is_err());
+        assert!(hex_decode("zz").is_err());
+    }
+}
@@ -1,47 +1,576 @@
-use std::collections::HashMap;
+use std::{
+    cell::{Cell, RefCell},
+    collections::{hash_map::Entry, HashMap},
+    mem::take,
+    rc::{Rc, Weak},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
-use super::{builtin::get_builtin, types::Value};
+use crate::frontend::{limits::Limits, progress::Progress, symbol::Symbol};
+
+use super::{
+    console::Console,
+    log::Logger,
+    module::Modules,
+    profile::Profiler,
+    random::Random,
+    registry::BuiltinRegistry,
+    trace::Tracer,
+    types::{Function, Value, ValuePrinter},
+};
 
 #[derive(Debug, Clone)]
 pub struct Environment {
     pub parent: Box<Option<Self>>,
-    pub variables: HashMap<String, (Value, bool)>,
+    // Keyed by symbol so the names coming from tokens are shared rather than copied, and
+    // still looked up with a plain `&str`. The variables of an environment are shared by
+    // its clones and the functions created in it, so they all see its assignments.
+    pub variables: Rc<RefCell<HashMap<Symbol, (Value, bool)>>>,
+    pub annotations: Rc<RefCell<HashMap<Symbol, String>>>,
+    // Looked up after every environment up the chain, so defining a builtin's name
+    // shadows the builtin instead of changing it.
+    pub builtins: Rc<BuiltinRegistry>,
+    pub interrupted: Arc<AtomicBool>,
+    // The expressions evaluated so far, which `limits.max_steps` bounds.
+    pub steps: Rc<Cell<usize>>,
+    pub tracer: Option<Rc<RefCell<dyn Tracer>>>,
+    pub logger: Rc<RefCell<Logger>>,
+    // Where `input` reads lines from and writes its prompts to.
+    pub console: Rc<RefCell<Console>>,
+    // The generator of `math.random`, which `math.seed` reseeds for every environment
+    // sharing it.
+    pub random: Rc<RefCell<Random>>,
+    pub profiler: Option<Rc<RefCell<Profiler>>>,
+    pub call_depth: usize,
+    pub is_strict: bool,
+    // What the source code run in it may be parsed up to, and do once it runs.
+    pub limits: Limits,
+    // Where the source code run in it starts in the document it's embedded in, which the
+    // spans given back by `run_source_with` and `evaluate_with_spans` are shifted by.
+    pub source_offset: usize,
+    // How `display`, and so `print`, `string` and `format`, write values out.
+    pub printer: ValuePrinter,
+    // How `import` finds modules, and the ones it evaluated already.
+    pub modules: Rc<RefCell<Modules>>,
+    // The id of the module the code runs in, which `import` resolves specifiers from.
+    pub module: Option<Rc<str>>,
+    // The callbacks following the parse and the top-level statements of the run.
+    pub progress: Option<Rc<Progress>>,
+    // The scopes of the run, which are collected once no environment holds it anymore.
+    // The environments functions close over don't, see `closure`.
+    pub arena: Option<Rc<ScopeArena>>,
 }
 
 impl Environment {
     pub fn new(parent: Option<Self>) -> Self {
-        Self {
-            parent: Box::new(parent),
-            variables: get_builtin()
-                .iter()
-                .map(|(key, value)| (key.clone(), (value.clone(), true)))
-                .collect(),
+        let builtins = match &parent {
+            Some(parent) => parent.builtins.clone(),
+            None => BuiltinRegistry::global(),
+        };
+        Self::with_parent(parent, builtins)
+    }
+
+    // A root environment falling back to the given builtins rather than the standard ones.
+    pub fn with_builtins(builtins: Rc<BuiltinRegistry>) -> Self {
+        Self::with_parent(None, builtins)
+    }
+
+    // The environment a function body runs in: names are looked up from where the
+    // function was created, while the state of the evaluation comes from the caller.
+    pub fn for_call(closure: &Self, caller: &Self) -> Self {
+        let mut environment = Self::new(Some(closure.clone()));
+        environment.join_run(caller);
+        environment.interrupted = caller.interrupted.clone();
+        environment.steps = caller.steps.clone();
+        environment.tracer = caller.tracer.clone();
+        environment.logger = caller.logger.clone();
+        environment.console = caller.console.clone();
+        environment.random = caller.random.clone();
+        environment.profiler = caller.profiler.clone();
+        environment.call_depth = caller.call_depth + 1;
+        environment.is_strict = caller.is_strict;
+        environment.limits = caller.limits.clone();
+        environment
+    }
+
+    // The state of the evaluation is carried over from the parent, a root environment
+    // starts from the defaults.
+    fn with_parent(parent: Option<Self>, builtins: Rc<BuiltinRegistry>) -> Self {
+        let mut environment = Self {
+            parent: Box::new(None),
+            variables: Rc::new(RefCell::new(HashMap::new())),
+            annotations: Rc::new(RefCell::new(HashMap::new())),
+            builtins,
+            interrupted: Arc::new(AtomicBool::new(false)),
+            steps: Rc::new(Cell::new(0)),
+            tracer: None,
+            logger: Rc::new(RefCell::new(Logger::default())),
+            console: Rc::new(RefCell::new(Console::default())),
+            random: Rc::new(RefCell::new(Random::from_time())),
+            profiler: None,
+            call_depth: 0,
+            is_strict: false,
+            limits: Limits::default(),
+            source_offset: 0,
+            printer: ValuePrinter::default(),
+            modules: Rc::new(RefCell::new(Modules::default())),
+            module: None,
+            progress: None,
+            arena: None,
+        };
+        if let Some(parent) = &parent {
+            environment.interrupted = parent.interrupted.clone();
+            environment.steps = parent.steps.clone();
+            environment.tracer = parent.tracer.clone();
+            environment.logger = parent.logger.clone();
+            environment.console = parent.console.clone();
+            environment.random = parent.random.clone();
+            environment.profiler = parent.profiler.clone();
+            environment.call_depth = parent.call_depth;
+            environment.is_strict = parent.is_strict;
+            environment.limits = parent.limits.clone();
+            environment.source_offset = parent.source_offset;
+            environment.printer = parent.printer.clone();
+            environment.modules = parent.modules.clone();
+            environment.module = parent.module.clone();
+            environment.progress = parent.progress.clone();
+            environment.arena = parent.arena.clone();
+        } else {
+            environment.arena = Some(Rc::new(ScopeArena::default()));
+        }
+        environment.parent = Box::new(parent);
+        environment.register();
+        environment
+    }
+
+    // The environment a function created in this one closes over: the same scopes, but
+    // without a hold on the run, as the function is usually stored in one of them and
+    // would keep the run from being collected.
+    pub fn closure(&self) -> Self {
+        let mut closure = self.clone();
+        let mut environment = Some(&mut closure);
+        while let Some(current) = environment {
+            current.arena = None;
+            environment = current.parent.as_mut().as_mut();
+        }
+        closure
+    }
+
+    // Makes the scope of the environment part of the run of `other`, to be collected
+    // along with the run's, e.g. for a call or the evaluation of an imported module.
+    pub fn join_run(&mut self, other: &Self) {
+        self.arena = other.arena.clone();
+        self.register();
+    }
+
+    fn register(&self) {
+        if let Some(arena) = &self.arena {
+            arena.register(&self.variables, &self.modules);
+        }
+    }
+
+    // A copy of the environment to make others from, which the environment can go on
+    // being used and changed without affecting.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot(self.isolated())
+    }
+
+    // An environment starting with the variables of the snapshot, and its own evaluation
+    // state. The builtins are shared rather than built again, the scopes are copied, so
+    // what runs in it never changes the snapshot or the other environments made from it.
+    pub fn from_snapshot(snapshot: &Snapshot) -> Self {
+        snapshot.0.isolated()
+    }
+
+    // A copy with scopes of its own, the functions in them closing over the copies, and
+    // the state of the evaluation reset. The settings, the builtins and the resolver of
+    // modules are kept.
+    fn isolated(&self) -> Self {
+        let mut scopes = Scopes::default();
+        let mut environment = scopes.copy(self);
+        environment.interrupted = Arc::new(AtomicBool::new(false));
+        environment.steps = Rc::new(Cell::new(0));
+        environment.tracer = None;
+        environment.logger = Rc::new(RefCell::new(Logger::default()));
+        environment.random = Rc::new(RefCell::new(*self.random.borrow()));
+        environment.profiler = None;
+        environment.call_depth = 0;
+        let resolver = self.modules.borrow().resolver.clone();
+        environment.modules = Rc::new(RefCell::new(Modules::new(resolver)));
+        environment.progress = None;
+        let arena = ScopeArena::default();
+        for variables in scopes.0.values() {
+            arena.register(variables, &environment.modules);
         }
+        environment.arena = Some(Rc::new(arena));
+        environment
+    }
+
+    // The interruption flag is shared by an environment and all of its descendants, so
+    // raising it from anywhere (e.g. a Ctrl-C handler) stops the evaluation using them.
+    pub fn interrupt(&self) {
+        self.interrupted.store(true, Ordering::SeqCst);
+    }
+
+    pub fn clear_interrupt(&self) {
+        self.interrupted.store(false, Ordering::SeqCst);
     }
 
-    pub fn define(&mut self, identifier: String, value: Value, is_constant: bool) {
-        self.variables.insert(identifier, (value, is_constant));
+    pub fn is_interrupted(&self) -> bool {
+        self.interrupted.load(Ordering::Relaxed)
+    }
+
+    // Defining a variable drops the type it might have been annotated with before.
+    pub fn define(&mut self, identifier: Symbol, value: Value, is_constant: bool) {
+        self.annotations.borrow_mut().remove(&*identifier);
+        self.variables
+            .borrow_mut()
+            .insert(identifier, (value, is_constant));
+    }
+
+    pub fn annotate(&mut self, identifier: Symbol, type_name: String) {
+        self.annotations.borrow_mut().insert(identifier, type_name);
+    }
+
+    // Changes the value of the nearest definition of the variable, wherever it was
+    // defined, returning whether there was one to change.
+    pub fn assign(&self, identifier: &str, value: Value) -> bool {
+        if let Some((current, _)) = self.variables.borrow_mut().get_mut(identifier) {
+            *current = value;
+            return true;
+        }
+        match &*self.parent {
+            Some(environment) => environment.assign(identifier, value),
+            None => false,
+        }
+    }
+
+    // Takes the value of the nearest definition of the variable, leaving `none` in its
+    // place until it's assigned again.
+    pub fn take(&self, identifier: &str) -> Option<Value> {
+        if let Some((current, _)) = self.variables.borrow_mut().get_mut(identifier) {
+            return Some(std::mem::replace(current, Value::None));
+        }
+        match &*self.parent {
+            Some(environment) => environment.take(identifier),
+            None => None,
+        }
+    }
+
+    // The variables of this environment and of the ones around it, for `restore` to put
+    // back once an evaluation that assigned some of them failed.
+    pub fn save(&self) -> Vec<HashMap<Symbol, (Value, bool)>> {
+        let mut saved = vec![self.variables.borrow().clone()];
+        if let Some(parent) = &*self.parent {
+            saved.extend(parent.save());
+        }
+        saved
+    }
+
+    pub fn restore(&self, saved: Vec<HashMap<Symbol, (Value, bool)>>) {
+        let mut environment = Some(self);
+        for variables in saved {
+            match environment {
+                Some(current) => {
+                    *current.variables.borrow_mut() = variables;
+                    environment = current.parent.as_ref().as_ref();
+                }
+                None => break,
+            }
+        }
+    }
+
+    // The annotation of the nearest definition of the variable.
+    pub fn annotation(&self, identifier: &str) -> Option<String> {
+        if self.variables.borrow().contains_key(identifier) {
+            self.annotations.borrow().get(identifier).cloned()
+        } else {
+            match &*self.parent {
+                Some(environment) => environment.annotation(identifier),
+                None => None,
+            }
+        }
+    }
+
+    // Every variable in scope with its nearest definition, sorted by name.
+    pub fn bindings(&self) -> Vec<(String, Value, bool)> {
+        let mut bindings: HashMap<String, (Value, bool)> = HashMap::new();
+        let mut environment = Some(self);
+        while let Some(current) = environment {
+            for (identifier, definition) in current.variables.borrow().iter() {
+                bindings
+                    .entry(identifier.to_string())
+                    .or_insert_with(|| definition.clone());
+            }
+            environment = current.parent.as_ref().as_ref();
+        }
+        let mut bindings: Vec<(String, Value, bool)> = bindings
+            .into_iter()
+            .map(|(identifier, (value, is_constant))| (identifier, value, is_constant))
+            .collect();
+        bindings.sort_by(|a, b| a.0.cmp(&b.0));
+        bindings
+    }
+
+    // Every variable in scope with the value of its nearest definition, along with the
+    // builtins it doesn't shadow when `include_builtins` is set.
+    pub fn visible_bindings(&self, include_builtins: bool) -> HashMap<String, Value> {
+        let mut bindings: HashMap<String, Value> = self
+            .bindings()
+            .into_iter()
+            .map(|(identifier, value, _)| (identifier, value))
+            .collect();
+        if include_builtins {
+            for builtin in self.builtins.builtins() {
+                bindings
+                    .entry(builtin.name.clone())
+                    .or_insert_with(|| builtin.value.clone());
+            }
+        }
+        bindings
+    }
+
+    // Every name code running in this environment can use, builtins included, and
+    // whether it's a constant, as the globals to resolve the code with.
+    pub fn globals(&self) -> Vec<(String, bool)> {
+        let mut globals: Vec<(String, bool)> = self
+            .bindings()
+            .into_iter()
+            .map(|(identifier, _, is_constant)| (identifier, is_constant))
+            .collect();
+        for builtin in self.builtins.builtins() {
+            if !globals
+                .iter()
+                .any(|(identifier, _)| *identifier == builtin.name)
+            {
+                globals.push((builtin.name.clone(), true));
+            }
+        }
+        globals
     }
 
     pub fn is_constant(&self, identifier: &str) -> Option<bool> {
-        if let Some((_, is_constant)) = self.variables.get(identifier) {
+        if let Some((_, is_constant)) = self.variables.borrow().get(identifier) {
             Some(*is_constant)
         } else {
             match &*self.parent {
                 Some(environment) => environment.is_constant(identifier),
-                None => None,
+                None => self.builtins.get(identifier).map(|_| true),
             }
         }
     }
 
     pub fn access(&self, identifier: &str) -> Option<Value> {
-        if let Some((value, _)) = self.variables.get(identifier) {
+        if let Some((value, _)) = self.variables.borrow().get(identifier) {
             Some(value.clone())
         } else {
             match &*self.parent {
                 Some(environment) => environment.access(identifier),
-                None => None,
+                None => self
+                    .builtins
+                    .get(identifier)
+                    .map(|builtin| builtin.value.clone()),
+            }
+        }
+    }
+}
+
+// A prepared environment, like one the prelude of the host ran in, that environments
+// are made from with `Environment::from_snapshot` rather than preparing each again.
+#[derive(Debug, Clone)]
+pub struct Snapshot(Environment);
+
+// The scopes copied so far by the ones they're copies of, so a scope shared by several
+// environments and closures is copied once and stays shared between the copies.
+#[derive(Default)]
+struct Scopes(HashMap<*const RefCell<Variables>, Rc<RefCell<Variables>>>);
+
+type Variables = HashMap<Symbol, (Value, bool)>;
+
+impl Scopes {
+    fn copy(&mut self, environment: &Environment) -> Environment {
+        let mut copy = environment.clone();
+        copy.arena = None;
+        copy.parent = Box::new(
+            environment
+                .parent
+                .as_ref()
+                .as_ref()
+                .map(|parent| self.copy(parent)),
+        );
+        copy.annotations = Rc::new(RefCell::new(environment.annotations.borrow().clone()));
+        let key = Rc::as_ptr(&environment.variables);
+        if let Some(variables) = self.0.get(&key) {
+            copy.variables = variables.clone();
+            return copy;
+        }
+        // Registered before its values are copied, as the functions among them usually
+        // close over it.
+        copy.variables = Rc::new(RefCell::new(HashMap::new()));
+        self.0.insert(key, copy.variables.clone());
+        let variables: Variables = environment
+            .variables
+            .borrow()
+            .iter()
+            .map(|(name, (value, is_constant))| {
+                (name.clone(), (self.copy_value(value), *is_constant))
+            })
+            .collect();
+        *copy.variables.borrow_mut() = variables;
+        copy
+    }
+
+    fn copy_value(&mut self, value: &Value) -> Value {
+        match value {
+            Value::Function(function) => Value::Function(Function {
+                closure: Box::new(self.copy(&function.closure)),
+                ..function.clone()
+            }),
+            Value::Object(pairs) => Value::Object(
+                pairs
+                    .iter()
+                    .map(|(key, value)| (key.clone(), Box::new(self.copy_value(value))))
+                    .collect(),
+            ),
+            Value::Array(values) => {
+                Value::Array(values.iter().map(|value| self.copy_value(value)).collect())
+            }
+            Value::Tuple(values) => {
+                Value::Tuple(values.iter().map(|value| self.copy_value(value)).collect())
+            }
+            value => value.clone(),
+        }
+    }
+}
+
+// The scopes made in a run, freed once the run is over even when functions close over
+// them. A function keeps the scopes it was created in alive, so one stored in a scope
+// it closes over, e.g. with `let f = fn() { ... }`, makes a cycle counting references
+// never frees. Once no environment holds the arena anymore, the scopes nothing outside
+// of them can reach are emptied, which breaks the cycles. The ones a function kept by
+// the host still reaches then, e.g. one given back by the run, are left as they are
+// for the function to go on working, and aren't collected later.
+#[derive(Debug, Default)]
+pub struct ScopeArena {
+    scopes: RefCell<Vec<Weak<RefCell<Variables>>>>,
+    // The modules imported in the run, whose values close over the scopes of the
+    // modules, and whose functions hold the modules in turn. They're those of the
+    // environments making the scopes, as a host may set its own after making the root.
+    modules: RefCell<Vec<Weak<RefCell<Modules>>>>,
+}
+
+impl ScopeArena {
+    fn register(&self, variables: &Rc<RefCell<Variables>>, modules: &Rc<RefCell<Modules>>) {
+        let mut scopes = self.scopes.borrow_mut();
+        // The scopes dropped already are left out whenever there's no room for another,
+        // so a long run doesn't keep a handle on every scope it made.
+        if scopes.len() == scopes.capacity() {
+            scopes.retain(|scope| scope.strong_count() > 0);
+        }
+        scopes.push(Rc::downgrade(variables));
+        let mut known = self.modules.borrow_mut();
+        if !known
+            .iter()
+            .any(|known| known.as_ptr() == Rc::as_ptr(modules))
+        {
+            known.push(Rc::downgrade(modules));
+        }
+    }
+}
+
+impl Drop for ScopeArena {
+    fn drop(&mut self) {
+        for modules in self.modules.take().iter().filter_map(Weak::upgrade) {
+            let cache = modules
+                .try_borrow_mut()
+                .map(|mut modules| modules.take_cache());
+            drop(cache);
+        }
+
+        let mut indices = HashMap::new();
+        let mut scopes = vec![];
+        for scope in self.scopes.take().iter().filter_map(Weak::upgrade) {
+            if let Entry::Vacant(entry) = indices.entry(Rc::as_ptr(&scope)) {
+                entry.insert(scopes.len());
+                scopes.push(scope);
+            }
+        }
+        // The references to each scope held by the functions in the others.
+        let mut references = vec![0; scopes.len()];
+        for scope in &scopes {
+            if let Ok(variables) = scope.try_borrow() {
+                for (value, _) in variables.values() {
+                    closed_over(value, &mut |variables| {
+                        if let Some(&index) = indices.get(&Rc::as_ptr(variables)) {
+                            references[index] += 1;
+                        }
+                    });
+                }
+            }
+        }
+        // A scope referenced from elsewhere too, by an environment or a value outside of
+        // the run, is reachable, and so is every scope its functions close over. The
+        // handle taken on each scope above is left out.
+        let mut reachable: Vec<bool> = scopes
+            .iter()
+            .zip(&references)
+            .map(|(scope, references)| Rc::strong_count(scope) - 1 > *references)
+            .collect();
+        let mut pending: Vec<usize> = (0..scopes.len()).filter(|&i| reachable[i]).collect();
+        while let Some(index) = pending.pop() {
+            if let Ok(variables) = scopes[index].try_borrow() {
+                for (value, _) in variables.values() {
+                    closed_over(value, &mut |variables| {
+                        if let Some(&index) = indices.get(&Rc::as_ptr(variables)) {
+                            if !reachable[index] {
+                                reachable[index] = true;
+                                pending.push(index);
+                            }
+                        }
+                    });
+                }
+            }
+        }
+        // Emptying the others drops the functions in them, and with them the cycles. The
+        // variables are dropped once none of the scopes is borrowed anymore.
+        let unreachable: Vec<Variables> = scopes
+            .iter()
+            .zip(&reachable)
+            .filter(|(_, reachable)| !**reachable)
+            .filter_map(|(scope, _)| {
+                scope
+                    .try_borrow_mut()
+                    .ok()
+                    .map(|mut scope| take(&mut *scope))
+            })
+            .collect();
+        drop(unreachable);
+    }
+}
+
+// Calls `visit` with every scope the functions in the value close over.
+fn closed_over(value: &Value, visit: &mut dyn FnMut(&Rc<RefCell<Variables>>)) {
+    match value {
+        Value::Function(function) => {
+            let mut environment = Some(&*function.closure);
+            while let Some(current) = environment {
+                visit(&current.variables);
+                environment = current.parent.as_ref().as_ref();
+            }
+        }
+        Value::Object(pairs) => {
+            for value in pairs.values() {
+                closed_over(value, visit);
+            }
+        }
+        Value::Array(values) | Value::Tuple(values) => {
+            for value in values {
+                closed_over(value, visit);
             }
         }
+        _ => {}
     }
 }
@@ -1,12 +1,23 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, rc::Rc, time::Instant};
 
 use crate::frontend::{
-    ast::{ConstStatement, Expression, LetStatement, Program, Statement},
+    ast::{
+        AccessExpression, AssignmentExpression, BinaryExpression, ConstStatement,
+        DestructuringStatement, Expression, ForStatement, FunctionExpression, FunctionStatement,
+        IndexExpression, LetStatement, NumericValue, Program, Statement, WhileStatement,
+    },
+    progress::Control,
     token::TokenKind,
-    utils::Error,
+    utils::{Error, TextSpan},
 };
 
-use super::{environment::Environment, types::Value};
+use super::{
+    builder::StringBuilder,
+    builtin::{properties, property},
+    environment::Environment,
+    operators::operations,
+    types::{Function, SetElement, Value, ValuePrinter},
+};
 
 pub fn evaluate(
     program: Program,
@@ -15,61 +26,607 @@ pub fn evaluate(
     let mut value = Value::None;
     let mut environment = Environment::new(parent);
 
-    for statement in program {
+    for (index, statement) in program.iter().enumerate() {
+        report_statement(index, program.len(), statement, &environment)?;
         value = evaluate_statement(statement, &mut environment)?;
     }
     Ok((value, environment))
 }
 
-fn evaluate_statement(statement: Statement, environment: &mut Environment) -> Result<Value, Error> {
-    match statement {
+// Like `evaluate`, but gives back the value of every top-level statement along with its
+// span rather than only the last one.
+pub fn evaluate_statements(
+    program: Program,
+    parent: Option<Environment>,
+) -> Result<(Vec<(TextSpan, Value)>, Environment), Error> {
+    let mut results = vec![];
+    let mut environment = Environment::new(parent);
+
+    for (index, statement) in program.iter().enumerate() {
+        report_statement(index, program.len(), statement, &environment)?;
+        let value = evaluate_statement(statement, &mut environment)?;
+        results.push((statement.text_span(), value));
+    }
+    Ok((results, environment))
+}
+
+// Tells the host's `on_statement` callback a top-level statement is about to run, the
+// evaluation being interrupted when it cancels.
+fn report_statement(
+    index: usize,
+    total: usize,
+    statement: &Statement,
+    environment: &Environment,
+) -> Result<(), Error> {
+    let Some(on_statement) = environment
+        .progress
+        .as_ref()
+        .and_then(|progress| progress.on_statement.as_ref())
+    else {
+        return Ok(());
+    };
+    let text_span = statement.text_span();
+    if on_statement(index, total, &text_span) == Control::Cancel {
+        environment.interrupt();
+        return Err(Error::new(
+            "Evaluation was interrupted".to_string(),
+            text_span,
+        ));
+    }
+    Ok(())
+}
+
+// The value as shown to users by the environment's printer, objects with a `__display`
+// function being shown as it returns.
+pub fn display(
+    value: &Value,
+    text_span: TextSpan,
+    environment: &mut Environment,
+) -> Result<String, Error> {
+    let printer = environment.printer.clone();
+    display_with(value, text_span, environment, &printer)
+}
+
+// Like `display`, with a printer of its own.
+pub fn display_with(
+    value: &Value,
+    text_span: TextSpan,
+    environment: &mut Environment,
+    printer: &ValuePrinter,
+) -> Result<String, Error> {
+    printer.print_with(value, &mut |object| {
+        display_hook(object, text_span.clone(), environment)
+    })
+}
+
+fn display_hook(
+    object: &Value,
+    text_span: TextSpan,
+    environment: &mut Environment,
+) -> Result<Option<String>, Error> {
+    let Value::Object(entries) = object else {
+        return Ok(None);
+    };
+    let Some(Value::Function(function)) = entries.get("__display").map(|hook| &**hook) else {
+        return Ok(None);
+    };
+    match call_function(
+        function,
+        vec![object.clone()],
+        text_span.clone(),
+        environment,
+    )? {
+        Value::String(s) => Ok(Some(Rc::unwrap_or_clone(s))),
+        other => Err(Error::new(
+            format!(
+                "Can't display the object as '__display' returned '{}' instead of a string",
+                other.short_repr()
+            ),
+            text_span,
+        )),
+    }
+}
+
+fn evaluate_statement(
+    statement: &Statement,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    let tracer = environment.tracer.clone();
+    if let Some(tracer) = &tracer {
+        tracer
+            .borrow_mut()
+            .before_statement(statement, environment.call_depth, environment)?;
+    }
+    let value = match statement {
         Statement::Let(l) => evaluate_let_statement(l, environment),
         Statement::Const(c) => evaluate_const_statement(c, environment),
-        Statement::Expression(e) => evaluate_expression(&e, environment),
+        Statement::Destructuring(d) => evaluate_destructuring_statement(d, environment),
+        Statement::Function(f) => evaluate_function_statement(f, environment),
+        Statement::While(w) => evaluate_while_statement(w, environment),
+        Statement::For(f) => evaluate_for_statement(f, environment),
+        Statement::Expression(e) => evaluate_expression(e, environment),
+    }?;
+    if let Some(tracer) = &tracer {
+        tracer
+            .borrow_mut()
+            .after_statement(statement, &value, environment.call_depth);
+    }
+    Ok(value)
+}
+
+// Evaluates a statement whose value is dropped, like the ones of a loop body, which
+// lets an assignment appending to a string skip copying it for its value.
+fn evaluate_statement_for_effect(
+    statement: &Statement,
+    environment: &mut Environment,
+) -> Result<(), Error> {
+    if let (Statement::Expression(Expression::Assignment(a)), None) =
+        (statement, &environment.tracer)
+    {
+        if let Some(result) = append_in_place(a, environment) {
+            return result;
+        }
+    }
+    evaluate_statement(statement, environment).map(|_| ())
+}
+
+// `s = s + a + b` appends the pieces to the string `s` holds in place rather than copying
+// it for every `+`, so building a string in a loop takes linear rather than quadratic
+// time. It only applies when the pieces can't see `s` while its string is taken out,
+// which rules out calls and operators objects could hook into, and gives the same
+// value, errors and step count as evaluating the assignment. `None` is for the
+// assignments it doesn't apply to.
+fn append_in_place(
+    assignment: &AssignmentExpression,
+    environment: &mut Environment,
+) -> Option<Result<(), Error>> {
+    let Expression::Identifier(target) = &*assignment.target else {
+        return None;
+    };
+    let name = &target.identifier.lexeme;
+    // The `+` of each piece, the last one first.
+    let mut operations = vec![];
+    let mut left: &Expression = &assignment.expression;
+    while let Expression::Binary(b) = left {
+        if b.operator.kind != TokenKind::Plus || !is_independent_of(&b.right, name) {
+            return None;
+        }
+        operations.push(b);
+        left = &b.left;
+    }
+    match left {
+        Expression::Identifier(i) if i.identifier.lexeme == *name && !operations.is_empty() => {}
+        _ => return None,
+    }
+    if environment.profiler.is_some()
+        || environment.is_constant(name) != Some(false)
+        || environment.annotation(name).is_some()
+    {
+        return None;
+    }
+    // The assignment, its operators and `s` are evaluated before any piece.
+    let steps = environment.steps.get() + operations.len() + 2;
+    if steps > environment.limits.max_steps {
+        return None;
+    }
+    let text = match environment.take(name)? {
+        Value::String(text) => text,
+        value => {
+            environment.assign(name, value);
+            return None;
+        }
+    };
+    environment.steps.set(steps);
+
+    let length = text.len();
+    // The variable held the only reference to the string, so it's grown without a copy.
+    let mut builder = StringBuilder::new(Rc::unwrap_or_clone(text), &environment.limits);
+    let result = append_pieces(&mut builder, &operations, environment);
+    let mut text = builder.finish();
+    // The variable keeps its string when the assignment fails.
+    if result.is_err() {
+        text.truncate(length);
+    }
+    environment.assign(name, Value::String(text.into()));
+    Some(result)
+}
+
+fn append_pieces(
+    builder: &mut StringBuilder,
+    operations: &[&BinaryExpression],
+    environment: &mut Environment,
+) -> Result<(), Error> {
+    for b in operations.iter().rev() {
+        match evaluate_expression(&b.right, environment)? {
+            Value::String(piece) => builder
+                .push(&piece)
+                .map_err(|message| Error::new(message, b.text_span()))?,
+            piece => {
+                let left = Value::String(builder.as_str().to_string().into());
+                return Err(binary_operand_error(b, &left, &piece));
+            }
+        }
+    }
+    Ok(())
+}
+
+// Whether evaluating the expression can neither read nor change the variable: it doesn't
+// name it and can't run any function that could.
+fn is_independent_of(expression: &Expression, name: &str) -> bool {
+    match expression {
+        Expression::None(_)
+        | Expression::Boolean(_)
+        | Expression::Numeric(_)
+        | Expression::String(_) => true,
+        Expression::Identifier(i) => &*i.identifier.lexeme != name,
+        Expression::Access(a) => is_independent_of(&a.object, name),
+        Expression::Index(i) => {
+            is_independent_of(&i.object, name) && is_independent_of(&i.index, name)
+        }
+        Expression::Unary(u) => is_independent_of(&u.right, name),
+        Expression::Conditional(c) => {
+            is_independent_of(&c.condition, name)
+                && is_independent_of(&c.consequent, name)
+                && is_independent_of(&c.alternative, name)
+        }
+        Expression::Tuple(t) => t.elements.iter().all(|e| is_independent_of(e, name)),
+        Expression::Array(a) => a.elements.iter().all(|e| is_independent_of(e, name)),
+        Expression::Object(o) => o.pairs.iter().all(|p| is_independent_of(&p.value, name)),
+        _ => false,
     }
 }
 
 fn evaluate_let_statement(
-    statement: LetStatement,
+    statement: &LetStatement,
     environment: &mut Environment,
 ) -> Result<Value, Error> {
-    let value = evaluate_expression(&statement.expression, environment)?;
-    environment.define(statement.identifier.lexeme, value, false);
+    let identifier = statement.identifier.lexeme.clone();
+    let value = evaluate_initializer(
+        &statement.expression,
+        &identifier,
+        statement.text_span(),
+        environment,
+    )?;
+    if let Some(annotation) = &statement.annotation {
+        check_annotation(
+            "bind",
+            &identifier,
+            &annotation.lexeme,
+            &value,
+            &annotation.text_span,
+        )?;
+    }
+    environment.define(identifier.clone(), value, false);
+    if let Some(annotation) = &statement.annotation {
+        environment.annotate(identifier, annotation.lexeme.to_string());
+    }
     Ok(Value::None)
 }
 
 fn evaluate_const_statement(
-    statement: ConstStatement,
+    statement: &ConstStatement,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    let identifier = statement.identifier.lexeme.clone();
+    let value = evaluate_initializer(
+        &statement.expression,
+        &identifier,
+        statement.text_span(),
+        environment,
+    )?;
+    if let Some(annotation) = &statement.annotation {
+        check_annotation(
+            "bind",
+            &identifier,
+            &annotation.lexeme,
+            &value,
+            &annotation.text_span,
+        )?;
+    }
+    environment.define(identifier.clone(), value, true);
+    if let Some(annotation) = &statement.annotation {
+        environment.annotate(identifier, annotation.lexeme.to_string());
+    }
+    Ok(Value::None)
+}
+
+// Errors from the initializer of a declaration tell which variables it was initializing,
+// and where the declaration is when they don't point at a larger piece of code already.
+fn evaluate_initializer(
+    expression: &Expression,
+    names: &str,
+    text_span: TextSpan,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    evaluate_expression(expression, environment).map_err(|error| {
+        let error = error.with_note(format!("while initializing '{names}'"));
+        match error.context {
+            Some(_) => error,
+            None => error.with_context(text_span),
+        }
+    })
+}
+
+// `action` names what's being done with the value in the error, e.g. `bind` or `assign`.
+fn check_annotation(
+    action: &str,
+    identifier: &str,
+    annotation: &str,
+    value: &Value,
+    text_span: &TextSpan,
+) -> Result<(), Error> {
+    if value.type_name() == annotation {
+        Ok(())
+    } else {
+        Err(Error::new(
+            format!(
+                "Can't {action} '{}' to '{identifier}' as it's a {} instead of a {annotation}",
+                value.short_repr(),
+                value.type_name()
+            ),
+            text_span.clone(),
+        ))
+    }
+}
+
+fn evaluate_function_statement(
+    statement: &FunctionStatement,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    let function = evaluate_function_expression(&statement.function, environment);
+    environment.define(statement.identifier.lexeme.clone(), function, false);
+    Ok(Value::None)
+}
+
+// The body runs in an environment of its own each time around, so its `let`s don't outlive
+// the iteration while its assignments still reach the variables outside. Evaluating the
+// condition checks for interruptions, which is what stops an endless loop on Ctrl-C.
+fn evaluate_while_statement(
+    statement: &WhileStatement,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    loop {
+        match evaluate_expression(&statement.condition, environment)? {
+            Value::Boolean(true) => {}
+            Value::Boolean(false) => return Ok(Value::None),
+            value => {
+                return Err(Error::new(
+                    format!(
+                        "Can't loop while '{}' as it's a {} instead of a boolean",
+                        value.short_repr(),
+                        value.type_name()
+                    ),
+                    statement.condition.text_span(),
+                ))
+            }
+        }
+        let mut body = Environment::new(Some(environment.clone()));
+        for statement in &statement.body {
+            evaluate_statement_for_effect(statement, &mut body)?;
+        }
+    }
+}
+
+// Goes over the elements of an array, a tuple or a set, the chars of a string or the keys of an
+// object in their sorted order, binding each to the loop variable in an environment of
+// its own. What's iterated over is taken once before the first iteration, so changing it
+// from the body doesn't change the iterations.
+fn evaluate_for_statement(
+    statement: &ForStatement,
     environment: &mut Environment,
 ) -> Result<Value, Error> {
-    let value = evaluate_expression(&statement.expression, environment)?;
-    environment.define(statement.identifier.lexeme, value, true);
+    let items: Vec<Value> = match evaluate_expression(&statement.iterable, environment)? {
+        Value::Array(elements) | Value::Tuple(elements) => elements,
+        Value::Set(elements) => elements.iter().map(SetElement::to_value).collect(),
+        Value::String(string) => string
+            .chars()
+            .map(|char| Value::String(char.to_string().into()))
+            .collect(),
+        // The keys are taken when the loop starts, so the body adding or removing keys of
+        // the object changes the object but not the keys visited.
+        Value::Object(object) => {
+            let mut keys: Vec<String> = object.into_keys().collect();
+            keys.sort();
+            keys.into_iter()
+                .map(|key| Value::String(key.into()))
+                .collect()
+        }
+        value => {
+            return Err(Error::new(
+                format!(
+                    "Can't iterate over '{}' as it's a {}",
+                    value.short_repr(),
+                    value.type_name()
+                ),
+                statement.iterable.text_span(),
+            ))
+        }
+    };
+    for item in items {
+        if environment.is_interrupted() {
+            return Err(Error::new(
+                "Evaluation was interrupted".to_string(),
+                statement.iterable.text_span(),
+            ));
+        }
+        let mut body = Environment::new(Some(environment.clone()));
+        body.define(statement.identifier.lexeme.clone(), item, false);
+        for statement in &statement.body {
+            evaluate_statement_for_effect(statement, &mut body)?;
+        }
+    }
     Ok(Value::None)
 }
 
+fn evaluate_destructuring_statement(
+    statement: &DestructuringStatement,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    let names: Vec<&str> = statement
+        .identifiers
+        .iter()
+        .map(|identifier| &*identifier.lexeme)
+        .collect();
+    let value = evaluate_initializer(
+        &statement.expression,
+        &names.join(", "),
+        statement.text_span(),
+        environment,
+    )?;
+    match value {
+        Value::Tuple(elements) if elements.len() == statement.identifiers.len() => {
+            let is_constant = statement.keyword.kind == TokenKind::Const;
+            for (identifier, element) in statement.identifiers.iter().zip(elements) {
+                environment.define(identifier.lexeme.clone(), element, is_constant);
+            }
+            Ok(Value::None)
+        }
+        value => Err(Error::new(
+            format!(
+                "Can't destructure '{}' into {} variable(s) as it's not a tuple of the same size",
+                value.short_repr(),
+                statement.identifiers.len()
+            ),
+            statement.expression.text_span(),
+        )),
+    }
+}
+
 fn evaluate_expression(
     expression: &Expression,
     environment: &mut Environment,
+) -> Result<Value, Error> {
+    count_step(expression, environment)?;
+    match environment.profiler.clone() {
+        None => evaluate_expression_kind(expression, environment),
+        Some(profiler) => {
+            let start = Instant::now();
+            let result = evaluate_expression_kind(expression, environment);
+            profiler
+                .borrow_mut()
+                .record(expression.text_span(), start.elapsed());
+            result
+        }
+    }
+}
+
+// Counts `expression` as one more step, unless the steps or the run are over.
+fn count_step(expression: &Expression, environment: &Environment) -> Result<(), Error> {
+    if environment.is_interrupted() {
+        return Err(Error::new(
+            "Evaluation was interrupted".to_string(),
+            expression.text_span(),
+        ));
+    }
+    let steps = environment.steps.get() + 1;
+    if steps > environment.limits.max_steps {
+        return Err(Error::new(
+            format!(
+                "Can't evaluate more than the limit of {} expressions",
+                environment.limits.max_steps
+            ),
+            expression.text_span(),
+        ));
+    }
+    environment.steps.set(steps);
+    Ok(())
+}
+
+// Evaluation goes left to right and top to bottom: operands, call arguments, tuple
+// elements and object values are evaluated in the order they're written, so the side
+// effects of assignments inside them happen in that order too. Later changes, like
+// folding constants, must keep to it.
+fn evaluate_expression_kind(
+    expression: &Expression,
+    environment: &mut Environment,
 ) -> Result<Value, Error> {
     match expression {
         Expression::None(_) => Ok(Value::None),
         Expression::Boolean(b) => Ok(Value::Boolean(b.value)),
-        Expression::Numeric(n) => Ok(Value::Number(n.value)),
+        Expression::Numeric(n) => Ok(match n.value {
+            NumericValue::Integer(i) => Value::Integer(i),
+            NumericValue::Float(f) => Value::Number(f),
+        }),
         Expression::String(s) => Ok(Value::String(s.value.clone())),
         Expression::Object(o) => {
             let mut pairs = vec![];
             for pair in &o.pairs {
                 pairs.push((
-                    pair.key.lexeme.clone(),
+                    pair.key.lexeme.to_string(),
                     Box::new(evaluate_expression(&pair.value, environment)?),
                 ));
             }
             Ok(Value::Object(HashMap::from_iter(pairs)))
         }
+        Expression::Tuple(t) => {
+            let mut elements = vec![];
+            for element in &t.elements {
+                elements.push(evaluate_expression(element, environment)?);
+            }
+            Ok(Value::Tuple(elements))
+        }
+        Expression::Array(a) => {
+            let mut elements = vec![];
+            for element in &a.elements {
+                elements.push(evaluate_expression(element, environment)?);
+            }
+            Ok(Value::Array(elements))
+        }
+        Expression::Index(i) => {
+            let value = evaluate_expression(&i.object, environment)?;
+            let index = evaluate_expression(&i.index, environment)?;
+            if matches!(value, Value::None) {
+                return Err(none_receiver_error(
+                    &i.object,
+                    format!(
+                        "Can't index the value with '{}' as it's none",
+                        index.short_repr()
+                    ),
+                    i.subscript_span(),
+                    i.text_span(),
+                ));
+            }
+            evaluate_index(i, &value, &index)
+        }
         Expression::Access(a) => {
             let value = evaluate_expression(&a.object, environment)?;
-            if let Value::Object(object) = value {
-                if let Some(value) = object.get(&a.property.lexeme) {
+            let is_missing = match &value {
+                Value::None => true,
+                Value::Object(object) => !object.contains_key(&*a.property.lexeme),
+                _ => false,
+            };
+            if a.is_optional() && is_missing {
+                return Ok(Value::None);
+            }
+            if matches!(value, Value::None) {
+                let what = match a.property.kind {
+                    TokenKind::Number => "element",
+                    _ => "property",
+                };
+                return Err(none_receiver_error(
+                    &a.object,
+                    format!(
+                        "Can't access the {what} '{}' as the value is none",
+                        a.property.lexeme
+                    ),
+                    a.property.text_span.clone(),
+                    a.text_span(),
+                ));
+            }
+            if let (TokenKind::Number, Value::Tuple(elements)) = (&a.property.kind, &value) {
+                match a.property.lexeme.parse::<usize>() {
+                    Ok(index) if index < elements.len() => Ok(elements[index].clone()),
+                    _ => Err(Error::new(
+                        format!(
+                            "Can't access the element '{}' as the tuple has {} element(s)",
+                            a.property.lexeme,
+                            elements.len()
+                        ),
+                        a.text_span(),
+                    )),
+                }
+            } else if let Value::Object(object) = value {
+                if let Some(value) = object.get(&*a.property.lexeme) {
                     Ok(*value.clone())
                 } else {
                     Err(Error::new(
@@ -77,17 +634,34 @@ fn evaluate_expression(
                             "Can't access the property '{}' as it's not defined",
                             a.property.lexeme
                         ),
-                        a.text_span(),
-                    ))
+                        a.property.text_span.clone(),
+                    )
+                    .with_context(a.text_span()))
                 }
+            } else if let Some(result) =
+                property(&value, &a.property.lexeme, a.text_span(), environment)
+            {
+                result
             } else {
-                Err(Error::new(
+                let available: Vec<&str> = properties(&value)
+                    .into_iter()
+                    .map(|(name, _)| name)
+                    .collect();
+                let message = if available.is_empty() {
                     format!(
-                        "Can't access property of '{}' as it's not accessible",
-                        value
-                    ),
-                    a.text_span(),
-                ))
+                        "Can't access the property '{}' of '{}' as it has no properties",
+                        a.property.lexeme,
+                        value.short_repr()
+                    )
+                } else {
+                    format!(
+                        "Can't access the property '{}' of '{}', the available ones are {}",
+                        a.property.lexeme,
+                        value.short_repr(),
+                        available.join(", ")
+                    )
+                };
+                Err(Error::new(message, a.property.text_span.clone()).with_context(a.text_span()))
             }
         }
         Expression::Identifier(i) => {
@@ -108,326 +682,3129 @@ fn evaluate_expression(
             match (u.operator.kind.clone(), right) {
                 (TokenKind::Bang, Value::Boolean(a)) => Ok(Value::Boolean(!a)),
 
-                (TokenKind::Plus, Value::Number(a)) => Ok(Value::Number(a)),
+                (TokenKind::Plus, value @ (Value::Integer(_) | Value::Number(_))) => Ok(value),
                 (TokenKind::Minus, Value::Number(a)) => Ok(Value::Number(-a)),
+                (TokenKind::Minus, Value::Integer(a)) => {
+                    a.checked_neg().map(Value::Integer).ok_or_else(|| {
+                        Error::new(
+                            format!("Can't negate '{a}' as it's out of the range of integers"),
+                            u.text_span(),
+                        )
+                    })
+                }
 
-                (operator, right) => Err(Error::new(
-                    format!("Can't use '{operator}' with '{right}'"),
-                    u.text_span(),
-                )),
+                // The operand is what's wrong, the whole expression shows the operator.
+                (operator, right) => {
+                    let expected = match operator {
+                        TokenKind::Bang => "boolean",
+                        _ => "number",
+                    };
+                    Err(Error::new(
+                        format!(
+                            "Can't use '{operator}' with '{}' as '{operator}' expects a {expected}, got a {}",
+                            right.short_repr(),
+                            right.type_name()
+                        ),
+                        u.right.text_span(),
+                    )
+                    .with_context(u.text_span()))
+                }
             }
         }
         Expression::Binary(b) => {
-            let left = evaluate_expression(&b.left, environment)?;
-            let right = evaluate_expression(&b.right, environment)?;
-            match (b.operator.kind.clone(), left, right) {
-                (TokenKind::Plus, Value::String(left), Value::String(right)) => {
-                    Ok(Value::String(left + &right))
+            // A chain like `1 + 2 + 3` is `(1 + 2) + 3`, so its operators are applied in
+            // a loop, the innermost first, rather than by recursing once per operator.
+            // Each one is counted and profiled as if it were evaluated on its own.
+            let mut chain = vec![b];
+            let mut leftmost = &*b.left;
+            while let Expression::Binary(inner) = leftmost {
+                count_step(leftmost, environment)?;
+                chain.push(inner);
+                leftmost = &inner.left;
+            }
+            let start = Instant::now();
+            let mut value = evaluate_expression(leftmost, environment)?;
+            for (index, b) in chain.iter().enumerate().rev() {
+                value = evaluate_binary(b, value, environment)?;
+                if let Some(profiler) = environment.profiler.as_ref().filter(|_| index > 0) {
+                    profiler.borrow_mut().record(b.text_span(), start.elapsed());
                 }
+            }
+            Ok(value)
+        }
+        // Only the chosen branch is evaluated.
+        Expression::Conditional(c) => match evaluate_expression(&c.condition, environment)? {
+            Value::Boolean(true) => evaluate_expression(&c.consequent, environment),
+            Value::Boolean(false) => evaluate_expression(&c.alternative, environment),
+            value => Err(Error::new(
+                format!(
+                    "Can't use '{}' as a condition as it's a {} instead of a boolean",
+                    value.short_repr(),
+                    value.type_name()
+                ),
+                c.condition.text_span(),
+            )
+            .with_context(c.text_span())),
+        },
+        Expression::Assignment(a) => evaluate_assignment(a, environment),
+        Expression::Function(f) => Ok(evaluate_function_expression(f, environment)),
+        // The statements run in an environment of their own, so what they declare is gone
+        // once the block is done.
+        Expression::Block(b) => {
+            let mut block = Environment::new(Some(environment.clone()));
+            let mut value = Value::None;
+            for statement in &b.statements {
+                value = evaluate_statement(statement, &mut block)?;
+            }
+            Ok(value)
+        }
+        Expression::Call(c) => {
+            let callee = evaluate_expression(&c.callee, environment)?;
+            let mut arguments = vec![];
+            for argument in &c.arguments {
+                arguments.push(evaluate_expression(argument, environment)?);
+            }
+            call_value(&callee, arguments, c.text_span(), environment)
+        }
+    }
+}
 
-                (TokenKind::Plus, Value::Number(left), Value::Number(right)) => {
-                    Ok(Value::Number(left + right))
-                }
-                (TokenKind::Minus, Value::Number(left), Value::Number(right)) => {
-                    Ok(Value::Number(left - right))
-                }
-                (TokenKind::Asterisk, Value::Number(left), Value::Number(right)) => {
-                    Ok(Value::Number(left * right))
-                }
-                (TokenKind::Slash, Value::Number(left), Value::Number(right)) => {
-                    if right == 0. {
-                        Err(Error::new("Can't divide by 0".to_string(), b.text_span()))
-                    } else {
-                        Ok(Value::Number(left / right))
-                    }
-                }
+// Accessing or indexing none is most often a lookup further left having given none,
+// which the note points back to, naming the property when the value came from one.
+fn none_receiver_error(
+    object: &Expression,
+    message: String,
+    text_span: TextSpan,
+    context: TextSpan,
+) -> Error {
+    let note = match object {
+        Expression::Access(inner) => format!(
+            "the property '{}' is none, did an earlier lookup fail?",
+            inner.property.lexeme
+        ),
+        _ => "the value is none, did an earlier lookup fail?".to_string(),
+    };
+    Error::new(message, text_span)
+        .with_context(context)
+        .with_note(note)
+}
 
-                (TokenKind::Greater, Value::Number(left), Value::Number(right)) => {
-                    Ok(Value::Boolean(left > right))
-                }
-                (TokenKind::Lesser, Value::Number(left), Value::Number(right)) => {
-                    Ok(Value::Boolean(left < right))
-                }
-                (TokenKind::GreaterOrEqual, Value::Number(left), Value::Number(right)) => {
-                    Ok(Value::Boolean(left >= right))
-                }
-                (TokenKind::LesserOrEqual, Value::Number(left), Value::Number(right)) => {
-                    Ok(Value::Boolean(left <= right))
-                }
+// Arrays and tuples are indexed by the position of an element, objects by a key.
+fn evaluate_index(index: &IndexExpression, value: &Value, key: &Value) -> Result<Value, Error> {
+    match (value, key) {
+        (
+            Value::Array(elements) | Value::Tuple(elements),
+            key @ (Value::Integer(_) | Value::Number(_)),
+        ) => {
+            let position = element_position(index, value.type_name(), elements.len(), key)?;
+            Ok(elements[position].clone())
+        }
+        (Value::Object(object), Value::String(key)) => match object.get(key.as_str()) {
+            Some(value) => Ok(*value.clone()),
+            None => Err(undefined_key(index, key)),
+        },
+        (Value::Array(_) | Value::Tuple(_) | Value::Object(_), _) => {
+            Err(key_type_error(index, value, key))
+        }
+        _ => Err(Error::new(
+            format!(
+                "Can't index '{}' as it's a {}",
+                value.short_repr(),
+                value.type_name()
+            ),
+            index.text_span(),
+        )),
+    }
+}
+
+fn element_position(
+    index: &IndexExpression,
+    type_name: &str,
+    length: usize,
+    key: &Value,
+) -> Result<usize, Error> {
+    let message = match key.as_integer().map(usize::try_from) {
+        Some(Ok(position)) if position < length => return Ok(position),
+        Some(Ok(_)) => format!(
+            "Can't access the element '{}' as the {type_name} has {length} element(s)",
+            key.short_repr()
+        ),
+        Some(Err(_)) => format!(
+            "Can't access the element '{}' as it's negative",
+            key.short_repr()
+        ),
+        _ => format!(
+            "Can't index the {type_name} with '{}' as it's not a whole number",
+            key.short_repr()
+        ),
+    };
+    Err(Error::new(message, index.subscript_span()).with_context(index.text_span()))
+}
 
-                (TokenKind::BangEqual, left, right) => Ok(Value::Boolean(left != right)),
-                (TokenKind::DoubleEqual, left, right) => Ok(Value::Boolean(left == right)),
+fn undefined_key(index: &IndexExpression, key: &str) -> Error {
+    Error::new(
+        format!("Can't access the property '{key}' as it's not defined"),
+        index.subscript_span(),
+    )
+    .with_context(index.text_span())
+}
 
-                (TokenKind::DoubleAmpersand, Value::Boolean(left), Value::Boolean(right)) => {
-                    Ok(Value::Boolean(left && right))
-                }
-                (TokenKind::DoublePipe, Value::Boolean(left), Value::Boolean(right)) => {
-                    Ok(Value::Boolean(left || right))
+fn key_type_error(index: &IndexExpression, value: &Value, key: &Value) -> Error {
+    Error::new(
+        format!(
+            "Can't index the {} with '{}' as it's a {}",
+            value.type_name(),
+            key.short_repr(),
+            key.type_name()
+        ),
+        index.subscript_span(),
+    )
+    .with_context(index.text_span())
+}
+
+fn evaluate_assignment(
+    assignment: &AssignmentExpression,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    let Some(identifier) = assignment.identifier() else {
+        return Err(Error::new(
+            format!(
+                "Can't assign to '{}' as it's not a variable, property or element",
+                assignment.target.to_source()
+            ),
+            assignment.target.text_span(),
+        ));
+    };
+    match environment.is_constant(&identifier.lexeme) {
+        None => Err(Error::new(
+            format!(
+                "Can't assign to the variable '{}' as it's not defined",
+                identifier.lexeme
+            ),
+            assignment.text_span(),
+        )),
+        Some(true) => Err(Error::new(
+            format!(
+                "Can't assign the variable '{}' as it's a constant",
+                identifier.lexeme
+            ),
+            assignment.text_span(),
+        )),
+        Some(false) => {
+            let path = evaluate_path(&assignment.target, environment)?;
+            let value = evaluate_expression(&assignment.expression, environment)?;
+            if path.is_empty() {
+                if let Some(annotation) = environment.annotation(&identifier.lexeme) {
+                    check_annotation(
+                        "assign",
+                        &identifier.lexeme,
+                        &annotation,
+                        &value,
+                        &assignment.text_span(),
+                    )?;
                 }
+                environment.assign(&identifier.lexeme, value.clone());
+            } else {
+                // Values don't alias each other, so the variable gets a changed copy.
+                let mut root = environment
+                    .access(&identifier.lexeme)
+                    .unwrap_or(Value::None);
+                assign_path(&mut root, &path, value.clone())?;
+                environment.assign(&identifier.lexeme, root);
+            }
+            Ok(value)
+        }
+    }
+}
+
+// A step from a value into a part of it, on the way to what an assignment changes. A
+// path only lives as long as its assignment, so its keys aren't worth boxing.
+#[allow(clippy::large_enum_variant)]
+enum PathKey<'a> {
+    Property(&'a AccessExpression),
+    Element(Value, &'a IndexExpression),
+}
+
+// The steps of an assignment target after its variable, with the indexes evaluated left
+// to right before the assigned value is.
+fn evaluate_path<'a>(
+    target: &'a Expression,
+    environment: &mut Environment,
+) -> Result<Vec<PathKey<'a>>, Error> {
+    match target {
+        Expression::Access(a) => {
+            let mut path = evaluate_path(&a.object, environment)?;
+            path.push(PathKey::Property(a));
+            Ok(path)
+        }
+        Expression::Index(i) => {
+            let mut path = evaluate_path(&i.object, environment)?;
+            let key = evaluate_expression(&i.index, environment)?;
+            path.push(PathKey::Element(key, i));
+            Ok(path)
+        }
+        _ => Ok(vec![]),
+    }
+}
 
-                (operator, left, right) => Err(Error::new(
-                    format!("Can't use '{operator}' with '{left}' and '{right}'"),
-                    b.text_span(),
-                )),
+// Only the last step may add a property, the ones before it have to exist already.
+fn assign_path(container: &mut Value, path: &[PathKey], value: Value) -> Result<(), Error> {
+    let Some((key, rest)) = path.split_first() else {
+        *container = value;
+        return Ok(());
+    };
+    let slot = match (key, &mut *container) {
+        (PathKey::Property(a), Value::Object(object)) => {
+            let property = a.property.lexeme.to_string();
+            if rest.is_empty() {
+                object.entry(property).or_insert(Box::new(Value::None))
+            } else {
+                object.get_mut(&property).ok_or_else(|| {
+                    Error::new(
+                        format!("Can't access the property '{property}' as it's not defined"),
+                        a.property.text_span.clone(),
+                    )
+                    .with_context(a.text_span())
+                })?
             }
         }
-        Expression::Assignment(a) => {
-            if let Some(is_constant) = environment.is_constant(&a.identifier.lexeme) {
-                if is_constant {
-                    Err(Error::new(
-                        format!(
-                            "Can't assign the variable '{}' as it's a constant",
-                            a.identifier.lexeme
-                        ),
-                        a.text_span(),
-                    ))
-                } else {
-                    let value = evaluate_expression(&a.expression, environment)?;
-                    environment.define(a.identifier.lexeme.clone(), value.clone(), false);
-                    Ok(value)
-                }
+        (PathKey::Property(a), _) => {
+            return Err(Error::new(
+                format!(
+                    "Can't assign to the property '{}' of '{}' as it's a {}",
+                    a.property.lexeme,
+                    container.short_repr(),
+                    container.type_name()
+                ),
+                a.property.text_span.clone(),
+            )
+            .with_context(a.text_span()))
+        }
+        (PathKey::Element(Value::String(key), i), Value::Object(object)) => {
+            if rest.is_empty() {
+                object
+                    .entry(key.to_string())
+                    .or_insert(Box::new(Value::None))
             } else {
-                Err(Error::new(
-                    format!(
-                        "Can't assign to the variable '{}' as it's not defined",
-                        a.identifier.lexeme
-                    ),
-                    a.text_span(),
-                ))
+                object
+                    .get_mut(key.as_str())
+                    .ok_or_else(|| undefined_key(i, key))?
             }
         }
+        (
+            PathKey::Element(key @ (Value::Integer(_) | Value::Number(_)), i),
+            Value::Array(elements),
+        ) => {
+            let position = element_position(i, "array", elements.len(), key)?;
+            &mut elements[position]
+        }
+        (PathKey::Element(key, i), Value::Array(_) | Value::Object(_)) => {
+            return Err(key_type_error(i, container, key))
+        }
+        (PathKey::Element(_, i), _) => {
+            return Err(Error::new(
+                format!(
+                    "Can't assign to an element of '{}' as it's a {}",
+                    container.short_repr(),
+                    container.type_name()
+                ),
+                i.text_span(),
+            ))
+        }
+    };
+    assign_path(slot, rest, value)
+}
+
+fn evaluate_function_expression(function: &FunctionExpression, environment: &Environment) -> Value {
+    Value::Function(Function::new(
+        function
+            .parameters
+            .iter()
+            .map(|parameter| parameter.lexeme.clone())
+            .collect(),
+        function.body.clone(),
+        environment.closure(),
+    ))
+}
+
+pub fn call_value(
+    callee: &Value,
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    match callee {
+        Value::Function(function) => call_function(function, arguments, text_span, environment),
+        Value::NativeFunction(function) => {
+            function.check_arguments(&arguments, &text_span)?;
+            (function.function)(arguments, text_span, environment)
+        }
+        callee => Err(Error::new(
+            format!(
+                "Can't call '{}' as it's not a function",
+                callee.short_repr()
+            ),
+            text_span,
+        )),
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use std::collections::HashMap;
+fn call_function(
+    function: &Function,
+    arguments: Vec<Value>,
+    text_span: TextSpan,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    if function.parameters.len() != arguments.len() {
+        return Err(Error::new(
+            format!(
+                "Can't call '{}' with {} argument(s) as it expects {}",
+                Value::Function(function.clone()).short_repr(),
+                arguments.len(),
+                function.parameters.len()
+            ),
+            text_span,
+        ));
+    }
 
-    use crate::{
-        frontend::{parser::parse, tokenizer::tokenize},
-        runtime::types::Value,
+    if environment.call_depth == environment.limits.max_call_depth {
+        return Err(Error::new(
+            format!(
+                "Can't call '{}' as calls are nested up to the limit of {} already",
+                Value::Function(function.clone()).short_repr(),
+                environment.limits.max_call_depth
+            ),
+            text_span,
+        ));
+    }
+    let mut environment = Environment::for_call(&function.closure, environment);
+    for (parameter, argument) in function.parameters.iter().zip(arguments) {
+        environment.define(parameter.clone(), argument, false);
+    }
+    let mut value = Value::None;
+    for statement in function.body.iter() {
+        value = evaluate_statement(statement, &mut environment)?;
+    }
+    Ok(value)
+}
+
+fn operator_hook_key(operator: &TokenKind) -> Option<&'static str> {
+    match operator {
+        TokenKind::Plus => Some("__add"),
+        TokenKind::Minus => Some("__sub"),
+        TokenKind::Asterisk => Some("__mul"),
+        TokenKind::Slash => Some("__div"),
+        TokenKind::Greater => Some("__gt"),
+        TokenKind::Lesser => Some("__lt"),
+        TokenKind::GreaterOrEqual => Some("__ge"),
+        TokenKind::LesserOrEqual => Some("__le"),
+        TokenKind::DoubleEqual | TokenKind::BangEqual => Some("__eq"),
+        _ => None,
+    }
+}
+
+// An object on the left side of a binary operator can take over the operator by
+// holding a function under the matching hook key, e.g. `a + b` calls `a.__add(a, b)`.
+// '!=' is derived from '__eq' by negating its result.
+fn evaluate_operator_hook(
+    b: &BinaryExpression,
+    left: &Value,
+    right: &Value,
+    environment: &mut Environment,
+) -> Result<Option<Value>, Error> {
+    let key = match operator_hook_key(&b.operator.kind) {
+        Some(key) => key,
+        None => return Ok(None),
+    };
+    let function = match left {
+        Value::Object(object) => match object.get(key).map(|hook| &**hook) {
+            Some(Value::Function(function)) => function.clone(),
+            _ => return Ok(None),
+        },
+        _ => return Ok(None),
     };
 
-    use super::evaluate;
+    let value = call_function(
+        &function,
+        vec![left.clone(), right.clone()],
+        b.text_span(),
+        environment,
+    )?;
+    if b.operator.kind == TokenKind::BangEqual {
+        match value {
+            Value::Boolean(value) => Ok(Some(Value::Boolean(!value))),
+            value => Err(Error::new(
+                format!(
+                    "Can't use '{key}' for '!=' as it returned '{}' instead of a boolean",
+                    value.short_repr()
+                ),
+                b.text_span(),
+            )),
+        }
+    } else {
+        Ok(Some(value))
+    }
+}
+
+// Applies the operator of `b` to its left side, already evaluated, evaluating the right
+// side unless the left one decides.
+fn evaluate_binary(
+    b: &BinaryExpression,
+    left: Value,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    if let Some(value) = short_circuit(b, &left, environment)? {
+        return Ok(value);
+    }
+    let right = evaluate_expression(&b.right, environment)?;
+    let gives_right = match b.operator.kind {
+        TokenKind::DoubleAmpersand | TokenKind::DoublePipe => !environment.is_strict,
+        TokenKind::DoubleQuestion => true,
+        _ => false,
+    };
+    if gives_right {
+        return Ok(right);
+    }
+    if let Some(value) = evaluate_operator_hook(b, &left, &right, environment)? {
+        return Ok(value);
+    }
+    check_strict_operands(b, &left, &right, environment)?;
+    match operations(&b.operator.kind)
+        .find_map(|operation| operation.apply(&left, &right, &environment.limits))
+    {
+        Some(result) => result.map_err(|message| Error::new(message, b.text_span())),
+        None => Err(binary_operand_error(b, &left, &right)),
+    }
+}
+
+// The value of '&&' and '||' when their left side decides it, so that the right side is
+// never evaluated. They go by truthiness and give back the operand deciding them, `||`
+// the first truthy one and `&&` the first falsy one, so `port || 8080` defaults a port
+// that's none. `??` only takes its right side when the left one is none, so unlike `||`
+// it keeps a left side that's false. When the left side doesn't decide, the value is
+// the right side.
+//
+// Strict mode keeps them to booleans: a left side that isn't one can't decide anything
+// and is an error before the right side runs.
+fn short_circuit(
+    b: &BinaryExpression,
+    left: &Value,
+    environment: &Environment,
+) -> Result<Option<Value>, Error> {
+    let operator = &b.operator.kind;
+    if *operator == TokenKind::DoubleQuestion {
+        return Ok((!matches!(left, Value::None)).then(|| left.clone()));
+    }
+    if !matches!(operator, TokenKind::DoubleAmpersand | TokenKind::DoublePipe) {
+        return Ok(None);
+    }
+    if !environment.is_strict || matches!(left, Value::Boolean(_)) {
+        let decides = left.is_truthy() == (*operator == TokenKind::DoublePipe);
+        return Ok(decides.then(|| left.clone()));
+    }
+    Err(Error::new(
+        format!(
+            "Can't use '{operator}' with '{}' as the left side is a {}",
+            left.short_repr(),
+            left.type_name()
+        ),
+        b.left.text_span(),
+    )
+    .with_context(b.text_span()))
+}
+
+// Points at the operand the operator can't take, the left one first, or at the whole
+// expression when both could be taken but not together (e.g. `1 + "a"`).
+fn binary_operand_error(b: &BinaryExpression, left: &Value, right: &Value) -> Error {
+    let operator = &b.operator.kind;
+    let accepts = |value: &Value| operations(operator).any(|operation| operation.accepts(value));
+    let message = format!(
+        "Can't use '{operator}' with '{}' and '{}'",
+        left.short_repr(),
+        right.short_repr()
+    );
+    if !accepts(left) {
+        Error::new(
+            format!("{message} as the left side is a {}", left.type_name()),
+            b.left.text_span(),
+        )
+        .with_context(b.text_span())
+    } else if !accepts(right) {
+        Error::new(
+            format!("{message} as the right side is a {}", right.type_name()),
+            b.right.text_span(),
+        )
+        .with_context(b.text_span())
+    } else {
+        Error::new(
+            format!(
+                "{message} as they're a {} and a {}",
+                left.type_name(),
+                right.type_name()
+            ),
+            b.text_span(),
+        )
+    }
+}
+
+// Strict mode turns the behaviors of the language that silently mix types into errors.
+// Every such behavior consults it here rather than in its own match arm.
+fn check_strict_operands(
+    b: &BinaryExpression,
+    left: &Value,
+    right: &Value,
+    environment: &Environment,
+) -> Result<(), Error> {
+    if !environment.is_strict {
+        return Ok(());
+    }
+    let is_equality = matches!(
+        b.operator.kind,
+        TokenKind::DoubleEqual | TokenKind::BangEqual
+    );
+    let is_mixed = left.type_name() != right.type_name()
+        && !matches!(left, Value::None)
+        && !matches!(right, Value::None);
+    if is_equality && is_mixed {
+        Err(Error::new(
+            format!(
+                "Can't compare '{}' and '{}' with '{}' as they're a {} and a {}, which strict mode doesn't allow",
+                left.short_repr(),
+                right.short_repr(),
+                b.operator.kind,
+                left.type_name(),
+                right.type_name()
+            ),
+            b.text_span(),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::RefCell,
+        collections::{BTreeSet, HashMap},
+        rc::Rc,
+        sync::atomic::Ordering,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use crate::{
+        frontend::{parser::parse, tokenizer::tokenize, utils::TextSpan},
+        run_source, run_source_with,
+        runtime::{
+            environment::Environment,
+            profile::Profiler,
+            types::{SetElement, Value},
+        },
+    };
+
+    use super::{display, evaluate, evaluate_statement};
+
+    const VECTOR: &str = "
+        let vector = fn(x, y) {
+            {
+                x: x,
+                y: y,
+                __add: fn(a, b) { vector(a.x + b.x, a.y + b.y) },
+                __eq: fn(a, b) { a.x == b.x && a.y == b.y },
+                __display: fn(v) { format(\"vector({}, {})\", v.x, v.y) }
+            }
+        }
+    ";
+
+    #[test]
+    fn test_evaluate_let_statement() {
+        let src = "let a = 2.71";
+        let expected_value = Value::None;
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, env) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+
+        let src = "a";
+        let expected_value = Value::Number(2.71);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, Some(env)).unwrap();
+        assert_eq!(val, expected_value);
+    }
+
+    #[test]
+    fn test_evaluate_const_statement() {
+        let src = "let E = 2.71";
+        let expected_value = Value::None;
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, env) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+
+        let src = "E";
+        let expected_value = Value::Number(2.71);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, Some(env)).unwrap();
+        assert_eq!(val, expected_value);
+    }
+
+    #[test]
+    fn test_evaluate_assignment_expression_with_let() {
+        let src = "let a = 2.71";
+        let expected_value = Value::None;
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, env) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+
+        let src = "a = 5";
+        let expected_value = Value::Number(5.);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, Some(env)).unwrap();
+        assert_eq!(val, expected_value);
+    }
+
+    #[test]
+    fn test_evaluate_assignment_expression_with_const() {
+        let src = "const a = 2.71";
+        let expected_value = Value::None;
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, env) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+
+        let src = "a = 5";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        assert!(evaluate(program, Some(env)).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_binary_arithmetic_expression() {
+        let src = "5 + 5 * 2 / 5 - 2";
+        let expected_value = Value::Number(5.);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+    }
+
+    #[test]
+    fn test_evaluate_left_associative_chains() {
+        for (src, expected) in [
+            ("10 - 3 - 2", 5.),
+            ("100 / 10 / 2", 5.),
+            ("20 / 2 / 5", 2.),
+            ("1 + 2 - 3 * 4 / 6", 1.),
+            ("2 * 3 / 6 * 4 - 1 - 1", 2.),
+        ] {
+            let tokens = tokenize(src).unwrap();
+            let program = parse(tokens).unwrap();
+            let (val, _) = evaluate(program, None).unwrap();
+            assert_eq!(val, Value::Number(expected), "{src}");
+        }
+    }
+
+    #[test]
+    fn test_evaluate_binary_comparison_expression() {
+        let src = "5 > 5";
+        let expected_value = Value::Boolean(false);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+
+        let src = "5 < 5";
+        let expected_value = Value::Boolean(false);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+
+        let src = "5 >= 5";
+        let expected_value = Value::Boolean(true);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+
+        let src = "5 <= 5";
+        let expected_value = Value::Boolean(true);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+    }
+
+    #[test]
+    fn test_evaluate_binary_equality_expression() {
+        let src = "5 == 5 != 5";
+        let expected_value = Value::Boolean(true);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+    }
+
+    #[test]
+    fn test_evaluate_binary_logical_expression() {
+        let src = "true && false || !true";
+        let expected_value = Value::Boolean(false);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+    }
+
+    #[test]
+    fn test_evaluate_logical_short_circuit() {
+        for (src, expected) in [
+            ("false && (1/0 == 1)", "false"),
+            ("true || (1/0 == 1)", "true"),
+            ("false && undefined_variable", "false"),
+            ("let calls = 0 let f = fn() { calls = calls + 1 true } let result = (false && f(), true || f(), true && f(), calls) result", "(false, true, true, 1)"),
+        ] {
+            let tokens = tokenize(src).unwrap();
+            let program = parse(tokens).unwrap();
+            let (val, _) = evaluate(program, None).unwrap();
+            assert_eq!(val.to_string(), expected, "{src}");
+        }
+        for src in ["true && (1/0 == 1)", "false || (1/0 == 1)"] {
+            let tokens = tokenize(src).unwrap();
+            let program = parse(tokens).unwrap();
+            let error = evaluate(program, None).unwrap_err();
+            assert_eq!(error.message, "Can't divide by 0", "{src}");
+        }
+    }
+
+    #[test]
+    fn test_evaluate_logical_truthiness() {
+        for (src, expected) in [
+            ("none || 5", "5"),
+            ("\"a\" || \"b\"", "a"),
+            ("false || none", "none"),
+            // 0 and empty values are truthy, only none and false are falsy.
+            ("0 && 1", "1"),
+            ("\"\" || \"default\"", ""),
+            ("[] && (1,)", "(1,)"),
+            ("none && 1", "none"),
+            ("1 && false && missing", "false"),
+            ("let config = {port: none} let port = config.port || 8080 port", "8080"),
+            // The right side still only runs when the left one doesn't decide.
+            ("let calls = 0 let f = fn() { calls = calls + 1 calls } let result = (1 || f(), none && f(), none || f(), calls) result", "(1, none, 1, 1)"),
+        ] {
+            assert_eq!(run_source(src).unwrap().to_string(), expected, "{src}");
+        }
+    }
+
+    #[test]
+    fn test_evaluate_nullish_coalescing() {
+        for (src, expected) in [
+            ("none ?? none ?? 3", "3"),
+            ("false ?? 1", "false"),
+            ("false || 1", "1"),
+            ("0 ?? 1", "0"),
+            ("none ?? false", "false"),
+            ("let config = {port: none} config.port ?? 8080", "8080"),
+            // `??` binds looser than `||`.
+            ("none || false ?? 1", "false"),
+            ("let calls = 0 let f = fn() { calls = calls + 1 calls } let result = (1 ?? f(), none ?? f(), calls) result", "(1, 1, 1)"),
+        ] {
+            assert_eq!(run_source(src).unwrap().to_string(), expected, "{src}");
+        }
+        let mut environment = Environment::new(None);
+        environment.is_strict = true;
+        assert_eq!(
+            run_source_with("none ?? \"a\"", &mut environment).unwrap(),
+            Value::String("a".to_string().into())
+        );
+
+        // Errors in an operand point at it rather than at the chain.
+        for (src, text_span) in [
+            ("none ?? (1 + \"a\") ?? 2", TextSpan::new(9, 16)),
+            ("(\"a\" - 1) ?? none ?? 2", TextSpan::new(1, 4)),
+        ] {
+            let error = run_source(src).unwrap_err();
+            assert_eq!(error.text_span, text_span, "{src}");
+        }
+    }
+
+    #[test]
+    fn test_evaluate_optional_chaining() {
+        // Plain accesses of none or of a missing property are errors, optional ones none.
+        for (src, message) in [
+            (
+                "let a = none a.b",
+                "Can't access the property 'b' as the value is none",
+            ),
+            (
+                "let a = {} a.b",
+                "Can't access the property 'b' as it's not defined",
+            ),
+            (
+                "let a = {b: none} a.b.c",
+                "Can't access the property 'c' as the value is none",
+            ),
+            // `?.` only covers its own access, the ones after it still need a value.
+            (
+                "let a = none a?.b.c",
+                "Can't access the property 'c' as the value is none",
+            ),
+            (
+                "let a = (1,) a?.length2",
+                "Can't access the property 'length2' of '(1,)', the available ones are length",
+            ),
+        ] {
+            let error = run_source(src).unwrap_err();
+            assert!(
+                error.message.starts_with(message),
+                "{src} failed with {}",
+                error.message
+            );
+        }
+        for (src, expected) in [
+            ("let a = none a?.b", "none"),
+            ("let a = {} a?.b", "none"),
+            ("let a = {b: {c: 1}} a?.b?.c", "1"),
+            ("let a = none a?.b?.c", "none"),
+            ("let a = {b: none} a?.b?.c", "none"),
+            ("let a = {b: false} a?.b", "false"),
+            ("let config = {} config?.server?.port ?? 8080", "8080"),
+            ("let t = (1, 2) t?.1", "2"),
+            ("\"ab\"?.length", "2"),
+        ] {
+            assert_eq!(run_source(src).unwrap().to_string(), expected, "{src}");
+        }
+        let error = run_source("let a = {} a?.b = 1").unwrap_err();
+        assert_eq!(
+            error.message,
+            "Can't assign to 'a?.b' as it's not a variable, property or element"
+        );
+    }
+
+    #[test]
+    fn test_evaluate_type() {
+        for (src, expected) in [
+            ("type(none)", "none"),
+            ("type(false)", "boolean"),
+            ("type(1)", "number"),
+            ("type(1.5)", "number"),
+            ("type(0.0 / 0)", "number"),
+            ("type(\"a\")", "string"),
+            ("type({})", "object"),
+            ("type({a: {b: [1]}})", "object"),
+            (
+                "let o = {a: {b: [1]}} let types = (type(o.a), type(o.a.b), type(o.a.b[0])) types",
+                "(object, array, number)",
+            ),
+            ("type([])", "array"),
+            ("type((1, 2))", "tuple"),
+            ("type(set(1))", "set"),
+            ("type(fn() {})", "function"),
+            ("fn f() {} type(f)", "function"),
+            ("type(len)", "function"),
+            ("type(math)", "object"),
+            ("type(type(1))", "string"),
+            ("let x = none type(x) == \"none\" ? 0 : x", "0"),
+        ] {
+            assert_eq!(run_source(src).unwrap().to_string(), expected, "{src}");
+        }
+        assert_eq!(
+            run_source("type()").unwrap_err().message,
+            "type expects 1 argument (value), got 0"
+        );
+    }
+
+    #[test]
+    fn test_evaluate_conversions() {
+        // The samples of every type, and what `number`, `string` and `boolean` make of them.
+        for (value, number, string, boolean) in [
+            ("none", "none", "none", "false"),
+            ("true", "1", "true", "true"),
+            ("false", "0", "false", "false"),
+            ("42", "42", "42", "true"),
+            ("0", "0", "0", "true"),
+            ("-4.5", "-4.5", "-4.5", "true"),
+            ("\"42\"", "42", "42", "true"),
+            ("\" -4.5 \"", "-4.5", " -4.5 ", "true"),
+            ("\"1e3\"", "1000.0", "1e3", "true"),
+            ("\"+7\"", "7", "+7", "true"),
+            (
+                "\"9223372036854775808\"",
+                "9223372036854775808.0",
+                "9223372036854775808",
+                "true",
+            ),
+            ("\"\"", "none", "", "true"),
+            ("\"12abc\"", "none", "12abc", "true"),
+            ("\"inf\"", "none", "inf", "true"),
+            ("\"1.2.3\"", "none", "1.2.3", "true"),
+            ("{a: 1}", "none", "{\n  a : 1\n}", "true"),
+            ("[1, 2]", "none", "[1, 2]", "true"),
+            ("(1,)", "none", "(1,)", "true"),
+            ("set(1)", "none", "{1}", "true"),
+            ("fn(x) {}", "none", "fn(x)", "true"),
+        ] {
+            for (conversion, expected) in
+                [("number", number), ("string", string), ("boolean", boolean)]
+            {
+                let src = format!("let value = {value} {conversion}(value)");
+                assert_eq!(run_source(&src).unwrap().to_string(), expected, "{src}");
+            }
+        }
+        assert_eq!(
+            run_source("number(\"42\") + 1").unwrap(),
+            Value::Integer(43)
+        );
+        assert_eq!(run_source("number(\"x\") ?? 0").unwrap(), Value::Integer(0));
+        assert_eq!(
+            run_source("number(42) == number(string(42))").unwrap(),
+            Value::Boolean(true)
+        );
+    }
+
+    #[test]
+    fn test_evaluate_random() {
+        let draw = "(math.random(), math.random_int(1, 6))";
+        let src = format!("let numbers = [{draw}, {draw}, {draw}, {draw}, {draw}] numbers");
+        let run_seeded = |seed: i64| {
+            let mut environment = Environment::new(None);
+            run_source_with(&format!("math.seed({seed})"), &mut environment).unwrap();
+            run_source_with(&src, &mut environment).unwrap()
+        };
+        // The same seed gives the same sequence, another one a different one.
+        assert_eq!(run_seeded(7), run_seeded(7));
+        assert_ne!(run_seeded(7), run_seeded(8));
+        let Value::Array(pairs) = run_seeded(7) else {
+            panic!("the numbers should be an array");
+        };
+        for pair in pairs {
+            let Value::Tuple(pair) = pair else {
+                panic!("each pair should be a tuple");
+            };
+            assert!(matches!(pair[0], Value::Number(n) if (0.0..1.0).contains(&n)));
+            assert!(matches!(pair[1], Value::Integer(n) if (1..=6).contains(&n)));
+        }
+
+        // Seeding again starts the sequence over, in functions too.
+        let value = run_source(
+            "fn roll() { math.random_int(-3, 3) } math.seed(1) let first = roll() math.seed(1) first == roll()",
+        )
+        .unwrap();
+        assert_eq!(value, Value::Boolean(true));
+        assert_eq!(
+            run_source("math.random_int(4, 4)").unwrap(),
+            Value::Integer(4)
+        );
+        for (src, message) in [
+            (
+                "math.random_int(3, 1)",
+                "Can't pick a number from 3 to 1 with 'math.random_int' as the range is empty",
+            ),
+            (
+                "math.random_int(1.5, 2)",
+                "Can't use '1.5' with 'math.random_int' as it's not a whole number",
+            ),
+            (
+                "math.seed(\"a\")",
+                "math.seed expects seed to be a number, got a string",
+            ),
+        ] {
+            assert_eq!(run_source(src).unwrap_err().message, message, "{src}");
+        }
+    }
+
+    #[test]
+    fn test_evaluate_trigonometry() {
+        for (src, expected) in [
+            ("math.sin(math.pi / 2)", 1.0),
+            ("math.sin(0)", 0.0),
+            ("math.cos(math.pi)", -1.0),
+            ("math.cos(0)", 1.0),
+            ("math.tan(math.pi / 4)", 1.0),
+            ("math.asin(1)", std::f64::consts::FRAC_PI_2),
+            ("math.acos(-1)", std::f64::consts::PI),
+            ("math.atan(1)", std::f64::consts::FRAC_PI_4),
+            ("math.atan2(1, -1)", 3.0 * std::f64::consts::FRAC_PI_4),
+            ("math.atan2(0, -1)", std::f64::consts::PI),
+            ("math.to_radians(180)", std::f64::consts::PI),
+            ("math.to_degrees(math.pi / 2)", 90.0),
+            ("math.to_degrees(math.atan2(-1, 0))", -90.0),
+        ] {
+            let Value::Number(n) = run_source(src).unwrap() else {
+                panic!("{src} should give a float");
+            };
+            assert!((n - expected).abs() < 1e-12, "{src} gave {n}");
+        }
+        assert_eq!(
+            run_source("math.is_nan(math.asin(2))").unwrap(),
+            Value::Boolean(true)
+        );
+        for (src, message, text_span) in [
+            (
+                "math.sin()",
+                "math.sin expects 1 argument (x), got 0",
+                TextSpan::new(0, 10),
+            ),
+            (
+                "math.cos(\"0\")",
+                "math.cos expects x to be a number, got a string",
+                TextSpan::new(0, 13),
+            ),
+            (
+                "math.atan2(1)",
+                "math.atan2 expects 2 arguments (y, x), got 1",
+                TextSpan::new(0, 13),
+            ),
+        ] {
+            let error = run_source(src).unwrap_err();
+            assert_eq!(error.message, message, "{src}");
+            assert_eq!(error.text_span, text_span, "{src}");
+        }
+    }
+
+    #[test]
+    fn test_evaluate_strings_module() {
+        for (src, expected) in [
+            ("strings.len(\"hello\")", "5"),
+            // Chars rather than bytes, whether they're accented or emoji.
+            ("strings.len(\"héllo\")", "5"),
+            ("strings.len(\"日本語🎉\")", "4"),
+            ("strings.len(\"\")", "0"),
+            ("strings.upper(\"straße\")", "STRASSE"),
+            ("strings.lower(\"ÉCOLE\")", "école"),
+            ("strings.trim(\"  a b \\n\")", "a b"),
+            ("strings.trim_start(\"\\t a \")", "a "),
+            ("strings.trim_end(\" a \\n\")", " a"),
+            ("string(42) + strings.upper(\"x\")", "42X"),
+        ] {
+            assert_eq!(run_source(src).unwrap().to_string(), expected, "{src}");
+        }
+        for (src, message, text_span) in [
+            (
+                "strings.len(1)",
+                "strings.len expects s to be a string, got a number",
+                TextSpan::new(0, 14),
+            ),
+            (
+                "let x = strings.trim([\" a \"])",
+                "strings.trim expects s to be a string, got an array",
+                TextSpan::new(8, 29),
+            ),
+            (
+                "strings.upper()",
+                "strings.upper expects 1 argument (s), got 0",
+                TextSpan::new(0, 15),
+            ),
+        ] {
+            let error = run_source(src).unwrap_err();
+            assert_eq!(error.message, message, "{src}");
+            assert_eq!(error.text_span, text_span, "{src}");
+        }
+    }
+
+    #[test]
+    fn test_evaluate_strings_search_and_replace() {
+        for (src, expected) in [
+            ("strings.contains(\"hello\", \"ell\")", "true"),
+            ("strings.contains(\"hello\", \"L\")", "false"),
+            ("strings.starts_with(\"hello\", \"he\")", "true"),
+            ("strings.starts_with(\"hello\", \"lo\")", "false"),
+            ("strings.ends_with(\"hello\", \"lo\")", "true"),
+            ("strings.index_of(\"hello\", \"l\")", "2"),
+            // Positions are in chars, absent needles none.
+            ("strings.index_of(\"héllo\", \"l\")", "2"),
+            ("strings.index_of(\"hello\", \"z\")", "none"),
+            // The empty string is found everywhere, first at the start.
+            ("strings.contains(\"abc\", \"\")", "true"),
+            ("strings.starts_with(\"abc\", \"\")", "true"),
+            ("strings.ends_with(\"\", \"\")", "true"),
+            ("strings.index_of(\"abc\", \"\")", "0"),
+            ("strings.replace(\"a-b-c\", \"-\", \"+\")", "a+b+c"),
+            ("strings.replace(\"aaa\", \"aa\", \"b\")", "ba"),
+            ("strings.replace(\"abc\", \"z\", \"y\")", "abc"),
+            ("strings.replace(\"a-b\", \"-\", \"\")", "ab"),
+            ("strings.repeat(\"ab\", 3)", "ababab"),
+            ("strings.repeat(\"ab\", 0)", ""),
+            ("strings.repeat(\"ab\", 2.0)", "abab"),
+            ("strings.repeat(\"\", 1000000000000)", ""),
+            // The arguments are left as they were.
+            (
+                "let s = \"a-b\" let t = strings.replace(s, \"-\", \"+\") s + t",
+                "a-ba+b",
+            ),
+        ] {
+            assert_eq!(run_source(src).unwrap().to_string(), expected, "{src}");
+        }
+        for (src, message) in [
+            (
+                "strings.repeat(\"ab\", -1)",
+                "Can't repeat a string -1 times with 'strings.repeat' as the count is negative",
+            ),
+            (
+                "strings.repeat(\"ab\", 1.5)",
+                "Can't use '1.5' with 'strings.repeat' as it's not a whole number",
+            ),
+            (
+                "strings.replace(\"ab\", \"\", \"-\")",
+                "Can't replace the empty string with 'strings.replace' as it's between every char",
+            ),
+            (
+                "strings.repeat(\"ab\", 1000000000000)",
+                "Can't make a string longer than the limit of 16777216 chars",
+            ),
+            (
+                "strings.contains(\"ab\", 1)",
+                "strings.contains expects needle to be a string, got a number",
+            ),
+        ] {
+            let error = run_source(&format!("let x = 1\n{src}")).unwrap_err();
+            assert_eq!(error.message, message, "{src}");
+            assert_eq!(
+                error.text_span,
+                TextSpan::new(10, 10 + src.chars().count()),
+                "{src}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_evaluate_strings_slicing() {
+        for (src, expected) in [
+            ("strings.slice(\"hello\", 1, 3)", "el"),
+            ("strings.slice(\"hello\", 2)", "llo"),
+            ("strings.slice(\"hello\", -3)", "llo"),
+            ("strings.slice(\"hello\", 1, -1)", "ell"),
+            // Positions past either end are clamped, an empty range gives an empty string.
+            ("strings.slice(\"hello\", -10, 2)", "he"),
+            ("strings.slice(\"hello\", 3, 100)", "lo"),
+            ("strings.slice(\"hello\", 4, 2)", ""),
+            ("strings.slice(\"hello\", 9)", ""),
+            ("strings.slice(\"\", 0, 1)", ""),
+            // Positions count chars, so multibyte ones are never split.
+            ("strings.slice(\"a🎉b😀c\", 1, 4)", "🎉b😀"),
+            ("strings.slice(\"a🎉b😀c\", -2)", "😀c"),
+            ("strings.slice(\"héllo\", 1, 2)", "é"),
+            ("strings.char_at(\"a🎉b\", 1)", "🎉"),
+            ("strings.char_at(\"a🎉b\", 2)", "b"),
+            ("strings.char_at(\"a🎉b\", -1)", "b"),
+            ("strings.char_at(\"a🎉b\", -3)", "a"),
+            ("strings.char_at(\"a🎉b\", 3)", "none"),
+            ("strings.char_at(\"a🎉b\", -4)", "none"),
+            ("strings.char_at(\"\", 0)", "none"),
+        ] {
+            assert_eq!(run_source(src).unwrap().to_string(), expected, "{src}");
+        }
+        for (src, message) in [
+            (
+                "strings.slice(\"ab\", 0.5)",
+                "Can't use '0.5' with 'strings.slice' as it's not a whole number",
+            ),
+            (
+                "strings.char_at(\"ab\")",
+                "strings.char_at expects 2 arguments (s, position), got 1",
+            ),
+        ] {
+            assert_eq!(run_source(src).unwrap_err().message, message, "{src}");
+        }
+    }
+
+    #[test]
+    fn test_evaluate_arrays_module() {
+        for (src, expected) in [
+            ("arrays.len([1, 2, 3])", "3"),
+            ("arrays.len([])", "0"),
+            ("arrays.push([1, 2], 3)", "[1, 2, 3]"),
+            ("arrays.push([], [])", "[[]]"),
+            ("arrays.concat([1], [2, 3])", "[1, 2, 3]"),
+            ("arrays.concat([], [])", "[]"),
+            ("arrays.slice([1, 2, 3, 4], 1, 3)", "[2, 3]"),
+            ("arrays.slice([1, 2, 3, 4], -2)", "[3, 4]"),
+            ("arrays.slice([1, 2, 3, 4], 3, 1)", "[]"),
+            ("arrays.slice([1, 2], -5, 10)", "[1, 2]"),
+            ("arrays.slice([], 0, 1)", "[]"),
+            ("arrays.reverse([1, \"a\", none])", "[none, a, 1]"),
+            ("arrays.reverse([])", "[]"),
+            ("arrays.contains([1, (2, 3)], (2, 3))", "true"),
+            ("arrays.contains([1.0], 1)", "true"),
+            ("arrays.contains([math.nan], math.nan)", "false"),
+            ("arrays.contains([], none)", "false"),
+            ("arrays.index_of([\"a\", \"b\", \"b\"], \"b\")", "1"),
+            ("arrays.index_of([\"a\"], \"z\")", "none"),
+            ("arrays.index_of([], 1)", "none"),
+            // The arrays given are left as they were.
+            ("let a = [1] let b = arrays.push(a, 2) let c = arrays.reverse(b) let result = (a, b, c) result", "([1], [1, 2], [2, 1])"),
+        ] {
+            assert_eq!(run_source(src).unwrap().to_string(), expected, "{src}");
+        }
+        for (src, message) in [
+            (
+                "arrays.len((1, 2))",
+                "arrays.len expects array to be an array, got a tuple",
+            ),
+            (
+                "arrays.concat([1], \"a\")",
+                "arrays.concat expects b to be an array, got a string",
+            ),
+            (
+                "arrays.push([1])",
+                "arrays.push expects 2 arguments (array, element), got 1",
+            ),
+            (
+                "arrays.slice([1], 0, 1.5)",
+                "Can't use '1.5' with 'arrays.slice' as it's not a whole number",
+            ),
+        ] {
+            let error = run_source(&format!("let x = 1\n{src}")).unwrap_err();
+            assert_eq!(error.message, message, "{src}");
+            assert_eq!(
+                error.text_span,
+                TextSpan::new(10, 10 + src.chars().count()),
+                "{src}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_evaluate_higher_order_arrays() {
+        for (src, expected) in [
+            ("arrays.map([1, 2, 3], fn(x) { x * 2 })", "[2, 4, 6]"),
+            ("arrays.map([], fn(x) { x * 2 })", "[]"),
+            ("arrays.map([1.5, -2], math.abs)", "[1.5, 2]"),
+            ("arrays.map([\"a\", \"b\"], strings.upper)", "[A, B]"),
+            ("arrays.filter([1, 2, 3, 4], fn(x) { x > 2 })", "[3, 4]"),
+            ("arrays.filter([], fn(x) { false })", "[]"),
+            ("arrays.reduce([1, 2, 3], fn(sum, x) { sum + x }, 10)", "16"),
+            ("arrays.reduce([1, 2, 3], fn(sum, x) { sum + x })", "6"),
+            ("arrays.reduce([], fn(sum, x) { sum + x }, 0)", "0"),
+            ("arrays.reduce([\"a\"], fn(s, x) { s + x })", "a"),
+            // Callbacks see the variables around them.
+            (
+                "let factor = 3 let result = arrays.map([1, 2], fn(x) { x * factor }) result",
+                "[3, 6]",
+            ),
+            (
+                "let total = arrays.reduce(arrays.filter(arrays.map([1, 2, 3, 4], fn(x) { x * x }), fn(x) { x > 4 }), fn(a, b) { a + b }) total",
+                "25",
+            ),
+        ] {
+            assert_eq!(run_source(src).unwrap().to_string(), expected, "{src}");
+        }
+        for (src, message) in [
+            (
+                "arrays.map([1], 2)",
+                "arrays.map expects f to be a function, got a number",
+            ),
+            (
+                "arrays.filter([1, 2], fn(x) { x })",
+                "Can't filter with 'fn(x)' as it gave '1' for '1', which isn't a boolean",
+            ),
+            (
+                "arrays.reduce([], fn(a, b) { a + b })",
+                "Can't reduce an empty array without an initial value",
+            ),
+            (
+                "arrays.map([1], fn(a, b) { a })",
+                "Can't call 'fn(a, b)' with 1 argument(s) as it expects 2",
+            ),
+        ] {
+            let error = run_source(&format!("let x = 1\n{src}")).unwrap_err();
+            assert_eq!(error.message, message, "{src}");
+            assert_eq!(
+                error.text_span,
+                TextSpan::new(10, 10 + src.chars().count()),
+                "{src}"
+            );
+        }
+        // Errors inside a callback keep their own span.
+        let error = run_source("arrays.map([0], fn(x) { 1 / x })").unwrap_err();
+        assert_eq!(error.message, "Can't divide by 0");
+        assert_eq!(error.text_span, TextSpan::new(24, 29));
+    }
+
+    #[test]
+    fn test_evaluate_sorting() {
+        for (src, expected) in [
+            ("arrays.sort([3, 1.5, -2, 10])", "[-2, 1.5, 3, 10]"),
+            (
+                "arrays.sort([\"pear\", \"Apple\", \"apple\"])",
+                "[Apple, apple, pear]",
+            ),
+            ("arrays.sort([])", "[]"),
+            ("arrays.sort([math.inf, 1])", "[1, inf]"),
+            (
+                "let a = [2, 1] let sorted = arrays.sort(a) let result = (a, sorted) result",
+                "([2, 1], [1, 2])",
+            ),
+            ("arrays.sort_by([1, 3, 2], fn(a, b) { b - a })", "[3, 2, 1]"),
+            ("arrays.sort_by([], fn(a, b) { a - b })", "[]"),
+            (
+                "arrays.sort_by([true, none], fn(a, b) { 0 })",
+                "[true, none]",
+            ),
+        ] {
+            assert_eq!(run_source(src).unwrap().to_string(), expected, "{src}");
+        }
+
+        // Sorting objects by a field, where the ones with the same age keep their order.
+        let src = "
+            let people = [
+                {name: \"c\", age: 30},
+                {name: \"a\", age: 25},
+                {name: \"d\", age: 30},
+                {name: \"b\", age: 20},
+                {name: \"e\", age: 25}
+            ]
+            let by_age = arrays.sort_by(people, fn(a, b) { a.age - b.age })
+            arrays.map(by_age, fn(person) { person.name })
+        ";
+        assert_eq!(run_source(src).unwrap().to_string(), "[b, a, e, c, d]");
+
+        for (src, message) in [
+            (
+                "arrays.sort([1, \"a\"])",
+                "Can't sort '1' with 'a' as they're not both numbers or both strings",
+            ),
+            (
+                "arrays.sort([1, none])",
+                "Can't sort 'none' as only numbers and strings can be sorted",
+            ),
+            (
+                "arrays.sort([1, math.nan])",
+                "Can't sort 'NaN' as it's not ordered with other numbers",
+            ),
+            (
+                "arrays.sort_by([1, 2], fn(a, b) { a < b })",
+                "Can't sort with 'fn(a, b)' as it gave 'true' for '1' and '2', which isn't a number",
+            ),
+            (
+                "arrays.sort_by([1, 2], 1)",
+                "arrays.sort_by expects compare to be a function, got a number",
+            ),
+        ] {
+            let error = run_source(&format!("let x = 1\n{src}")).unwrap_err();
+            assert_eq!(error.message, message, "{src}");
+            assert_eq!(error.text_span, TextSpan::new(10, 10 + src.chars().count()), "{src}");
+        }
+        // Errors inside the comparator keep their own span.
+        let error = run_source("arrays.sort_by([0, 1], fn(a, b) { b / a })").unwrap_err();
+        assert_eq!(error.message, "Can't divide by 0");
+        assert_eq!(error.text_span, TextSpan::new(34, 39));
+    }
+
+    #[test]
+    fn test_evaluate_time() {
+        let src = "
+            let before = time.monotonic()
+            let again = time.monotonic()
+            time.sleep(0.01)
+            let after = time.monotonic()
+            let result = (again >= before, after - again >= 0.01, time.sleep(0))
+            result
+        ";
+        assert_eq!(run_source(src).unwrap().to_string(), "(true, true, none)");
+
+        // Some time after this was written, and before the year 3000.
+        let Value::Number(now) = run_source("time.now()").unwrap() else {
+            panic!("time.now() should give a float");
+        };
+        assert!((1.7e9..3.25e10).contains(&now), "{now}");
+
+        for (src, message) in [
+            (
+                "time.sleep(-1)",
+                "Can't sleep for '-1' seconds as it's not a positive number or 0",
+            ),
+            (
+                "time.sleep(math.nan)",
+                "Can't sleep for 'NaN' seconds as it's not a positive number or 0",
+            ),
+            (
+                "time.sleep(math.inf)",
+                "Can't sleep for 'inf' seconds as it's too long",
+            ),
+            (
+                "time.sleep(\"1\")",
+                "time.sleep expects seconds to be a number, got a string",
+            ),
+            ("time.now(1)", "time.now expects 0 arguments, got 1"),
+        ] {
+            let error = run_source(&format!("let x = 1\n{src}")).unwrap_err();
+            assert_eq!(error.message, message, "{src}");
+            assert_eq!(
+                error.text_span,
+                TextSpan::new(10, 10 + src.chars().count()),
+                "{src}"
+            );
+        }
+
+        // Sleeping stops when evaluation is interrupted.
+        let mut environment = Environment::new(None);
+        let interrupted = environment.interrupted.clone();
+        let interrupter = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            interrupted.store(true, Ordering::SeqCst);
+        });
+        let start = Instant::now();
+        let error = run_source_with("time.sleep(60)", &mut environment).unwrap_err();
+        interrupter.join().unwrap();
+        assert_eq!(error.message, "Evaluation was interrupted");
+        assert_eq!(error.text_span, TextSpan::new(0, 14));
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_evaluate_assertions() {
+        for src in [
+            "assert(true)",
+            "assert(1 < 2, \"ordered\")",
+            "assert_eq(1, 1.0)",
+            "assert_eq({a: [1]}, {a: [1]})",
+            "assert_eq(none, none)",
+        ] {
+            assert_eq!(run_source(src).unwrap(), Value::None, "{src}");
+        }
+        for (src, message, notes) in [
+            ("assert(false)", "Assertion failed", vec![]),
+            (
+                "assert(1 > 2, \"1 isn't above 2\")",
+                "Assertion failed: 1 isn't above 2",
+                vec![],
+            ),
+            (
+                "assert(1)",
+                "Assertion failed",
+                vec!["the condition was '1' rather than true"],
+            ),
+            (
+                "assert_eq(\"a\", [\"a\"])",
+                "Assertion failed as the values aren't equal",
+                vec!["left: a", "right: [a]"],
+            ),
+            (
+                "assert(true, 1)",
+                "assert expects message to be a string, got a number",
+                vec![],
+            ),
+        ] {
+            let error = run_source(&format!("let x = 1\n{src}")).unwrap_err();
+            assert_eq!(error.message, message, "{src}");
+            assert_eq!(error.notes, notes, "{src}");
+            assert_eq!(
+                error.text_span,
+                TextSpan::new(10, 10 + src.chars().count()),
+                "{src}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_evaluate_process() {
+        let path = std::env::var("PATH").unwrap();
+        for (src, expected) in [
+            // Outside of `fns <file>` nothing registers any arguments.
+            ("process.args", "[]".to_string()),
+            ("process.get(\"PATH\")", path.clone()),
+            ("process.vars().PATH", path),
+            ("process.get(\"FNS_SURELY_NOT_SET\")", "none".to_string()),
+            ("process.get(\"\")", "none".to_string()),
+            ("process.get(\"A=B\")", "none".to_string()),
+        ] {
+            assert_eq!(run_source(src).unwrap().to_string(), expected, "{src}");
+        }
+        let error = run_source("process.get(1)").unwrap_err();
+        assert_eq!(
+            error.message,
+            "process.get expects name to be a string, got a number"
+        );
+    }
+
+    #[test]
+    fn test_evaluate_string_concatenation_expression() {
+        let src = "\"hello, \" + \"world!\"";
+        let expected_value = Value::String("hello, world!".to_string().into());
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+    }
+
+    #[test]
+    fn test_evaluate_unary_expression() {
+        let src = "--+-5";
+        let expected_value = Value::Number(-5.);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+
+        let src = "!true";
+        let expected_value = Value::Boolean(false);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+    }
+
+    #[test]
+    fn test_evaluate_access_expression() {
+        let src = "{wip: true}.wip";
+        let expected_value = Value::Boolean(true);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+
+        let src = "let config = {server: {http: {port: 80}}, pairs: ((1, 2), 3)}
+let result = (config.server.http.port, (config).server.http, config.pairs.0.1, config.server.http.port.abs) result";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val.to_string(), "(80, {\n  port : 80\n}, 2, 80)");
+
+        let src = "let config = {server: {}} config.server.http.port";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let error = evaluate(program, None).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Can't access the property 'http' as it's not defined"
+        );
+        assert_eq!(error.text_span, TextSpan::new(40, 44));
+    }
+
+    #[test]
+    fn test_evaluate_access_on_none() {
+        for (src, message, text_span, context, note) in [
+            (
+                "let a = {b: none} a.b.c",
+                "Can't access the property 'c' as the value is none",
+                TextSpan::new(22, 23),
+                TextSpan::new(18, 23),
+                "the property 'b' is none, did an earlier lookup fail?",
+            ),
+            (
+                "let a = {b: none} a.b[0]",
+                "Can't index the value with '0' as it's none",
+                TextSpan::new(21, 24),
+                TextSpan::new(18, 24),
+                "the property 'b' is none, did an earlier lookup fail?",
+            ),
+            (
+                "let t = (1, none) t.1.0",
+                "Can't access the element '0' as the value is none",
+                TextSpan::new(22, 23),
+                TextSpan::new(18, 23),
+                "the property '1' is none, did an earlier lookup fail?",
+            ),
+            (
+                "let x = none x[\"k\"]",
+                "Can't index the value with 'k' as it's none",
+                TextSpan::new(14, 19),
+                TextSpan::new(13, 19),
+                "the value is none, did an earlier lookup fail?",
+            ),
+        ] {
+            let error = run_source(src).unwrap_err();
+            assert_eq!(error.message, message, "{src}");
+            assert_eq!(error.text_span, text_span, "{src}");
+            assert_eq!(error.context, Some(context), "{src}");
+            assert_eq!(error.notes, vec![note.to_string()], "{src}");
+        }
+    }
+
+    #[test]
+    fn test_evaluate_keyword_keys() {
+        let src =
+            "let config = {let: 1, none: \"missing\"} let pair = (config.none, config.let) pair";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val.to_string(), "(missing, 1)");
+
+        let src = "let config = {none: 2, true: 3} config.none * config.true";
+        let expected_value = Value::Number(6.0);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+    }
+
+    #[test]
+    fn test_evaluate_object_expression() {
+        let src = "{name: \"fns\", paradigm: \"functional\", wip: true}";
+        let expected_value = Value::Object(HashMap::from_iter(vec![
+            (
+                "name".to_string(),
+                Box::new(Value::String("fns".to_string().into())),
+            ),
+            (
+                "paradigm".to_string(),
+                Box::new(Value::String("functional".to_string().into())),
+            ),
+            ("wip".to_string(), Box::new(Value::Boolean(true))),
+        ]));
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+    }
+
+    #[test]
+    fn test_evaluate_string_expression() {
+        let src = "\"hello, world!\"";
+        let expected_value = Value::String("hello, world!".to_string().into());
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+    }
+
+    #[test]
+    fn test_evaluate_numeric_expression() {
+        let src = "5";
+        let expected_value = Value::Number(5.);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+    }
+
+    #[test]
+    fn test_evaluate_boolean_true_expression() {
+        let src = "true";
+        let expected_value = Value::Boolean(true);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+    }
+
+    #[test]
+    fn test_evaluate_boolean_false_expression() {
+        let src = "false";
+        let expected_value = Value::Boolean(false);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+    }
+
+    #[test]
+    fn test_evaluate_none_expression() {
+        let src = "none";
+        let expected_value = Value::None;
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+    }
+
+    #[test]
+    fn test_evaluate_function_call_expression() {
+        let src = "let add = fn(a, b) { let sum = a + b  sum }";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (_, env) = evaluate(program, None).unwrap();
+
+        let src = "add(2, 3)";
+        let expected_value = Value::Number(5.);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, env) = evaluate(program, Some(env)).unwrap();
+        assert_eq!(val, expected_value);
+
+        let src = "add(2)";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        assert!(evaluate(program, Some(env)).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_operator_hook_addition() {
+        let tokens = tokenize(VECTOR).unwrap();
+        let program = parse(tokens).unwrap();
+        let (_, env) = evaluate(program, None).unwrap();
+
+        let src = "let c = vector(1, 2) + vector(3, 4)";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (_, env) = evaluate(program, Some(env)).unwrap();
+
+        let src = "c.x";
+        let expected_value = Value::Number(4.);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, env) = evaluate(program, Some(env)).unwrap();
+        assert_eq!(val, expected_value);
+
+        let src = "c.y";
+        let expected_value = Value::Number(6.);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, Some(env)).unwrap();
+        assert_eq!(val, expected_value);
+    }
+
+    #[test]
+    fn test_evaluate_binary_operand_errors() {
+        for (src, message, text_span, context) in [
+            (
+                "1 + true",
+                "Can't use '+' with '1' and 'true' as the right side is a boolean",
+                TextSpan::new(4, 8),
+                Some(TextSpan::new(0, 8)),
+            ),
+            (
+                "none - 1",
+                "Can't use '-' with 'none' and '1' as the left side is a none",
+                TextSpan::new(0, 4),
+                Some(TextSpan::new(0, 8)),
+            ),
+            (
+                "1 + \"a\"",
+                "Can't use '+' with '1' and 'a' as they're a number and a string",
+                TextSpan::new(0, 7),
+                None,
+            ),
+        ] {
+            let tokens = tokenize(src).unwrap();
+            let program = parse(tokens).unwrap();
+            let error = evaluate(program, None).unwrap_err();
+            assert_eq!(error.message, message);
+            assert_eq!(error.text_span, text_span);
+            assert_eq!(error.context, context);
+        }
+    }
+
+    #[test]
+    fn test_evaluate_identity_comparison() {
+        let src = "
+let point = fn(x, label) { {x: x, label: label, __eq: fn(a, b) { a.x == b.x }} }
+let a = point(1, \"a\")
+let b = point(1, \"b\")
+let alias = a
+let result = (a == b, a === b, a !== b, a === alias, 1 === 1, 1 === \"1\", point === point)
+result";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(
+            val.to_string(),
+            "(true, false, true, true, true, false, true)"
+        );
+    }
+
+    #[test]
+    fn test_evaluate_operator_hook_equality() {
+        let tokens = tokenize(VECTOR).unwrap();
+        let program = parse(tokens).unwrap();
+        let (_, env) = evaluate(program, None).unwrap();
+
+        let src = "vector(1, 2) == vector(1, 2)";
+        let expected_value = Value::Boolean(true);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, env) = evaluate(program, Some(env)).unwrap();
+        assert_eq!(val, expected_value);
+
+        let src = "vector(1, 2) != vector(2, 1)";
+        let expected_value = Value::Boolean(true);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, Some(env)).unwrap();
+        assert_eq!(val, expected_value);
+    }
+
+    #[test]
+    fn test_evaluate_operator_hook_display() {
+        let tokens = tokenize(VECTOR).unwrap();
+        let program = parse(tokens).unwrap();
+        let (_, env) = evaluate(program, None).unwrap();
+
+        let src = "vector(1, 2.5)";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, mut env) = evaluate(program, Some(env)).unwrap();
+        let output = display(&val, TextSpan::new(0, src.len()), &mut env).unwrap();
+        assert_eq!(output, "vector(1, 2.5)");
+    }
+
+    #[test]
+    fn test_evaluate_display_limits() {
+        let src = "let v = {} let i = 0 while i < 20 { v = {inner: v, hidden: {__display: fn(o) { \"shown\" }}} i = i + 1 } v";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, mut env) = evaluate(program, None).unwrap();
+        env.printer.max_depth = 1;
+        let output = display(&val, TextSpan::new(0, src.len()), &mut env).unwrap();
+        let mut lines: Vec<&str> = output.lines().collect();
+        lines.sort();
+        assert_eq!(lines, vec!["  hidden : shown", "  inner : {…}", "{", "}"]);
+
+        env.printer.max_depth = 20;
+        env.printer.max_length = 50;
+        let output = display(&val, TextSpan::new(0, src.len()), &mut env).unwrap();
+        assert_eq!(output.chars().count(), 50);
+        assert!(output.ends_with('…'));
+    }
+
+    #[test]
+    fn test_evaluate_operator_hook_fallback() {
+        let src = "{x: 1} + 1";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        assert!(evaluate(program, None).is_err());
+
+        let src = "{__add: 1} + 1";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        assert!(evaluate(program, None).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_set_expression() {
+        let src = "set(1, 2, 2, 3)";
+        let expected_value = Value::Set(BTreeSet::from_iter(vec![
+            SetElement::Number(1.),
+            SetElement::Number(2.),
+            SetElement::Number(3.),
+        ]));
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+        assert_eq!(val.to_string(), "{1, 2, 3}");
+
+        let src = "set(3, 1, 2) == set(1, 2, 3)";
+        let expected_value = Value::Boolean(true);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+
+        let src = "set(1, {a: 1})";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        assert!(evaluate(program, None).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_set_builtins() {
+        let src = "let s = sets.add(set(\"a\", none), true)";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (_, env) = evaluate(program, None).unwrap();
+
+        let src = "s";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, env) = evaluate(program, Some(env)).unwrap();
+        assert_eq!(val.to_string(), "{none, true, a}");
+
+        let src = "sets.has(s, \"a\") && !sets.has(sets.remove(s, \"a\"), \"a\")";
+        let expected_value = Value::Boolean(true);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, env) = evaluate(program, Some(env)).unwrap();
+        assert_eq!(val, expected_value);
+
+        let src = "len(sets.union(set(1, 2), set(2, 3)))";
+        let expected_value = Value::Number(3.);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, env) = evaluate(program, Some(env)).unwrap();
+        assert_eq!(val, expected_value);
+
+        let src = "sets.intersection(set(1, 2), set(2, 3))";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, env) = evaluate(program, Some(env)).unwrap();
+        assert_eq!(val.to_string(), "{2}");
+
+        let src = "sets.add(1, 2)";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        assert!(evaluate(program, Some(env)).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_tuple_expression() {
+        let src = "(1, \"a\", (true,))";
+        let expected_value = Value::Tuple(vec![
+            Value::Number(1.),
+            Value::String("a".to_string().into()),
+            Value::Tuple(vec![Value::Boolean(true)]),
+        ]);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+        assert_eq!(val.to_string(), "(1, a, (true,))");
+
+        let src = "(1 + 2) * 3";
+        let expected_value = Value::Number(9.);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+
+        let src = "(1, 2) == (1, 2)";
+        let expected_value = Value::Boolean(true);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+    }
+
+    #[test]
+    fn test_evaluate_tuple_access_expression() {
+        let src = "let pair = (5, \"five\")";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (_, env) = evaluate(program, None).unwrap();
+
+        let src = "pair.1";
+        let expected_value = Value::String("five".to_string().into());
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, env) = evaluate(program, Some(env)).unwrap();
+        assert_eq!(val, expected_value);
+
+        let src = "pair.2";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        assert!(evaluate(program, Some(env)).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_destructuring_statement() {
+        let src = "const (number, name) = (5, \"five\")";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (_, env) = evaluate(program, None).unwrap();
+
+        let src = "name";
+        let expected_value = Value::String("five".to_string().into());
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, env) = evaluate(program, Some(env)).unwrap();
+        assert_eq!(val, expected_value);
+
+        let src = "number = 6";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        assert!(evaluate(program, Some(env)).is_err());
+
+        let src = "let (a, b) = (1, 2, 3)";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        assert!(evaluate(program, None).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_regex_builtins() {
+        let src = "regex.is_match(\"^\\\\d+$\", \"2024\")";
+        let expected_value = Value::Boolean(true);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+
+        let src = "regex.find(\"[a-z]+\", \"42 apples\")";
+        let expected_value = Value::String("apples".to_string().into());
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+
+        let src = "regex.find_all(\"(\\\\w+)=(\\\\d+)?\", \"a=1 b= c=3\")";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val.to_string(), "[(a, 1), (b, none), (c, 3)]");
+        assert_eq!(
+            run_source("type(regex.find_all(\"\\\\d\", \"a1b2\"))")
+                .unwrap()
+                .to_string(),
+            "array"
+        );
+        // Matching runs without recursion, so long inputs don't overflow the stack.
+        assert_eq!(
+            run_source("strings.len(regex.find(\".*\", strings.repeat(\"a\", 20000)))").unwrap(),
+            Value::Integer(20000)
+        );
+        // Each path a search tries is a step, so one trying exponentially many stops at
+        // the limit of steps, or when evaluation is interrupted.
+        let src = "regex.is_match(\"(a|a)*b\", \"aaaaaaaaaaaaaaaaaaaaaaaaaaaaa\")";
+        let mut environment = Environment::new(None);
+        environment.limits.max_steps = 10_000;
+        let error = run_source_with(src, &mut environment).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Can't finish 'regex.is_match' within the limit of 10000 steps"
+        );
+        assert_eq!(error.text_span, TextSpan::new(0, src.len()));
+        let mut environment = Environment::new(None);
+        let interrupted = environment.interrupted.clone();
+        let interrupter = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            interrupted.store(true, Ordering::SeqCst);
+        });
+        let error = run_source_with(src, &mut environment).unwrap_err();
+        interrupter.join().unwrap();
+        assert_eq!(error.message, "Evaluation was interrupted");
+
+        let src = "regex.replace(\"(\\\\w+)@(\\\\w+)\", \"me@home\", \"$2 at $1\")";
+        let expected_value = Value::String("home at me".to_string().into());
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+    }
+
+    #[test]
+    fn test_evaluate_regex_invalid_pattern() {
+        let src = "regex.find(\"a(b\", \"ab\")";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let error = evaluate(program, None).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Can't compile the pattern 'a(b' at position 3: unclosed group"
+        );
+        assert_eq!(error.text_span, TextSpan::new(0, src.len()));
+    }
+
+    #[test]
+    fn test_evaluate_hash_builtins() {
+        let src = "hash.fnv(\"fns\")";
+        let expected_value = Value::Number(3052785746.);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+
+        let src = "hash.sha256(\"abc\")";
+        let expected_value = Value::String(
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+                .to_string()
+                .into(),
+        );
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+
+        let src = "hash.sha256(5)";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let error = evaluate(program, None).unwrap_err();
+        assert_eq!(error.text_span, TextSpan::new(0, src.len()));
+    }
+
+    #[test]
+    fn test_evaluate_encoding_builtins() {
+        let src = "encoding.base64_decode(encoding.base64_encode(\"fns: fun\"))";
+        let expected_value = Value::String("fns: fun".to_string().into());
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+
+        let src = "encoding.hex_decode(encoding.hex_encode(\"ünïcode\"))";
+        let expected_value = Value::String("ünïcode".to_string().into());
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+
+        let src = "encoding.base64_decode(\"not base64!\")";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let error = evaluate(program, None).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Can't decode the input of 'encoding.base64_decode', invalid character ' ' at position 3"
+        );
+        assert_eq!(error.text_span, TextSpan::new(0, src.len()));
+
+        let src = "encoding.hex_decode(\"ff\")";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let error = evaluate(program, None).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Can't decode the input of 'encoding.hex_decode' as it isn't valid UTF-8 text"
+        );
+    }
+
+    #[test]
+    fn test_evaluate_number_formatting() {
+        for (src, expected) in [
+            ("numbers.to_fixed(2.345, 2)", "2.35"),
+            ("numbers.to_fixed(-2.5, 0)", "-3"),
+            (
+                "string(1234567.5, {decimals: 0, separator: \",\"})",
+                "1,234,568",
+            ),
+            ("string(-0.5, {decimals: 0})", "-1"),
+            ("string((1, \"a\"))", "(1, a)"),
+            ("format(\"{} and {}\", 0.1 + 0.2, \"text\")", "0.3 and text"),
+            (
+                "format(\"{:.3}|{:,}|{:,.1}\", 2.0005, 12345, -9876.55)",
+                "2.001|12,345|-9,876.6",
+            ),
+            ("format(\"{{{}}}\", 1)", "{1}"),
+        ] {
+            let tokens = tokenize(src).unwrap();
+            let program = parse(tokens).unwrap();
+            let (val, _) = evaluate(program, None).unwrap();
+            assert_eq!(val, Value::String(expected.to_string().into()), "{src}");
+        }
+
+        for (src, message) in [
+            (
+                "format(\"{:.x}\", 1)",
+                "Can't use the template '{:.x}' with 'format', invalid placeholder '{:.x}' at position 0",
+            ),
+            (
+                "format(\"{} {}\", 1)",
+                "Can't fill the template '{} {}' as it has 2 placeholders for 1 value",
+            ),
+            (
+                "format(\"{:.2}\", \"a\")",
+                "Can't use 'a' with 'format' as it's not a number",
+            ),
+            (
+                "string(1, {decimals: 1.5})",
+                "Can't show 1.5 decimals with 'string' as it's not a whole number from 0 to 100",
+            ),
+            ("string(1, {digits: 1})", "Can't use the option 'digits' with 'string'"),
+        ] {
+            let tokens = tokenize(src).unwrap();
+            let program = parse(tokens).unwrap();
+            let error = evaluate(program, None).unwrap_err();
+            assert_eq!(error.message, message);
+            assert_eq!(error.text_span, TextSpan::new(0, src.len()));
+        }
+    }
+
+    #[test]
+    fn test_evaluate_runtime_limits() {
+        let mut env = Environment::new(None);
+        env.limits.max_call_depth = 10;
+        env.limits.max_string_length = 8;
+        let error = run_source_with("fn f(n) { f(n + 1) } f(0)", &mut env).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Can't call 'fn(n)' as calls are nested up to the limit of 10 already"
+        );
+        assert_eq!(error.text_span, TextSpan::new(10, 18));
+        let error =
+            run_source_with("let s = \"ab\" s = s + s s = s + s s = s + s", &mut env).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Can't make a string longer than the limit of 8 chars"
+        );
+        assert_eq!(error.text_span, TextSpan::new(37, 42));
+
+        env.limits.max_steps = 100;
+        let error = run_source_with("let i = 0 while true { i = i + 1 }", &mut env).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Can't evaluate more than the limit of 100 expressions"
+        );
+        // The steps are counted over everything run in the environment.
+        assert!(run_source_with("1", &mut env).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_long_chains() {
+        // Chains are evaluated in a loop, so they're only bounded by their own limit.
+        let src = vec!["1"; 500].join(" + ");
+        assert_eq!(run_source(&src).unwrap(), Value::Integer(500));
+        let src = format!("none ?? {} || 2 ?? 3", vec!["false"; 300].join(" && "));
+        assert_eq!(run_source(&src).unwrap(), Value::Integer(2));
+
+        // Each operator is still a step, the outer ones counted first.
+        let mut env = Environment::new(None);
+        env.limits.max_steps = 2;
+        let error = run_source_with("1 + 2 + 3 + 4", &mut env).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Can't evaluate more than the limit of 2 expressions"
+        );
+        assert_eq!(error.text_span, TextSpan::new(0, 5));
+        let mut env = Environment::new(None);
+        run_source_with("1 + 2 + 3 + 4", &mut env).unwrap();
+        assert_eq!(env.steps.get(), 7);
+    }
+
+    // The value or error of the source code along with the steps it took, evaluating
+    // every `+` when `naive` is set, which profiling does.
+    fn run_appending(
+        src: &str,
+        naive: bool,
+        limits: impl Fn(&mut Environment),
+    ) -> (Result<String, (String, TextSpan)>, usize) {
+        let mut env = Environment::new(None);
+        limits(&mut env);
+        if naive {
+            env.profiler = Some(Rc::new(RefCell::new(Profiler::default())));
+        }
+        let result = run_source_with(src, &mut env)
+            .map(|value| value.to_string())
+            .map_err(|error| (error.message, error.text_span));
+        (result, env.steps.get())
+    }
+
+    #[test]
+    fn test_evaluate_literals_share_their_value() {
+        let program = parse(tokenize("\"shared\"").unwrap()).unwrap();
+        let mut environment = Environment::new(None);
+        let first = evaluate_statement(&program[0], &mut environment).unwrap();
+        let second = evaluate_statement(&program[0], &mut environment).unwrap();
+        let (Value::String(first), Value::String(second)) = (first, second) else {
+            panic!("the literal should evaluate to a string");
+        };
+        assert!(Rc::ptr_eq(&first, &second));
+
+        // Appending to a string from a literal copies it rather than changing the literal.
+        let src = "fn f() { let s = \"ab\" s = s + \"c\" s } let result = (f(), f()) result";
+        assert_eq!(run_source(src).unwrap().to_string(), "(abc, abc)");
+    }
+
+    #[test]
+    fn test_evaluate_append_in_place() {
+        for src in [
+            "let p = {q: \"!\"} let s = \"\" let i = 0 while i < 50 { s = s + \"é\" + (i < 25 ? \"x\" : p.q) i = i + 1 } s",
+            "let s = \"a\" for c in \"bcd\" { s = s + c + s } s",
+            "let s = \"a\" let n = 1 while true { s = s + \"b\" + n }",
+            "let s = \"a\" while true { s = s + \"b\" + missing }",
+            "let s = 1 for i in [1, 2] { s = s + i } s",
+            "const s = \"a\" for i in [1] { s = s + \"b\" }",
+            "let s: string = \"a\" for i in [1] { s = s + \"b\" } s",
+            "let s = \"\" while true { s = s + \"ab\" }",
+        ] {
+            let limits = |env: &mut Environment| env.limits.max_string_length = 9;
+            assert_eq!(
+                run_appending(src, false, limits),
+                run_appending(src, true, limits),
+                "{src}"
+            );
+        }
+        let src = "let s = \"\" let i = 0 while true { s = s + \"a\" + \"b\" i = i + 1 }";
+        for max_steps in 40..50 {
+            let limits = |env: &mut Environment| env.limits.max_steps = max_steps;
+            assert_eq!(
+                run_appending(src, false, limits),
+                run_appending(src, true, limits),
+                "{max_steps} steps"
+            );
+        }
+
+        // Appending takes time linear in the length of the string, not quadratic.
+        let build = |pieces: usize| {
+            let src = format!(
+                "let piece = \"{}\" let s = \"\" let i = 0 while i < {pieces} {{ s = s + piece i = i + 1 }} len(s)",
+                "a".repeat(1_000)
+            );
+            let start = Instant::now();
+            let (result, _) = run_appending(&src, false, |_| {});
+            assert_eq!(result, Ok((pieces * 1_000).to_string()));
+            start.elapsed()
+        };
+        let short = (0..3).map(|_| build(256)).min().unwrap();
+        let long = (0..3).map(|_| build(1_024)).min().unwrap();
+        assert!(long < short * 10, "{short:?} for 256KB, {long:?} for 1MB");
+    }
+
+    #[test]
+    fn test_evaluate_conditional() {
+        let mut env = Environment::new(None);
+        let src = "fn sign(n) { n > 0 ? \"positive\" : n < 0 ? \"negative\" : \"zero\" } (sign(2), sign(-2), sign(0))";
+        let value = run_source_with(src, &mut env).unwrap();
+        assert_eq!(value.to_string(), "(positive, negative, zero)");
+
+        // Only the chosen branch runs.
+        let src = "let calls = 0 fn count() { calls = calls + 1 } let a = true ? 1 : count() let b = false ? count() : 2 let result = (a, b, calls) result";
+        let value = run_source_with(src, &mut env).unwrap();
+        assert_eq!(value.to_string(), "(1, 2, 0)");
+
+        let src = "let b = 1 ? 2 : 3";
+        let error = run_source_with(src, &mut env).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Can't use '1' as a condition as it's a number instead of a boolean"
+        );
+        assert_eq!(error.text_span, TextSpan::new(8, 9));
+        assert_eq!(error.context, Some(TextSpan::new(8, 17)));
+    }
+
+    #[test]
+    fn test_evaluate_interrupted() {
+        let src = "let a = 5";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (_, env) = evaluate(program, None).unwrap();
+
+        env.interrupted.store(true, Ordering::SeqCst);
+        let src = "let b = a + 1";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let error = evaluate(program, Some(env.clone())).unwrap_err();
+        assert_eq!(error.message, "Evaluation was interrupted");
+
+        env.clear_interrupt();
+        let src = "a";
+        let expected_value = Value::Number(5.);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, Some(env)).unwrap();
+        assert_eq!(val, expected_value);
+    }
+
+    #[test]
+    fn test_evaluate_interrupted_shared_with_children() {
+        let env = Environment::new(None);
+        let child = Environment::new(Some(env.clone()));
+        env.interrupted.store(true, Ordering::SeqCst);
+        assert!(child.is_interrupted());
+    }
+
+    #[test]
+    fn test_evaluate_function_statement() {
+        let src = "fn square(x) { x * x } square(4)";
+        let expected_value = Value::Number(16.);
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, expected_value);
+    }
+
+    #[test]
+    fn test_evaluate_pseudo_properties() {
+        let src = "
+let word = \"Hello\"
+let numbers = set(1, 2)
+let result = (word.length, word.upper, word.lower, (3.7).floor, (3.2).ceil, (-2.5).round, (-4).abs, (1, 2, 3).length, numbers.length)
+result";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val.to_string(), "(5, HELLO, hello, 3, 4, -3, 4, 3, 2)");
+
+        let src = "math.floor(3.7) == (3.7).floor";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, Value::Boolean(true));
+
+        let src = "\"hi\".size";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let error = evaluate(program, None).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Can't access the property 'size' of 'hi', the available ones are length, lower, upper"
+        );
+        assert_eq!(error.text_span, TextSpan::new(5, 9));
+        assert_eq!(error.context, Some(TextSpan::new(0, 9)));
+
+        let src = "true.length";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let error = evaluate(program, None).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Can't access the property 'length' of 'true' as it has no properties"
+        );
+    }
+
+    #[test]
+    fn test_evaluate_undefined_property_span() {
+        let src = "let config = { port: 80 } config.host";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let error = evaluate(program, None).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Can't access the property 'host' as it's not defined"
+        );
+        assert_eq!(error.text_span, TextSpan::new(33, 37));
+        assert_eq!(error.context, Some(TextSpan::new(26, 37)));
+    }
+
+    #[test]
+    fn test_evaluate_order() {
+        let sources = [
+            ("let a = 0 let result = (a = 1) + (a = a * 10) result", "11"),
+            (
+                "let a = 1 let result = (a = a * 2, a = a + 3, a) result",
+                "(2, 5, 5)",
+            ),
+            (
+                "let a = 0 let o = {x: a = a + 1, y: a = a * 10, x: a = a + 2} let result = (o.x, o.y, a) result",
+                "(12, 10, 12)",
+            ),
+            (
+                "let t = \"\" fn f(a, b, c) { a + b + c } f(t = t + \"a\", t = t + \"b\", t = t + \"c\")",
+                "aababc",
+            ),
+        ];
+        for (src, expected) in sources {
+            let tokens = tokenize(src).unwrap();
+            let program = parse(tokens).unwrap();
+            let (val, _) = evaluate(program, None).unwrap();
+            assert_eq!(val.to_string(), expected, "{src}");
+        }
+    }
 
     #[test]
-    fn test_evaluate_let_statement() {
-        let src = "let a = 2.71";
-        let expected_value = Value::None;
+    fn test_evaluate_closures() {
+        let src = "
+let make_counter = fn() { let n = 0 fn() { n = n + 1 } }
+let a = make_counter()
+let b = make_counter()
+a() a() b()
+let result = (a(), b()) result";
         let tokens = tokenize(src).unwrap();
         let program = parse(tokens).unwrap();
-        let (val, env) = evaluate(program, None).unwrap();
-        assert_eq!(val, expected_value);
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val.to_string(), "(3, 2)");
 
-        let src = "a";
-        let expected_value = Value::Number(2.71);
+        // Free variables are looked up where the function was created, not where it's called.
+        let src = "fn adder(x) { fn(y) { x + y } } let add2 = adder(2) fn call(f) { let x = 100 f(3) } call(add2)";
         let tokens = tokenize(src).unwrap();
         let program = parse(tokens).unwrap();
-        let (val, _) = evaluate(program, Some(env)).unwrap();
-        assert_eq!(val, expected_value);
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, Value::Number(5.));
+
+        let src = "let total = 0 fn add(n) { total = total + n } add(2) add(3) total";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, Value::Number(5.));
     }
 
     #[test]
-    fn test_evaluate_const_statement() {
-        let src = "let E = 2.71";
-        let expected_value = Value::None;
+    fn test_evaluate_while() {
+        let src = "let i = 0 let total = 0 while i < 5 { let next = i + 1 total = total + next i = next } let result = (i, total) result";
         let tokens = tokenize(src).unwrap();
         let program = parse(tokens).unwrap();
         let (val, env) = evaluate(program, None).unwrap();
-        assert_eq!(val, expected_value);
+        assert_eq!(val.to_string(), "(5, 15)");
+        // The `let`s of the body don't leak out of the loop.
+        assert_eq!(env.access("next"), None);
 
-        let src = "E";
-        let expected_value = Value::Number(2.71);
+        let src = "while false { a } 1";
         let tokens = tokenize(src).unwrap();
         let program = parse(tokens).unwrap();
-        let (val, _) = evaluate(program, Some(env)).unwrap();
-        assert_eq!(val, expected_value);
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val, Value::Number(1.));
+
+        let src = "let n = 3 while n { n = n - 1 }";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let error = evaluate(program, None).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Can't loop while '3' as it's a number instead of a boolean"
+        );
+        assert_eq!(error.text_span, TextSpan::new(16, 17));
     }
 
     #[test]
-    fn test_evaluate_assignment_expression_with_let() {
-        let src = "let a = 2.71";
-        let expected_value = Value::None;
+    fn test_evaluate_for() {
+        let src =
+            "let total = 0 for n in (1, 2, 3) { let double = n * 2 total = total + double } total";
         let tokens = tokenize(src).unwrap();
         let program = parse(tokens).unwrap();
         let (val, env) = evaluate(program, None).unwrap();
-        assert_eq!(val, expected_value);
+        assert_eq!(val, Value::Number(12.));
+        // Neither the loop variable nor the `let`s of the body leak out of the loop.
+        assert_eq!(env.access("n"), None);
+        assert_eq!(env.access("double"), None);
 
-        let src = "a = 5";
-        let expected_value = Value::Number(5.);
+        let src = "let s = \"\" for c in \"héllo\" { s = c + s } for k in {b: 1, a: 2} { s = s + k } for e in set(\"y\", \"x\") { s = s + e } s";
         let tokens = tokenize(src).unwrap();
         let program = parse(tokens).unwrap();
-        let (val, _) = evaluate(program, Some(env)).unwrap();
-        assert_eq!(val, expected_value);
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val.to_string(), "olléhabxy");
+
+        let src = "let n = 3 for x in n {}";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let error = evaluate(program, None).unwrap_err();
+        assert_eq!(error.message, "Can't iterate over '3' as it's a number");
+        assert_eq!(error.text_span, TextSpan::new(19, 20));
     }
 
     #[test]
-    fn test_evaluate_assignment_expression_with_const() {
-        let src = "const a = 2.71";
-        let expected_value = Value::None;
+    fn test_evaluate_integers() {
+        for (src, expected) in [
+            ("3 / 2", Value::Integer(1)),
+            ("-7 / 2", Value::Integer(-3)),
+            ("3.0 / 2", Value::Number(1.5)),
+            ("2 * 3 - 1", Value::Integer(5)),
+            ("1 + 0.5", Value::Number(1.5)),
+            // Integers stay exact past where floats have gaps between whole numbers.
+            (
+                "9007199254740993 + 0",
+                Value::Integer(9_007_199_254_740_993),
+            ),
+            (
+                "9007199254740993 == 9007199254740992.0",
+                Value::Boolean(false),
+            ),
+            ("1 == 1.0", Value::Boolean(true)),
+            ("1 != 1.0", Value::Boolean(false)),
+            ("2 > 1.5", Value::Boolean(true)),
+            ("-(2)", Value::Integer(-2)),
+            ("math.floor(2.7)", Value::Integer(2)),
+            ("len(\"abc\")", Value::Integer(3)),
+            ("[10, 20][1.0]", Value::Integer(20)),
+        ] {
+            let value = run_source(src).unwrap();
+            assert_eq!(value, expected, "{src}");
+            assert_eq!(
+                matches!(value, Value::Integer(_)),
+                matches!(expected, Value::Integer(_)),
+                "{src}"
+            );
+        }
+        for (src, expected) in [
+            ("4 / 2", "2"),
+            ("4.0 / 2", "2.0"),
+            ("(1, 1.5, 2.0)", "(1, 1.5, 2.0)"),
+        ] {
+            assert_eq!(run_source(src).unwrap().to_string(), expected, "{src}");
+        }
+
+        // Integer arithmetic overflowing is an error rather than a float.
+        for (src, message, text_span) in [
+            (
+                "9223372036854775807 + 1",
+                "Can't compute the result as it's out of the range of integers",
+                TextSpan::new(0, 23),
+            ),
+            (
+                "let big = 3037000500 big * big",
+                "Can't compute the result as it's out of the range of integers",
+                TextSpan::new(21, 30),
+            ),
+            (
+                "let min = -9223372036854775807 - 1 let result = -min",
+                "Can't negate '-9223372036854775808' as it's out of the range of integers",
+                TextSpan::new(48, 52),
+            ),
+            ("1 / 0", "Can't divide by 0", TextSpan::new(0, 5)),
+        ] {
+            let error = run_source(src).unwrap_err();
+            assert_eq!(error.message, message, "{src}");
+            assert_eq!(error.text_span, text_span, "{src}");
+        }
+        assert_eq!(
+            run_source("9223372036854775807.0 + 1").unwrap(),
+            Value::Number(9_223_372_036_854_775_808.)
+        );
+        let error = run_source("9223372036854775808").unwrap_err();
+        assert_eq!(
+            error.message,
+            "Can't parse '9223372036854775808' as an integer as it's too large"
+        );
+    }
+
+    #[test]
+    fn test_evaluate_non_finite_numbers() {
+        for (src, expected) in [
+            ("1.0 / 0", "inf"),
+            ("-1 / 0.0", "-inf"),
+            ("0.0 / 0", "NaN"),
+            ("math.inf == 1.0 / 0", "true"),
+            ("math.inf > 9223372036854775807", "true"),
+            ("-math.inf", "-inf"),
+            ("math.inf - math.inf", "NaN"),
+            // NaN is unequal to everything, itself included.
+            ("math.nan == math.nan", "false"),
+            ("math.nan != math.nan", "true"),
+            ("let n = 0.0 / 0 n == n", "false"),
+            ("(math.nan < 1, math.nan >= 1)", "(false, false)"),
+            (
+                "(math.is_nan(0.0 / 0), math.is_nan(math.inf), math.is_nan(1))",
+                "(true, false, false)",
+            ),
+            (
+                "(math.is_finite(1.5), math.is_finite(2), math.is_finite(-1.0 / 0), math.is_finite(math.nan))",
+                "(true, true, false, false)",
+            ),
+        ] {
+            assert_eq!(run_source(src).unwrap().to_string(), expected, "{src}");
+        }
+        // Integers have no infinity to divide by 0 to.
+        for src in ["1 / 0", "0 / 0", "let n = -5 n / (2 - 2)"] {
+            let error = run_source(src).unwrap_err();
+            assert_eq!(error.message, "Can't divide by 0", "{src}");
+        }
+        let error = run_source("math.is_nan(\"1\")").unwrap_err();
+        assert_eq!(
+            error.message,
+            "math.is_nan expects x to be a number, got a string"
+        );
+    }
+
+    #[test]
+    fn test_evaluate_for_over_object_under_mutation() {
+        for (body, visited, object) in [
+            // Added keys aren't visited, even ones sorting after the current one.
+            ("o.z = 3", "ab", "{a: 1, b: 2, z: 3}"),
+            // Removing the current key leaves the rest of the loop alone.
+            ("o = objects.remove(o, k)", "ab", "{}"),
+            // Keys removed before their turn are still visited.
+            ("o = objects.remove(o, \"b\")", "ab", "{a: 1}"),
+        ] {
+            let src = format!(
+                "let o = {{a: 1, b: 2}} let s = \"\" for k in o {{ s = s + k {body} }} let result = (s, o) result"
+            );
+            let expected = run_source(&format!("({visited:?}, {object})")).unwrap();
+            assert_eq!(run_source(&src).unwrap(), expected, "{body}");
+        }
+        // Looking a removed key up is the same error as for any missing key.
+        let error =
+            run_source("let o = {a: 1, b: 2} for k in o { o = objects.remove(o, \"b\") o[k] }")
+                .unwrap_err();
+        assert_eq!(
+            error.message,
+            "Can't access the property 'b' as it's not defined"
+        );
+        assert_eq!(error.text_span, TextSpan::new(62, 65));
+    }
+
+    #[test]
+    fn test_evaluate_array() {
+        let src = "let a = 1 let xs = [a, [a + 1, \"b\"], {k: [true]}, (none,),] xs";
         let tokens = tokenize(src).unwrap();
         let program = parse(tokens).unwrap();
-        let (val, env) = evaluate(program, None).unwrap();
-        assert_eq!(val, expected_value);
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val.to_string(), "[1, [2, b], {\n  k : [true]\n}, (none,)]");
+        assert_eq!(val.type_name(), "array");
 
-        let src = "a = 5";
+        for (src, expected) in [
+            ("[1, 2] == [1, 2]", true),
+            ("[[1], {a: [2]}] == [[1], {a: [2]}]", true),
+            ("[1, 2] == [2, 1]", false),
+            ("[1, 2] == (1, 2)", false),
+            ("[] == []", true),
+        ] {
+            let tokens = tokenize(src).unwrap();
+            let program = parse(tokens).unwrap();
+            let (val, _) = evaluate(program, None).unwrap();
+            assert_eq!(val, Value::Boolean(expected), "{src}");
+        }
+
+        // Elements are evaluated left to right.
+        let src = "let a = 1 let xs = [a = a * 10, a = a + 1] xs";
         let tokens = tokenize(src).unwrap();
         let program = parse(tokens).unwrap();
-        assert!(evaluate(program, Some(env)).is_err());
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val.to_string(), "[10, 11]");
     }
 
     #[test]
-    fn test_evaluate_binary_arithmetic_expression() {
-        let src = "5 + 5 * 2 / 5 - 2";
-        let expected_value = Value::Number(5.);
+    fn test_evaluate_index() {
+        let src =
+            "let matrix = [[1, 2], [3, 4]] let points = [{x: 5}] let i = 0 let o = {key: \"v\"}
+let result = (matrix[1][0], matrix[i][i + 1], points[i].x, o[\"k\" + \"ey\"], (7, 8)[1]) result";
         let tokens = tokenize(src).unwrap();
         let program = parse(tokens).unwrap();
         let (val, _) = evaluate(program, None).unwrap();
-        assert_eq!(val, expected_value);
+        assert_eq!(val.to_string(), "(3, 2, 5, v, 8)");
+
+        for (src, expected_message, expected_span) in [
+            (
+                "let a = [1, 2] a[2]",
+                "Can't access the element '2' as the array has 2 element(s)",
+                TextSpan::new(16, 19),
+            ),
+            (
+                "let a = [1, 2] a[0.5]",
+                "Can't index the array with '0.5' as it's not a whole number",
+                TextSpan::new(16, 21),
+            ),
+            (
+                "let a = [1, 2] a[-1]",
+                "Can't access the element '-1' as it's negative",
+                TextSpan::new(16, 20),
+            ),
+            (
+                "let o = {a: 1} o[\"b\"]",
+                "Can't access the property 'b' as it's not defined",
+                TextSpan::new(16, 21),
+            ),
+            (
+                "let o = {a: 1} o[0]",
+                "Can't index the object with '0' as it's a number",
+                TextSpan::new(16, 19),
+            ),
+            (
+                "let n = 1 n[0]",
+                "Can't index '1' as it's a number",
+                TextSpan::new(10, 14),
+            ),
+        ] {
+            let tokens = tokenize(src).unwrap();
+            let program = parse(tokens).unwrap();
+            let error = evaluate(program, None).unwrap_err();
+            assert_eq!(error.message, expected_message);
+            assert_eq!(error.text_span, expected_span);
+        }
     }
 
     #[test]
-    fn test_evaluate_binary_comparison_expression() {
-        let src = "5 > 5";
-        let expected_value = Value::Boolean(false);
+    fn test_evaluate_path_assignment() {
+        let src = "let o = {a: {b: 1}, xs: [1, [2, 3]]} let copy = o let i = 1
+o.a.b = 10 o[\"c\"] = 4 o.xs[i][0] = 20 o.a[\"d\"] = o.xs[0] = 5
+let result = (o.a.b, o.c, o.xs, o.a.d, copy.a.b) result";
         let tokens = tokenize(src).unwrap();
         let program = parse(tokens).unwrap();
         let (val, _) = evaluate(program, None).unwrap();
-        assert_eq!(val, expected_value);
+        assert_eq!(val.to_string(), "(10, 4, [5, [20, 3]], 5, 1)");
 
-        let src = "5 < 5";
-        let expected_value = Value::Boolean(false);
+        // Functions see the variable changed, as they do for plain assignments.
+        let src =
+            "let counts = {n: 0} fn bump() { counts.n = counts.n + 1 } bump() bump() counts.n";
         let tokens = tokenize(src).unwrap();
         let program = parse(tokens).unwrap();
         let (val, _) = evaluate(program, None).unwrap();
-        assert_eq!(val, expected_value);
+        assert_eq!(val, Value::Number(2.));
 
-        let src = "5 >= 5";
-        let expected_value = Value::Boolean(true);
+        for (src, expected_message, expected_span) in [
+            (
+                "let a = {b: none} a.b.c = 1",
+                "Can't assign to the property 'c' of 'none' as it's a none",
+                TextSpan::new(22, 23),
+            ),
+            (
+                "let a = {} a.b.c = 1",
+                "Can't access the property 'b' as it's not defined",
+                TextSpan::new(13, 14),
+            ),
+            (
+                "let a = [1] a[1] = 2",
+                "Can't access the element '1' as the array has 1 element(s)",
+                TextSpan::new(13, 16),
+            ),
+            (
+                "let a = (1, 2) a[0] = 2",
+                "Can't assign to an element of '(1, 2)' as it's a tuple",
+                TextSpan::new(15, 19),
+            ),
+            (
+                "const a = {b: 1} a.b = 2",
+                "Can't assign the variable 'a' as it's a constant",
+                TextSpan::new(17, 24),
+            ),
+            (
+                "b.c = 2",
+                "Can't assign to the variable 'b' as it's not defined",
+                TextSpan::new(0, 7),
+            ),
+        ] {
+            let tokens = tokenize(src).unwrap();
+            let program = parse(tokens).unwrap();
+            let error = evaluate(program, None).unwrap_err();
+            assert_eq!(error.message, expected_message);
+            assert_eq!(error.text_span, expected_span);
+        }
+
+        // A failed assignment leaves the variable as it was.
+        let mut environment = Environment::new(None);
+        run_source_with("let a = {b: {c: 1}, d: 2}", &mut environment).unwrap();
+        run_source_with("a.d = 3 a.b.x.y = 4", &mut environment).unwrap_err();
+        assert_eq!(
+            run_source_with("let result = (a.d, a.b) result", &mut environment)
+                .unwrap()
+                .to_string(),
+            "(2, {\n  c : 1\n})"
+        );
+    }
+
+    #[test]
+    fn test_evaluate_block() {
+        let src = "let a = 1 let b = { let a = 10 let c = a + 1 c * 2 } let result = (a, b) result";
         let tokens = tokenize(src).unwrap();
         let program = parse(tokens).unwrap();
-        let (val, _) = evaluate(program, None).unwrap();
-        assert_eq!(val, expected_value);
+        let (val, env) = evaluate(program, None).unwrap();
+        assert_eq!(val.to_string(), "(1, 22)");
+        assert_eq!(env.access("c"), None);
 
-        let src = "5 <= 5";
-        let expected_value = Value::Boolean(true);
+        // Assignments reach the variables outside, and a block ending with a declaration
+        // is none.
+        let src = "let a = 1 let b = { a = 2 let d = 3 } let result = (a, b) result";
         let tokens = tokenize(src).unwrap();
         let program = parse(tokens).unwrap();
         let (val, _) = evaluate(program, None).unwrap();
-        assert_eq!(val, expected_value);
+        assert_eq!(val.to_string(), "(2, none)");
     }
 
     #[test]
-    fn test_evaluate_binary_equality_expression() {
-        let src = "5 == 5 != 5";
-        let expected_value = Value::Boolean(false);
-        let tokens = tokenize(src).unwrap();
-        let program = parse(tokens).unwrap();
-        let (val, _) = evaluate(program, None).unwrap();
-        assert_eq!(val, expected_value);
+    fn test_evaluate_unary_errors() {
+        for (src, message, text_span, context) in [
+            (
+                "!5",
+                "Can't use '!' with '5' as '!' expects a boolean, got a number",
+                TextSpan::new(1, 2),
+                TextSpan::new(0, 2),
+            ),
+            (
+                "-\"hi\"",
+                "Can't use '-' with 'hi' as '-' expects a number, got a string",
+                TextSpan::new(1, 5),
+                TextSpan::new(0, 5),
+            ),
+            (
+                "+ (1, 2)",
+                "Can't use '+' with '(1, 2)' as '+' expects a number, got a tuple",
+                TextSpan::new(2, 8),
+                TextSpan::new(0, 8),
+            ),
+            (
+                "!!none",
+                "Can't use '!' with 'none' as '!' expects a boolean, got a none",
+                TextSpan::new(2, 6),
+                TextSpan::new(1, 6),
+            ),
+        ] {
+            let tokens = tokenize(src).unwrap();
+            let program = parse(tokens).unwrap();
+            let error = evaluate(program, None).unwrap_err();
+            assert_eq!(error.message, message);
+            assert_eq!(error.text_span, text_span, "{src}");
+            assert_eq!(error.context, Some(context), "{src}");
+        }
     }
 
     #[test]
-    fn test_evaluate_binary_logical_expression() {
-        let src = "true && false || !true";
-        let expected_value = Value::Boolean(false);
-        let tokens = tokenize(src).unwrap();
+    fn test_evaluate_while_interrupted() {
+        let env = Environment::new(None);
+        let interrupted = env.interrupted.clone();
+        let interrupter = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            interrupted.store(true, Ordering::SeqCst);
+        });
+        let tokens = tokenize("while true {}").unwrap();
         let program = parse(tokens).unwrap();
-        let (val, _) = evaluate(program, None).unwrap();
-        assert_eq!(val, expected_value);
+        let error = evaluate(program, Some(env)).unwrap_err();
+        interrupter.join().unwrap();
+        assert_eq!(error.message, "Evaluation was interrupted");
     }
 
     #[test]
-    fn test_evaluate_string_concatenation_expression() {
-        let src = "\"hello, \" + \"world!\"";
-        let expected_value = Value::String("hello, world!".to_string());
+    fn test_evaluate_initializer_errors() {
+        let src = "let a = 1\nconst b = a *\n  c";
         let tokens = tokenize(src).unwrap();
         let program = parse(tokens).unwrap();
-        let (val, _) = evaluate(program, None).unwrap();
-        assert_eq!(val, expected_value);
+        let error = evaluate(program, None).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Can't access the variable 'c' as it's not defined"
+        );
+        assert_eq!(error.text_span, TextSpan::new(26, 27));
+        assert_eq!(error.context, Some(TextSpan::new(10, 27)));
+        assert_eq!(error.notes, vec!["while initializing 'b'"]);
+
+        // The initializer of a declaration inside a function called by another.
+        let src = "fn f() { let inner = none + 1 inner } let (x, y) = f()";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let error = evaluate(program, None).unwrap_err();
+        assert_eq!(
+            error.notes,
+            vec!["while initializing 'inner'", "while initializing 'x, y'"]
+        );
+        assert_eq!(error.context, Some(TextSpan::new(21, 29)));
     }
 
     #[test]
-    fn test_evaluate_unary_expression() {
-        let src = "--+-5";
-        let expected_value = Value::Number(-5.);
+    fn test_evaluate_errors_show_short_values() {
+        let src = format!("let s = \"{}\" s - 1", "a".repeat(500));
+        let tokens = tokenize(&src).unwrap();
+        let program = parse(tokens).unwrap();
+        let error = evaluate(program, None).unwrap_err();
+        assert_eq!(
+            error.message,
+            format!(
+                "Can't use '-' with '{}…' and '1' as the left side is a string",
+                "a".repeat(39)
+            )
+        );
+
+        let src = "let o = {a: 1, b: 2, c: 3, d: 4, e: 5, f: 6, g: 7, h: 8, i: 9, j: 10} o - 1";
         let tokens = tokenize(src).unwrap();
         let program = parse(tokens).unwrap();
-        let (val, _) = evaluate(program, None).unwrap();
-        assert_eq!(val, expected_value);
+        let error = evaluate(program, None).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Can't use '-' with '{…10 keys…}' and '1' as the left side is a object"
+        );
 
-        let src = "!true";
-        let expected_value = Value::Boolean(false);
+        let src = "let o = {b: \"it's\", a: (1, 2)} o()";
         let tokens = tokenize(src).unwrap();
         let program = parse(tokens).unwrap();
-        let (val, _) = evaluate(program, None).unwrap();
-        assert_eq!(val, expected_value);
+        let error = evaluate(program, None).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Can't call '{a: (1, 2), b: it\\'s}' as it's not a function"
+        );
     }
 
     #[test]
-    fn test_evaluate_access_expression() {
-        let src = "{wip: true}.wip";
-        let expected_value = Value::Boolean(true);
+    fn test_evaluate_clone_builtin() {
+        let src = "
+let original = {name: \"fns\", inner: {values: (1, set(2))}}
+let copy = clone(original)
+copy = {name: copy.name, inner: {}}
+let result = (original == clone(original), original.inner, copy.inner)
+result";
         let tokens = tokenize(src).unwrap();
         let program = parse(tokens).unwrap();
         let (val, _) = evaluate(program, None).unwrap();
-        assert_eq!(val, expected_value);
+        assert_eq!(val.to_string(), "(true, {\n  values : (1, {2})\n}, {})");
+
+        let src = "clone(1, 2)";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let error = evaluate(program, None).unwrap_err();
+        assert_eq!(error.message, "clone expects 1 argument (value), got 2");
     }
 
     #[test]
-    fn test_evaluate_object_expression() {
-        let src = "{name: \"fns\", paradigm: \"functional\", wip: true}";
-        let expected_value = Value::Object(HashMap::from_iter(vec![
+    fn test_evaluate_native_arguments() {
+        let cases = [
             (
-                "name".to_string(),
-                Box::new(Value::String("fns".to_string())),
+                "math.floor(1, 2)",
+                "math.floor expects 1 argument (x), got 2",
             ),
             (
-                "paradigm".to_string(),
-                Box::new(Value::String("functional".to_string())),
+                "regex.replace(\"a\", \"b\")",
+                "regex.replace expects 3 arguments (pattern, input, replacement), got 2",
             ),
-            ("wip".to_string(), Box::new(Value::Boolean(true))),
-        ]));
+            (
+                "sets.union(set())",
+                "sets.union expects 2 arguments (a, b), got 1",
+            ),
+            (
+                "help(1, 2)",
+                "help expects 0 to 1 arguments (value?), got 2",
+            ),
+            (
+                "math.abs(\"1\")",
+                "math.abs expects x to be a number, got a string",
+            ),
+            (
+                "env(1)",
+                "env expects include_builtins to be a boolean, got a number",
+            ),
+            (
+                "hash.fnv({})",
+                "hash.fnv expects input to be a string, got an object",
+            ),
+        ];
+        for (src, expected_message) in cases {
+            let tokens = tokenize(src).unwrap();
+            let program = parse(tokens).unwrap();
+            let error = evaluate(program, None).unwrap_err();
+            assert_eq!(error.message, expected_message);
+            assert_eq!(error.text_span.starting_index, 0);
+            assert_eq!(error.text_span.ending_index, src.len());
+        }
+
+        let src = "let result = (set(), set(1, 2), help() == help()) result";
         let tokens = tokenize(src).unwrap();
         let program = parse(tokens).unwrap();
         let (val, _) = evaluate(program, None).unwrap();
-        assert_eq!(val, expected_value);
+        assert_eq!(val.to_string(), "(set(), {1, 2}, true)");
     }
 
     #[test]
-    fn test_evaluate_string_expression() {
-        let src = "\"hello, world!\"";
-        let expected_value = Value::String("hello, world!".to_string());
+    fn test_evaluate_env_builtin() {
+        let src = "
+let a = 1
+let b = \"outer\"
+fn f(b) {
+    let c = true
+    env()
+}
+f(2)";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        match val {
+            Value::Object(object) => {
+                let mut keys: Vec<&String> = object.keys().collect();
+                keys.sort();
+                assert_eq!(keys, vec!["a", "b", "c", "f"]);
+                assert_eq!(*object["b"], Value::Number(2.0));
+                assert_eq!(*object["c"], Value::Boolean(true));
+            }
+            value => panic!("Expected an object, got {value}"),
+        }
+
+        let src = "let len = 3 let e = env() e.len";
+        let expected_value = Value::Number(3.0);
         let tokens = tokenize(src).unwrap();
         let program = parse(tokens).unwrap();
         let (val, _) = evaluate(program, None).unwrap();
         assert_eq!(val, expected_value);
+
+        let src = "let e = env(true) e.set";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert_eq!(val.to_string(), "native fn set");
     }
 
     #[test]
-    fn test_evaluate_numeric_expression() {
-        let src = "5";
-        let expected_value = Value::Number(5.);
+    fn test_evaluate_help_builtin() {
+        let src = "help(hash)";
+        let expected_value = Value::String(
+            "hash: hash functions over the UTF-8 bytes of a string\n  \
+             hash.fnv(input: string)     the 32-bit FNV-1a hash of the input as a number\n  \
+             hash.sha256(input: string)  the SHA-256 digest of the input as hexadecimal text"
+                .to_string()
+                .into(),
+        );
         let tokens = tokenize(src).unwrap();
         let program = parse(tokens).unwrap();
         let (val, _) = evaluate(program, None).unwrap();
         assert_eq!(val, expected_value);
-    }
 
-    #[test]
-    fn test_evaluate_boolean_true_expression() {
-        let src = "true";
-        let expected_value = Value::Boolean(true);
+        // `math` holds NaN, so it's found without being equal to itself.
+        let src = "help(math)";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let (val, _) = evaluate(program, None).unwrap();
+        assert!(val
+            .to_string()
+            .starts_with("math: mathematical constants and functions\n  pi "));
+
+        let src = "help(regex.find)";
+        let expected_value = Value::String(
+            "regex.find(pattern: string, input: string)\n  the first match of the pattern in the input, or none"
+                .to_string().into(),
+        );
         let tokens = tokenize(src).unwrap();
         let program = parse(tokens).unwrap();
         let (val, _) = evaluate(program, None).unwrap();
         assert_eq!(val, expected_value);
-    }
 
-    #[test]
-    fn test_evaluate_boolean_false_expression() {
-        let src = "false";
-        let expected_value = Value::Boolean(false);
+        let src = "let double = fn(x) { x * 2 } help(double)";
+        let expected_value = Value::String("'fn(x)' has no documentation".to_string().into());
         let tokens = tokenize(src).unwrap();
         let program = parse(tokens).unwrap();
         let (val, _) = evaluate(program, None).unwrap();
@@ -435,12 +3812,89 @@ mod tests {
     }
 
     #[test]
-    fn test_evaluate_none_expression() {
-        let src = "none";
-        let expected_value = Value::None;
+    fn test_evaluate_strict_mode() {
+        let evaluate_in = |src: &str, is_strict: bool| {
+            let mut environment = Environment::new(None);
+            environment.is_strict = is_strict;
+            let program = parse(tokenize(src).unwrap()).unwrap();
+            evaluate(program, Some(environment))
+                .map(|(value, _)| value)
+                .map_err(|error| error.message)
+        };
+
+        let src = "1 == \"1\"";
+        assert_eq!(evaluate_in(src, false), Ok(Value::Boolean(false)));
+        assert_eq!(
+            evaluate_in(src, true),
+            Err("Can't compare '1' and '1' with '==' as they're a number and a string, which strict mode doesn't allow".to_string())
+        );
+
+        let src = "{ a: 1 } != (1,)";
+        assert_eq!(evaluate_in(src, false), Ok(Value::Boolean(true)));
+        assert!(evaluate_in(src, true).is_err());
+
+        let src = "none == 0 || 1 == 1";
+        assert_eq!(evaluate_in(src, false), Ok(Value::Boolean(true)));
+        assert_eq!(evaluate_in(src, true), Ok(Value::Boolean(true)));
+
+        // '&&' and '||' only take booleans, on either side.
+        for (src, value, message, text_span, context) in [
+            (
+                "true && 1 * 2",
+                Value::Integer(2),
+                "Can't use '&&' with 'true' and '2' as the right side is a number",
+                TextSpan::new(8, 13),
+                Some(TextSpan::new(0, 13)),
+            ),
+            (
+                "1 || missing",
+                Value::Integer(1),
+                "Can't use '||' with '1' as the left side is a number",
+                TextSpan::new(0, 1),
+                Some(TextSpan::new(0, 12)),
+            ),
+        ] {
+            assert_eq!(evaluate_in(src, false), Ok(value), "{src}");
+            let mut environment = Environment::new(None);
+            environment.is_strict = true;
+            let program = parse(tokenize(src).unwrap()).unwrap();
+            let error = evaluate(program, Some(environment)).unwrap_err();
+            assert_eq!(error.message, message);
+            assert_eq!(error.text_span, text_span);
+            assert_eq!(error.context, context);
+        }
+
+        let src = "\"a\" + 1";
+        assert!(evaluate_in(src, false).is_err());
+        assert!(evaluate_in(src, true).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_type_annotations() {
+        let src = "let count: number = 0 const name: string = \"fns\" count = count + 1";
+        let expected_value = Value::Number(1.);
         let tokens = tokenize(src).unwrap();
         let program = parse(tokens).unwrap();
         let (val, _) = evaluate(program, None).unwrap();
         assert_eq!(val, expected_value);
+
+        let src = "let count: number = \"zero\"";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let error = evaluate(program, None).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Can't bind 'zero' to 'count' as it's a string instead of a number"
+        );
+        assert_eq!(error.text_span, TextSpan::new(11, 17));
+
+        let src = "let count: number = 0 let bump = fn() { count = true } bump()";
+        let tokens = tokenize(src).unwrap();
+        let program = parse(tokens).unwrap();
+        let error = evaluate(program, None).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Can't assign 'true' to 'count' as it's a boolean instead of a number"
+        );
     }
 }
@@ -0,0 +1,255 @@
+use crate::frontend::ast::Number;
+
+use super::types::format_number;
+
+// Number formatting shared by `numbers.to_fixed`, the options of `string` and the
+// placeholders of `format`, so all three round and group digits the same way.
+
+// More decimals than this can't tell two numbers apart anymore.
+pub const MAX_DECIMALS: usize = 100;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct NumberFormat {
+    pub decimals: Option<usize>,
+    pub separator: Option<String>,
+}
+
+impl NumberFormat {
+    pub fn apply(&self, n: Number) -> String {
+        let text = match self.decimals {
+            Some(decimals) => to_fixed(n, decimals),
+            None => format_number(n),
+        };
+        match &self.separator {
+            Some(separator) => group_thousands(&text, separator),
+            None => text,
+        }
+    }
+}
+
+// The number with exactly `decimals` digits after the point. Rounding works on the
+// shortest decimal form of the number, the one it's written as, with halves away from
+// zero like `math.round`, so `1.005` gives `1.01` even though the closest binary
+// number to it is a little below.
+pub fn to_fixed(n: Number, decimals: usize) -> String {
+    if !n.is_finite() {
+        return format_number(n);
+    }
+    let shortest = n.abs().to_string();
+    let (whole, fraction) = shortest.split_once('.').unwrap_or((&shortest, ""));
+    let mut digits: Vec<u8> = whole.bytes().chain(fraction.bytes()).collect();
+    let point = whole.len();
+    let kept = point + decimals;
+    let round_up = digits.get(kept).is_some_and(|digit| *digit >= b'5');
+    digits.resize(kept, b'0');
+    if round_up {
+        let mut carry = true;
+        for digit in digits.iter_mut().rev() {
+            if *digit == b'9' {
+                *digit = b'0';
+            } else {
+                *digit += 1;
+                carry = false;
+                break;
+            }
+        }
+        if carry {
+            digits.insert(0, b'1');
+        }
+    }
+
+    let point = digits.len() - decimals;
+    let mut text = String::from_utf8(digits[..point].to_vec()).unwrap();
+    if decimals > 0 {
+        text.push('.');
+        text.push_str(std::str::from_utf8(&digits[point..]).unwrap());
+    }
+    // A negative number rounded to zero loses its sign.
+    if n < 0.0 && text.bytes().any(|digit| matches!(digit, b'1'..=b'9')) {
+        text.insert(0, '-');
+    }
+    text
+}
+
+// The text of a number with the separator between every three digits of its whole part.
+pub fn group_thousands(text: &str, separator: &str) -> String {
+    let (sign, rest) = match text.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", text),
+    };
+    let length = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    let (whole, tail) = rest.split_at(length);
+    let mut grouped = String::from(sign);
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (length - i) % 3 == 0 {
+            grouped.push_str(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped + tail
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Piece {
+    Text(String),
+    Placeholder(NumberFormat),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SpecError {
+    pub message: String,
+    pub position: usize,
+}
+
+// Splits a `format` template into text and placeholders. A placeholder is `{}`, or
+// `{:spec}` where the spec is an optional `,` for thousands separators followed by an
+// optional `.` and a number of decimals, like `{:,.2}`. `{{` and `}}` stand for braces.
+pub fn parse_template(template: &str) -> Result<Vec<Piece>, SpecError> {
+    let chars: Vec<char> = template.chars().collect();
+    let mut pieces = vec![];
+    let mut text = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match (chars[i], chars.get(i + 1)) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                text.push(chars[i]);
+                i += 2;
+            }
+            ('}', _) => {
+                return Err(SpecError {
+                    message: "unmatched '}'".to_string(),
+                    position: i,
+                })
+            }
+            ('{', _) => {
+                let Some(length) = chars[i..].iter().position(|c| *c == '}') else {
+                    return Err(SpecError {
+                        message: "unclosed placeholder".to_string(),
+                        position: i,
+                    });
+                };
+                let spec: String = chars[i + 1..i + length].iter().collect();
+                let format = parse_spec(&spec).map_err(|message| SpecError {
+                    message,
+                    position: i,
+                })?;
+                if !text.is_empty() {
+                    pieces.push(Piece::Text(std::mem::take(&mut text)));
+                }
+                pieces.push(Piece::Placeholder(format));
+                i += length + 1;
+            }
+            (c, _) => {
+                text.push(c);
+                i += 1;
+            }
+        }
+    }
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    Ok(pieces)
+}
+
+fn parse_spec(spec: &str) -> Result<NumberFormat, String> {
+    if spec.is_empty() {
+        return Ok(NumberFormat::default());
+    }
+    let invalid = || format!("invalid placeholder '{{{spec}}}'");
+    let rest = spec.strip_prefix(':').ok_or_else(invalid)?;
+    let (separator, rest) = match rest.strip_prefix(',') {
+        Some(rest) => (Some(",".to_string()), rest),
+        None => (None, rest),
+    };
+    let decimals = match rest.strip_prefix('.') {
+        Some(digits) if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) => {
+            match digits.parse::<usize>() {
+                Ok(decimals) if decimals <= MAX_DECIMALS => Some(decimals),
+                _ => return Err(format!("can't show more than {MAX_DECIMALS} decimals")),
+            }
+        }
+        None if rest.is_empty() => None,
+        _ => return Err(invalid()),
+    };
+    Ok(NumberFormat {
+        decimals,
+        separator,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{group_thousands, parse_template, to_fixed, NumberFormat, Piece, SpecError};
+
+    #[test]
+    fn test_to_fixed() {
+        for (n, decimals, expected) in [
+            (1.005, 2, "1.01"),
+            (2.5, 0, "3"),
+            (-2.5, 0, "-3"),
+            (0.125, 2, "0.13"),
+            (-0.125, 2, "-0.13"),
+            (9.995, 2, "10.00"),
+            (99.5, 0, "100"),
+            (1.0, 3, "1.000"),
+            (-0.001, 2, "0.00"),
+            (0.0, 1, "0.0"),
+            (1.4, 0, "1"),
+            (123.456, 1, "123.5"),
+            (1e21, 1, "1000000000000000000000.0"),
+            (f64::INFINITY, 2, "inf"),
+        ] {
+            assert_eq!(
+                to_fixed(n, decimals),
+                expected,
+                "{n} to {decimals} decimals"
+            );
+        }
+    }
+
+    #[test]
+    fn test_group_thousands() {
+        assert_eq!(group_thousands("1234567.891", ","), "1,234,567.891");
+        assert_eq!(group_thousands("-123456", "_"), "-123_456");
+        assert_eq!(group_thousands("999", ","), "999");
+        let format = NumberFormat {
+            decimals: Some(2),
+            separator: Some(" ".to_string()),
+        };
+        assert_eq!(format.apply(-9999.999), "-10 000.00");
+    }
+
+    #[test]
+    fn test_parse_template() {
+        assert_eq!(
+            parse_template("{{{}}} is {:,.2}").unwrap(),
+            vec![
+                Piece::Text("{".to_string()),
+                Piece::Placeholder(NumberFormat::default()),
+                Piece::Text("} is ".to_string()),
+                Piece::Placeholder(NumberFormat {
+                    decimals: Some(2),
+                    separator: Some(",".to_string()),
+                }),
+            ]
+        );
+        for (template, message, position) in [
+            ("a {:.x}", "invalid placeholder '{:.x}'", 2),
+            ("{:.}", "invalid placeholder '{:.}'", 0),
+            ("{2}", "invalid placeholder '{2}'", 0),
+            ("{:.101}", "can't show more than 100 decimals", 0),
+            ("ab {", "unclosed placeholder", 3),
+            ("a } b", "unmatched '}'", 2),
+        ] {
+            assert_eq!(
+                parse_template(template),
+                Err(SpecError {
+                    message: message.to_string(),
+                    position
+                })
+            );
+        }
+    }
+}
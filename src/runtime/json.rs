@@ -0,0 +1,147 @@
+use super::types::Value;
+
+// Serializes a value as JSON. Object keys are sorted so the output is stable, tuples
+// and sets become arrays too and functions, along with numbers JSON has no notation for,
+// can't be represented at all.
+pub fn to_json(value: &Value, pretty: bool) -> Result<String, String> {
+    let mut output = String::new();
+    write_json(value, pretty, 0, &mut output)?;
+    Ok(output)
+}
+
+fn write_json(
+    value: &Value,
+    pretty: bool,
+    depth: usize,
+    output: &mut String,
+) -> Result<(), String> {
+    match value {
+        Value::None => output.push_str("null"),
+        Value::Boolean(b) => output.push_str(&b.to_string()),
+        Value::Integer(i) => output.push_str(&i.to_string()),
+        // The shortest notation reading back as the same number, rather than the 15
+        // significant digits of `display`, so no precision is lost on the way.
+        Value::Number(n) if n.is_finite() => output.push_str(&format!("{n:?}")),
+        Value::String(s) => write_json_string(s, output),
+        Value::Array(elements) | Value::Tuple(elements) => {
+            write_json_array(elements.iter(), pretty, depth, output)?
+        }
+        Value::Set(set) => {
+            let elements: Vec<Value> = set.iter().map(|element| element.to_value()).collect();
+            write_json_array(elements.iter(), pretty, depth, output)?
+        }
+        Value::Object(object) => {
+            let mut keys: Vec<&String> = object.keys().collect();
+            keys.sort();
+            output.push('{');
+            for (index, key) in keys.iter().enumerate() {
+                if index > 0 {
+                    output.push(',');
+                }
+                write_indentation(pretty, depth + 1, output);
+                write_json_string(key, output);
+                output.push_str(if pretty { ": " } else { ":" });
+                write_json(&object[*key], pretty, depth + 1, output)?;
+            }
+            if !keys.is_empty() {
+                write_indentation(pretty, depth, output);
+            }
+            output.push('}');
+        }
+        value => return Err(format!("Can't represent '{value}' as JSON")),
+    }
+    Ok(())
+}
+
+fn write_json_array<'a>(
+    elements: impl Iterator<Item = &'a Value>,
+    pretty: bool,
+    depth: usize,
+    output: &mut String,
+) -> Result<(), String> {
+    output.push('[');
+    let mut is_empty = true;
+    for (index, element) in elements.enumerate() {
+        if index > 0 {
+            output.push(',');
+        }
+        write_indentation(pretty, depth + 1, output);
+        write_json(element, pretty, depth + 1, output)?;
+        is_empty = false;
+    }
+    if !is_empty {
+        write_indentation(pretty, depth, output);
+    }
+    output.push(']');
+    Ok(())
+}
+
+fn write_indentation(pretty: bool, depth: usize, output: &mut String) {
+    if pretty {
+        output.push('\n');
+        output.push_str(&"  ".repeat(depth));
+    }
+}
+
+fn write_json_string(s: &str, output: &mut String) {
+    output.push('"');
+    for char in s.chars() {
+        match char {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            char if (char as u32) < 0x20 => output.push_str(&format!("\\u{:04x}", char as u32)),
+            char => output.push(char),
+        }
+    }
+    output.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::run_source;
+
+    use super::to_json;
+
+    fn json(src: &str, pretty: bool) -> Result<String, String> {
+        to_json(&run_source(src).unwrap(), pretty)
+    }
+
+    #[test]
+    fn test_to_json() {
+        assert_eq!(
+            json("{port: 8080, debug: false}", false),
+            Ok("{\"debug\":false,\"port\":8080}".to_string())
+        );
+        assert_eq!(
+            json(
+                "{name: \"fns\", inner: {values: (1, none, set(2.5))}, empty: {}}",
+                false
+            ),
+            Ok("{\"empty\":{},\"inner\":{\"values\":[1,null,[2.5]]},\"name\":\"fns\"}".to_string())
+        );
+        assert_eq!(json("none", false), Ok("null".to_string()));
+        assert_eq!(
+            json("(0.1 + 0.2, 2.0, 10000000000000000.0, -0.000001)", false),
+            Ok("[0.30000000000000004,2.0,1e16,-1e-6]".to_string())
+        );
+        assert_eq!(
+            json("{a: (1, 2), b: ()}", true),
+            Ok("{\n  \"a\": [\n    1,\n    2\n  ],\n  \"b\": []\n}".to_string())
+        );
+    }
+
+    #[test]
+    fn test_to_json_unrepresentable() {
+        assert_eq!(
+            json("{a: {b: len}}", false),
+            Err("Can't represent 'native fn len' as JSON".to_string())
+        );
+        assert_eq!(
+            json("fn(x) { x }", false),
+            Err("Can't represent 'fn(x)' as JSON".to_string())
+        );
+    }
+}
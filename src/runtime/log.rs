@@ -0,0 +1,127 @@
+use std::{
+    fmt,
+    io::{stderr, Write},
+};
+
+#[derive(Debug, PartialEq, PartialOrd, Clone, Copy)]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "debug" => Some(Self::Debug),
+            "info" => Some(Self::Info),
+            "warn" => Some(Self::Warn),
+            "error" => Some(Self::Error),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Debug => write!(f, "debug"),
+            Self::Info => write!(f, "info"),
+            Self::Warn => write!(f, "warn"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+// The state behind the `log` builtin module, shared by an environment and all of its
+// descendants. A level forced from outside the script (e.g. by the command line) takes
+// precedence over the one set by `log.set_level`.
+pub struct Logger {
+    pub level: LogLevel,
+    pub forced_level: Option<LogLevel>,
+    pub sink: Box<dyn Write>,
+}
+
+impl Logger {
+    pub fn new(sink: Box<dyn Write>) -> Self {
+        Self {
+            level: LogLevel::Info,
+            forced_level: None,
+            sink,
+        }
+    }
+
+    pub fn log(&mut self, level: LogLevel, message: &str) {
+        if level >= self.forced_level.unwrap_or(self.level) {
+            // Logging is best effort, a failing sink shouldn't stop the evaluation.
+            let _ = writeln!(self.sink, "[{level}] {message}");
+        }
+    }
+}
+
+impl Default for Logger {
+    fn default() -> Self {
+        Self::new(Box::new(stderr()))
+    }
+}
+
+impl fmt::Debug for Logger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Logger")
+            .field("level", &self.level)
+            .field("forced_level", &self.forced_level)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, io::Write, rc::Rc};
+
+    use crate::{
+        frontend::{parser::parse, tokenizer::tokenize},
+        runtime::{environment::Environment, evaluator::evaluate},
+    };
+
+    use super::{LogLevel, Logger};
+
+    #[derive(Clone)]
+    struct Sink(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Sink {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn log(src: &str, forced_level: Option<LogLevel>) -> String {
+        let sink = Sink(Rc::new(RefCell::new(vec![])));
+        let environment = Environment::new(None);
+        *environment.logger.borrow_mut() = Logger::new(Box::new(sink.clone()));
+        environment.logger.borrow_mut().forced_level = forced_level;
+        evaluate(parse(tokenize(src).unwrap()).unwrap(), Some(environment)).unwrap();
+        let output = sink.0.borrow().clone();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_log_levels() {
+        let src = "log.debug(1) log.info(\"two\", 2) log.warn((3, true)) log.error(none)";
+        assert_eq!(
+            log(src, None),
+            "[info] two 2\n[warn] (3, true)\n[error] none\n"
+        );
+
+        let src = "log.set_level(\"warn\") log.debug(1) log.info(2) log.warn(3) log.error(4)";
+        assert_eq!(log(src, None), "[warn] 3\n[error] 4\n");
+        assert_eq!(
+            log(src, Some(LogLevel::Debug)),
+            "[debug] 1\n[info] 2\n[warn] 3\n[error] 4\n"
+        );
+    }
+}
@@ -1,5 +1,19 @@
+pub mod console;
 pub mod environment;
 pub mod evaluator;
+pub mod json;
+pub mod log;
+pub mod module;
+pub mod profile;
+pub mod random;
+pub mod registry;
+pub mod trace;
 pub mod types;
 
+mod builder;
 mod builtin;
+mod encoding;
+mod format;
+mod hash;
+mod operators;
+mod regex;
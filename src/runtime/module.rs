@@ -0,0 +1,317 @@
+use std::{
+    collections::HashMap,
+    env, fmt, fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::frontend::{
+    parser::parse_with_limits,
+    tokenizer::tokenize,
+    utils::{Error, TextSpan},
+};
+
+use super::{environment::Environment, evaluator::evaluate, types::Value};
+
+// The source of a module along with the id telling it apart from every other module,
+// the same whichever specifier it was imported by.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedModule {
+    pub id: String,
+    pub source: String,
+}
+
+// Finds the module a specifier of `import` stands for. `importer` is the id of the
+// module importing it, `None` for the code the host runs. Hosts without a file system,
+// or with one of their own, give the environment a resolver of their own.
+pub trait ModuleResolver {
+    fn resolve(&self, specifier: &str, importer: Option<&str>) -> Result<ResolvedModule, String>;
+}
+
+// Resolves modules to files, adding the `.fns` extension to specifiers without one.
+// Specifiers starting with `.` are relative to the importing file, or the working
+// directory outside of one, others are looked for in each directory of the search path
+// in turn. Ids are canonical paths.
+#[derive(Debug, Clone, Default)]
+pub struct FileSystemResolver {
+    pub search_path: Vec<PathBuf>,
+}
+
+impl FileSystemResolver {
+    pub fn new(search_path: Vec<PathBuf>) -> Self {
+        Self { search_path }
+    }
+
+    // The search path taken from `FNS_PATH`, separated like `PATH` is.
+    pub fn from_env() -> Self {
+        Self::new(
+            env::var_os("FNS_PATH")
+                .map(|paths| env::split_paths(&paths).collect())
+                .unwrap_or_default(),
+        )
+    }
+
+    fn candidates(&self, specifier: &str, importer: Option<&str>) -> Vec<PathBuf> {
+        let mut file = PathBuf::from(specifier);
+        if file.extension().is_none() {
+            file.set_extension("fns");
+        }
+        if specifier.starts_with('.') || file.is_absolute() {
+            let directory = importer
+                .and_then(|importer| Path::new(importer).parent())
+                .map(Path::to_path_buf)
+                .unwrap_or_default();
+            vec![directory.join(file)]
+        } else {
+            self.search_path
+                .iter()
+                .map(|directory| directory.join(&file))
+                .collect()
+        }
+    }
+}
+
+impl ModuleResolver for FileSystemResolver {
+    fn resolve(&self, specifier: &str, importer: Option<&str>) -> Result<ResolvedModule, String> {
+        for path in self.candidates(specifier, importer) {
+            if let Ok(source) = fs::read_to_string(&path) {
+                let id = fs::canonicalize(&path).unwrap_or(path);
+                return Ok(ResolvedModule {
+                    id: id.display().to_string(),
+                    source,
+                });
+            }
+        }
+        Err("no file was found for it".to_string())
+    }
+}
+
+// The modules imported while running a program, shared by every environment of it so
+// that each module is evaluated once however many times it's imported.
+pub struct Modules {
+    pub resolver: Rc<dyn ModuleResolver>,
+    // The value of every module evaluated so far, by id.
+    cache: HashMap<String, Value>,
+    // The modules being evaluated, the innermost last, to tell an import cycle.
+    loading: Vec<String>,
+}
+
+impl Modules {
+    pub fn new(resolver: Rc<dyn ModuleResolver>) -> Self {
+        Self {
+            resolver,
+            cache: HashMap::new(),
+            loading: vec![],
+        }
+    }
+
+    // Forgets the modules evaluated so far, giving back their values.
+    pub fn take_cache(&mut self) -> HashMap<String, Value> {
+        std::mem::take(&mut self.cache)
+    }
+}
+
+impl Default for Modules {
+    fn default() -> Self {
+        Self::new(Rc::new(FileSystemResolver::from_env()))
+    }
+}
+
+impl fmt::Debug for Modules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Modules")
+            .field("cache", &self.cache)
+            .field("loading", &self.loading)
+            .finish()
+    }
+}
+
+// The module a specifier stands for, as an object of its top-level variables. The
+// module runs once, in an environment of its own sharing the builtins, limits and state
+// of the evaluation of the importer.
+pub fn import(
+    specifier: &str,
+    text_span: TextSpan,
+    environment: &mut Environment,
+) -> Result<Value, Error> {
+    let error = |reason: String| {
+        Error::new(
+            format!("Can't import '{specifier}' as {reason}"),
+            text_span.clone(),
+        )
+    };
+    let resolver = environment.modules.borrow().resolver.clone();
+    let module = resolver
+        .resolve(specifier, environment.module.as_deref())
+        .map_err(&error)?;
+    {
+        let modules = environment.modules.borrow();
+        if let Some(value) = modules.cache.get(&module.id) {
+            return Ok(value.clone());
+        }
+        if modules.loading.contains(&module.id) {
+            return Err(error(
+                "it imports itself, through the modules it imports".to_string(),
+            ));
+        }
+    }
+
+    environment
+        .modules
+        .borrow_mut()
+        .loading
+        .push(module.id.clone());
+    let result = evaluate_module(&module, environment);
+    environment.modules.borrow_mut().loading.pop();
+    // The spans of the error are in the source of the module, so where it happened is
+    // given in a note, followed by the notes of the error.
+    let value = result.map_err(|inner| {
+        let (line, column) = inner.text_span.position(&module.source);
+        let mut error = error("running it failed".to_string()).with_note(format!(
+            "{}, line {line}, column {column}: {}",
+            module.id, inner.message
+        ));
+        error.notes.extend(inner.notes);
+        error
+    })?;
+    environment
+        .modules
+        .borrow_mut()
+        .cache
+        .insert(module.id, value.clone());
+    Ok(value)
+}
+
+fn evaluate_module(module: &ResolvedModule, importer: &Environment) -> Result<Value, Error> {
+    let mut environment = Environment::with_builtins(importer.builtins.clone());
+    environment.interrupted = importer.interrupted.clone();
+    environment.steps = importer.steps.clone();
+    environment.tracer = importer.tracer.clone();
+    environment.logger = importer.logger.clone();
+    environment.console = importer.console.clone();
+    environment.random = importer.random.clone();
+    environment.profiler = importer.profiler.clone();
+    environment.call_depth = importer.call_depth + 1;
+    environment.is_strict = importer.is_strict;
+    environment.limits = importer.limits.clone();
+    environment.printer = importer.printer.clone();
+    environment.modules = importer.modules.clone();
+    environment.module = Some(module.id.as_str().into());
+    environment.join_run(importer);
+    let program = tokenize(&module.source)
+        .and_then(|tokens| parse_with_limits(tokens, &environment.limits))?;
+    let (_, environment) = evaluate(program, Some(environment))?;
+    let exports = environment
+        .variables
+        .borrow()
+        .iter()
+        .map(|(name, (value, _))| (name.to_string(), Box::new(value.clone())))
+        .collect();
+    Ok(Value::Object(exports))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, collections::HashMap, rc::Rc};
+
+    use crate::{
+        frontend::utils::{Error, TextSpan},
+        run_source_with,
+        runtime::{
+            environment::Environment,
+            registry::{function, BuiltinRegistry},
+            types::Value,
+        },
+    };
+
+    use super::{ModuleResolver, Modules, ResolvedModule};
+
+    // Modules kept in memory by name, the id of a module being its name without the
+    // `./` it may be imported with.
+    struct MemoryResolver(HashMap<&'static str, &'static str>);
+
+    impl ModuleResolver for MemoryResolver {
+        fn resolve(&self, specifier: &str, _: Option<&str>) -> Result<ResolvedModule, String> {
+            let id = specifier.trim_start_matches("./");
+            match self.0.get(id) {
+                Some(source) => Ok(ResolvedModule {
+                    id: id.to_string(),
+                    source: source.to_string(),
+                }),
+                None => Err(format!("there's no module '{id}'")),
+            }
+        }
+    }
+
+    thread_local! {
+        static EVALUATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    fn count(_: Vec<Value>, _: TextSpan, _: &mut Environment) -> Result<Value, Error> {
+        EVALUATIONS.with(|evaluations| evaluations.set(evaluations.get() + 1));
+        Ok(Value::None)
+    }
+
+    fn environment() -> Environment {
+        let mut registry = BuiltinRegistry::standard();
+        registry.register(function("count()", count, "counts a module evaluation"));
+        let environment = Environment::with_builtins(Rc::new(registry));
+        let modules = HashMap::from([
+            ("shared", "count() let base = 10"),
+            (
+                "a",
+                "let shared = import(\"shared\") let a = shared.base + 1",
+            ),
+            ("b", "let b = import(\"./shared\").base + 2"),
+            ("cycle", "let c = import(\"cycle\")"),
+            ("broken", "let x = 1\nx()"),
+        ]);
+        environment
+            .modules
+            .replace(Modules::new(Rc::new(MemoryResolver(modules))));
+        environment
+    }
+
+    #[test]
+    fn test_import_evaluates_modules_once() {
+        let mut environment = environment();
+        let value = run_source_with(
+            "let a = import(\"a\") let b = import(\"b\") let result = (a.a, b.b, import(\"shared\").base) result",
+            &mut environment,
+        )
+        .unwrap();
+        assert_eq!(value.to_string(), "(11, 12, 10)");
+        assert_eq!(EVALUATIONS.with(Cell::get), 1);
+        // The cache belongs to the environment, a fresh one evaluates the module again.
+        run_source_with("import(\"b\")", &mut self::environment()).unwrap();
+        assert_eq!(EVALUATIONS.with(Cell::get), 2);
+    }
+
+    #[test]
+    fn test_import_errors() {
+        let mut environment = environment();
+        let error = run_source_with("let m = import(\"missing\")", &mut environment).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Can't import 'missing' as there's no module 'missing'"
+        );
+        assert_eq!(error.text_span, TextSpan::new(8, 25));
+
+        let error = run_source_with("import(\"broken\")", &mut environment).unwrap_err();
+        assert_eq!(error.message, "Can't import 'broken' as running it failed");
+        assert_eq!(
+            error.notes,
+            vec!["broken, line 2, column 1: Can't call '1' as it's not a function".to_string()]
+        );
+
+        let error = run_source_with("import(\"cycle\")", &mut environment).unwrap_err();
+        assert_eq!(error.message, "Can't import 'cycle' as running it failed");
+        assert_eq!(
+            error.notes,
+            vec![
+                "cycle, line 1, column 9: Can't import 'cycle' as it imports itself, through the modules it imports".to_string(),
+                "while initializing 'c'".to_string(),
+            ]
+        );
+    }
+}
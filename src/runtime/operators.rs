@@ -0,0 +1,284 @@
+use std::cmp::Ordering;
+
+use crate::frontend::{ast::Number, limits::Limits, token::TokenKind};
+
+use super::{
+    builder::concatenate,
+    types::{numeric_order, Value},
+};
+
+// What a binary operator does with its operands, which also tells the types it takes:
+// numbers for arithmetic, division and comparisons, strings for concatenation,
+// booleans for the logical operators in strict mode, outside of which the evaluator
+// takes them by truthiness, and anything for equality. Arithmetic on two
+// integers gives an integer, `None` when it overflows, and on any float a float.
+// Dividing a float by 0 gives an infinity, or NaN for `0.0 / 0`, as integers have
+// neither dividing an integer by 0 is an error.
+pub enum Operation {
+    Arithmetic(fn(Number, Number) -> Number, fn(i64, i64) -> Option<i64>),
+    Division,
+    Comparison(fn(Ordering) -> bool),
+    Concatenation,
+    Logical(fn(bool, bool) -> bool),
+    // Whether the operands are equal, or unequal when negated.
+    Equality { negated: bool },
+}
+
+// Every binary operator and the operations it can do, tried in order. Operands no row
+// of their operator takes are an error, so supporting an operator for more types is
+// adding a row here, and a documented combination in the tests below.
+pub const BINARY_OPERATIONS: &[(TokenKind, Operation)] = &[
+    (
+        TokenKind::Plus,
+        Operation::Arithmetic(|a, b| a + b, i64::checked_add),
+    ),
+    (TokenKind::Plus, Operation::Concatenation),
+    (
+        TokenKind::Minus,
+        Operation::Arithmetic(|a, b| a - b, i64::checked_sub),
+    ),
+    (
+        TokenKind::Asterisk,
+        Operation::Arithmetic(|a, b| a * b, i64::checked_mul),
+    ),
+    (TokenKind::Slash, Operation::Division),
+    (TokenKind::Greater, Operation::Comparison(Ordering::is_gt)),
+    (TokenKind::Lesser, Operation::Comparison(Ordering::is_lt)),
+    (
+        TokenKind::GreaterOrEqual,
+        Operation::Comparison(Ordering::is_ge),
+    ),
+    (
+        TokenKind::LesserOrEqual,
+        Operation::Comparison(Ordering::is_le),
+    ),
+    (
+        TokenKind::DoubleEqual,
+        Operation::Equality { negated: false },
+    ),
+    (TokenKind::BangEqual, Operation::Equality { negated: true }),
+    // Identity never goes through '__eq'. Values don't alias one another, so besides
+    // functions, which are the same when they come from the same definition, being the
+    // same value is being equal.
+    (
+        TokenKind::TripleEqual,
+        Operation::Equality { negated: false },
+    ),
+    (
+        TokenKind::BangDoubleEqual,
+        Operation::Equality { negated: true },
+    ),
+    (
+        TokenKind::DoubleAmpersand,
+        Operation::Logical(|a, b| a && b),
+    ),
+    (TokenKind::DoublePipe, Operation::Logical(|a, b| a || b)),
+];
+
+impl Operation {
+    // Whether the operation takes the value on either side.
+    pub fn accepts(&self, value: &Value) -> bool {
+        match self {
+            Operation::Arithmetic(..) | Operation::Division | Operation::Comparison(_) => {
+                matches!(value, Value::Integer(_) | Value::Number(_))
+            }
+            Operation::Concatenation => matches!(value, Value::String(_)),
+            Operation::Logical(_) => matches!(value, Value::Boolean(_)),
+            Operation::Equality { .. } => true,
+        }
+    }
+
+    // The result of the operation, `None` when it doesn't take the operands, or the
+    // message of the error it ends in.
+    pub fn apply(
+        &self,
+        left: &Value,
+        right: &Value,
+        limits: &Limits,
+    ) -> Option<Result<Value, String>> {
+        if !self.accepts(left) || !self.accepts(right) {
+            return None;
+        }
+        let result = match (self, left, right) {
+            (Operation::Arithmetic(_, operation), Value::Integer(a), Value::Integer(b)) => {
+                operation(*a, *b).map(Value::Integer).ok_or_else(overflow)
+            }
+            (Operation::Division, Value::Integer(_), Value::Integer(0)) => {
+                Err("Can't divide by 0".to_string())
+            }
+            // Integers divide to the integer part of their quotient, `7 / 2` being 3.
+            (Operation::Division, Value::Integer(a), Value::Integer(b)) => {
+                a.checked_div(*b).map(Value::Integer).ok_or_else(overflow)
+            }
+            (Operation::Arithmetic(operation, _), left, right) => {
+                Ok(Value::Number(operation(float(left), float(right))))
+            }
+            (Operation::Division, left, right) => Ok(Value::Number(float(left) / float(right))),
+            // NaN is neither smaller, greater nor equal to anything.
+            (Operation::Comparison(operation), left, right) => Ok(Value::Boolean(
+                numeric_order(left, right).is_some_and(operation),
+            )),
+            (Operation::Concatenation, Value::String(a), Value::String(b)) => {
+                concatenate(a, b, limits).map(|text| Value::String(text.into()))
+            }
+            (Operation::Logical(operation), Value::Boolean(a), Value::Boolean(b)) => {
+                Ok(Value::Boolean(operation(*a, *b)))
+            }
+            (Operation::Equality { negated }, left, right) => {
+                Ok(Value::Boolean((left == right) != *negated))
+            }
+            _ => return None,
+        };
+        Some(result)
+    }
+}
+
+fn float(value: &Value) -> Number {
+    value.as_number().unwrap_or(Number::NAN)
+}
+
+fn overflow() -> String {
+    "Can't compute the result as it's out of the range of integers".to_string()
+}
+
+// The operations of an operator, in the order they're tried.
+pub fn operations(operator: &TokenKind) -> impl Iterator<Item = &'static Operation> + '_ {
+    BINARY_OPERATIONS
+        .iter()
+        .filter(move |(kind, _)| kind == operator)
+        .map(|(_, operation)| operation)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{run_source, runtime::operators::BINARY_OPERATIONS};
+
+    // A value of every type, each the same wherever it's used so that equality holds
+    // between two values of the same type.
+    const SAMPLES: [(&str, &str); 9] = [
+        ("none", "none"),
+        ("boolean", "true"),
+        ("number", "6"),
+        ("string", "\"ab\""),
+        ("object", "{a: 1}"),
+        ("tuple", "(1,)"),
+        ("array", "[1]"),
+        ("set", "set(1)"),
+        ("function", "f"),
+    ];
+
+    const OPERATORS: [&str; 15] = [
+        "+", "-", "*", "/", ">", "<", ">=", "<=", "==", "!=", "===", "!==", "&&", "||", "??",
+    ];
+
+    enum Outcome {
+        Value(&'static str),
+        // Whether both sides are of the same type, or of different ones.
+        SameType,
+        DifferentTypes,
+        // The left side when it decides the value, being falsy for '&&', truthy for '||'
+        // and anything but none for '??', else the right one.
+        Operand,
+    }
+
+    // Every combination of operand types a binary operator takes, `any` standing for
+    // every type. All other combinations are errors.
+    const MATRIX: [(&str, &str, &str, Outcome); 16] = [
+        ("+", "number", "number", Outcome::Value("12")),
+        ("+", "string", "string", Outcome::Value("abab")),
+        ("-", "number", "number", Outcome::Value("0")),
+        ("*", "number", "number", Outcome::Value("36")),
+        ("/", "number", "number", Outcome::Value("1")),
+        (">", "number", "number", Outcome::Value("false")),
+        ("<", "number", "number", Outcome::Value("false")),
+        (">=", "number", "number", Outcome::Value("true")),
+        ("<=", "number", "number", Outcome::Value("true")),
+        ("==", "any", "any", Outcome::SameType),
+        ("!=", "any", "any", Outcome::DifferentTypes),
+        ("===", "any", "any", Outcome::SameType),
+        ("!==", "any", "any", Outcome::DifferentTypes),
+        ("&&", "any", "any", Outcome::Operand),
+        ("||", "any", "any", Outcome::Operand),
+        ("??", "any", "any", Outcome::Operand),
+    ];
+
+    fn documented(operator: &str, side: &str, type_name: &str) -> bool {
+        MATRIX.iter().any(|(documented, left, right, _)| {
+            let documented_type = if side == "left" { left } else { right };
+            *documented == operator && (*documented_type == "any" || *documented_type == type_name)
+        })
+    }
+
+    #[test]
+    fn test_binary_operator_matrix() {
+        for operator in OPERATORS {
+            for (left_type, left) in SAMPLES {
+                for (right_type, right) in SAMPLES {
+                    let source_code =
+                        format!("let f = fn() {{}} let result = {left} {operator} {right} result");
+                    let row = MATRIX.iter().find(|(documented, l, r, _)| {
+                        *documented == operator
+                            && (*l == "any" || *l == left_type)
+                            && (*r == "any" || *r == right_type)
+                    });
+                    let result = run_source(&source_code);
+                    match row {
+                        Some((_, _, _, outcome)) => {
+                            let expected = match outcome {
+                                Outcome::Value(value) => value.to_string(),
+                                Outcome::SameType => (left_type == right_type).to_string(),
+                                Outcome::DifferentTypes => (left_type != right_type).to_string(),
+                                Outcome::Operand => {
+                                    let left_decides = (left_type == "none") == (operator == "&&");
+                                    let operand = if left_decides { left } else { right };
+                                    run_source(&format!(
+                                        "let f = fn() {{}} let result = {operand} result"
+                                    ))
+                                    .unwrap()
+                                    .to_string()
+                                }
+                            };
+                            assert_eq!(
+                                result.map(|value| value.to_string()).ok(),
+                                Some(expected),
+                                "{source_code}"
+                            );
+                        }
+                        None => {
+                            let message = result.expect_err(&source_code).message;
+                            let prefix = format!("Can't use '{operator}' with ");
+                            let reason = if !documented(operator, "left", left_type) {
+                                format!("as the left side is a {left_type}")
+                            } else if !documented(operator, "right", right_type) {
+                                format!("as the right side is a {right_type}")
+                            } else {
+                                format!("as they're a {left_type} and a {right_type}")
+                            };
+                            assert!(
+                                message.starts_with(&prefix) && message.ends_with(&reason),
+                                "{source_code} failed with {message}"
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_binary_operations_are_documented() {
+        for (kind, _) in BINARY_OPERATIONS {
+            assert!(
+                MATRIX
+                    .iter()
+                    .any(|(operator, ..)| *operator == kind.to_string()),
+                "{kind} has operations but no documented row"
+            );
+        }
+        // The errors operations end in rather than their operands.
+        assert_eq!(
+            run_source("1 / 0").unwrap_err().message,
+            "Can't divide by 0"
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use std::{collections::HashMap, time::Duration};
+
+use crate::frontend::utils::TextSpan;
+
+use super::trace::describe_span;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ProfileEntry {
+    pub duration: Duration,
+    pub hits: usize,
+}
+
+// Aggregates the time spent evaluating each expression by its span. The time of an
+// expression includes the time of the expressions nested in it.
+#[derive(Debug, Default)]
+pub struct Profiler {
+    pub entries: HashMap<(usize, usize), ProfileEntry>,
+}
+
+impl Profiler {
+    pub fn record(&mut self, text_span: TextSpan, duration: Duration) {
+        let entry = self
+            .entries
+            .entry((text_span.starting_index, text_span.ending_index))
+            .or_default();
+        entry.duration += duration;
+        entry.hits += 1;
+    }
+
+    // A table of the `count` spans that took the most time, slowest first.
+    pub fn report(&self, source_code: &str, count: usize) -> String {
+        let source_code: Vec<char> = source_code.chars().collect();
+        let mut entries: Vec<(&(usize, usize), &ProfileEntry)> = self.entries.iter().collect();
+        entries.sort_by(|(a_span, a), (b_span, b)| {
+            b.duration
+                .cmp(&a.duration)
+                .then(b.hits.cmp(&a.hits))
+                .then(a_span.cmp(b_span))
+        });
+
+        let mut report = format!("{:>12}  {:>8}  source\n", "total (ms)", "hits");
+        for ((starting_index, ending_index), entry) in entries.into_iter().take(count) {
+            let text_span = TextSpan::new(*starting_index, *ending_index);
+            report.push_str(&format!(
+                "{:>12.3}  {:>8}  {}\n",
+                entry.duration.as_secs_f64() * 1000.0,
+                entry.hits,
+                describe_span(&source_code, &text_span)
+            ));
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use crate::{
+        frontend::{parser::parse, tokenizer::tokenize},
+        runtime::{environment::Environment, evaluator::evaluate},
+    };
+
+    use super::Profiler;
+
+    #[test]
+    fn test_profiler() {
+        let src = "
+            let grow = fn(s) { s + s + s + s }
+            grow(grow(grow(grow(grow(\"fns\")))))
+        ";
+        let profiler = Rc::new(RefCell::new(Profiler::default()));
+        let mut environment = Environment::new(None);
+        environment.profiler = Some(profiler.clone());
+        evaluate(parse(tokenize(src).unwrap()).unwrap(), Some(environment)).unwrap();
+
+        let report = profiler.borrow().report(src, usize::MAX);
+        let line = report
+            .lines()
+            .find(|line| line.ends_with("[line 2] s + s + s + s"))
+            .unwrap();
+        assert_eq!(line.split_whitespace().nth(1), Some("5"));
+        // The operators inside the chain are profiled too.
+        let line = report
+            .lines()
+            .find(|line| line.ends_with("[line 2] s + s + s"))
+            .unwrap();
+        assert_eq!(line.split_whitespace().nth(1), Some("5"));
+        assert!(report.starts_with("  total (ms)      hits  source\n"));
+    }
+}
@@ -0,0 +1,76 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// The generator behind `math.random` and `math.random_int`, a xorshift64* seeded
+// through SplitMix64 so that close seeds still give unrelated sequences. It's fast and
+// reproducible rather than suitable for anything secret.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        let mut z = seed.wrapping_add(0x9e3779b97f4a7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^= z >> 31;
+        // xorshift never leaves a state of 0.
+        Self {
+            state: if z == 0 { 1 } else { z },
+        }
+    }
+
+    // Seeded from the clock, so runs that don't seed it differ.
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_nanos() as u64);
+        Self::new(nanos)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545f4914f6cdd1d)
+    }
+
+    // A float in [0, 1), from the 53 high bits a float holds exactly.
+    pub fn next_float(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // An integer from `low` to `high`, both included, `low` being at most `high`.
+    pub fn next_between(&mut self, low: i64, high: i64) -> i64 {
+        let range = (high as i128 - low as i128 + 1) as u128;
+        let offset = (self.next_u64() as u128 * range) >> 64;
+        (low as i128 + offset as i128) as i64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Random;
+
+    #[test]
+    fn test_random() {
+        let mut a = Random::new(42);
+        let mut b = Random::new(42);
+        let sequence: Vec<u64> = (0..5).map(|_| a.next_u64()).collect();
+        assert_eq!(sequence, (0..5).map(|_| b.next_u64()).collect::<Vec<_>>());
+        assert_ne!(Random::new(43).next_u64(), sequence[0]);
+        assert_ne!(Random::new(0), Random::new(u64::MAX));
+
+        let mut seen = [false; 5];
+        for _ in 0..1_000 {
+            assert!((0.0..1.0).contains(&a.next_float()));
+            let n = a.next_between(-2, 2);
+            assert!((-2..=2).contains(&n));
+            seen[(n + 2) as usize] = true;
+        }
+        // Both bounds come up.
+        assert_eq!(seen, [true; 5]);
+        assert_eq!(a.next_between(7, 7), 7);
+        a.next_between(i64::MIN, i64::MAX);
+    }
+}
@@ -0,0 +1,696 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+// A small backtracking regular expression engine backing the `regex` builtin module.
+// It supports literals, `.`, `^`, `$`, character classes (`[a-z]`, `[^0-9]`), the
+// `\d \w \s` shorthands and their negations, capturing and non-capturing (`(?:...)`)
+// groups, alternation and the `* + ? {n} {n,} {n,m}` quantifiers, lazy with a `?` suffix.
+// Patterns are compiled to a small program of instructions, run with a stack of the
+// choices left to try rather than by recursion, so long inputs can't overflow the stack.
+// Searches call back for every path they try, which can stop them, as a pattern like
+// `(a|a)*b` tries exponentially many.
+
+#[derive(Debug)]
+enum Node {
+    Char(char),
+    Any,
+    Class(Vec<(char, char)>, bool),
+    Start,
+    End,
+    Concat(Vec<Node>),
+    Alternation(Vec<Node>),
+    Group(Box<Node>, Option<usize>),
+    Repeat(Box<Node>, usize, Option<usize>, bool),
+}
+
+// An instruction of a compiled pattern. `Split` tries its first target before its
+// second, `Save` records the position in a capture slot, `Mark` records it in a
+// register and `Progress` fails unless the position moved since its register's `Mark`.
+#[derive(Debug)]
+enum Instruction {
+    Char(char),
+    Any,
+    Class(Vec<(char, char)>, bool),
+    Start,
+    End,
+    Split(usize, usize),
+    Jump(usize),
+    Save(usize),
+    Mark(usize),
+    Progress(usize),
+    Match,
+}
+
+// The most instructions a pattern compiles to, as counted repetitions copy what they
+// repeat and `(a{1000}){1000}` would otherwise take a million.
+const MAX_INSTRUCTIONS: usize = 100_000;
+
+// The largest count a repetition may have, well past what fits in `MAX_INSTRUCTIONS`
+// anyway, so `a{1000000000000}` is refused before it's compiled.
+const MAX_REPETITION: usize = 100_000;
+
+// The most compiled patterns kept in the cache before it's emptied.
+const MAX_CACHED: usize = 256;
+
+#[derive(Debug)]
+pub struct Regex {
+    program: Vec<Instruction>,
+    group_count: usize,
+    register_count: usize,
+}
+
+pub type Captures = Vec<Option<(usize, usize)>>;
+
+#[derive(Debug, PartialEq)]
+pub struct PatternError {
+    pub message: String,
+    pub position: usize,
+}
+
+thread_local! {
+    static CACHE: RefCell<HashMap<String, Rc<Regex>>> = RefCell::new(HashMap::new());
+}
+
+// Compiled patterns are cached by their source so calling a regex builtin in a loop
+// doesn't recompile the same pattern on every iteration.
+pub fn compile_cached(pattern: &str) -> Result<Rc<Regex>, PatternError> {
+    if let Some(regex) = CACHE.with(|cache| cache.borrow().get(pattern).cloned()) {
+        return Ok(regex);
+    }
+    let regex = Rc::new(Regex::new(pattern)?);
+    CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        // Scripts building patterns on the fly would otherwise grow it forever.
+        if cache.len() >= MAX_CACHED {
+            cache.clear();
+        }
+        cache.insert(pattern.to_string(), regex.clone())
+    });
+    Ok(regex)
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Self, PatternError> {
+        let mut parser = PatternParser {
+            pattern: pattern.chars().collect(),
+            position: 0,
+            group_count: 0,
+        };
+        let root = parser.parse_alternation()?;
+        if parser.position < parser.pattern.len() {
+            return Err(parser.error("unmatched ')'"));
+        }
+        let mut compiler = Compiler {
+            program: vec![],
+            register_count: 0,
+        };
+        compiler.compile(&root);
+        compiler.program.push(Instruction::Match);
+        if compiler.program.len() > MAX_INSTRUCTIONS {
+            return Err(PatternError {
+                message: "pattern is too large".to_string(),
+                position: 0,
+            });
+        }
+        Ok(Self {
+            program: compiler.program,
+            group_count: parser.group_count,
+            register_count: compiler.register_count,
+        })
+    }
+
+    pub fn group_count(&self) -> usize {
+        self.group_count
+    }
+
+    // Finds the leftmost match starting at or after the char index `start`. The first
+    // capture is the whole match, followed by one entry per capturing group. `charge` is
+    // called for every path tried, the search stopping with its error.
+    pub fn find_at<E>(
+        &self,
+        input: &[char],
+        start: usize,
+        charge: &mut impl FnMut() -> Result<(), E>,
+    ) -> Result<Option<Captures>, E> {
+        for position in start..=input.len() {
+            if let Some(captures) = self.match_at(input, position, charge)? {
+                return Ok(Some(captures));
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn find_all<E>(
+        &self,
+        input: &[char],
+        charge: &mut impl FnMut() -> Result<(), E>,
+    ) -> Result<Vec<Captures>, E> {
+        let mut matches = vec![];
+        let mut position = 0;
+        while position <= input.len() {
+            match self.find_at(input, position, charge)? {
+                Some(captures) => {
+                    let (start, end) = captures[0].unwrap_or((position, position));
+                    position = if end == start { end + 1 } else { end };
+                    matches.push(captures);
+                }
+                None => break,
+            }
+        }
+        Ok(matches)
+    }
+}
+
+struct PatternParser {
+    pattern: Vec<char>,
+    position: usize,
+    group_count: usize,
+}
+
+impl PatternParser {
+    fn error(&self, message: &str) -> PatternError {
+        PatternError {
+            message: message.to_string(),
+            position: self.position,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.pattern.get(self.position).copied()
+    }
+
+    fn parse_alternation(&mut self) -> Result<Node, PatternError> {
+        let mut alternatives = vec![self.parse_sequence()?];
+        while self.peek() == Some('|') {
+            self.position += 1;
+            alternatives.push(self.parse_sequence()?);
+        }
+        if alternatives.len() == 1 {
+            Ok(alternatives.remove(0))
+        } else {
+            Ok(Node::Alternation(alternatives))
+        }
+    }
+
+    fn parse_sequence(&mut self) -> Result<Node, PatternError> {
+        let mut nodes = vec![];
+        while let Some(char) = self.peek() {
+            if char == '|' || char == ')' {
+                break;
+            }
+            let atom = self.parse_atom()?;
+            nodes.push(self.parse_quantifier(atom)?);
+        }
+        Ok(Node::Concat(nodes))
+    }
+
+    fn parse_quantifier(&mut self, atom: Node) -> Result<Node, PatternError> {
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => return self.parse_counted_quantifier(atom),
+            _ => return Ok(atom),
+        };
+        self.position += 1;
+        self.finish_quantifier(atom, min, max)
+    }
+
+    fn parse_counted_quantifier(&mut self, atom: Node) -> Result<Node, PatternError> {
+        self.position += 1;
+        let min = self
+            .parse_count()
+            .ok_or_else(|| self.error("expected a repetition count"))?;
+        let max = if self.peek() == Some(',') {
+            self.position += 1;
+            self.parse_count()
+        } else {
+            Some(min)
+        };
+        if min.max(max.unwrap_or(0)) > MAX_REPETITION {
+            return Err(self.error("repetition count is too large"));
+        }
+        if self.peek() != Some('}') {
+            return Err(self.error("unclosed repetition"));
+        }
+        if max.is_some_and(|max| max < min) {
+            return Err(self.error("repetition range is reversed"));
+        }
+        self.position += 1;
+        self.finish_quantifier(atom, min, max)
+    }
+
+    // A count too large for a `usize` is taken as the largest one, to be refused as such.
+    fn parse_count(&mut self) -> Option<usize> {
+        let start = self.position;
+        while self.peek().is_some_and(|char| char.is_ascii_digit()) {
+            self.position += 1;
+        }
+        if start == self.position {
+            return None;
+        }
+        let digits: String = self.pattern[start..self.position].iter().collect();
+        Some(digits.parse().unwrap_or(usize::MAX))
+    }
+
+    fn finish_quantifier(
+        &mut self,
+        atom: Node,
+        min: usize,
+        max: Option<usize>,
+    ) -> Result<Node, PatternError> {
+        if matches!(atom, Node::Start | Node::End) {
+            return Err(self.error("nothing to repeat"));
+        }
+        let greedy = if self.peek() == Some('?') {
+            self.position += 1;
+            false
+        } else {
+            true
+        };
+        if matches!(self.peek(), Some('*' | '+' | '?' | '{')) {
+            return Err(self.error("nothing to repeat"));
+        }
+        Ok(Node::Repeat(Box::new(atom), min, max, greedy))
+    }
+
+    fn parse_atom(&mut self) -> Result<Node, PatternError> {
+        let char = self.pattern[self.position];
+        match char {
+            '(' => {
+                self.position += 1;
+                let index = if self.pattern[self.position..].starts_with(&['?', ':']) {
+                    self.position += 2;
+                    None
+                } else {
+                    self.group_count += 1;
+                    Some(self.group_count)
+                };
+                let inner = self.parse_alternation()?;
+                if self.peek() != Some(')') {
+                    return Err(self.error("unclosed group"));
+                }
+                self.position += 1;
+                Ok(Node::Group(Box::new(inner), index))
+            }
+            '[' => self.parse_class(),
+            '*' | '+' | '?' | '{' => Err(self.error("nothing to repeat")),
+            '.' => {
+                self.position += 1;
+                Ok(Node::Any)
+            }
+            '^' => {
+                self.position += 1;
+                Ok(Node::Start)
+            }
+            '$' => {
+                self.position += 1;
+                Ok(Node::End)
+            }
+            '\\' => {
+                self.position += 1;
+                let (ranges, negated) = self.parse_escape()?;
+                if ranges.len() == 1 && ranges[0].0 == ranges[0].1 && !negated {
+                    Ok(Node::Char(ranges[0].0))
+                } else {
+                    Ok(Node::Class(ranges, negated))
+                }
+            }
+            char => {
+                self.position += 1;
+                Ok(Node::Char(char))
+            }
+        }
+    }
+
+    fn parse_escape(&mut self) -> Result<(Vec<(char, char)>, bool), PatternError> {
+        let char = self
+            .peek()
+            .ok_or_else(|| self.error("trailing backslash"))?;
+        self.position += 1;
+        let digits = vec![('0', '9')];
+        let word = vec![('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')];
+        let space = vec![(' ', ' '), ('\t', '\r')];
+        match char {
+            'd' => Ok((digits, false)),
+            'D' => Ok((digits, true)),
+            'w' => Ok((word, false)),
+            'W' => Ok((word, true)),
+            's' => Ok((space, false)),
+            'S' => Ok((space, true)),
+            'n' => Ok((vec![('\n', '\n')], false)),
+            't' => Ok((vec![('\t', '\t')], false)),
+            'r' => Ok((vec![('\r', '\r')], false)),
+            char if char.is_alphanumeric() => {
+                self.position -= 1;
+                Err(self.error("unknown escape"))
+            }
+            char => Ok((vec![(char, char)], false)),
+        }
+    }
+
+    fn parse_class(&mut self) -> Result<Node, PatternError> {
+        let start = self.position;
+        self.position += 1;
+        let negated = if self.peek() == Some('^') {
+            self.position += 1;
+            true
+        } else {
+            false
+        };
+        let mut ranges = vec![];
+        loop {
+            let char = match self.peek() {
+                Some(char) => char,
+                None => {
+                    self.position = start;
+                    return Err(self.error("unclosed character class"));
+                }
+            };
+            if char == ']' && !ranges.is_empty() {
+                self.position += 1;
+                break;
+            }
+            self.position += 1;
+            let low = if char == '\\' {
+                let (escaped, escaped_negated) = self.parse_escape()?;
+                if escaped_negated {
+                    self.position -= 2;
+                    return Err(self.error("negated escapes aren't supported in a class"));
+                }
+                if escaped.len() > 1 || escaped[0].0 != escaped[0].1 {
+                    ranges.extend(escaped);
+                    continue;
+                }
+                escaped[0].0
+            } else {
+                char
+            };
+            if self.peek() == Some('-') && self.pattern.get(self.position + 1) != Some(&']') {
+                self.position += 1;
+                let high = self
+                    .peek()
+                    .ok_or_else(|| self.error("unclosed character class"))?;
+                if high < low {
+                    return Err(self.error("character range is reversed"));
+                }
+                self.position += 1;
+                ranges.push((low, high));
+            } else {
+                ranges.push((low, low));
+            }
+        }
+        Ok(Node::Class(ranges, negated))
+    }
+}
+
+struct Compiler {
+    program: Vec<Instruction>,
+    register_count: usize,
+}
+
+impl Compiler {
+    fn compile(&mut self, node: &Node) {
+        // Past the limit the pattern is refused anyway, so there's no use going on.
+        if self.program.len() > MAX_INSTRUCTIONS {
+            return;
+        }
+        match node {
+            Node::Char(char) => self.program.push(Instruction::Char(*char)),
+            Node::Any => self.program.push(Instruction::Any),
+            Node::Class(ranges, negated) => self
+                .program
+                .push(Instruction::Class(ranges.clone(), *negated)),
+            Node::Start => self.program.push(Instruction::Start),
+            Node::End => self.program.push(Instruction::End),
+            Node::Concat(nodes) => {
+                for node in nodes {
+                    self.compile(node);
+                }
+            }
+            Node::Alternation(alternatives) => {
+                let mut jumps = vec![];
+                for (index, alternative) in alternatives.iter().enumerate() {
+                    if index + 1 == alternatives.len() {
+                        self.compile(alternative);
+                        break;
+                    }
+                    let split = self.placeholder();
+                    self.compile(alternative);
+                    jumps.push(self.placeholder());
+                    self.program[split] = Instruction::Split(split + 1, self.program.len());
+                }
+                let end = self.program.len();
+                for jump in jumps {
+                    self.program[jump] = Instruction::Jump(end);
+                }
+            }
+            Node::Group(inner, index) => match index {
+                Some(index) => {
+                    self.program.push(Instruction::Save(index * 2));
+                    self.compile(inner);
+                    self.program.push(Instruction::Save(index * 2 + 1));
+                }
+                None => self.compile(inner),
+            },
+            Node::Repeat(inner, min, max, greedy) => {
+                for _ in 0..*min {
+                    self.compile(inner);
+                }
+                match max {
+                    Some(max) => {
+                        // Each optional copy is skipped along with the ones after it.
+                        let mut splits = vec![];
+                        for _ in *min..*max {
+                            splits.push(self.placeholder());
+                            self.compile_iteration(inner);
+                        }
+                        let end = self.program.len();
+                        for split in splits {
+                            self.program[split] = self.split(split + 1, end, *greedy);
+                        }
+                    }
+                    None => {
+                        let split = self.placeholder();
+                        self.compile_iteration(inner);
+                        self.program.push(Instruction::Jump(split));
+                        self.program[split] = self.split(split + 1, self.program.len(), *greedy);
+                    }
+                }
+            }
+        }
+    }
+
+    // An iteration past the minimum count that consumes nothing can't make progress, so
+    // it fails rather than loop forever.
+    fn compile_iteration(&mut self, inner: &Node) {
+        let register = self.register_count;
+        self.register_count += 1;
+        self.program.push(Instruction::Mark(register));
+        self.compile(inner);
+        self.program.push(Instruction::Progress(register));
+    }
+
+    fn split(&self, repeat: usize, skip: usize, greedy: bool) -> Instruction {
+        if greedy {
+            Instruction::Split(repeat, skip)
+        } else {
+            Instruction::Split(skip, repeat)
+        }
+    }
+
+    // An instruction filled in once its targets are known.
+    fn placeholder(&mut self) -> usize {
+        self.program.push(Instruction::Match);
+        self.program.len() - 1
+    }
+}
+
+// What to do when the path being tried fails: try another one, or undo a slot set
+// along the failed path.
+enum Backtrack {
+    Branch(usize, usize),
+    Restore(usize, Option<usize>),
+}
+
+impl Regex {
+    // The captures of the first match starting exactly at `start`, trying alternatives
+    // in the order the pattern gives them like a recursive backtracking matcher would.
+    fn match_at<E>(
+        &self,
+        input: &[char],
+        start: usize,
+        charge: &mut impl FnMut() -> Result<(), E>,
+    ) -> Result<Option<Captures>, E> {
+        // Capture slots come first, two per group, then the registers of `Mark`.
+        let first_register = (self.group_count + 1) * 2;
+        let mut slots: Vec<Option<usize>> = vec![None; first_register + self.register_count];
+        let mut stack = vec![Backtrack::Branch(0, start)];
+        while let Some(backtrack) = stack.pop() {
+            let (mut pc, mut position) = match backtrack {
+                Backtrack::Branch(pc, position) => {
+                    charge()?;
+                    (pc, position)
+                }
+                Backtrack::Restore(slot, value) => {
+                    slots[slot] = value;
+                    continue;
+                }
+            };
+            loop {
+                match &self.program[pc] {
+                    Instruction::Char(char) if input.get(position) == Some(char) => position += 1,
+                    Instruction::Any if input.get(position).is_some_and(|char| *char != '\n') => {
+                        position += 1
+                    }
+                    Instruction::Class(ranges, negated)
+                        if input.get(position).is_some_and(|char| {
+                            ranges.iter().any(|(low, high)| low <= char && char <= high) != *negated
+                        }) =>
+                    {
+                        position += 1
+                    }
+                    Instruction::Start if position == 0 => {}
+                    Instruction::End if position == input.len() => {}
+                    Instruction::Split(first, second) => {
+                        stack.push(Backtrack::Branch(*second, position));
+                        pc = *first;
+                        continue;
+                    }
+                    Instruction::Jump(target) => {
+                        pc = *target;
+                        continue;
+                    }
+                    Instruction::Save(slot) => {
+                        stack.push(Backtrack::Restore(*slot, slots[*slot]));
+                        slots[*slot] = Some(position);
+                    }
+                    Instruction::Mark(register) => {
+                        let slot = first_register + register;
+                        stack.push(Backtrack::Restore(slot, slots[slot]));
+                        slots[slot] = Some(position);
+                    }
+                    Instruction::Progress(register)
+                        if slots[first_register + register] != Some(position) => {}
+                    Instruction::Match => {
+                        let mut captures: Captures = (0..=self.group_count)
+                            .map(|group| slots[group * 2].zip(slots[group * 2 + 1]))
+                            .collect();
+                        captures[0] = Some((start, position));
+                        return Ok(Some(captures));
+                    }
+                    // The instruction doesn't match here, so this path fails.
+                    _ => break,
+                }
+                pc += 1;
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compile_cached, Regex, CACHE, MAX_CACHED};
+
+    // Lets a search try as many paths as it takes.
+    fn free() -> Result<(), ()> {
+        Ok(())
+    }
+
+    fn find(pattern: &str, input: &str) -> Option<String> {
+        let input: Vec<char> = input.chars().collect();
+        Regex::new(pattern)
+            .unwrap()
+            .find_at(&input, 0, &mut free)
+            .unwrap()
+            .and_then(|captures| captures[0])
+            .map(|(start, end)| input[start..end].iter().collect())
+    }
+
+    #[test]
+    fn test_regex_find() {
+        assert_eq!(find("a+b", "xaaab"), Some("aaab".to_string()));
+        assert_eq!(find("^a", "ba"), None);
+        assert_eq!(find("\\d{2,3}", "a12345"), Some("123".to_string()));
+        assert_eq!(find("[^a-c]+", "abcdef"), Some("def".to_string()));
+        assert_eq!(find("(cat|dog)s?$", "hotdogs"), Some("dogs".to_string()));
+        assert_eq!(find("a.*?b", "aXbYb"), Some("aXb".to_string()));
+        assert_eq!(find("(a*)*b", "aaab"), Some("aaab".to_string()));
+    }
+
+    #[test]
+    fn test_regex_long_input() {
+        let input = "a".repeat(200_000);
+        assert_eq!(find(".*", &input), Some(input.clone()));
+        assert_eq!(find("(a|b)*?$", &input), Some(input.clone()));
+        assert_eq!(find("a*b", &input[..2_000]), None);
+    }
+
+    #[test]
+    fn test_regex_captures() {
+        let input: Vec<char> = "xab".chars().collect();
+        let regex = Regex::new("(a*)?(b)|(x)").unwrap();
+        assert_eq!(
+            regex.find_at(&input, 1, &mut free),
+            Ok(Some(vec![Some((1, 3)), Some((1, 2)), Some((2, 3)), None]))
+        );
+        // An optional iteration matching nothing doesn't count, leaving its group unset.
+        let regex = Regex::new("(a*)?b").unwrap();
+        assert_eq!(
+            regex.find_at(&input, 2, &mut free),
+            Ok(Some(vec![Some((2, 3)), None]))
+        );
+    }
+
+    #[test]
+    fn test_regex_cache_is_bounded() {
+        for n in 0..MAX_CACHED * 2 {
+            compile_cached(&format!("a{{{n}}}")).unwrap();
+        }
+        assert!(CACHE.with(|cache| cache.borrow().len()) <= MAX_CACHED);
+    }
+
+    #[test]
+    fn test_regex_invalid_pattern() {
+        let error = Regex::new("ab(c").unwrap_err();
+        assert_eq!(error.message, "unclosed group");
+        assert_eq!(error.position, 4);
+
+        let error = Regex::new("a)").unwrap_err();
+        assert_eq!(error.position, 1);
+
+        let error = Regex::new("*a").unwrap_err();
+        assert_eq!(error.message, "nothing to repeat");
+        assert_eq!(error.position, 0);
+
+        let error = Regex::new("(a{1000}){1000}").unwrap_err();
+        assert_eq!(error.message, "pattern is too large");
+
+        for pattern in [
+            "a{1000000000000}",
+            "a{1,1000000}",
+            "a{99999999999999999999999,}",
+        ] {
+            let error = Regex::new(pattern).unwrap_err();
+            assert_eq!(error.message, "repetition count is too large", "{pattern}");
+        }
+    }
+
+    #[test]
+    fn test_regex_search_stops_when_charged() {
+        // Every `a` can be matched either way, so failing tries 2^29 paths.
+        let regex = Regex::new("(a|a)*b").unwrap();
+        let input: Vec<char> = "a".repeat(29).chars().collect();
+        let mut paths = 0;
+        let mut charge = || {
+            paths += 1;
+            if paths > 10_000 {
+                Err("too many paths")
+            } else {
+                Ok(())
+            }
+        };
+        assert_eq!(regex.find_at(&input, 0, &mut charge), Err("too many paths"));
+        assert_eq!(regex.find_all(&input, &mut charge), Err("too many paths"));
+    }
+}
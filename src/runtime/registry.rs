@@ -0,0 +1,194 @@
+use std::rc::Rc;
+
+use super::{
+    builtin::register_builtins,
+    types::{NativeFunction, NativeFunctionPointer, Value},
+};
+
+// A builtin value along with the description `help` shows for it. Modules keep their
+// members around so they can be described too.
+#[derive(Debug, Clone)]
+pub struct Builtin {
+    pub name: String,
+    pub value: Value,
+    pub description: String,
+    pub members: Vec<Builtin>,
+}
+
+pub fn constant(name: &str, value: Value, description: &str) -> Builtin {
+    Builtin {
+        name: name.to_string(),
+        value,
+        description: description.to_string(),
+        members: vec![],
+    }
+}
+
+// The name of a function is taken from its signature, e.g. `find` for
+// `regex.find(pattern, input)`.
+pub fn function(signature: &str, function: NativeFunctionPointer, description: &str) -> Builtin {
+    let function = NativeFunction::new(signature, function);
+    Builtin {
+        name: function
+            .name
+            .rsplit('.')
+            .next()
+            .unwrap_or_default()
+            .to_string(),
+        value: Value::NativeFunction(function),
+        description: description.to_string(),
+        members: vec![],
+    }
+}
+
+pub fn module(name: &str, description: &str, members: Vec<Builtin>) -> Builtin {
+    let mut module = Builtin {
+        name: name.to_string(),
+        value: Value::None,
+        description: description.to_string(),
+        members,
+    };
+    module.update_value();
+    module
+}
+
+impl Builtin {
+    // A module is an object of its members' values, so it's rebuilt whenever they change.
+    fn update_value(&mut self) {
+        self.value = Value::Object(
+            self.members
+                .iter()
+                .map(|member| (member.name.clone(), Box::new(member.value.clone())))
+                .collect(),
+        );
+    }
+}
+
+thread_local! {
+    static GLOBAL: Rc<BuiltinRegistry> = Rc::new(BuiltinRegistry::standard());
+}
+
+// The builtins environments fall back to for the names they don't define. It's built
+// once and shared by every environment using it, which is why embedders add their own
+// builtins to a registry before creating the environment with it.
+#[derive(Debug, Clone, Default)]
+pub struct BuiltinRegistry {
+    builtins: Vec<Builtin>,
+}
+
+impl BuiltinRegistry {
+    // Every builtin fns comes with.
+    pub fn standard() -> Self {
+        let mut registry = Self::default();
+        register_builtins(&mut registry);
+        registry
+    }
+
+    // The standard builtins, built the first time they're asked for.
+    pub fn global() -> Rc<Self> {
+        GLOBAL.with(Rc::clone)
+    }
+
+    // Registering a name again replaces what it was registered as.
+    pub fn register(&mut self, builtin: Builtin) {
+        insert(&mut self.builtins, builtin);
+    }
+
+    // Registers a value under a path like `answer` or `config.port`, creating the
+    // modules along the path that don't exist yet.
+    pub fn register_value(&mut self, path: &str, value: Value) {
+        let mut segments: Vec<&str> = path.split('.').collect();
+        let name = segments.pop().unwrap_or_default();
+        insert_at(&mut self.builtins, &segments, constant(name, value, ""));
+    }
+
+    pub fn register_module(&mut self, name: &str, description: &str, entries: Vec<Builtin>) {
+        self.register(module(name, description, entries));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Builtin> {
+        self.builtins.iter().find(|builtin| builtin.name == name)
+    }
+
+    pub fn builtins(&self) -> &[Builtin] {
+        &self.builtins
+    }
+}
+
+fn insert(builtins: &mut Vec<Builtin>, builtin: Builtin) {
+    match builtins.iter_mut().find(|b| b.name == builtin.name) {
+        Some(existing) => *existing = builtin,
+        None => builtins.push(builtin),
+    }
+}
+
+fn insert_at(builtins: &mut Vec<Builtin>, modules: &[&str], builtin: Builtin) {
+    match modules.split_first() {
+        None => insert(builtins, builtin),
+        Some((name, rest)) => {
+            let index = match builtins.iter().position(|b| b.name == *name) {
+                Some(index) => index,
+                None => {
+                    builtins.push(module(name, "", vec![]));
+                    builtins.len() - 1
+                }
+            };
+            let module = &mut builtins[index];
+            insert_at(&mut module.members, rest, builtin);
+            module.update_value();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::{
+        frontend::utils::{Error, TextSpan},
+        run_source_with,
+        runtime::{environment::Environment, types::Value},
+    };
+
+    use super::{constant, function, BuiltinRegistry};
+
+    fn double(
+        arguments: Vec<Value>,
+        text_span: TextSpan,
+        _: &mut Environment,
+    ) -> Result<Value, Error> {
+        match arguments.as_slice() {
+            [Value::Integer(n)] => Ok(Value::Integer(n * 2)),
+            _ => Err(Error::new("Can't double that".to_string(), text_span)),
+        }
+    }
+
+    #[test]
+    fn test_register_custom_module() {
+        let mut registry = BuiltinRegistry::standard();
+        registry.register_module(
+            "app",
+            "helpers of the embedding application",
+            vec![
+                function("app.double(n)", double, "twice n"),
+                constant("name", Value::String("demo".to_string().into()), "its name"),
+            ],
+        );
+        registry.register_value("app.config.port", Value::Integer(8080));
+        registry.register_value("answer", Value::Integer(42));
+
+        let mut environment = Environment::with_builtins(Rc::new(registry));
+        let value = run_source_with(
+            "let config = app.config let result = (app.double(answer), app.name, config.port, len(\"ab\")) result",
+            &mut environment,
+        )
+        .unwrap();
+        assert_eq!(value.to_string(), "(84, demo, 8080, 2)");
+
+        // Environments made from it share the registry, and the global one is untouched.
+        let child = Environment::new(Some(environment));
+        assert!(child.access("answer").is_some());
+        assert!(Environment::new(None).access("answer").is_none());
+        assert_eq!(child.is_constant("app"), Some(true));
+    }
+}
@@ -0,0 +1,105 @@
+use std::{fmt::Debug, io::Write};
+
+use crate::frontend::{
+    ast::Statement,
+    utils::{ends_line, Error, TextSpan},
+};
+
+use super::{environment::Environment, types::Value};
+
+// Hooks invoked by the evaluator around every statement it evaluates. `depth` is the
+// number of function calls the statement is nested in. Returning an error from
+// `before_statement` aborts the evaluation with it.
+pub trait Tracer: Debug {
+    fn before_statement(
+        &mut self,
+        statement: &Statement,
+        depth: usize,
+        environment: &Environment,
+    ) -> Result<(), Error>;
+    fn after_statement(&mut self, statement: &Statement, value: &Value, depth: usize);
+}
+
+// Writes the line and the text of each statement before it's evaluated and the value
+// it evaluated to after, indented by the depth of the statement.
+#[derive(Debug)]
+pub struct StatementTracer<W: Write + Debug> {
+    source_code: Vec<char>,
+    pub output: W,
+}
+
+impl<W: Write + Debug> StatementTracer<W> {
+    pub fn new(source_code: &str, output: W) -> Self {
+        Self {
+            source_code: source_code.chars().collect(),
+            output,
+        }
+    }
+}
+
+impl<W: Write + Debug> Tracer for StatementTracer<W> {
+    fn before_statement(
+        &mut self,
+        statement: &Statement,
+        depth: usize,
+        _: &Environment,
+    ) -> Result<(), Error> {
+        let description = describe_statement(&self.source_code, statement);
+        // Tracing is best effort, a failing output shouldn't stop the evaluation.
+        let _ = writeln!(self.output, "{}{description}", "  ".repeat(depth));
+        Ok(())
+    }
+
+    fn after_statement(&mut self, _: &Statement, value: &Value, depth: usize) {
+        let _ = writeln!(self.output, "{}  => {value}", "  ".repeat(depth));
+    }
+}
+
+pub fn describe_statement(source_code: &[char], statement: &Statement) -> String {
+    describe_span(source_code, &statement.text_span())
+}
+
+// Describes a span by its line and its text, of which only the first line is kept for
+// spans covering several lines.
+pub fn describe_span(source_code: &[char], text_span: &TextSpan) -> String {
+    let end = text_span.ending_index.min(source_code.len());
+    let start = text_span.starting_index.min(end);
+    let line = (0..start)
+        .filter(|index| ends_line(source_code, *index))
+        .count()
+        + 1;
+    let text: String = source_code[start..end].iter().collect();
+    match text.split_once(['\n', '\r']) {
+        Some((first_line, _)) => format!("[line {line}] {} ...", first_line.trim_end()),
+        None => format!("[line {line}] {text}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use crate::{
+        frontend::{parser::parse, tokenizer::tokenize},
+        runtime::{environment::Environment, evaluator::evaluate},
+    };
+
+    use super::StatementTracer;
+
+    #[test]
+    fn test_statement_tracer() {
+        let src = "let double = fn(x) { x * 2 }\nlet a = double(2)\na + 1";
+        let tracer = Rc::new(RefCell::new(StatementTracer::new(src, vec![])));
+        let mut environment = Environment::new(None);
+        environment.tracer = Some(tracer.clone());
+        evaluate(parse(tokenize(src).unwrap()).unwrap(), Some(environment)).unwrap();
+
+        let output = String::from_utf8(tracer.borrow().output.clone()).unwrap();
+        assert_eq!(
+            output,
+            "[line 1] let double = fn(x) { x * 2 }\n  => none\n\
+             [line 2] let a = double(2)\n  [line 1] x * 2\n    => 4\n  => none\n\
+             [line 3] a + 1\n  => 5\n"
+        );
+    }
+}
@@ -1,34 +1,1259 @@
-use std::{collections::HashMap, fmt};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeSet, HashMap},
+    convert::Infallible,
+    fmt,
+    hash::{Hash, Hasher},
+    rc::Rc,
+};
 
-use crate::frontend::ast::Number;
+use crate::frontend::{
+    ast::{Number, Program},
+    symbol::Symbol,
+    utils::{Error, TextSpan},
+};
 
-#[derive(Debug, PartialEq, Clone)]
+use super::environment::Environment;
+
+#[derive(Debug, Clone)]
 pub enum Value {
     Object(HashMap<String, Box<Value>>),
-    String(String),
+    Function(Function),
+    NativeFunction(NativeFunction),
+    Set(BTreeSet<SetElement>),
+    Array(Vec<Value>),
+    Tuple(Vec<Value>),
+    String(Rc<String>),
+    // Numbers written without a decimal point, and what integer arithmetic gives.
+    Integer(i64),
     Number(Number),
     Boolean(bool),
     None,
 }
 
-impl fmt::Display for Value {
+// Integers and floats are equal when they're the same number, `1 == 1.0`, and NaN is
+// unequal to everything, itself included.
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Object(a), Value::Object(b)) => a == b,
+            (Value::Function(a), Value::Function(b)) => a == b,
+            (Value::NativeFunction(a), Value::NativeFunction(b)) => a == b,
+            (Value::Set(a), Value::Set(b)) => a == b,
+            (Value::Array(a), Value::Array(b)) | (Value::Tuple(a), Value::Tuple(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Boolean(a), Value::Boolean(b)) => a == b,
+            (Value::None, Value::None) => true,
+            (a, b) => numeric_order(a, b) == Some(Ordering::Equal),
+        }
+    }
+}
+
+// A function keeps the environment it was created in, so the variables it refers to
+// outlive the call that created it. It's boxed, being far larger than any other value.
+#[derive(Clone)]
+pub struct Function {
+    pub parameters: Vec<Symbol>,
+    pub body: Rc<Program>,
+    pub closure: Box<Environment>,
+}
+
+impl Function {
+    pub fn new(parameters: Vec<Symbol>, body: Rc<Program>, closure: Environment) -> Self {
+        Self {
+            parameters,
+            body,
+            closure: Box::new(closure),
+        }
+    }
+}
+
+// The closure is left out as it usually holds the function itself.
+impl fmt::Debug for Function {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Function")
+            .field("parameters", &self.parameters)
+            .field("body", &self.body)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.body, &other.body)
+    }
+}
+
+pub type NativeFunctionPointer = fn(Vec<Value>, TextSpan, &mut Environment) -> Result<Value, Error>;
+
+#[derive(Debug, Clone)]
+pub struct NativeFunction {
+    pub name: String,
+    pub signature: String,
+    pub parameters: Vec<Parameter>,
+    pub function: NativeFunctionPointer,
+}
+
+// A parameter of a native function as written in its signature: `x` takes any value,
+// `x: number` only numbers, `x?` may be left out and `xs...` takes all the remaining
+// arguments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub name: String,
+    pub type_name: Option<String>,
+    pub is_optional: bool,
+    pub is_variadic: bool,
+}
+
+impl Parameter {
+    fn parse(parameter: &str) -> Self {
+        let (name, type_name) = match parameter.split_once(':') {
+            Some((name, type_name)) => (name.trim(), Some(type_name.trim().to_string())),
+            None => (parameter.trim(), None),
+        };
+        let is_variadic = name.ends_with("...");
+        let is_optional = name.ends_with('?');
+        Self {
+            name: name
+                .trim_end_matches("...")
+                .trim_end_matches('?')
+                .to_string(),
+            type_name,
+            is_optional,
+            is_variadic,
+        }
+    }
+}
+
+impl fmt::Display for Parameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let suffix = match (self.is_optional, self.is_variadic) {
+            (true, _) => "?",
+            (_, true) => "...",
+            _ => "",
+        };
+        write!(f, "{}{suffix}", self.name)
+    }
+}
+
+impl NativeFunction {
+    // The name is the part of the signature before its parameters, e.g. `regex.find`
+    // for `regex.find(pattern: string, input: string)`.
+    pub fn new(signature: &str, function: NativeFunctionPointer) -> Self {
+        let (name, parameters) = match signature.split_once('(') {
+            Some((name, parameters)) => (name, parameters.trim_end_matches(')')),
+            None => (signature, ""),
+        };
+        Self {
+            name: name.to_string(),
+            signature: signature.to_string(),
+            parameters: parameters
+                .split(',')
+                .filter(|parameter| !parameter.trim().is_empty())
+                .map(Parameter::parse)
+                .collect(),
+            function,
+        }
+    }
+
+    // Checks the arguments against the parameters before the function is called, so
+    // its body can take their count and types for granted.
+    pub fn check_arguments(&self, arguments: &[Value], text_span: &TextSpan) -> Result<(), Error> {
+        let required = self
+            .parameters
+            .iter()
+            .filter(|parameter| !parameter.is_optional && !parameter.is_variadic)
+            .count();
+        let maximum = if self
+            .parameters
+            .iter()
+            .any(|parameter| parameter.is_variadic)
+        {
+            None
+        } else {
+            Some(self.parameters.len())
+        };
+        if arguments.len() < required || maximum.is_some_and(|maximum| arguments.len() > maximum) {
+            let expected = match maximum {
+                Some(maximum) if maximum == required => plural(required, "argument"),
+                Some(maximum) => format!("{required} to {maximum} arguments"),
+                None => format!("at least {}", plural(required, "argument")),
+            };
+            let parameters: Vec<String> = self.parameters.iter().map(|p| p.to_string()).collect();
+            let parameters = if parameters.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", parameters.join(", "))
+            };
+            return Err(Error::new(
+                format!(
+                    "{} expects {expected}{parameters}, got {}",
+                    self.name,
+                    arguments.len()
+                ),
+                text_span.clone(),
+            ));
+        }
+
+        for (index, argument) in arguments.iter().enumerate() {
+            let parameter = &self.parameters[index.min(self.parameters.len() - 1)];
+            if let Some(type_name) = &parameter.type_name {
+                if argument.type_name() != type_name {
+                    return Err(Error::new(
+                        format!(
+                            "{} expects {} to be {}, got {}",
+                            self.name,
+                            parameter.name,
+                            with_article(type_name),
+                            with_article(argument.type_name())
+                        ),
+                        text_span.clone(),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("{count} {noun}")
+    } else {
+        format!("{count} {noun}s")
+    }
+}
+
+fn with_article(type_name: &str) -> String {
+    if type_name.starts_with(['a', 'e', 'i', 'o', 'u']) {
+        format!("an {type_name}")
+    } else {
+        format!("a {type_name}")
+    }
+}
+
+impl PartialEq for NativeFunction {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+// Only values with a total order can live in a set. Elements are ordered by kind
+// first (none < booleans < numbers < strings) and then by their content, numbers
+// using the IEEE total order with -0 folded into 0. Whole floats an integer can hold
+// are kept as that integer, so `set(1, 1.0)` has one element.
+#[derive(Debug, Clone)]
+pub enum SetElement {
+    None,
+    Boolean(bool),
+    Integer(i64),
+    Number(Number),
+    String(Rc<String>),
+}
+
+impl SetElement {
+    pub fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::None => Some(SetElement::None),
+            Value::Boolean(b) => Some(SetElement::Boolean(*b)),
+            Value::Integer(i) => Some(SetElement::Integer(*i)),
+            Value::Number(n) => Some(match whole_integer(*n) {
+                Some(i) => SetElement::Integer(i),
+                None => SetElement::Number(*n),
+            }),
+            Value::String(s) => Some(SetElement::String(s.clone())),
+            _ => None,
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        match self {
+            SetElement::None => Value::None,
+            SetElement::Boolean(b) => Value::Boolean(*b),
+            SetElement::Integer(i) => Value::Integer(*i),
+            SetElement::Number(n) => Value::Number(*n),
+            SetElement::String(s) => Value::String(s.clone()),
+        }
+    }
+
+    fn rank(&self) -> u8 {
         match self {
-            Value::Object(o) => {
-                if o.is_empty() {
-                    write!(f, "{{}}")
-                } else {
-                    writeln!(f, "{{")?;
-                    for (key, value) in o.iter() {
-                        writeln!(f, "  {key} : {value}")?;
+            SetElement::None => 0,
+            SetElement::Boolean(_) => 1,
+            SetElement::Integer(_) | SetElement::Number(_) => 2,
+            SetElement::String(_) => 3,
+        }
+    }
+}
+
+impl Ord for SetElement {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (SetElement::Boolean(a), SetElement::Boolean(b)) => a.cmp(b),
+            (SetElement::Integer(a), SetElement::Integer(b)) => a.cmp(b),
+            (SetElement::Number(a), SetElement::Number(b)) => a.total_cmp(b),
+            (SetElement::Integer(a), SetElement::Number(b)) => compare_integer_with_float(*a, *b),
+            (SetElement::Number(a), SetElement::Integer(b)) => {
+                compare_integer_with_float(*b, *a).reverse()
+            }
+            (SetElement::String(a), SetElement::String(b)) => a.cmp(b),
+            (a, b) => a.rank().cmp(&b.rank()),
+        }
+    }
+}
+
+impl PartialOrd for SetElement {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for SetElement {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SetElement {}
+
+// The most chars of a value error messages show.
+const SHORT_REPR_LIMIT: usize = 40;
+
+impl Value {
+    // A value the way error messages show it: on one line, with quotes and backslashes
+    // escaped so it can't break the quoting around it and cut short past a few dozen
+    // chars. Objects too long to show are summed up by their number of keys.
+    pub fn short_repr(&self) -> String {
+        let text = match self {
+            Value::Object(object) if !object.is_empty() => {
+                let mut pairs: Vec<String> = object
+                    .iter()
+                    .map(|(key, value)| format!("{}: {}", escape(key), value.short_repr()))
+                    .collect();
+                pairs.sort();
+                let text = format!("{{{}}}", pairs.join(", "));
+                if text.chars().count() > SHORT_REPR_LIMIT {
+                    return format!("{{…{} keys…}}", object.len());
+                }
+                text
+            }
+            value => escape(&value.to_string()),
+        };
+        elide(text, SHORT_REPR_LIMIT)
+    }
+
+    // A total order over every value, for sorting and for keys. Values of different kinds
+    // are ordered by kind: none, booleans, numbers, strings, arrays, tuples, sets,
+    // objects, functions and native functions. Numbers are ordered numerically whether
+    // they're integers or floats, with -0 equal to 0 and every NaN equal to the others and
+    // after all other numbers. Strings are ordered by their chars, arrays, tuples and sets
+    // element by element and objects entry by entry in the order of their keys, where a
+    // shorter sequence comes before a longer one it starts. Functions have no meaningful
+    // order and are only ordered by identity, their definition for functions and their
+    // name for native ones.
+    pub fn compare(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Value::Boolean(a), Value::Boolean(b)) => a.cmp(b),
+            (Value::Integer(a), Value::Integer(b)) => a.cmp(b),
+            (Value::Number(a), Value::Number(b)) => compare_numbers(*a, *b),
+            (Value::Integer(a), Value::Number(b)) => compare_integer_with_float(*a, *b),
+            (Value::Number(a), Value::Integer(b)) => compare_integer_with_float(*b, *a).reverse(),
+            (Value::String(a), Value::String(b)) => a.cmp(b),
+            (Value::Array(a), Value::Array(b)) | (Value::Tuple(a), Value::Tuple(b)) => {
+                compare_sequences(a.iter(), b.iter())
+            }
+            (Value::Set(a), Value::Set(b)) => {
+                let a: Vec<Value> = a.iter().map(SetElement::to_value).collect();
+                let b: Vec<Value> = b.iter().map(SetElement::to_value).collect();
+                compare_sequences(a.iter(), b.iter())
+            }
+            (Value::Object(a), Value::Object(b)) => {
+                let (a, b) = (sorted_entries(a), sorted_entries(b));
+                for ((a_key, a_value), (b_key, b_value)) in a.iter().zip(b.iter()) {
+                    let ordering = a_key.cmp(b_key).then_with(|| a_value.compare(b_value));
+                    if ordering != Ordering::Equal {
+                        return ordering;
                     }
-                    write!(f, "}}")
                 }
+                a.len().cmp(&b.len())
+            }
+            (Value::Function(a), Value::Function(b)) => {
+                Rc::as_ptr(&a.body).cmp(&Rc::as_ptr(&b.body))
+            }
+            (Value::NativeFunction(a), Value::NativeFunction(b)) => a.name.cmp(&b.name),
+            (a, b) => a.rank().cmp(&b.rank()),
+        }
+    }
+
+    // Hashes a value consistently with `compare`: values it finds equal hash the same,
+    // so -0 hashes like 0, every NaN like the others and whole floats like the integer
+    // they're equal to. Other floats are hashed by their bits and functions by their
+    // identity.
+    pub fn hash_into<H: Hasher>(&self, state: &mut H) {
+        self.rank().hash(state);
+        match self {
+            Value::Boolean(b) => b.hash(state),
+            Value::Integer(i) => i.hash(state),
+            Value::Number(n) => match whole_integer(*n) {
+                Some(i) => i.hash(state),
+                None => canonical_number(*n).to_bits().hash(state),
+            },
+            Value::String(s) => s.hash(state),
+            Value::Array(elements) | Value::Tuple(elements) => {
+                elements.len().hash(state);
+                for element in elements {
+                    element.hash_into(state);
+                }
+            }
+            Value::Set(elements) => {
+                elements.len().hash(state);
+                for element in elements {
+                    element.to_value().hash_into(state);
+                }
+            }
+            Value::Object(object) => {
+                object.len().hash(state);
+                for (key, value) in sorted_entries(object) {
+                    key.hash(state);
+                    value.hash_into(state);
+                }
+            }
+            Value::Function(f) => Rc::as_ptr(&f.body).hash(state),
+            Value::NativeFunction(f) => f.name.hash(state),
+            Value::None => {}
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            Value::None => 0,
+            Value::Boolean(_) => 1,
+            Value::Integer(_) | Value::Number(_) => 2,
+            Value::String(_) => 3,
+            Value::Array(_) => 4,
+            Value::Tuple(_) => 5,
+            Value::Set(_) => 6,
+            Value::Object(_) => 7,
+            Value::Function(_) => 8,
+            Value::NativeFunction(_) => 9,
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Object(_) => "object",
+            Value::Function(_) | Value::NativeFunction(_) => "function",
+            Value::Set(_) => "set",
+            Value::Array(_) => "array",
+            Value::Tuple(_) => "tuple",
+            Value::String(_) => "string",
+            Value::Integer(_) | Value::Number(_) => "number",
+            Value::Boolean(_) => "boolean",
+            Value::None => "none",
+        }
+    }
+
+    // What '&&' and '||' go by: `none` and `false` are falsy, everything else, 0 and
+    // empty strings included, is truthy.
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Value::None | Value::Boolean(false))
+    }
+}
+
+// Accessors for hosts reading the values code gives back, without matching on them.
+impl Value {
+    /// The value at a dotted path of object keys, a number being the position of an
+    /// element of an array or tuple, or `None` when there's nothing there.
+    ///
+    /// ```
+    /// use fns::run_source;
+    ///
+    /// let config = run_source("{server: {port: 8080, hosts: [\"a\", \"b\"]}, debug: true}").unwrap();
+    /// assert_eq!(config.get("server.port").and_then(|port| port.as_number()), Some(8080.0));
+    /// assert_eq!(config.get("server.hosts.1").and_then(|host| host.as_str()), Some("b"));
+    /// assert_eq!(config.get("debug").and_then(|debug| debug.as_bool()), Some(true));
+    /// assert_eq!(config.get("server.port.number"), None);
+    /// ```
+    pub fn get(&self, path: &str) -> Option<&Value> {
+        self.try_get(path).ok()
+    }
+
+    /// Like `get`, but telling which segment of the path is missing.
+    ///
+    /// ```
+    /// use fns::run_source;
+    ///
+    /// let config = run_source("{server: {port: 8080}}").unwrap();
+    /// assert_eq!(
+    ///     config.try_get("server.host").unwrap_err(),
+    ///     "Can't get 'server.host' as 'server' has no key 'host'"
+    /// );
+    /// assert_eq!(
+    ///     config.try_get("server.port.number").unwrap_err(),
+    ///     "Can't get 'server.port.number' as 'server.port' is a number"
+    /// );
+    /// assert_eq!(config.try_get_number("server.port"), Ok(8080.0));
+    /// ```
+    pub fn try_get(&self, path: &str) -> Result<&Value, String> {
+        let mut value = self;
+        let mut walked = 0;
+        for key in path.split('.') {
+            let parent = match walked {
+                0 => "the value".to_string(),
+                _ => format!("'{}'", &path[..walked - 1]),
+            };
+            let next = match value {
+                Value::Object(object) => object.get(key).map(|value| &**value),
+                Value::Array(elements) | Value::Tuple(elements) => key
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| elements.get(index)),
+                _ => {
+                    return Err(format!(
+                        "Can't get '{path}' as {parent} is a {}",
+                        value.type_name()
+                    ))
+                }
+            };
+            value = next.ok_or_else(|| match value {
+                Value::Object(_) => format!("Can't get '{path}' as {parent} has no key '{key}'"),
+                _ => format!("Can't get '{path}' as {parent} has no element '{key}'"),
+            })?;
+            walked += key.len() + 1;
+        }
+        Ok(value)
+    }
+
+    pub fn try_get_number(&self, path: &str) -> Result<Number, String> {
+        let value = self.try_get(path)?;
+        value
+            .as_number()
+            .ok_or_else(|| mismatch(path, "number", value))
+    }
+
+    pub fn try_get_str(&self, path: &str) -> Result<&str, String> {
+        let value = self.try_get(path)?;
+        value
+            .as_str()
+            .ok_or_else(|| mismatch(path, "string", value))
+    }
+
+    pub fn try_get_bool(&self, path: &str) -> Result<bool, String> {
+        let value = self.try_get(path)?;
+        value
+            .as_bool()
+            .ok_or_else(|| mismatch(path, "boolean", value))
+    }
+
+    // Integers as floats, for hosts that don't tell them apart.
+    pub fn as_number(&self) -> Option<Number> {
+        match self {
+            Value::Integer(i) => Some(*i as Number),
+            Value::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    // Whole floats an integer can hold as well as integers.
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            Value::Integer(i) => Some(*i),
+            Value::Number(n) => whole_integer(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&HashMap<String, Box<Value>>> {
+        match self {
+            Value::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(elements) => Some(elements),
+            _ => None,
+        }
+    }
+}
+
+fn mismatch(path: &str, expected: &str, value: &Value) -> String {
+    format!(
+        "Can't get '{path}' as a {expected} as it's a {}",
+        value.type_name()
+    )
+}
+
+fn compare_numbers(a: Number, b: Number) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        (false, false) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+    }
+}
+
+// How an integer and a float compare, exactly rather than by turning the integer into
+// a float, which would round large ones. NaN comes after every integer.
+pub fn compare_integer_with_float(i: i64, n: Number) -> Ordering {
+    if n.is_nan() {
+        return Ordering::Less;
+    }
+    match whole_integer(n.trunc()) {
+        Some(whole) => i.cmp(&whole).then(if n.fract() > 0. {
+            Ordering::Less
+        } else if n.fract() < 0. {
+            Ordering::Greater
+        } else {
+            Ordering::Equal
+        }),
+        // Past the range of integers on either side.
+        None if n > 0. => Ordering::Less,
+        None => Ordering::Greater,
+    }
+}
+
+// How two numbers of either kind compare, `None` for NaN or values that aren't numbers.
+pub fn numeric_order(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Integer(a), Value::Integer(b)) => Some(a.cmp(b)),
+        (Value::Number(a), Value::Number(b)) => a.partial_cmp(b),
+        (Value::Integer(a), Value::Number(b)) if !b.is_nan() => {
+            Some(compare_integer_with_float(*a, *b))
+        }
+        (Value::Number(a), Value::Integer(b)) if !a.is_nan() => {
+            Some(compare_integer_with_float(*b, *a).reverse())
+        }
+        _ => None,
+    }
+}
+
+// The integer a whole float stands for, when an integer can hold it.
+pub fn whole_integer(n: Number) -> Option<i64> {
+    // 2^63, the first float past the largest integer.
+    const LIMIT: Number = 9_223_372_036_854_775_808.;
+    (n.fract() == 0. && (-LIMIT..LIMIT).contains(&n)).then_some(n as i64)
+}
+
+// The one number of those `compare_numbers` finds equal that stands for all of them.
+fn canonical_number(n: Number) -> Number {
+    if n.is_nan() {
+        Number::NAN
+    } else if n == 0. {
+        0.
+    } else {
+        n
+    }
+}
+
+fn compare_sequences<'a>(
+    mut a: impl Iterator<Item = &'a Value>,
+    mut b: impl Iterator<Item = &'a Value>,
+) -> Ordering {
+    loop {
+        match (a.next(), b.next()) {
+            (Some(a), Some(b)) => match a.compare(b) {
+                Ordering::Equal => continue,
+                ordering => return ordering,
+            },
+            (Some(_), None) => return Ordering::Greater,
+            (None, Some(_)) => return Ordering::Less,
+            (None, None) => return Ordering::Equal,
+        }
+    }
+}
+
+fn sorted_entries(object: &HashMap<String, Box<Value>>) -> Vec<(&String, &Value)> {
+    let mut entries: Vec<(&String, &Value)> = object
+        .iter()
+        .map(|(key, value)| (key, value.as_ref()))
+        .collect();
+    entries.sort_by_key(|(key, _)| *key);
+    entries
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for char in text.chars() {
+        match char {
+            '\\' => escaped.push_str("\\\\"),
+            '\'' => escaped.push_str("\\'"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            char => escaped.push(char),
+        }
+    }
+    escaped
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&ValuePrinter::default().print(self))
+    }
+}
+
+// How values are written out. `Display` uses the default configuration, and so does
+// `print` unless the host changes the environment's printer, while the repl echoes values
+// with `ValuePrinter::repl`. Values never alias each other, so a value can't contain
+// itself and there's no cycle to look out for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValuePrinter {
+    // The most chars a line holding an object on its own can take, past which the object
+    // gets a line per key. With 0 every object does.
+    pub max_width: usize,
+    // Whether the keys of objects are sorted, rather than in the order of the object,
+    // which isn't the order they were added in.
+    pub sort_keys: bool,
+    // Whether strings are written as string literals, in quotes and with escapes.
+    pub quote_strings: bool,
+    // How deep elements are shown, the ones of containers nested deeper being `…`.
+    pub max_depth: usize,
+    // The spaces each level of nesting indents the keys of an object by.
+    pub indent: usize,
+    // The most chars of the output, past which it's cut with `…`.
+    pub max_length: usize,
+}
+
+impl Default for ValuePrinter {
+    fn default() -> Self {
+        Self {
+            max_width: 0,
+            sort_keys: false,
+            quote_strings: false,
+            max_depth: 8,
+            indent: 2,
+            max_length: 10_000,
+        }
+    }
+}
+
+// Asked about every object before it's written, giving the text to write in its place.
+pub type PrintHook<'a, E> = dyn FnMut(&Value) -> Result<Option<String>, E> + 'a;
+
+impl ValuePrinter {
+    // How the repl echoes values: strings quoted so `"1"` isn't taken for `1`, keys in a
+    // stable order and small objects on one line.
+    pub fn repl() -> Self {
+        Self {
+            max_width: 80,
+            sort_keys: true,
+            quote_strings: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn print(&self, value: &Value) -> String {
+        self.print_with(value, &mut |_| Ok::<_, Infallible>(None))
+            .unwrap_or_else(|never| match never {})
+    }
+
+    pub fn print_with<E>(&self, value: &Value, hook: &mut PrintHook<E>) -> Result<String, E> {
+        let text = self.value(value, self.max_depth, 0, 0, hook)?;
+        Ok(elide(text, self.max_length))
+    }
+
+    // The value written from `column` on a line indented `level` times.
+    fn value<E>(
+        &self,
+        value: &Value,
+        depth: usize,
+        level: usize,
+        column: usize,
+        hook: &mut PrintHook<E>,
+    ) -> Result<String, E> {
+        if let Value::Object(_) = value {
+            if let Some(text) = hook(value)? {
+                return Ok(text);
+            }
+        }
+        Ok(match value {
+            Value::Object(o) if o.is_empty() => "{}".to_string(),
+            Value::Object(_) if depth == 0 => "{…}".to_string(),
+            Value::Object(o) => self.object(o, depth, level, column, hook)?,
+            Value::Set(set) if set.is_empty() => "set()".to_string(),
+            Value::Set(_) if depth == 0 => "{…}".to_string(),
+            Value::Set(set) => {
+                let elements: Vec<Value> = set.iter().map(SetElement::to_value).collect();
+                self.sequence(("{", "}"), &elements, depth, level, column, hook)?
+            }
+            Value::Array(elements) if !elements.is_empty() && depth == 0 => "[…]".to_string(),
+            Value::Array(elements) => {
+                self.sequence(("[", "]"), elements, depth, level, column, hook)?
+            }
+            Value::Tuple(elements) if !elements.is_empty() && depth == 0 => "(…)".to_string(),
+            Value::Tuple(elements) if elements.len() == 1 => {
+                self.sequence(("(", ",)"), elements, depth, level, column, hook)?
+            }
+            Value::Tuple(elements) => {
+                self.sequence(("(", ")"), elements, depth, level, column, hook)?
+            }
+            Value::Function(function) => format!("fn({})", function.parameters.join(", ")),
+            Value::NativeFunction(function) => format!("native fn {}", function.name),
+            Value::String(s) if self.quote_strings => string_literal(s),
+            Value::String(s) => s.to_string(),
+            Value::Integer(i) => i.to_string(),
+            Value::Number(n) => format_float(*n),
+            Value::Boolean(b) => b.to_string(),
+            Value::None => "none".to_string(),
+        })
+    }
+
+    fn object<E>(
+        &self,
+        object: &HashMap<String, Box<Value>>,
+        depth: usize,
+        level: usize,
+        column: usize,
+        hook: &mut PrintHook<E>,
+    ) -> Result<String, E> {
+        let mut entries: Vec<(&String, &Box<Value>)> = object.iter().collect();
+        if self.sort_keys {
+            entries.sort_by_key(|(key, _)| *key);
+        }
+        let indentation = (level + 1) * self.indent;
+        let mut pieces = vec![];
+        let mut length = 0;
+        for (key, value) in entries {
+            let prefix = format!("{key} : ");
+            let column = indentation + prefix.chars().count();
+            let piece = prefix + &self.value(value, depth - 1, level + 1, column, hook)?;
+            length += piece.len();
+            pieces.push(piece);
+            // Past the length limit the rest would be cut anyway, so it isn't written.
+            if length > self.max_length {
+                break;
+            }
+        }
+        let line = format!("{{{}}}", pieces.join(", "));
+        if self.max_width > 0
+            && !line.contains('\n')
+            && column + line.chars().count() <= self.max_width
+        {
+            return Ok(line);
+        }
+        let mut text = "{\n".to_string();
+        for piece in pieces {
+            text.push_str(&" ".repeat(indentation));
+            text.push_str(&piece);
+            text.push('\n');
+        }
+        text.push_str(&" ".repeat(level * self.indent));
+        text.push('}');
+        Ok(text)
+    }
+
+    fn sequence<E>(
+        &self,
+        (open, close): (&str, &str),
+        elements: &[Value],
+        depth: usize,
+        level: usize,
+        column: usize,
+        hook: &mut PrintHook<E>,
+    ) -> Result<String, E> {
+        let mut text = open.to_string();
+        for (index, element) in elements.iter().enumerate() {
+            if index > 0 {
+                text.push_str(", ");
+            }
+            let column = match text.rfind('\n') {
+                Some(newline) => text[newline + 1..].chars().count(),
+                None => column + text.chars().count(),
+            };
+            text.push_str(&self.value(element, depth - 1, level, column, hook)?);
+            if text.len() > self.max_length {
+                break;
+            }
+        }
+        text.push_str(close);
+        Ok(text)
+    }
+}
+
+// The string as a string literal giving it back.
+fn string_literal(text: &str) -> String {
+    let mut literal = "\"".to_string();
+    for char in text.chars() {
+        match char {
+            '\\' => literal.push_str("\\\\"),
+            '"' => literal.push_str("\\\""),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            '\0' => literal.push_str("\\0"),
+            char => literal.push(char),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+// The text cut to at most `limit` chars, the last one being `…` when anything was cut.
+pub fn elide(text: String, limit: usize) -> String {
+    if text.chars().count() > limit {
+        let mut text: String = text.chars().take(limit.saturating_sub(1)).collect();
+        text.push('…');
+        text
+    } else {
+        text
+    }
+}
+
+// How numbers are formatted as text. Whole numbers are printed in full without a decimal
+// point. Others are first rounded to 15 significant digits, which hides the rounding
+// errors of binary floating point (`0.1 + 0.2` shows as `0.3`), then printed with as few
+// digits as give that rounded number back. Magnitudes from 1e21 on or below 1e-7 switch
+// to exponent form (`1e21`, `1.5e-8`).
+pub fn format_number(n: Number) -> String {
+    if !n.is_finite() {
+        return n.to_string();
+    }
+    if n == 0.0 {
+        return "0".to_string();
+    }
+    if n.fract() == 0.0 && n.abs() < 1e21 {
+        return format!("{n:.0}");
+    }
+    let rounded: Number = format!("{n:.14e}").parse().unwrap_or(n);
+    if rounded.abs() >= 1e21 || rounded.abs() < 1e-7 {
+        format!("{rounded:e}")
+    } else {
+        rounded.to_string()
+    }
+}
+
+// How floats are shown in values, like `format_number` but with whole ones ending in
+// `.0` so they aren't taken for integers.
+pub fn format_float(n: Number) -> String {
+    let text = format_number(n);
+    if n.is_finite() && !text.contains(['.', 'e']) {
+        text + ".0"
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cmp::Ordering,
+        collections::{hash_map::DefaultHasher, BTreeSet, HashMap},
+        hash::Hasher,
+    };
+
+    use crate::{run_source, runtime::registry::BuiltinRegistry};
+
+    use super::{format_float, format_number, SetElement, Value, ValuePrinter};
+
+    fn hash(value: &Value) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash_into(&mut hasher);
+        hasher.finish()
+    }
+
+    fn object(entries: &[(&str, Value)]) -> Value {
+        Value::Object(
+            entries
+                .iter()
+                .map(|(key, value)| (key.to_string(), Box::new(value.clone())))
+                .collect::<HashMap<_, _>>(),
+        )
+    }
+
+    #[test]
+    fn test_value_compare_and_hash() {
+        let n = Value::Number;
+        let i = Value::Integer;
+        let s = |s: &str| Value::String(s.to_string().into());
+        let t = Value::Tuple;
+
+        // Ascending, kinds first and then contents.
+        let ordered = [
+            Value::None,
+            Value::Boolean(false),
+            Value::Boolean(true),
+            n(f64::NEG_INFINITY),
+            i(i64::MIN),
+            n(-1.5),
+            i(-1),
+            n(-0.5),
+            n(0.),
+            i(1),
+            n(2.5),
+            i(i64::MAX - 1),
+            i(i64::MAX),
+            n(9_223_372_036_854_775_808.),
+            n(f64::INFINITY),
+            n(f64::NAN),
+            s(""),
+            s("a"),
+            s("ab"),
+            s("b"),
+            Value::Array(vec![]),
+            Value::Array(vec![t(vec![])]),
+            t(vec![]),
+            t(vec![n(1.)]),
+            t(vec![n(1.), n(f64::NAN)]),
+            t(vec![n(1.), s("a")]),
+            t(vec![n(2.)]),
+            Value::Set(BTreeSet::from([SetElement::Number(1.)])),
+            object(&[]),
+            object(&[("a", n(1.))]),
+            object(&[("a", n(1.)), ("b", n(0.))]),
+            object(&[("a", n(2.))]),
+            object(&[("b", t(vec![]))]),
+        ];
+        for (i, a) in ordered.iter().enumerate() {
+            for (j, b) in ordered.iter().enumerate() {
+                assert_eq!(a.compare(b), i.cmp(&j), "{a:?} against {b:?}");
             }
-            Value::String(s) => write!(f, "{s}"),
-            Value::Number(n) => write!(f, "{n}"),
-            Value::Boolean(b) => write!(f, "{b}"),
-            Value::None => write!(f, "none"),
         }
+
+        // Equal values hash the same, even when they're spelled apart.
+        let equal = [
+            (n(0.), n(-0.)),
+            (i(0), n(-0.)),
+            (i(-3), n(-3.)),
+            (n(f64::NAN), n(-f64::NAN)),
+            (
+                t(vec![object(&[("x", n(-0.))]), n(f64::NAN)]),
+                t(vec![object(&[("x", n(0.))]), n(f64::NAN)]),
+            ),
+            (
+                object(&[("a", n(1.)), ("b", t(vec![s("c")]))]),
+                object(&[("b", t(vec![s("c")])), ("a", n(1.))]),
+            ),
+        ];
+        for (a, b) in equal {
+            assert_eq!(a.compare(&b), Ordering::Equal, "{a:?} against {b:?}");
+            assert_eq!(hash(&a), hash(&b), "{a:?} against {b:?}");
+        }
+        assert_ne!(hash(&n(1.)), hash(&s("1")));
+        // Integers and floats are the same number when they're equal, and only then.
+        assert_eq!(i(3), n(3.));
+        assert_ne!(i(i64::MAX), n(9_223_372_036_854_775_808.));
+        assert_ne!(i(1), n(f64::NAN));
+        assert_eq!(
+            Value::Set(BTreeSet::from([SetElement::Integer(1)])),
+            run_source("set(1, 1.0)").unwrap()
+        );
+        assert_ne!(hash(&t(vec![n(1.), n(2.)])), hash(&t(vec![n(2.), n(1.)])));
+    }
+
+    #[test]
+    fn test_value_compare_functions() {
+        let registry = BuiltinRegistry::standard();
+        let clone = registry.get("clone").unwrap().value.clone();
+        let len = registry.get("len").unwrap().value.clone();
+
+        // Native functions are ordered and hashed by their name.
+        assert_eq!(clone.compare(&len), Ordering::Less);
+        assert_eq!(len.compare(&len.clone()), Ordering::Equal);
+        assert_eq!(hash(&len), hash(&len.clone()));
+        assert_eq!(len.compare(&object(&[])), Ordering::Greater);
+
+        // Functions by their definition, so copies of one are equal.
+        let functions = run_source("fn f() {} fn g() {} let result = (f, g, f) result").unwrap();
+        let Value::Tuple(functions) = functions else {
+            panic!("Expected a tuple, got {functions:?}");
+        };
+        assert_eq!(functions[0].compare(&functions[2]), Ordering::Equal);
+        assert_eq!(hash(&functions[0]), hash(&functions[2]));
+        assert_ne!(functions[0].compare(&functions[1]), Ordering::Equal);
+        assert_eq!(
+            functions[0].compare(&functions[1]),
+            functions[1].compare(&functions[0]).reverse()
+        );
+        assert_eq!(functions[0].compare(&clone), Ordering::Less);
+    }
+
+    #[test]
+    fn test_format_number() {
+        for (n, expected) in [
+            (0.0, "0"),
+            (-0.0, "0"),
+            (42.0, "42"),
+            (-7.0, "-7"),
+            (2.5, "2.5"),
+            (0.1 + 0.2, "0.3"),
+            (1.0 / 3.0, "0.333333333333333"),
+            (2.0 / 3.0, "0.666666666666667"),
+            (1.1 * 3.0, "3.3"),
+            (123456789.123, "123456789.123"),
+            (9007199254740992.0, "9007199254740992"),
+            (1e20, "100000000000000000000"),
+            (1e21, "1e21"),
+            (-2.5e25, "-2.5e25"),
+            (1e-7, "0.0000001"),
+            (1.5e-8, "1.5e-8"),
+            (f64::INFINITY, "inf"),
+        ] {
+            assert_eq!(format_number(n), expected, "{n:?}");
+        }
+        // Whole floats are shown with a point in values, to tell them from integers.
+        for (n, expected) in [(42.0, "42.0"), (-0.0, "0.0"), (2.5, "2.5"), (1e21, "1e21")] {
+            assert_eq!(format_float(n), expected, "{n:?}");
+        }
+    }
+
+    #[test]
+    fn test_value_render_limits() {
+        let nested = run_source(
+            "let v = [] let i = 0 while i < 20 { v = [v] i = i + 1 } let result = (v,) result",
+        )
+        .unwrap();
+        assert_eq!(nested.to_string(), "([[[[[[[[…]]]]]]]],)");
+        let limits = ValuePrinter {
+            max_depth: 2,
+            max_length: 100,
+            ..ValuePrinter::default()
+        };
+        assert_eq!(limits.print(&nested), "([[…]],)");
+        assert_eq!(limits.print(&Value::Array(vec![])), "[]");
+
+        // An object holding itself holds a copy of what it was, so it's finite anyway.
+        let object = run_source("let o = {} o.self = o o.self = o o.self = o o").unwrap();
+        assert_eq!(
+            object.to_string(),
+            "{\n  self : {\n    self : {\n      self : {}\n    }\n  }\n}"
+        );
+
+        let long =
+            run_source("let s = \"ab\" let i = 0 while i < 15 { s = s + s i = i + 1 } s").unwrap();
+        let output = long.to_string();
+        assert_eq!(output.chars().count(), 10_000);
+        assert!(output.ends_with("ba…"));
+        let output = limits.print(&Value::Array(vec![long; 1000]));
+        assert_eq!(output.chars().count(), 100);
+        assert!(output.starts_with("[abab") && output.ends_with('…'));
+    }
+
+    #[test]
+    fn test_value_printer() {
+        let value = run_source(
+            "{name: \"fns\", tags: [\"a\", \"b\\n\"], nested: {point: (1, 2.0), empty: {}, deep: {deeper: {deepest: set(1)}}}}",
+        )
+        .unwrap();
+        let sorted = ValuePrinter {
+            sort_keys: true,
+            ..ValuePrinter::default()
+        };
+        assert_eq!(
+            sorted.print(&value),
+            r#"{
+  name : fns
+  nested : {
+    deep : {
+      deeper : {
+        deepest : {1}
+      }
+    }
+    empty : {}
+    point : (1, 2.0)
+  }
+  tags : [a, b
+]
+}"#
+        );
+        assert_eq!(
+            ValuePrinter::repl().print(&value),
+            r#"{
+  name : "fns"
+  nested : {deep : {deeper : {deepest : {1}}}, empty : {}, point : (1, 2.0)}
+  tags : ["a", "b\n"]
+}"#
+        );
+        let narrow = ValuePrinter {
+            max_width: 30,
+            sort_keys: true,
+            quote_strings: true,
+            max_depth: 3,
+            indent: 4,
+            ..ValuePrinter::default()
+        };
+        assert_eq!(
+            narrow.print(&value),
+            r#"{
+    name : "fns"
+    nested : {
+        deep : {deeper : {…}}
+        empty : {}
+        point : (1, 2.0)
+    }
+    tags : ["a", "b\n"]
+}"#
+        );
+    }
+
+    #[test]
+    fn test_value_accessors() {
+        let value = run_source(
+            "{app: {name: \"demo\", ports: [80, 443], limits: (1, {depth: 3}), flag: false}}",
+        )
+        .unwrap();
+        assert_eq!(value.try_get_str("app.name"), Ok("demo"));
+        assert_eq!(value.try_get_number("app.ports.1"), Ok(443.));
+        assert_eq!(value.try_get_number("app.limits.1.depth"), Ok(3.));
+        assert_eq!(value.try_get_bool("app.flag"), Ok(false));
+        assert_eq!(
+            value.get("app.ports").and_then(Value::as_array),
+            Some(&[Value::Number(80.), Value::Number(443.)][..])
+        );
+        assert_eq!(
+            value
+                .get("app")
+                .and_then(Value::as_object)
+                .map(HashMap::len),
+            Some(4)
+        );
+        assert_eq!(value.get("app.name").and_then(Value::as_number), None);
+
+        for (path, message) in [
+            (
+                "missing",
+                "Can't get 'missing' as the value has no key 'missing'",
+            ),
+            ("app.nam", "Can't get 'app.nam' as 'app' has no key 'nam'"),
+            // A path going on past a value that isn't an object.
+            (
+                "app.name.first",
+                "Can't get 'app.name.first' as 'app.name' is a string",
+            ),
+            (
+                "app.ports.2",
+                "Can't get 'app.ports.2' as 'app.ports' has no element '2'",
+            ),
+            (
+                "app.ports.x",
+                "Can't get 'app.ports.x' as 'app.ports' has no element 'x'",
+            ),
+        ] {
+            assert_eq!(value.try_get(path), Err(message.to_string()));
+            assert_eq!(value.get(path), None);
+        }
+        assert_eq!(
+            value.try_get_number("app.name"),
+            Err("Can't get 'app.name' as a number as it's a string".to_string())
+        );
+        assert_eq!(
+            Value::Number(1.).try_get("a"),
+            Err("Can't get 'a' as the value is a number".to_string())
+        );
     }
 }
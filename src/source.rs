@@ -0,0 +1,99 @@
+use std::{fmt, fs::read, path::Path, process::exit};
+
+// Files that can't be found or opened exit with 66 and files that aren't text with 65,
+// following sysexits.h.
+const NO_INPUT: i32 = 66;
+const DATA_ERROR: i32 = 65;
+
+#[derive(Debug, PartialEq)]
+pub struct LoadError {
+    pub message: String,
+    pub exit_code: i32,
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error: {}", self.message)
+    }
+}
+
+// Reads a source file for every command that takes one. Invalid UTF-8 is an error rather
+// than being replaced, as running a file that isn't what it looks like helps no one.
+pub fn load_source(path: &Path) -> Result<String, LoadError> {
+    let name = path.display();
+    if path.is_dir() {
+        return Err(LoadError {
+            message: format!(
+                "Could not read '{name}' as it's a directory, `fns test {name}` runs the files in it"
+            ),
+            exit_code: NO_INPUT,
+        });
+    }
+    let bytes = read(path).map_err(|error| LoadError {
+        message: format!("Could not read '{name}': {error}"),
+        exit_code: NO_INPUT,
+    })?;
+    String::from_utf8(bytes).map_err(|error| LoadError {
+        message: format!(
+            "Could not read '{name}' as it isn't valid UTF-8 (from byte {})",
+            error.utf8_error().valid_up_to()
+        ),
+        exit_code: DATA_ERROR,
+    })
+}
+
+// Loads a source file or exits with the error, for the commands that can't go on without.
+pub fn load_source_or_exit(path: &Path) -> String {
+    load_source(path).unwrap_or_else(|error| {
+        eprintln!("{error}");
+        exit(error.exit_code);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env::temp_dir,
+        fs::{create_dir_all, remove_dir, remove_file, write},
+        process,
+    };
+
+    use super::load_source;
+
+    #[test]
+    fn test_load_source() {
+        let path = temp_dir().join(format!("fns-load-{}.fns", process::id()));
+
+        write(&path, "let a = 1").unwrap();
+        assert_eq!(load_source(&path), Ok("let a = 1".to_string()));
+
+        write(&path, b"let a = \"\xff\"").unwrap();
+        let error = load_source(&path).unwrap_err();
+        assert_eq!(
+            error.message,
+            format!(
+                "Could not read '{}' as it isn't valid UTF-8 (from byte 9)",
+                path.display()
+            )
+        );
+        assert_eq!(error.exit_code, 65);
+
+        remove_file(&path).unwrap();
+        let error = load_source(&path).unwrap_err();
+        assert!(error
+            .message
+            .starts_with(&format!("Could not read '{}': ", path.display())));
+        assert_eq!(error.exit_code, 66);
+    }
+
+    #[test]
+    fn test_load_source_directory() {
+        let path = temp_dir().join(format!("fns-load-dir-{}", process::id()));
+        create_dir_all(&path).unwrap();
+        let error = load_source(&path).unwrap_err();
+        remove_dir(&path).unwrap();
+
+        assert!(error.message.contains("as it's a directory, `fns test"));
+        assert_eq!(error.exit_code, 66);
+    }
+}
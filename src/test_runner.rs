@@ -0,0 +1,119 @@
+use std::{
+    fs::read_dir,
+    path::{Path, PathBuf},
+};
+
+use fns::{
+    frontend::{
+        limits::Limits,
+        utils::{Error, TextSpan},
+    },
+    run_source_with,
+    runtime::{environment::Environment, evaluator::call_value, types::Value},
+};
+
+use crate::source::load_source;
+
+#[derive(Debug, PartialEq)]
+pub struct TestSummary {
+    pub passed: usize,
+    pub failed: usize,
+}
+
+// Runs every `.fns` file found under `path`, each in a fresh environment. A file defining
+// functions whose name starts with `test_` gets each of them called as a separate test
+// after the file itself was evaluated, otherwise evaluating the whole file is the test.
+// A test fails when it raises an error or returns `false`.
+pub fn run_tests(path: &Path, limits: &Limits) -> TestSummary {
+    let mut summary = TestSummary {
+        passed: 0,
+        failed: 0,
+    };
+    let mut files = vec![];
+    discover(path, &mut files);
+    for file in files {
+        run_file(&file, limits, &mut summary);
+    }
+    println!("\n{} passed, {} failed", summary.passed, summary.failed);
+    summary
+}
+
+fn discover(path: &Path, files: &mut Vec<PathBuf>) {
+    if path.is_dir() {
+        let mut entries: Vec<PathBuf> = match read_dir(path) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .map(|e| e.path())
+                .collect(),
+            Err(_) => vec![],
+        };
+        entries.sort();
+        for entry in entries {
+            if entry.is_dir() || entry.extension().is_some_and(|e| e == "fns") {
+                discover(&entry, files);
+            }
+        }
+    } else {
+        files.push(path.to_path_buf());
+    }
+}
+
+fn run_file(path: &Path, limits: &Limits, summary: &mut TestSummary) {
+    let name = path.display().to_string();
+    let source_code = match load_source(path) {
+        Ok(source_code) => source_code,
+        Err(error) => {
+            println!("FAIL {name}");
+            eprintln!("{error}");
+            summary.failed += 1;
+            return;
+        }
+    };
+
+    let mut environment = Environment::new(None);
+    environment.limits = limits.clone();
+    if let Err(error) = run_source_with(&source_code, &mut environment) {
+        report_failure(&name, &error, &source_code, summary);
+        return;
+    }
+
+    let mut tests: Vec<(String, Value)> = environment
+        .variables
+        .borrow()
+        .iter()
+        .filter(|(identifier, (value, _))| {
+            identifier.starts_with("test_") && matches!(value, Value::Function(_))
+        })
+        .map(|(identifier, (value, _))| (identifier.to_string(), value.clone()))
+        .collect();
+    tests.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    if tests.is_empty() {
+        println!("PASS {name}");
+        summary.passed += 1;
+        return;
+    }
+
+    for (identifier, test) in tests {
+        let name = format!("{name}::{identifier}");
+        let mut environment = environment.clone();
+        match call_value(&test, vec![], TextSpan::new(0, 0), &mut environment) {
+            Ok(Value::Boolean(false)) => {
+                println!("FAIL {name}");
+                eprintln!("Error: The test returned false");
+                summary.failed += 1;
+            }
+            Ok(_) => {
+                println!("PASS {name}");
+                summary.passed += 1;
+            }
+            Err(error) => report_failure(&name, &error, &source_code, summary),
+        }
+    }
+}
+
+fn report_failure(name: &str, error: &Error, source_code: &str, summary: &mut TestSummary) {
+    println!("FAIL {name}");
+    error.report(source_code);
+    summary.failed += 1;
+}
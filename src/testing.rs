@@ -0,0 +1,71 @@
+use std::{io, thread};
+
+use crate::{
+    frontend::{limits::Limits, utils::TextSpan},
+    run_source_with,
+    runtime::{console::Console, environment::Environment, evaluator::display},
+};
+
+// What `check_no_panic` lets a program do: nesting and calls as deep as by default, so
+// they're checked on the stack the defaults need, and few enough steps and short enough
+// strings that any input ends quickly, whether with a value or an error.
+pub fn fuzz_limits() -> Limits {
+    Limits {
+        max_statements: 1_000,
+        max_object_pairs: 1_000,
+        max_string_length: 10_000,
+        max_steps: 25_000,
+        ..Limits::default()
+    }
+}
+
+/// Runs `source` through the tokenizer, the parser and the evaluator with the limits
+/// of `fuzz_limits`, on a thread with the stack they need, then displays its value.
+/// Errors are fine, any stage panicking isn't: it panics again with the source that
+/// made it.
+///
+/// ```
+/// fns::testing::check_no_panic("let a = (1, [2]) a.1[0] + ");
+/// fns::testing::check_no_panic("while true {}");
+/// ```
+pub fn check_no_panic(source: &str) {
+    let source_code = source.to_string();
+    let outcome = thread::Builder::new()
+        .stack_size(fuzz_limits().stack_size())
+        .spawn(move || {
+            let mut environment = Environment::new(None);
+            environment.limits = fuzz_limits();
+            // `input` finds no input rather than waiting for some on stdin.
+            *environment.console.borrow_mut() =
+                Console::new(Box::new(io::empty()), Box::new(io::sink()));
+            if let Ok(value) = run_source_with(&source_code, &mut environment) {
+                let _ = display(&value, TextSpan::new(0, 0), &mut environment);
+            }
+        })
+        .expect("the thread running the pipeline should start")
+        .join();
+    if outcome.is_err() {
+        panic!("fns panicked on {source:?}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use super::check_no_panic;
+
+    // Every input that made a stage panic once is kept in `tests/fixtures/fuzz`, see
+    // CONTRIBUTING.md for adding one.
+    #[test]
+    fn test_fuzz_corpus() {
+        let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/fuzz");
+        let mut inputs = 0;
+        for entry in fs::read_dir(corpus).unwrap() {
+            let bytes = fs::read(entry.unwrap().path()).unwrap();
+            check_no_panic(&String::from_utf8_lossy(&bytes));
+            inputs += 1;
+        }
+        assert!(inputs > 0);
+    }
+}
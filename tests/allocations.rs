@@ -0,0 +1,49 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use fns::run_source;
+
+// Counts the allocations made by the whole test binary, which only has this test so
+// that nothing else allocates while it measures.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations(source_code: &str) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    run_source(source_code).unwrap();
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+// A string literal evaluates to a handle on the string parsed once, so evaluating it
+// costs no more allocations than evaluating a number does.
+#[test]
+fn string_literals_are_not_copied_when_evaluated() {
+    let program = |literal: &str| {
+        format!(
+            "let i = 0 let a = none while i < 1000 {{ a = {literal} a = {literal} a = {literal} i = i + 1 }} a"
+        )
+    };
+    let numbers = allocations(&program("12345"));
+    let strings = allocations(&program("\"a literal long enough to be copied\""));
+    assert!(
+        strings < numbers + 100,
+        "{strings} allocations for strings against {numbers} for numbers"
+    );
+}
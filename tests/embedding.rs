@@ -0,0 +1,143 @@
+use std::{rc::Rc, time::Instant};
+
+use fns::{
+    evaluate_with_spans,
+    frontend::utils::TextSpan,
+    run_source, run_source_with,
+    runtime::{
+        environment::Environment, evaluator::call_value, registry::BuiltinRegistry, types::Value,
+    },
+};
+
+// A template with fns snippets between `{{` and `}}`, the way a host would embed them.
+fn embed(snippet: &str) -> (String, usize) {
+    let prefix = format!("{}{{{{ ", "-".repeat(97));
+    (
+        format!("{prefix}{snippet} }}}} trailing text"),
+        prefix.len(),
+    )
+}
+
+fn environment_at(offset: usize) -> Environment {
+    let mut environment = Environment::new(None);
+    environment.source_offset = offset;
+    environment
+}
+
+fn spanned<'a>(document: &'a str, text_span: &TextSpan) -> &'a str {
+    &document[text_span.starting_index..text_span.ending_index]
+}
+
+#[test]
+fn errors_point_into_the_document() {
+    let (document, offset) = embed("let a = 1 a + b");
+    assert_eq!(offset, 100);
+    let error =
+        run_source_with(&document[offset..offset + 15], &mut environment_at(offset)).unwrap_err();
+    assert_eq!(error.text_span, TextSpan::new(114, 115));
+    assert_eq!(spanned(&document, &error.text_span), "b");
+
+    // Errors found before running, and the context around a span, are shifted too.
+    let (document, offset) = embed("let a = (1");
+    let error =
+        run_source_with(&document[offset..offset + 10], &mut environment_at(offset)).unwrap_err();
+    assert_eq!(error.text_span.starting_index, 110);
+
+    let (document, offset) = embed("let o = {a: 1} o.b");
+    let error =
+        run_source_with(&document[offset..offset + 18], &mut environment_at(offset)).unwrap_err();
+    assert_eq!(spanned(&document, &error.text_span), "b");
+    assert_eq!(spanned(&document, &error.context.unwrap()), "o.b");
+}
+
+#[test]
+fn statements_come_back_with_their_spans() {
+    let (document, offset) = embed("let a = 2 a * 21 none");
+    let mut environment = environment_at(offset);
+    let (value, statements) =
+        evaluate_with_spans(&document[offset..offset + 21], &mut environment).unwrap();
+    assert_eq!(value, Value::None);
+    let statements: Vec<(&str, String)> = statements
+        .iter()
+        .map(|(text_span, value)| (spanned(&document, text_span), value.to_string()))
+        .collect();
+    assert_eq!(
+        statements,
+        vec![
+            ("let a = 2", "none".to_string()),
+            ("a * 21", "42".to_string()),
+            ("none", "none".to_string()),
+        ]
+    );
+    assert_eq!(environment.access("a"), Some(Value::Integer(2)));
+}
+
+#[test]
+fn snapshots_isolate_environments() {
+    let mut prototype = Environment::new(None);
+    run_source_with(
+        "let config = {pi: math.pi, hits: 0} fn hit() { config.hits = config.hits + 1 config.hits } const name = \"prototype\"",
+        &mut prototype,
+    )
+    .unwrap();
+    let snapshot = prototype.snapshot();
+
+    let mut first = Environment::from_snapshot(&snapshot);
+    let value = run_source_with(
+        "let math = 2 let extra = true config.pi = 0 hit() hit()",
+        &mut first,
+    )
+    .unwrap();
+    assert_eq!(value, Value::Integer(2));
+    assert!(run_source_with("name = 1", &mut first).is_err());
+
+    // Neither the definitions nor the assignments of the first reach the second, the
+    // function it calls assigning its own copy of `config`.
+    let mut second = Environment::from_snapshot(&snapshot);
+    assert_eq!(
+        run_source_with("hit()", &mut second).unwrap(),
+        Value::Integer(1)
+    );
+    assert_eq!(
+        run_source_with("(config.pi == math.pi, name)", &mut second)
+            .unwrap()
+            .to_string(),
+        "(true, prototype)"
+    );
+    assert_eq!(second.access("extra"), None);
+    assert_eq!(
+        run_source_with("config.hits", &mut prototype).unwrap(),
+        Value::Integer(0)
+    );
+
+    // The builtins are shared rather than built again for every environment.
+    assert!(Rc::ptr_eq(&first.builtins, &second.builtins));
+    let start = Instant::now();
+    for _ in 0..100 {
+        Environment::from_snapshot(&snapshot);
+    }
+    let from_snapshot = start.elapsed();
+    let start = Instant::now();
+    for _ in 0..100 {
+        BuiltinRegistry::standard();
+    }
+    assert!(from_snapshot < start.elapsed());
+}
+
+#[test]
+fn functions_outlive_the_run_they_were_made_in() {
+    // Once the run is over, the scopes its functions close over are collected, except for
+    // the ones the functions given back to the host still need.
+    let counter = run_source("let n = 0 fn() { n = n + 1 n }").unwrap();
+    let countdown = run_source("fn f(n) { n == 0 ? \"done\" : f(n - 1) } f").unwrap();
+    let mut environment = Environment::new(None);
+    let mut call = |function: &Value, arguments| {
+        call_value(function, arguments, TextSpan::new(0, 0), &mut environment).unwrap()
+    };
+    assert_eq!(call(&counter, vec![]), Value::Integer(1));
+    assert_eq!(call(&counter, vec![]), Value::Integer(2));
+    assert_eq!(
+        call(&countdown, vec![Value::Integer(3)]).to_string(),
+        "done"
+    );
+}
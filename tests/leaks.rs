@@ -0,0 +1,88 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use fns::{run_source, run_source_with, runtime::environment::Environment};
+
+// Tracks the bytes the whole test binary has allocated and not freed yet, which only has
+// this test so that nothing else allocates while it measures.
+struct CountingAllocator;
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        LIVE.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+// The bytes left allocated by running `run` a hundred times, after running it once to
+// fill what's allocated only the first time, like the standard builtins. The harness
+// may allocate a little meanwhile, far less than a run leaking would.
+fn bytes_leaked(mut run: impl FnMut()) -> usize {
+    run();
+    let before = LIVE.load(Ordering::Relaxed);
+    for _ in 0..100 {
+        run();
+    }
+    LIVE.load(Ordering::Relaxed).saturating_sub(before)
+}
+
+// Functions close over the scopes they're stored in, which counting references alone
+// never frees. Whatever a run allocates is freed once it's over, as long as it doesn't
+// give back a function, which keeps the scopes it closes over.
+#[test]
+fn runs_free_the_functions_they_make() {
+    for source_code in [
+        "let f = fn() { 1 } f()",
+        "fn f(n) { n == 0 ? 0 : f(n - 1) } f(10)",
+        "fn a(n) { n == 0 ? 0 : b(n - 1) } fn b(n) { a(n) } a(3)",
+        "let make = fn() { let n = 0 fn() { n = n + 1 } } let c = make() c() c()",
+        "let o = {f: none} o.f = fn() { o } let fs = [fn() { fs }] o.f() none",
+        "let g = none g = fn() { 1 } g()",
+        "let i = 0 while i < 10 { let g = fn() { i } i = g() + 1 }",
+    ] {
+        let leaked = bytes_leaked(|| {
+            run_source(source_code).unwrap();
+        });
+        assert!(leaked < 1024, "{leaked} bytes leaked by {source_code:?}");
+    }
+
+    // The modules imported hold functions closing over their scopes too.
+    let module = format!(
+        "{}/tests/fixtures/leaks/module.fns",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    let source_code = format!("let module = import({module:?}) module.double(2)");
+    let leaked = bytes_leaked(|| {
+        run_source(&source_code).unwrap();
+    });
+    assert!(leaked < 1024, "{leaked} bytes leaked by imports");
+
+    let mut prototype = Environment::new(None);
+    run_source_with(
+        "fn double(x) { x * 2 } let helpers = {triple: fn(x) { 3 * x }}",
+        &mut prototype,
+    )
+    .unwrap();
+    let snapshot = prototype.snapshot();
+    let leaked = bytes_leaked(|| {
+        let mut sandbox = Environment::from_snapshot(&snapshot);
+        run_source_with(
+            "let f = fn() { double(1) } helpers.triple(f())",
+            &mut sandbox,
+        )
+        .unwrap();
+    });
+    assert!(leaked < 1024, "{leaked} bytes leaked by the sandboxes");
+}
@@ -0,0 +1,65 @@
+use std::{fs, path::Path};
+
+use fns::{
+    frontend::{token::TokenKind, tokenizer::tokenize},
+    run_source,
+};
+
+// Each fixture is written with `\r\n` line endings, and checked against the same source
+// with `\n` and with lone `\r` endings.
+fn variants(name: &str) -> Vec<String> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/line_endings")
+        .join(name);
+    let crlf = fs::read_to_string(path).unwrap();
+    assert!(
+        crlf.contains("\r\n"),
+        "{name} should have CRLF line endings"
+    );
+    vec![crlf.replace("\r\n", "\n"), crlf.replace("\r\n", "\r"), crlf]
+}
+
+// The tokens with the line and column each starts at, the line breaks in strings
+// written as `\n`.
+fn tokens(source_code: &str) -> Vec<(TokenKind, String, (usize, usize))> {
+    tokenize(source_code)
+        .unwrap()
+        .into_iter()
+        .map(|token| {
+            let lexeme = token.lexeme.replace("\r\n", "\n").replace('\r', "\n");
+            (token.kind, lexeme, token.text_span.position(source_code))
+        })
+        .collect()
+}
+
+fn outcome(source_code: &str) -> Result<String, (String, (usize, usize))> {
+    run_source(source_code)
+        .map(|value| value.to_string())
+        .map_err(|error| (error.message, error.text_span.position(source_code)))
+}
+
+#[test]
+fn line_endings_give_the_same_tokens_and_outcomes() {
+    for (fixture, expected) in [
+        ("comments.fns", Ok("6".to_string())),
+        ("strings.fns", Ok("(13, first\nsecond\n)".to_string())),
+        (
+            "runtime_error.fns",
+            Err((
+                "Can't access the variable 'missing' as it's not defined".to_string(),
+                (5, 5),
+            )),
+        ),
+        (
+            "parse_error.fns",
+            Err(("Unexpected end of input, expected ')'".to_string(), (3, 1))),
+        ),
+    ] {
+        let variants = variants(fixture);
+        let lf = &variants[0];
+        for source_code in &variants {
+            assert_eq!(tokens(source_code), tokens(lf), "{fixture} {source_code:?}");
+            assert_eq!(outcome(source_code), expected, "{fixture} {source_code:?}");
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use std::process::{Command, Output};
+
+fn lint(arguments: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_fns"))
+        .arg("lint")
+        .args(arguments)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn lint_reports_each_fixture() {
+    for (fixture, expected) in [
+        (
+            "undefined_variable",
+            "Warning (undefined-variable): 'nmae' isn't defined",
+        ),
+        (
+            "const_reassign",
+            "Warning (const-reassign): 'limit' is declared as a constant",
+        ),
+        (
+            "use_before_declaration",
+            "Warning (use-before-declaration): 'count'",
+        ),
+        ("shadowed_builtin", "Warning (shadowed-builtin): 'len'"),
+        (
+            "unused_empty_object",
+            "Warning (unused-empty-object): 'cache'",
+        ),
+        ("self_comparison", "Warning (self-comparison): 'a'"),
+        (
+            "unused_expression",
+            "Warning (unused-expression): 'name == \"x\"'",
+        ),
+    ] {
+        let output = lint(&[&format!("tests/fixtures/lint/{fixture}.fns")]);
+        let stderr = String::from_utf8(output.stderr).unwrap();
+
+        assert!(!output.status.success(), "{fixture}");
+        assert!(stderr.contains(expected), "{fixture}: {stderr}");
+        assert_eq!(stderr.matches("[warning in line").count(), 1, "{fixture}");
+    }
+}
+
+#[test]
+fn lint_succeeds_on_clean_or_allowed_code() {
+    let output = lint(&["tests/fixtures/lint/clean.fns"]);
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+
+    let output = lint(&[
+        "tests/fixtures/lint/shadowed_builtin.fns",
+        "--allow",
+        "shadowed-builtin",
+    ]);
+    assert!(output.status.success());
+}
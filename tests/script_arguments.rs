@@ -0,0 +1,35 @@
+use std::process::Command;
+
+#[test]
+fn scripts_get_their_arguments_and_environment_variables() {
+    let output = Command::new(env!("CARGO_BIN_EXE_fns"))
+        .args([
+            "--strict",
+            "tests/fixtures/script_arguments/echo.fns",
+            "foo",
+            "bar baz",
+            "--",
+            "--verbose",
+        ])
+        .env("FNS_TEST_GREETING", "hello")
+        .env_remove("FNS_TEST_UNSET")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert!(output.status.success(), "{stderr}");
+    assert_eq!(stderr, "[warn] [foo, bar baz, --verbose]\n");
+}
+
+#[test]
+fn scripts_without_arguments_get_an_empty_array() {
+    let output = Command::new(env!("CARGO_BIN_EXE_fns"))
+        .args(["run", "tests/fixtures/script_arguments/echo.fns"])
+        .env("FNS_TEST_GREETING", "hello")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert!(!output.status.success());
+    assert!(stderr.contains("[error in line: 1, column: 1]\nError: Assertion failed as the values aren't equal\n  left: []\n"));
+}
@@ -0,0 +1,32 @@
+use std::{
+    fs,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+// The examples in syntax/ are documentation, so each of them has to keep running.
+#[test]
+fn syntax_examples_run() {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("syntax");
+    let mut examples = 0;
+    for entry in fs::read_dir(directory).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|extension| extension != "fns") {
+            continue;
+        }
+        let output = Command::new(env!("CARGO_BIN_EXE_fns"))
+            .arg("run")
+            .arg(&path)
+            .stdin(Stdio::null())
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{} failed:\n{}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        examples += 1;
+    }
+    assert!(examples > 0);
+}
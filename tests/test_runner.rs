@@ -0,0 +1,55 @@
+use std::process::Command;
+
+#[test]
+fn test_runner_reports_passing_and_failing_tests() {
+    let output = Command::new(env!("CARGO_BIN_EXE_fns"))
+        .args(["test", "tests/fixtures/test_runner"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert!(!output.status.success());
+    assert!(stdout.contains("PASS tests/fixtures/test_runner/arithmetic.fns::test_addition\n"));
+    assert!(stdout
+        .contains("FAIL tests/fixtures/test_runner/arithmetic.fns::test_addition_with_boolean\n"));
+    assert!(stdout.contains("PASS tests/fixtures/test_runner/script.fns\n"));
+    assert!(stdout.ends_with("2 passed, 1 failed\n"));
+    assert!(stderr.contains("[error in line: 1, column: 20]"));
+}
+
+#[test]
+fn test_runner_succeeds_when_everything_passes() {
+    let output = Command::new(env!("CARGO_BIN_EXE_fns"))
+        .args(["test", "tests/fixtures/test_runner/script.fns"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.status.success());
+    assert!(stdout.ends_with("1 passed, 0 failed\n"));
+}
+
+#[test]
+fn test_runner_reports_failed_assertions() {
+    let output = Command::new(env!("CARGO_BIN_EXE_fns"))
+        .args(["test", "tests/fixtures/assertions"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert!(!output.status.success());
+    let file = "tests/fixtures/assertions/assertions.fns";
+    assert!(stdout.contains(&format!("PASS {file}::test_passing_assertions\n")));
+    assert!(stdout.contains(&format!("FAIL {file}::test_failing_assert\n")));
+    assert!(stdout.contains(&format!("FAIL {file}::test_failing_assert_eq\n")));
+    assert!(stdout.ends_with("1 passed, 2 failed\n"));
+
+    assert!(stderr.contains(
+        "[error in line: 11, column: 5]\nError: Assertion failed: 3 squared should be 10\n"
+    ));
+    assert!(stderr.contains(
+        "[error in line: 15, column: 5]\nError: Assertion failed as the values aren't equal\n  left: 2.25\n  right: 2\n"
+    ));
+}